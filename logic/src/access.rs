@@ -0,0 +1,605 @@
+//
+// RFID access control: whitelist, enrollment, anti-passback and event log
+//
+
+use core::fmt;
+use eeprom24x::{addr_size, page_size, Eeprom24x};
+use embedded_hal::blocking::i2c;
+
+pub const MAX_UID_LEN: usize = 10;
+pub const WHITELIST_SIZE: usize = 32;
+pub const LOG_SIZE: usize = 64;
+
+// persistent whitelist layout: magic, version, count, master, entries, checksum
+const MAGIC: [u8; 2] = *b"AC";
+const VERSION: u8 = 1;
+const ENTRY_SIZE: usize = 1 + MAX_UID_LEN;
+const HEADER_SIZE: usize = 4;
+
+pub const STORAGE_SIZE: usize = HEADER_SIZE + ENTRY_SIZE * (WHITELIST_SIZE + 1) + 1;
+
+/// Card UID as stored in whitelist
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CardId {
+    bytes: [u8; MAX_UID_LEN],
+    len: u8,
+}
+
+impl CardId {
+    pub fn new(uid: &[u8]) -> Option<Self> {
+        if uid.is_empty() || uid.len() > MAX_UID_LEN {
+            return None;
+        }
+
+        let mut bytes = [0u8; MAX_UID_LEN];
+        bytes[..uid.len()].copy_from_slice(uid);
+
+        Some(CardId {
+            bytes,
+            len: uid.len() as u8,
+        })
+    }
+
+    /// Parse hex UID with optional ':' separators, e.g. 04:a1:b2:c3
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut bytes = [0u8; MAX_UID_LEN];
+        let mut len = 0;
+        let mut digits = s.bytes().filter(|c| *c != b':');
+
+        loop {
+            let hi = match digits.next() {
+                Some(c) => hex_digit(c)?,
+                None => break,
+            };
+            let lo = hex_digit(digits.next()?)?;
+
+            if len == MAX_UID_LEN {
+                return None;
+            }
+
+            bytes[len] = hi << 4 | lo;
+            len += 1;
+        }
+
+        CardId::new(&bytes[..len])
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+
+    fn store(&self, buf: &mut [u8]) {
+        buf[0] = self.len;
+        buf[1..ENTRY_SIZE].copy_from_slice(&self.bytes);
+    }
+
+    fn load(buf: &[u8]) -> Option<Self> {
+        let len = buf[0] as usize;
+        if len > MAX_UID_LEN {
+            return None;
+        }
+
+        CardId::new(&buf[1..1 + len])
+    }
+}
+
+impl fmt::Display for CardId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, b) in self.as_bytes().iter().enumerate() {
+            if i > 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{:02x}", b)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |acc, b| acc.rotate_left(1) ^ b)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    /// whitelist has no free entries
+    Full,
+    /// card is already in whitelist
+    Exists,
+    /// card is not in whitelist
+    NotFound,
+}
+
+/// Whitelist of cards with optional master card used for enrollment
+#[derive(Clone)]
+pub struct Whitelist {
+    entries: [CardId; WHITELIST_SIZE],
+    count: usize,
+    master: Option<CardId>,
+}
+
+impl Default for Whitelist {
+    fn default() -> Self {
+        Whitelist {
+            entries: [CardId::default(); WHITELIST_SIZE],
+            count: 0,
+            master: None,
+        }
+    }
+}
+
+impl Whitelist {
+    pub fn contains(&self, id: &CardId) -> bool {
+        self.iter().any(|e| e == id)
+    }
+
+    pub fn add(&mut self, id: CardId) -> Result<(), Error> {
+        if self.contains(&id) {
+            return Err(Error::Exists);
+        }
+
+        if self.count == WHITELIST_SIZE {
+            return Err(Error::Full);
+        }
+
+        self.entries[self.count] = id;
+        self.count += 1;
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &CardId) -> Result<(), Error> {
+        let pos = self.iter().position(|e| e == id).ok_or(Error::NotFound)?;
+
+        self.entries.copy_within(pos + 1..self.count, pos);
+        self.count -= 1;
+
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CardId> {
+        self.entries[..self.count].iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn master(&self) -> Option<&CardId> {
+        self.master.as_ref()
+    }
+
+    pub fn set_master(&mut self, id: Option<CardId>) {
+        self.master = id;
+    }
+
+    pub fn serialize(&self, buf: &mut [u8; STORAGE_SIZE]) {
+        *buf = [0u8; STORAGE_SIZE];
+
+        buf[0..2].copy_from_slice(&MAGIC);
+        buf[2] = VERSION;
+        buf[3] = self.count as u8;
+
+        if let Some(m) = self.master {
+            m.store(&mut buf[HEADER_SIZE..HEADER_SIZE + ENTRY_SIZE]);
+        }
+
+        for (n, e) in self.iter().enumerate() {
+            let pos = HEADER_SIZE + ENTRY_SIZE * (n + 1);
+            e.store(&mut buf[pos..pos + ENTRY_SIZE]);
+        }
+
+        buf[STORAGE_SIZE - 1] = checksum(&buf[..STORAGE_SIZE - 1]);
+    }
+
+    /// Returns None for blank or corrupted storage
+    pub fn deserialize(buf: &[u8; STORAGE_SIZE]) -> Option<Self> {
+        if buf[0..2] != MAGIC || buf[2] != VERSION {
+            return None;
+        }
+
+        if buf[STORAGE_SIZE - 1] != checksum(&buf[..STORAGE_SIZE - 1]) {
+            return None;
+        }
+
+        let count = buf[3] as usize;
+        if count > WHITELIST_SIZE {
+            return None;
+        }
+
+        let mut list = Whitelist::default();
+
+        if buf[HEADER_SIZE] != 0 {
+            list.master = Some(CardId::load(&buf[HEADER_SIZE..HEADER_SIZE + ENTRY_SIZE])?);
+        }
+
+        for n in 0..count {
+            let pos = HEADER_SIZE + ENTRY_SIZE * (n + 1);
+            list.entries[n] = CardId::load(&buf[pos..pos + ENTRY_SIZE])?;
+        }
+
+        list.count = count;
+
+        Some(list)
+    }
+}
+
+/// Page oriented persistent storage for the whitelist
+pub trait Storage {
+    type Error;
+
+    /// Writes must not cross page boundaries
+    const PAGE_SIZE: usize;
+
+    fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    fn write_page(&mut self, addr: u32, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// AT24C04 and other 24x EEPROMs with 16 byte pages
+impl<I2C, E> Storage for Eeprom24x<I2C, page_size::B16, addr_size::OneByte>
+where
+    I2C: i2c::Write<Error = E> + i2c::WriteRead<Error = E>,
+{
+    type Error = eeprom24x::Error<E>;
+
+    const PAGE_SIZE: usize = 16;
+
+    fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.read_data(addr, buf)
+    }
+
+    fn write_page(&mut self, addr: u32, data: &[u8]) -> Result<(), Self::Error> {
+        Eeprom24x::write_page(self, addr, data)
+    }
+}
+
+/// Load whitelist from the start of storage: None for blank or corrupted storage
+pub fn load_whitelist<S: Storage>(storage: &mut S) -> Result<Option<Whitelist>, S::Error> {
+    let mut buf = [0u8; STORAGE_SIZE];
+    storage.read(0, &mut buf)?;
+
+    Ok(Whitelist::deserialize(&buf))
+}
+
+/// Save whitelist page by page: wait is called after every page write
+/// to let EEPROM finish its internal write cycle
+pub fn save_whitelist<S: Storage>(
+    storage: &mut S,
+    list: &Whitelist,
+    mut wait: impl FnMut(),
+) -> Result<(), S::Error> {
+    let mut writer = WhitelistWriter::new(list);
+
+    while !writer.done() {
+        writer.write_next(storage)?;
+        wait();
+    }
+
+    Ok(())
+}
+
+/// Whitelist snapshot saved one page at a time, so that the caller can
+/// do other work while EEPROM runs its internal write cycle
+#[derive(Debug, Clone)]
+pub struct WhitelistWriter {
+    buf: [u8; STORAGE_SIZE],
+    pos: usize,
+}
+
+impl WhitelistWriter {
+    pub fn new(list: &Whitelist) -> Self {
+        let mut buf = [0u8; STORAGE_SIZE];
+        list.serialize(&mut buf);
+
+        WhitelistWriter { buf, pos: 0 }
+    }
+
+    /// All pages are written
+    pub fn done(&self) -> bool {
+        self.pos == STORAGE_SIZE
+    }
+
+    /// Write the next page, nothing is written when done
+    pub fn write_next<S: Storage>(&mut self, storage: &mut S) -> Result<(), S::Error> {
+        if self.done() {
+            return Ok(());
+        }
+
+        let end = (self.pos + S::PAGE_SIZE).min(STORAGE_SIZE);
+
+        storage.write_page(self.pos as u32, &self.buf[self.pos..end])?;
+        self.pos = end;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decision {
+    Granted,
+    Denied,
+    /// master card switched controller to enrollment mode
+    EnrollStart,
+    /// card added to whitelist in enrollment mode
+    Enrolled,
+    /// card removed from whitelist in enrollment mode
+    Removed,
+    /// enrollment failed, e.g. whitelist is full
+    EnrollFailed,
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Decision::Granted => "GRANTED",
+            Decision::Denied => "DENIED",
+            Decision::EnrollStart => "ENROLL",
+            Decision::Enrolled => "ADDED",
+            Decision::Removed => "REMOVED",
+            Decision::EnrollFailed => "FAILED",
+        };
+
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Event {
+    pub id: CardId,
+    /// timestamp in ms
+    pub time: u32,
+    pub decision: Decision,
+}
+
+/// Fixed size ring buffer keeping the most recent events
+pub struct EventLog {
+    events: [Option<Event>; LOG_SIZE],
+    head: usize,
+    len: usize,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        EventLog {
+            events: [None; LOG_SIZE],
+            head: 0,
+            len: 0,
+        }
+    }
+}
+
+impl EventLog {
+    pub fn push(&mut self, event: Event) {
+        self.events[self.head] = Some(event);
+        self.head = (self.head + 1) % LOG_SIZE;
+        self.len = (self.len + 1).min(LOG_SIZE);
+    }
+
+    /// Iterate from the oldest to the newest event
+    pub fn iter(&self) -> impl Iterator<Item = &Event> {
+        let start = (self.head + LOG_SIZE - self.len) % LOG_SIZE;
+        (0..self.len).filter_map(move |n| self.events[(start + n) % LOG_SIZE].as_ref())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// repeated reads of the same card within this window are ignored
+    pub debounce_ms: u32,
+    /// relay on time after access is granted
+    pub unlock_ms: u32,
+    /// enrollment mode is left after this timeout
+    pub enroll_ms: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            debounce_ms: 2_000,
+            unlock_ms: 3_000,
+            enroll_ms: 10_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Normal,
+    Enroll(u32),
+}
+
+/// Access controller: all times are ms timestamps from a wrapping u32 clock
+pub struct AccessControl {
+    pub whitelist: Whitelist,
+    pub log: EventLog,
+    config: Config,
+    mode: Mode,
+    last: Option<(CardId, u32)>,
+    unlock: Option<u32>,
+    dirty: bool,
+}
+
+impl AccessControl {
+    pub fn new(whitelist: Whitelist, config: Config) -> Self {
+        AccessControl {
+            whitelist,
+            log: EventLog::default(),
+            config,
+            mode: Mode::Normal,
+            last: None,
+            unlock: None,
+            dirty: false,
+        }
+    }
+
+    /// Handle card read: returns None for debounced repeated reads
+    pub fn on_card(&mut self, id: CardId, now: u32) -> Option<Decision> {
+        if let Some((last, time)) = self.last {
+            if last == id && now.wrapping_sub(time) < self.config.debounce_ms {
+                self.last = Some((id, now));
+                return None;
+            }
+        }
+
+        self.last = Some((id, now));
+
+        let is_master = self.whitelist.master() == Some(&id);
+
+        let decision = match self.mode {
+            Mode::Normal if is_master => {
+                self.mode = Mode::Enroll(now);
+                Decision::EnrollStart
+            }
+            Mode::Normal => {
+                if self.whitelist.contains(&id) {
+                    self.unlock = Some(now);
+                    Decision::Granted
+                } else {
+                    Decision::Denied
+                }
+            }
+            Mode::Enroll(_) if is_master => {
+                self.mode = Mode::Normal;
+                return None;
+            }
+            Mode::Enroll(_) => {
+                self.mode = Mode::Normal;
+                self.toggle(id)
+            }
+        };
+
+        self.log.push(Event {
+            id,
+            time: now,
+            decision,
+        });
+
+        Some(decision)
+    }
+
+    /// Enter enrollment mode without master card, e.g. from serial console
+    pub fn start_enroll(&mut self, now: u32) {
+        self.mode = Mode::Enroll(now);
+    }
+
+    pub fn enrolling(&self) -> bool {
+        self.mode != Mode::Normal
+    }
+
+    /// Expire timeouts: returns relay state
+    pub fn poll(&mut self, now: u32) -> bool {
+        if let Mode::Enroll(start) = self.mode {
+            if now.wrapping_sub(start) >= self.config.enroll_ms {
+                self.mode = Mode::Normal;
+            }
+        }
+
+        if let Some(start) = self.unlock {
+            if now.wrapping_sub(start) >= self.config.unlock_ms {
+                self.unlock = None;
+            }
+        }
+
+        self.unlock.is_some()
+    }
+
+    pub fn add(&mut self, id: CardId) -> Result<(), Error> {
+        self.whitelist.add(id)?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &CardId) -> Result<(), Error> {
+        self.whitelist.remove(id)?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub fn set_master(&mut self, id: Option<CardId>) {
+        self.whitelist.set_master(id);
+        self.dirty = true;
+    }
+
+    /// Whitelist changed and has to be saved
+    pub fn dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Whitelist changed since the last call and has to be saved
+    pub fn take_dirty(&mut self) -> bool {
+        core::mem::replace(&mut self.dirty, false)
+    }
+
+    fn toggle(&mut self, id: CardId) -> Decision {
+        if self.whitelist.contains(&id) {
+            self.remove(&id).ok();
+            Decision::Removed
+        } else if self.add(id).is_ok() {
+            Decision::Enrolled
+        } else {
+            Decision::EnrollFailed
+        }
+    }
+}
+
+/// Serial console commands
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Help,
+    List,
+    Add(CardId),
+    Del(CardId),
+    Master(CardId),
+    Enroll,
+    Log,
+    ClearLog,
+}
+
+pub fn parse_command(line: &str) -> Option<Command> {
+    let mut words = line.split_whitespace();
+    let cmd = words.next()?;
+    let arg = words.next();
+
+    if words.next().is_some() {
+        return None;
+    }
+
+    match (cmd, arg) {
+        ("help", None) => Some(Command::Help),
+        ("list", None) => Some(Command::List),
+        ("add", Some(uid)) => CardId::parse(uid).map(Command::Add),
+        ("del", Some(uid)) => CardId::parse(uid).map(Command::Del),
+        ("master", Some(uid)) => CardId::parse(uid).map(Command::Master),
+        ("enroll", None) => Some(Command::Enroll),
+        ("log", None) => Some(Command::Log),
+        ("clear", None) => Some(Command::ClearLog),
+        _ => None,
+    }
+}
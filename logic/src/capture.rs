@@ -0,0 +1,149 @@
+//
+// PWM input measurement: averaging and prescaler auto-ranging
+//
+
+// counter range used by auto-ranging
+const MIN_COUNTS: u64 = 8192;
+const MAX_COUNTS: u64 = 0xffff;
+const TARGET_COUNTS: u64 = 0xc000;
+
+pub const AVERAGE_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+    /// no signal is reported after this time without edges
+    pub timeout_ms: u32,
+    /// number of measurements in moving average: 1..=AVERAGE_SIZE
+    pub average: usize,
+    /// input filter: IC1F/IC2F value 0..15
+    pub filter: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            timeout_ms: 2000,
+            average: 4,
+            filter: 0,
+        }
+    }
+}
+
+/// Single period measurement in timer input clock ticks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    pub period: u64,
+    pub high: u64,
+    pub clk: u32,
+}
+
+impl Measurement {
+    /// Frequency in mHz
+    pub fn frequency_mhz(&self) -> u64 {
+        if self.period == 0 {
+            return 0;
+        }
+
+        self.clk as u64 * 1000 / self.period
+    }
+
+    pub fn frequency_hz(&self) -> u32 {
+        (self.frequency_mhz() / 1000) as u32
+    }
+
+    pub fn period_us(&self) -> u64 {
+        self.period * 1_000_000 / self.clk as u64
+    }
+
+    pub fn high_us(&self) -> u64 {
+        self.high * 1_000_000 / self.clk as u64
+    }
+
+    /// Duty cycle in 0.1% units
+    pub fn duty_permille(&self) -> u16 {
+        if self.period == 0 {
+            return 0;
+        }
+
+        (self.high.min(self.period) * 1000 / self.period) as u16
+    }
+
+    /// Revolutions per minute for tachometer giving n pulses per revolution
+    pub fn rpm(&self, pulses: u32) -> u32 {
+        (self.frequency_mhz() * 60 / 1000 / pulses.max(1) as u64) as u32
+    }
+}
+
+/// Moving average of measurements
+#[derive(Debug, Clone, Copy)]
+pub struct Average {
+    items: [(u64, u64); AVERAGE_SIZE],
+    size: usize,
+    pos: usize,
+    len: usize,
+}
+
+impl Average {
+    pub fn new(size: usize) -> Self {
+        Average {
+            items: [(0, 0); AVERAGE_SIZE],
+            size: size.max(1).min(AVERAGE_SIZE),
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, m: &Measurement) {
+        self.items[self.pos] = (m.period, m.high);
+        self.pos = (self.pos + 1) % self.size;
+        self.len = (self.len + 1).min(self.size);
+    }
+
+    pub fn clear(&mut self) {
+        self.pos = 0;
+        self.len = 0;
+    }
+
+    pub fn get(&self, clk: u32) -> Option<Measurement> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let (period, high) = self.items[..self.len]
+            .iter()
+            .fold((0, 0), |(p, h), (ip, ih)| (p + ip, h + ih));
+
+        Some(Measurement {
+            period: period / self.len as u64,
+            high: high / self.len as u64,
+            clk,
+        })
+    }
+}
+
+/// Prescaler keeping period within counter range, None if current one is fine
+pub fn auto_range(period: u64, psc: u16) -> Option<u16> {
+    let counts = period / (psc as u64 + 1);
+
+    if counts <= MAX_COUNTS && (counts >= MIN_COUNTS || psc == 0) {
+        return None;
+    }
+
+    let div = (period / TARGET_COUNTS + 1).min(0x1_0000);
+    let new = (div - 1) as u16;
+
+    if new == psc {
+        None
+    } else {
+        Some(new)
+    }
+}
+
+/// Time between two edges of the input signal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    /// signal level during the interval
+    pub high: bool,
+    /// duration in us
+    pub us: u32,
+}
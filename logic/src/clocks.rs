@@ -0,0 +1,278 @@
+//
+// Clock tree decoding and validation: bus frequencies from RCC_CFGR,
+// datasheet limits, SPI and UART baud rates resulting from HAL prescalers
+//
+
+use core::fmt;
+
+pub const HSI_HZ: u32 = 8_000_000;
+pub const USB_HZ: u32 = 48_000_000;
+
+pub const SYSCLK_MAX_HZ: u32 = 72_000_000;
+pub const PCLK1_MAX_HZ: u32 = 36_000_000;
+pub const PCLK2_MAX_HZ: u32 = 72_000_000;
+pub const ADCCLK_MAX_HZ: u32 = 14_000_000;
+
+/// UART receiver tolerates ~4% total error with 16x oversampling,
+/// keep own part within 2% to leave room for the other side
+pub const UART_MAX_ERROR_PERMILLE: u32 = 20;
+
+// RCC_CFGR fields
+const CFGR_SWS_SHIFT: u32 = 2;
+const CFGR_HPRE_SHIFT: u32 = 4;
+const CFGR_PPRE1_SHIFT: u32 = 8;
+const CFGR_PPRE2_SHIFT: u32 = 11;
+const CFGR_ADCPRE_SHIFT: u32 = 14;
+const CFGR_PLLSRC: u32 = 1 << 16;
+const CFGR_PLLXTPRE: u32 = 1 << 17;
+const CFGR_PLLMUL_SHIFT: u32 = 18;
+const CFGR_USBPRE: u32 = 1 << 22;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Domain {
+    Sysclk,
+    Hclk,
+    Pclk1,
+    Pclk2,
+    Adc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    /// clock exceeds its max frequency
+    TooFast { domain: Domain, hz: u32, max: u32 },
+    /// PLL is off or its output can not be divided to 48MHz
+    NoUsbClock,
+    /// SPI prescaler result is out of requested tolerance
+    SpiBaud { requested: u32, actual: u32 },
+    /// UART baud rate error exceeds UART_MAX_ERROR_PERMILLE
+    UartBaud { requested: u32, actual: u32 },
+    /// requested baud rate is zero
+    ZeroBaud,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Hsi,
+    Hse,
+    Pll,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bus {
+    /// USART2/3, SPI2, TIM2..4
+    Apb1,
+    /// USART1, SPI1, ADC, TIM1
+    Apb2,
+}
+
+/// Frequencies of the running clock tree in Hz
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Report {
+    pub source: Source,
+    pub sysclk: u32,
+    pub hclk: u32,
+    pub pclk1: u32,
+    pub pclk2: u32,
+    pub pclk1_tim: u32,
+    pub pclk2_tim: u32,
+    pub adcclk: u32,
+    /// 0 if PLL is off
+    pub usbclk: u32,
+}
+
+impl Report {
+    /// Decode RCC_CFGR value
+    pub fn from_registers(cfgr: u32, pll_on: bool, hse_hz: u32) -> Self {
+        let field = |shift: u32, mask: u32| (cfgr >> shift) & mask;
+
+        let pll_in = if cfgr & CFGR_PLLSRC == 0 {
+            HSI_HZ / 2
+        } else if cfgr & CFGR_PLLXTPRE != 0 {
+            hse_hz / 2
+        } else {
+            hse_hz
+        };
+
+        // PLLMUL 0b0000..0b1110 is x2..x16, 0b1111 is x16 as well
+        let pll = pll_in * (field(CFGR_PLLMUL_SHIFT, 0xf) + 2).min(16);
+
+        let (source, sysclk) = match field(CFGR_SWS_SHIFT, 0b11) {
+            0b01 => (Source::Hse, hse_hz),
+            0b10 => (Source::Pll, pll),
+            _ => (Source::Hsi, HSI_HZ),
+        };
+
+        let hpre = match field(CFGR_HPRE_SHIFT, 0xf) {
+            0b1000 => 2,
+            0b1001 => 4,
+            0b1010 => 8,
+            0b1011 => 16,
+            0b1100 => 64,
+            0b1101 => 128,
+            0b1110 => 256,
+            0b1111 => 512,
+            _ => 1,
+        };
+
+        let ppre = |shift: u32| match field(shift, 0b111) {
+            0b100 => 2,
+            0b101 => 4,
+            0b110 => 8,
+            0b111 => 16,
+            _ => 1,
+        };
+
+        let hclk = sysclk / hpre;
+        let ppre1 = ppre(CFGR_PPRE1_SHIFT);
+        let ppre2 = ppre(CFGR_PPRE2_SHIFT);
+        let pclk1 = hclk / ppre1;
+        let pclk2 = hclk / ppre2;
+
+        // timers run at double PCLK when APB is divided
+        let tim = |pclk: u32, ppre: u32| if ppre == 1 { pclk } else { pclk * 2 };
+
+        let adcclk = pclk2 / (2 * (field(CFGR_ADCPRE_SHIFT, 0b11) + 1));
+
+        let usbclk = match (pll_on, cfgr & CFGR_USBPRE != 0) {
+            (false, _) => 0,
+            (true, true) => pll,
+            (true, false) => pll * 2 / 3,
+        };
+
+        Report {
+            source,
+            sysclk,
+            hclk,
+            pclk1,
+            pclk2,
+            pclk1_tim: tim(pclk1, ppre1),
+            pclk2_tim: tim(pclk2, ppre2),
+            adcclk,
+            usbclk,
+        }
+    }
+
+    pub fn pclk(&self, bus: Bus) -> u32 {
+        match bus {
+            Bus::Apb1 => self.pclk1,
+            Bus::Apb2 => self.pclk2,
+        }
+    }
+
+    /// Check clocks against datasheet limits
+    pub fn validate(&self) -> Result<(), Error> {
+        let limits = [
+            (Domain::Sysclk, self.sysclk, SYSCLK_MAX_HZ),
+            (Domain::Hclk, self.hclk, SYSCLK_MAX_HZ),
+            (Domain::Pclk1, self.pclk1, PCLK1_MAX_HZ),
+            (Domain::Pclk2, self.pclk2, PCLK2_MAX_HZ),
+            (Domain::Adc, self.adcclk, ADCCLK_MAX_HZ),
+        ];
+
+        match limits.iter().find(|(_, hz, max)| hz > max) {
+            Some(&(domain, hz, max)) => Err(Error::TooFast { domain, hz, max }),
+            None => Ok(()),
+        }
+    }
+
+    pub fn check_usb(&self) -> Result<(), Error> {
+        if self.usbclk == USB_HZ {
+            Ok(())
+        } else {
+            Err(Error::NoUsbClock)
+        }
+    }
+
+    /// Actual SPI baud rate, if within tolerance from the requested one
+    pub fn check_spi(&self, bus: Bus, baud: u32, tolerance_permille: u32) -> Result<u32, Error> {
+        if baud == 0 {
+            return Err(Error::ZeroBaud);
+        }
+
+        let actual = spi_baud(self.pclk(bus), baud);
+
+        if error_permille(baud, actual) <= tolerance_permille {
+            Ok(actual)
+        } else {
+            Err(Error::SpiBaud {
+                requested: baud,
+                actual,
+            })
+        }
+    }
+
+    /// Actual UART baud rate, if error is within UART_MAX_ERROR_PERMILLE
+    pub fn check_uart(&self, bus: Bus, baud: u32) -> Result<u32, Error> {
+        if baud == 0 {
+            return Err(Error::ZeroBaud);
+        }
+
+        let actual = uart_baud(self.pclk(bus), baud);
+
+        if error_permille(baud, actual) <= UART_MAX_ERROR_PERMILLE {
+            Ok(actual)
+        } else {
+            Err(Error::UartBaud {
+                requested: baud,
+                actual,
+            })
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "SYSCLK {} Hz from {:?}", self.sysclk, self.source)?;
+        writeln!(f, "HCLK   {} Hz", self.hclk)?;
+        writeln!(f, "PCLK1  {} Hz, timers {} Hz", self.pclk1, self.pclk1_tim)?;
+        writeln!(f, "PCLK2  {} Hz, timers {} Hz", self.pclk2, self.pclk2_tim)?;
+        writeln!(f, "ADC    {} Hz", self.adcclk)?;
+        write!(f, "USB    {} Hz", self.usbclk)
+    }
+}
+
+/// SPI baud rate set by HAL: same prescaler selection as in Spi::spiX,
+/// 0 for zero baud rate
+pub const fn spi_baud(pclk: u32, baud: u32) -> u32 {
+    if baud == 0 {
+        return 0;
+    }
+
+    let br = match pclk / baud {
+        0..=2 => 0,
+        3..=5 => 1,
+        6..=11 => 2,
+        12..=23 => 3,
+        24..=47 => 4,
+        48..=95 => 5,
+        96..=191 => 6,
+        _ => 7,
+    };
+
+    pclk >> (br + 1)
+}
+
+/// UART baud rate set by HAL: BRR is PCLK divided by baud rate,
+/// 0 for zero baud rate
+pub const fn uart_baud(pclk: u32, baud: u32) -> u32 {
+    if baud == 0 {
+        return 0;
+    }
+
+    match pclk / baud {
+        0 => 0,
+        brr => pclk / brr,
+    }
+}
+
+/// Relative error of actual rate in 0.1% units, u32::MAX for zero requested rate
+pub const fn error_permille(requested: u32, actual: u32) -> u32 {
+    if requested == 0 {
+        return u32::MAX;
+    }
+
+    let diff = actual.abs_diff(requested);
+
+    (diff as u64 * 1000 / requested as u64) as u32
+}
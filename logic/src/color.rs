@@ -0,0 +1,316 @@
+//
+// Color pipeline for WS2812 strips
+//
+// HSV/HSL conversion, gamma correction, brightness, color temperature and
+// power budget limiting. Hue, saturation and value are all scaled to 0..255.
+//
+
+use smart_leds::RGB8;
+
+// gamma 2.8 correction table
+const GAMMA8: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 5, 5, 5,
+    5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 9, 9, 9, 10, 10, 10, 11, 11, 11, 12, 12, 13, 13, 13, 14,
+    14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19, 20, 20, 21, 21, 22, 22, 23, 24, 24, 25, 25, 26, 27,
+    27, 28, 29, 29, 30, 31, 32, 32, 33, 34, 35, 35, 36, 37, 38, 39, 39, 40, 41, 42, 43, 44, 45, 46,
+    47, 48, 49, 50, 50, 51, 52, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 66, 67, 68, 69, 70, 72,
+    73, 74, 75, 77, 78, 79, 81, 82, 83, 85, 86, 87, 89, 90, 92, 93, 95, 96, 98, 99, 101, 102, 104,
+    105, 107, 109, 110, 112, 114, 115, 117, 119, 120, 122, 124, 126, 127, 129, 131, 133, 135, 137,
+    138, 140, 142, 144, 146, 148, 150, 152, 154, 156, 158, 160, 162, 164, 167, 169, 171, 173, 175,
+    177, 180, 182, 184, 186, 189, 191, 193, 196, 198, 200, 203, 205, 208, 210, 213, 215, 218, 220,
+    223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255,
+];
+
+/// Scale value by factor: 255 keeps value unchanged
+pub fn scale8(v: u8, factor: u8) -> u8 {
+    ((v as u16 * (factor as u16 + 1)) >> 8) as u8
+}
+
+pub fn scale(c: RGB8, factor: u8) -> RGB8 {
+    RGB8 {
+        r: scale8(c.r, factor),
+        g: scale8(c.g, factor),
+        b: scale8(c.b, factor),
+    }
+}
+
+/// Perceptual gamma correction of a single color
+pub fn gamma(c: RGB8) -> RGB8 {
+    RGB8 {
+        r: GAMMA8[c.r as usize],
+        g: GAMMA8[c.g as usize],
+        b: GAMMA8[c.b as usize],
+    }
+}
+
+pub fn gamma_frame(frame: &mut [RGB8]) {
+    for led in frame.iter_mut() {
+        *led = gamma(*led);
+    }
+}
+
+/// Global brightness: 255 keeps frame unchanged
+pub fn brightness(frame: &mut [RGB8], level: u8) {
+    if level == 255 {
+        return;
+    }
+
+    for led in frame.iter_mut() {
+        *led = scale(*led, level);
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Hsv {
+    pub h: u8,
+    pub s: u8,
+    pub v: u8,
+}
+
+impl Hsv {
+    pub const fn new(h: u8, s: u8, v: u8) -> Self {
+        Hsv { h, s, v }
+    }
+
+    pub fn from_rgb(c: RGB8) -> Self {
+        let max = c.r.max(c.g).max(c.b) as i32;
+        let min = c.r.min(c.g).min(c.b) as i32;
+        let delta = max - min;
+
+        if delta == 0 {
+            return Hsv::new(0, 0, max as u8);
+        }
+
+        let (r, g, b) = (c.r as i32, c.g as i32, c.b as i32);
+        let h = if max == r {
+            43 * (g - b) / delta
+        } else if max == g {
+            85 + 43 * (b - r) / delta
+        } else {
+            171 + 43 * (r - g) / delta
+        };
+
+        Hsv {
+            h: h.rem_euclid(256) as u8,
+            s: (255 * delta / max) as u8,
+            v: max as u8,
+        }
+    }
+
+    pub fn to_rgb(self) -> RGB8 {
+        if self.s == 0 {
+            return RGB8 {
+                r: self.v,
+                g: self.v,
+                b: self.v,
+            };
+        }
+
+        let (h, s, v) = (self.h as u32, self.s as u32, self.v as u32);
+
+        // six regions of 43 hue steps each
+        let region = h / 43;
+        let rem = (h - region * 43) * 6;
+
+        let p = ((v * (255 - s)) >> 8) as u8;
+        let q = ((v * (255 - ((s * rem) >> 8))) >> 8) as u8;
+        let t = ((v * (255 - ((s * (255 - rem)) >> 8))) >> 8) as u8;
+        let v = v as u8;
+
+        let (r, g, b) = match region {
+            0 => (v, t, p),
+            1 => (q, v, p),
+            2 => (p, v, t),
+            3 => (p, q, v),
+            4 => (t, p, v),
+            _ => (v, p, q),
+        };
+
+        RGB8 { r, g, b }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Hsl {
+    pub h: u8,
+    pub s: u8,
+    pub l: u8,
+}
+
+impl Hsl {
+    pub const fn new(h: u8, s: u8, l: u8) -> Self {
+        Hsl { h, s, l }
+    }
+
+    pub fn from_rgb(c: RGB8) -> Self {
+        let max = c.r.max(c.g).max(c.b) as u32;
+        let min = c.r.min(c.g).min(c.b) as u32;
+        let l = (max + min) / 2;
+
+        let s = if max == min {
+            0
+        } else if l < 128 {
+            255 * (max - min) / (max + min)
+        } else {
+            255 * (max - min) / (510 - max - min)
+        };
+
+        Hsl {
+            h: Hsv::from_rgb(c).h,
+            s: s as u8,
+            l: l as u8,
+        }
+    }
+
+    pub fn to_rgb(self) -> RGB8 {
+        self.to_hsv().to_rgb()
+    }
+
+    pub fn to_hsv(self) -> Hsv {
+        let l = self.l as u32;
+        let v = l + self.s as u32 * l.min(255 - l) / 255;
+        let s = if v == 0 { 0 } else { 2 * 255 * (v - l) / v };
+
+        Hsv {
+            h: self.h,
+            s: s.min(255) as u8,
+            v: v as u8,
+        }
+    }
+}
+
+impl From<Hsv> for RGB8 {
+    fn from(c: Hsv) -> RGB8 {
+        c.to_rgb()
+    }
+}
+
+impl From<Hsl> for RGB8 {
+    fn from(c: Hsl) -> RGB8 {
+        c.to_rgb()
+    }
+}
+
+/// Color temperature presets: per channel scale factors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Temperature {
+    /// 1900K
+    Candle,
+    /// 2600K
+    Tungsten40W,
+    /// 2850K
+    Tungsten100W,
+    /// 3200K
+    Halogen,
+    /// 5200K
+    CarbonArc,
+    /// 5400K
+    HighNoonSun,
+    /// 6000K
+    DirectSunlight,
+    /// 7000K
+    OvercastSky,
+    /// 20000K
+    ClearBlueSky,
+    /// no correction
+    Uncorrected,
+}
+
+impl Temperature {
+    pub fn factors(self) -> RGB8 {
+        let (r, g, b) = match self {
+            Temperature::Candle => (255, 147, 41),
+            Temperature::Tungsten40W => (255, 197, 143),
+            Temperature::Tungsten100W => (255, 214, 170),
+            Temperature::Halogen => (255, 241, 224),
+            Temperature::CarbonArc => (255, 250, 244),
+            Temperature::HighNoonSun => (255, 255, 251),
+            Temperature::DirectSunlight => (255, 255, 255),
+            Temperature::OvercastSky => (201, 226, 255),
+            Temperature::ClearBlueSky => (64, 156, 255),
+            Temperature::Uncorrected => (255, 255, 255),
+        };
+
+        RGB8 { r, g, b }
+    }
+
+    pub fn correct(self, c: RGB8) -> RGB8 {
+        let f = self.factors();
+
+        RGB8 {
+            r: scale8(c.r, f.r),
+            g: scale8(c.g, f.g),
+            b: scale8(c.b, f.b),
+        }
+    }
+
+    pub fn correct_frame(self, frame: &mut [RGB8]) {
+        if self == Temperature::Uncorrected {
+            return;
+        }
+
+        for led in frame.iter_mut() {
+            *led = self.correct(*led);
+        }
+    }
+}
+
+/// Power budget limiter for strips powered from USB or small supplies
+#[derive(Debug, Clone, Copy)]
+pub struct PowerLimit {
+    /// total current limit for the strip
+    pub limit_ma: u32,
+    /// current of a single color channel at full brightness
+    pub channel_ma: u32,
+    /// quiescent current of a single LED
+    pub idle_ma: u32,
+}
+
+impl PowerLimit {
+    /// Typical 5050 WS2812B: 20mA per channel, 1mA per LED when dark
+    pub const fn new(limit_ma: u32) -> Self {
+        PowerLimit {
+            limit_ma,
+            channel_ma: 20,
+            idle_ma: 1,
+        }
+    }
+
+    /// Estimated strip current in mA for the frame
+    pub fn estimate(&self, frame: &[RGB8]) -> u32 {
+        let sum: u32 = frame
+            .iter()
+            .map(|c| c.r as u32 + c.g as u32 + c.b as u32)
+            .sum();
+
+        self.idle(frame.len()) + sum * self.channel_ma / 255
+    }
+
+    /// Scale frame down to fit the limit, returns applied scale factor
+    pub fn apply(&self, frame: &mut [RGB8]) -> u8 {
+        let idle = self.idle(frame.len());
+        let total = self.estimate(frame);
+
+        if total <= self.limit_ma {
+            return 255;
+        }
+
+        // idle current can not be reduced
+        let active = total - idle;
+        if active == 0 {
+            return 0;
+        }
+
+        let budget = self.limit_ma.saturating_sub(idle);
+        let factor = (budget * 256 / active).saturating_sub(1).min(254) as u8;
+
+        brightness(frame, factor);
+
+        factor
+    }
+
+    fn idle(&self, leds: usize) -> u32 {
+        leds as u32 * self.idle_ma
+    }
+}
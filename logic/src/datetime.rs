@@ -0,0 +1,186 @@
+//
+// Calendar date and time with Unix time conversion
+//
+// Unix time is kept in unsigned 32-bit counter like in STM32F1 RTC:
+// supported range is 1970-01-01 00:00:00 .. 2106-02-07 06:28:15 UTC.
+//
+// Date conversion uses days-from-civil algorithm by Howard Hinnant.
+//
+
+use core::fmt;
+
+/// Seconds in a day
+pub const DAY_SECONDS: u32 = 86_400;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Monday,
+    Weekday::Tuesday,
+    Weekday::Wednesday,
+    Weekday::Thursday,
+    Weekday::Friday,
+    Weekday::Saturday,
+    Weekday::Sunday,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+pub fn is_leap_year(year: u16) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+pub fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+// days since 1970-01-01, month is 1..=12
+fn days_from_civil(year: u16, month: u8, day: u8) -> u32 {
+    let y = year as u32 - (month <= 2) as u32;
+    let m = month as u32;
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as u32 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146_097 + doe - 719_468
+}
+
+// year, month and day from days since 1970-01-01
+fn civil_from_days(days: u32) -> (u16, u8, u8) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + (m <= 2) as u32;
+
+    (y as u16, m as u8, d as u8)
+}
+
+impl DateTime {
+    /// Validated date and time within Unix time range
+    pub fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Option<Self> {
+        let dt = DateTime {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        };
+
+        if !(1970..=2106).contains(&year)
+            || day == 0
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 59
+        {
+            return None;
+        }
+
+        // last representable second is in February 2106
+        if year == 2106 && dt > DateTime::from_unix(u32::MAX) {
+            return None;
+        }
+
+        Some(dt)
+    }
+
+    pub fn from_unix(t: u32) -> Self {
+        let (year, month, day) = civil_from_days(t / DAY_SECONDS);
+        let secs = t % DAY_SECONDS;
+
+        DateTime {
+            year,
+            month,
+            day,
+            hour: (secs / 3600) as u8,
+            minute: (secs / 60 % 60) as u8,
+            second: (secs % 60) as u8,
+        }
+    }
+
+    pub fn to_unix(&self) -> u32 {
+        let days = days_from_civil(self.year, self.month, self.day);
+
+        days.wrapping_mul(DAY_SECONDS)
+            .wrapping_add(self.hour as u32 * 3600 + self.minute as u32 * 60 + self.second as u32)
+    }
+
+    pub fn weekday(&self) -> Weekday {
+        // 1970-01-01 was Thursday
+        let days = days_from_civil(self.year, self.month, self.day);
+
+        WEEKDAYS[((days + 3) % 7) as usize]
+    }
+
+    /// Parse "YYYY-MM-DD HH:MM:SS", 'T' is accepted as separator as well
+    pub fn parse(s: &str) -> Option<Self> {
+        let b = s.trim().as_bytes();
+        if b.len() != 19 {
+            return None;
+        }
+
+        let seps = [(4, b'-'), (7, b'-'), (13, b':'), (16, b':')];
+        if seps.iter().any(|(i, c)| b[*i] != *c) || (b[10] != b' ' && b[10] != b'T') {
+            return None;
+        }
+
+        let num = |from: usize, to: usize| -> Option<u16> {
+            b[from..to].iter().try_fold(0u16, |v, c| {
+                if c.is_ascii_digit() {
+                    Some(v * 10 + (c - b'0') as u16)
+                } else {
+                    None
+                }
+            })
+        };
+
+        DateTime::new(
+            num(0, 4)?,
+            num(5, 7)? as u8,
+            num(8, 10)? as u8,
+            num(11, 13)? as u8,
+            num(14, 16)? as u8,
+            num(17, 19)? as u8,
+        )
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
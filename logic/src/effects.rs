@@ -0,0 +1,448 @@
+//
+// Animation effects for WS2812 strips
+//
+// Effects render complete frames into RGB8 buffers given the time in ms since
+// animation start, so they can be driven by any periodic timer.
+//
+
+use crate::color::{self, scale, scale8};
+use smart_leds::RGB8;
+
+// maximum strip length for effects keeping per-LED state
+pub const MAX_LEDS: usize = 256;
+
+pub trait Effect {
+    fn render(&mut self, frame: &mut [RGB8], time: u32);
+}
+
+pub fn add(a: RGB8, b: RGB8) -> RGB8 {
+    RGB8 {
+        r: a.r.saturating_add(b.r),
+        g: a.g.saturating_add(b.g),
+        b: a.b.saturating_add(b.b),
+    }
+}
+
+/// Color wheel: red -> green -> blue -> red for positions 0..255
+pub fn wheel(pos: u8) -> RGB8 {
+    let p = pos as u16 * 3;
+
+    match pos {
+        0..=84 => RGB8 {
+            r: (255 - p) as u8,
+            g: p as u8,
+            b: 0,
+        },
+        85..=169 => RGB8 {
+            r: 0,
+            g: (255 - (p - 255)) as u8,
+            b: (p - 255) as u8,
+        },
+        _ => RGB8 {
+            r: (p - 510) as u8,
+            g: 0,
+            b: (255 - (p - 510)) as u8,
+        },
+    }
+}
+
+/// Sine approximation: full period over 0..255, result in 0..255
+pub fn sin8(x: u8) -> u8 {
+    // parabola over each half period
+    let half = (x & 0x7f) as u32;
+    let y = (half * (128 - half) * 255) / (64 * 64);
+    let y = y.min(255) as u8 / 2;
+
+    if x < 128 {
+        128 + y
+    } else {
+        127 - y
+    }
+}
+
+/// Xorshift PRNG: deterministic for a given seed
+#[derive(Debug, Clone, Copy)]
+pub struct Rng(u32);
+
+impl Rng {
+    pub fn new(seed: u32) -> Self {
+        Rng(if seed == 0 { 0x2545_f491 } else { seed })
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        (self.next_u32() >> 24) as u8
+    }
+
+    /// Random number in 0..n
+    pub fn below(&mut self, n: u32) -> u32 {
+        if n == 0 {
+            0
+        } else {
+            self.next_u32() % n
+        }
+    }
+}
+
+fn clear(frame: &mut [RGB8]) {
+    for led in frame.iter_mut() {
+        *led = RGB8::default();
+    }
+}
+
+// position of moving object in steps for speed given in steps per second
+fn steps(time: u32, speed: u32) -> u32 {
+    (time as u64 * speed as u64 / 1000) as u32
+}
+
+/// Moving rainbow
+#[derive(Debug, Clone, Copy)]
+pub struct Rainbow {
+    /// wheel positions per second
+    pub speed: u32,
+    /// wheel positions between neighbour LEDs
+    pub spread: u8,
+}
+
+impl Effect for Rainbow {
+    fn render(&mut self, frame: &mut [RGB8], time: u32) {
+        let start = steps(time, self.speed) as u8;
+
+        for (i, led) in frame.iter_mut().enumerate() {
+            *led = wheel(start.wrapping_add((i as u8).wrapping_mul(self.spread)));
+        }
+    }
+}
+
+/// Theater chase: every n-th LED lit, pattern moves along the strip
+#[derive(Debug, Clone, Copy)]
+pub struct Chase {
+    pub color: RGB8,
+    pub spacing: usize,
+    /// LEDs per second
+    pub speed: u32,
+}
+
+impl Effect for Chase {
+    fn render(&mut self, frame: &mut [RGB8], time: u32) {
+        let spacing = self.spacing.max(1);
+        let offset = steps(time, self.speed) as usize % spacing;
+
+        for (i, led) in frame.iter_mut().enumerate() {
+            *led = if (i + spacing - offset) % spacing == 0 {
+                self.color
+            } else {
+                RGB8::default()
+            };
+        }
+    }
+}
+
+/// Strip is filled with color LED by LED and then cleared the same way
+#[derive(Debug, Clone, Copy)]
+pub struct ColorWipe {
+    pub color: RGB8,
+    /// LEDs per second
+    pub speed: u32,
+}
+
+impl Effect for ColorWipe {
+    fn render(&mut self, frame: &mut [RGB8], time: u32) {
+        let n = frame.len();
+        if n == 0 {
+            return;
+        }
+
+        let pos = steps(time, self.speed) as usize % (2 * n);
+
+        for (i, led) in frame.iter_mut().enumerate() {
+            let lit = if pos < n { i < pos } else { i >= pos - n };
+            *led = if lit { self.color } else { RGB8::default() };
+        }
+    }
+}
+
+/// Whole strip fades in and out
+#[derive(Debug, Clone, Copy)]
+pub struct Breathing {
+    pub color: RGB8,
+    pub period_ms: u32,
+}
+
+impl Effect for Breathing {
+    fn render(&mut self, frame: &mut [RGB8], time: u32) {
+        let period = self.period_ms.max(1);
+        let phase = ((time % period) as u64 * 256 / period as u64) as u8;
+
+        // start from dark: shift sine by quarter period
+        let level = sin8(phase.wrapping_sub(64));
+        let level = scale8(level, level);
+        let color = scale(self.color, level);
+
+        for led in frame.iter_mut() {
+            *led = color;
+        }
+    }
+}
+
+/// Bright head with fading tail running along the strip
+#[derive(Debug, Clone, Copy)]
+pub struct Comet {
+    pub color: RGB8,
+    pub tail: usize,
+    /// LEDs per second
+    pub speed: u32,
+}
+
+impl Effect for Comet {
+    fn render(&mut self, frame: &mut [RGB8], time: u32) {
+        let n = frame.len();
+        let tail = self.tail.max(1);
+        let head = steps(time, self.speed) as usize % (n + tail);
+
+        for (i, led) in frame.iter_mut().enumerate() {
+            *led = if i <= head && head - i < tail {
+                let level = 255 * (tail - (head - i)) / tail;
+                scale(self.color, level as u8)
+            } else {
+                RGB8::default()
+            };
+        }
+    }
+}
+
+/// Random LEDs light up and slowly fade out
+#[derive(Debug, Clone)]
+pub struct Twinkle {
+    pub color: RGB8,
+    /// chance of new twinkle per frame: 0..255
+    pub density: u8,
+    /// brightness decrease per frame
+    pub fade: u8,
+    levels: [u8; MAX_LEDS],
+    rng: Rng,
+}
+
+impl Twinkle {
+    pub fn new(color: RGB8, density: u8, fade: u8, seed: u32) -> Self {
+        Twinkle {
+            color,
+            density,
+            fade,
+            levels: [0; MAX_LEDS],
+            rng: Rng::new(seed),
+        }
+    }
+}
+
+impl Effect for Twinkle {
+    fn render(&mut self, frame: &mut [RGB8], _time: u32) {
+        let n = frame.len().min(MAX_LEDS);
+
+        for level in self.levels[..n].iter_mut() {
+            *level = level.saturating_sub(self.fade);
+        }
+
+        if n > 0 && self.rng.next_u8() < self.density {
+            let i = self.rng.below(n as u32) as usize;
+            self.levels[i] = 255;
+        }
+
+        clear(frame);
+
+        for (led, level) in frame.iter_mut().zip(self.levels[..n].iter()) {
+            *led = scale(self.color, *level);
+        }
+    }
+}
+
+/// Fire simulation: heat rises from the first LED and cools down
+#[derive(Debug, Clone)]
+pub struct Fire {
+    /// cooling per frame: higher values give shorter flames
+    pub cooling: u8,
+    /// chance of new spark per frame: 0..255
+    pub sparking: u8,
+    heat: [u8; MAX_LEDS],
+    rng: Rng,
+}
+
+impl Fire {
+    pub fn new(cooling: u8, sparking: u8, seed: u32) -> Self {
+        Fire {
+            cooling,
+            sparking,
+            heat: [0; MAX_LEDS],
+            rng: Rng::new(seed),
+        }
+    }
+}
+
+/// Black body palette: black -> red -> yellow -> white
+pub fn heat_color(heat: u8) -> RGB8 {
+    // scale 0..255 to 0..191 and split into three ranges
+    let t = scale8(heat, 191);
+    let ramp = (t & 0x3f) << 2;
+
+    match t {
+        0..=63 => RGB8 {
+            r: ramp,
+            g: 0,
+            b: 0,
+        },
+        64..=127 => RGB8 {
+            r: 255,
+            g: ramp,
+            b: 0,
+        },
+        _ => RGB8 {
+            r: 255,
+            g: 255,
+            b: ramp,
+        },
+    }
+}
+
+impl Effect for Fire {
+    fn render(&mut self, frame: &mut [RGB8], _time: u32) {
+        let n = frame.len().min(MAX_LEDS);
+        if n == 0 {
+            return;
+        }
+
+        let heat = &mut self.heat[..n];
+        let max_cool = (self.cooling as u32 * 10 / n as u32 + 2).min(255);
+
+        // cool down every cell
+        for h in heat.iter_mut() {
+            *h = h.saturating_sub(self.rng.below(max_cool + 1) as u8);
+        }
+
+        // heat drifts up and diffuses
+        for k in (2..n).rev() {
+            heat[k] = ((heat[k - 1] as u16 + 2 * heat[k - 2] as u16) / 3) as u8;
+        }
+
+        // new sparks near the bottom
+        if self.rng.next_u8() < self.sparking {
+            let y = self.rng.below(n.min(7) as u32) as usize;
+            heat[y] = heat[y].saturating_add(160 + self.rng.below(96) as u8);
+        }
+
+        for (led, h) in frame.iter_mut().zip(heat.iter()) {
+            *led = heat_color(*h);
+        }
+    }
+}
+
+/// Additive composition of two effects
+#[derive(Debug, Clone)]
+pub struct Layers<A, B> {
+    pub bottom: A,
+    pub top: B,
+    scratch: [RGB8; MAX_LEDS],
+}
+
+impl<A: Effect, B: Effect> Layers<A, B> {
+    pub fn new(bottom: A, top: B) -> Self {
+        Layers {
+            bottom,
+            top,
+            scratch: [RGB8::default(); MAX_LEDS],
+        }
+    }
+}
+
+impl<A: Effect, B: Effect> Effect for Layers<A, B> {
+    fn render(&mut self, frame: &mut [RGB8], time: u32) {
+        let n = frame.len().min(MAX_LEDS);
+
+        self.bottom.render(frame, time);
+        self.top.render(&mut self.scratch[..n], time);
+
+        for (led, top) in frame.iter_mut().zip(self.scratch[..n].iter()) {
+            *led = add(*led, *top);
+        }
+    }
+}
+
+/// Effect rendered in reverse direction
+#[derive(Debug, Clone)]
+pub struct Reverse<E>(pub E);
+
+impl<E: Effect> Effect for Reverse<E> {
+    fn render(&mut self, frame: &mut [RGB8], time: u32) {
+        self.0.render(frame, time);
+        frame.reverse();
+    }
+}
+
+/// All effects in one type, so effect can be switched at runtime
+#[derive(Debug, Clone)]
+pub enum Effects {
+    Rainbow(Rainbow),
+    Chase(Chase),
+    ColorWipe(ColorWipe),
+    Breathing(Breathing),
+    Comet(Comet),
+    Twinkle(Twinkle),
+    Fire(Fire),
+}
+
+impl Effect for Effects {
+    fn render(&mut self, frame: &mut [RGB8], time: u32) {
+        match self {
+            Effects::Rainbow(e) => e.render(frame, time),
+            Effects::Chase(e) => e.render(frame, time),
+            Effects::ColorWipe(e) => e.render(frame, time),
+            Effects::Breathing(e) => e.render(frame, time),
+            Effects::Comet(e) => e.render(frame, time),
+            Effects::Twinkle(e) => e.render(frame, time),
+            Effects::Fire(e) => e.render(frame, time),
+        }
+    }
+}
+
+/// Frame timing and global brightness on top of an effect
+pub struct Animation<E> {
+    pub effect: E,
+    /// global brightness applied after effect rendering
+    pub brightness: u8,
+    time: u32,
+}
+
+impl<E: Effect> Animation<E> {
+    pub fn new(effect: E) -> Self {
+        Animation {
+            effect,
+            brightness: 255,
+            time: 0,
+        }
+    }
+
+    /// Change effect and restart animation time
+    pub fn set_effect(&mut self, effect: E) {
+        self.effect = effect;
+        self.time = 0;
+    }
+
+    /// Advance animation by dt ms and render next frame
+    pub fn next_frame(&mut self, frame: &mut [RGB8], dt: u32) {
+        self.time = self.time.wrapping_add(dt);
+        self.effect.render(frame, self.time);
+
+        color::brightness(frame, self.brightness);
+    }
+
+    pub fn time(&self) -> u32 {
+        self.time
+    }
+}
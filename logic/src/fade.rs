@@ -0,0 +1,345 @@
+//
+// LED fading engine: ramps, breathing and blink patterns
+//
+// Fader computes brightness level for the current time step, the level is
+// then applied to a hardware PWM channel or to a software PWM output on any
+// GPIO. Time is given in ms increments from a periodic timer interrupt.
+//
+
+use core::convert::Infallible;
+use embedded_hal::digital::v2::OutputPin;
+use embedded_hal::PwmPin;
+
+pub const MAX_LEVEL: u8 = 255;
+
+/// Mapping of brightness level to PWM duty
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ramp {
+    Linear,
+    /// cubic curve: perceptually even steps
+    Gamma,
+}
+
+impl Ramp {
+    /// Duty cycle for the level scaled to 0..=max
+    pub fn duty(self, level: u8, max: u16) -> u16 {
+        let l = level as u64;
+        let m = MAX_LEVEL as u64;
+
+        let d = match self {
+            Ramp::Linear => max as u64 * l / m,
+            Ramp::Gamma => max as u64 * l * l * l / (m * m * m),
+        };
+
+        d as u16
+    }
+}
+
+/// Pattern step: hold or fade to the level for the given time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step {
+    pub level: u8,
+    pub ms: u16,
+    pub fade: bool,
+}
+
+impl Step {
+    pub const fn hold(level: u8, ms: u16) -> Self {
+        Step {
+            level,
+            ms,
+            fade: false,
+        }
+    }
+
+    pub const fn fade(level: u8, ms: u16) -> Self {
+        Step {
+            level,
+            ms,
+            fade: true,
+        }
+    }
+}
+
+pub const BLINK_SLOW: &[Step] = &[Step::hold(MAX_LEVEL, 1000), Step::hold(0, 1000)];
+pub const BLINK_FAST: &[Step] = &[Step::hold(MAX_LEVEL, 100), Step::hold(0, 100)];
+pub const HEARTBEAT: &[Step] = &[
+    Step::hold(MAX_LEVEL, 80),
+    Step::hold(0, 120),
+    Step::hold(MAX_LEVEL, 80),
+    Step::hold(0, 720),
+];
+pub const SOS: &[Step] = &[
+    Step::hold(MAX_LEVEL, 150),
+    Step::hold(0, 150),
+    Step::hold(MAX_LEVEL, 150),
+    Step::hold(0, 150),
+    Step::hold(MAX_LEVEL, 150),
+    Step::hold(0, 450),
+    Step::hold(MAX_LEVEL, 450),
+    Step::hold(0, 150),
+    Step::hold(MAX_LEVEL, 450),
+    Step::hold(0, 150),
+    Step::hold(MAX_LEVEL, 450),
+    Step::hold(0, 450),
+    Step::hold(MAX_LEVEL, 150),
+    Step::hold(0, 150),
+    Step::hold(MAX_LEVEL, 150),
+    Step::hold(0, 150),
+    Step::hold(MAX_LEVEL, 150),
+    Step::hold(0, 1500),
+];
+
+// error blink codes timing
+const CODE_ON_MS: u32 = 200;
+const CODE_OFF_MS: u32 = 300;
+const CODE_PAUSE_MS: u32 = 1500;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Fixed,
+    Fade {
+        from: u8,
+        to: u8,
+        ms: u32,
+    },
+    Breathe {
+        period: u32,
+    },
+    Pattern {
+        steps: &'static [Step],
+        index: usize,
+        from: u8,
+    },
+    Code {
+        count: u8,
+    },
+}
+
+/// Brightness state machine for a single LED
+#[derive(Debug, Clone, Copy)]
+pub struct Fader {
+    mode: Mode,
+    level: u8,
+    elapsed: u32,
+    pub ramp: Ramp,
+}
+
+impl Fader {
+    pub const fn new(ramp: Ramp) -> Self {
+        Fader {
+            mode: Mode::Fixed,
+            level: 0,
+            elapsed: 0,
+            ramp,
+        }
+    }
+
+    pub fn set(&mut self, level: u8) {
+        self.start(Mode::Fixed);
+        self.level = level;
+    }
+
+    /// Fade from current level to target level in ms
+    pub fn fade_to(&mut self, level: u8, ms: u32) {
+        self.start(Mode::Fade {
+            from: self.level,
+            to: level,
+            ms,
+        });
+    }
+
+    /// Continuous fade in and out with the given period
+    pub fn breathe(&mut self, period: u32) {
+        self.start(Mode::Breathe { period });
+    }
+
+    /// Repeat sequence of steps
+    pub fn pattern(&mut self, steps: &'static [Step]) {
+        self.start(Mode::Pattern {
+            steps,
+            index: 0,
+            from: self.level,
+        });
+    }
+
+    /// Repeat count short blinks followed by a pause
+    pub fn blink_code(&mut self, count: u8) {
+        self.start(Mode::Code { count });
+    }
+
+    /// Fading or pattern is in progress
+    pub fn active(&self) -> bool {
+        match self.mode {
+            Mode::Fixed => false,
+            Mode::Fade { ms, .. } => self.elapsed < ms,
+            _ => true,
+        }
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// PWM duty for current level
+    pub fn duty(&self, max: u16) -> u16 {
+        self.ramp.duty(self.level, max)
+    }
+
+    /// Advance by dt ms and return new level
+    pub fn tick(&mut self, dt: u32) -> u8 {
+        self.elapsed = self.elapsed.saturating_add(dt);
+
+        self.level = match self.mode {
+            Mode::Fixed => self.level,
+            Mode::Fade { from, to, ms } => interpolate(from, to, self.elapsed, ms),
+            Mode::Breathe { period } => {
+                let period = period.max(2);
+                let t = self.elapsed % period;
+                let half = period / 2;
+
+                if t < half {
+                    interpolate(0, MAX_LEVEL, t, half)
+                } else {
+                    interpolate(MAX_LEVEL, 0, t - half, period - half)
+                }
+            }
+            Mode::Pattern { steps, index, from } => self.step(steps, index, from),
+            Mode::Code { count } => {
+                let blinks = count as u32 * (CODE_ON_MS + CODE_OFF_MS);
+                let t = self.elapsed % (blinks + CODE_PAUSE_MS);
+
+                if t < blinks && t % (CODE_ON_MS + CODE_OFF_MS) < CODE_ON_MS {
+                    MAX_LEVEL
+                } else {
+                    0
+                }
+            }
+        };
+
+        self.level
+    }
+
+    /// Apply current level to hardware PWM channel
+    pub fn apply<P>(&self, pwm: &mut P)
+    where
+        P: PwmPin<Duty = u16>,
+    {
+        pwm.set_duty(self.duty(pwm.get_max_duty()));
+    }
+
+    fn step(&mut self, steps: &'static [Step], mut index: usize, mut from: u8) -> u8 {
+        if steps.iter().all(|s| s.ms == 0) {
+            return steps.first().map_or(self.level, |s| s.level);
+        }
+
+        // skip all steps that are already over
+        while self.elapsed >= steps[index].ms as u32 {
+            self.elapsed -= steps[index].ms as u32;
+            from = steps[index].level;
+            index = (index + 1) % steps.len();
+        }
+
+        self.mode = Mode::Pattern { steps, index, from };
+
+        let s = steps[index];
+        if s.fade {
+            interpolate(from, s.level, self.elapsed, s.ms as u32)
+        } else {
+            s.level
+        }
+    }
+
+    fn start(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.elapsed = 0;
+    }
+}
+
+fn interpolate(from: u8, to: u8, t: u32, duration: u32) -> u8 {
+    if t >= duration {
+        return to;
+    }
+
+    let from = from as i64;
+    let to = to as i64;
+
+    (from + (to - from) * t as i64 / duration as i64) as u8
+}
+
+/// Software PWM on a GPIO pin
+///
+/// Call `tick` from a fast periodic timer: PWM period is `steps` ticks.
+pub struct SoftPwm<P> {
+    pin: P,
+    duty: u16,
+    steps: u16,
+    counter: u16,
+    inverted: bool,
+}
+
+impl<P> SoftPwm<P>
+where
+    P: OutputPin<Error = Infallible>,
+{
+    /// Inverted output is for active low LEDs like PC13
+    pub fn new(pin: P, steps: u16, inverted: bool) -> Self {
+        let mut pwm = SoftPwm {
+            pin,
+            duty: 0,
+            steps: steps.max(1),
+            counter: 0,
+            inverted,
+        };
+
+        pwm.output(false);
+        pwm
+    }
+
+    pub fn tick(&mut self) {
+        let on = self.counter < self.duty;
+        self.output(on);
+
+        self.counter += 1;
+        if self.counter >= self.steps {
+            self.counter = 0;
+        }
+    }
+
+    pub fn release(self) -> P {
+        self.pin
+    }
+
+    fn output(&mut self, on: bool) {
+        if on != self.inverted {
+            self.pin.set_high().ok();
+        } else {
+            self.pin.set_low().ok();
+        }
+    }
+}
+
+impl<P> PwmPin for SoftPwm<P>
+where
+    P: OutputPin<Error = Infallible>,
+{
+    type Duty = u16;
+
+    fn disable(&mut self) {
+        self.duty = 0;
+    }
+
+    fn enable(&mut self) {}
+
+    fn get_duty(&self) -> u16 {
+        self.duty
+    }
+
+    fn get_max_duty(&self) -> u16 {
+        self.steps
+    }
+
+    fn set_duty(&mut self, duty: u16) {
+        self.duty = duty.min(self.steps);
+    }
+}
@@ -0,0 +1,62 @@
+//
+// Filters for noisy sensor readings: ADC samples, echo timings
+//
+
+/// Median of last N values: rejects single spurious readings
+#[derive(Debug, Clone, Copy)]
+pub struct Median<const N: usize> {
+    items: [u32; N],
+    pos: usize,
+    len: usize,
+}
+
+impl<const N: usize> Default for Median<N> {
+    fn default() -> Self {
+        Median::new()
+    }
+}
+
+impl<const N: usize> Median<N> {
+    pub const fn new() -> Self {
+        Median {
+            items: [0; N],
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, value: u32) {
+        if N == 0 {
+            return;
+        }
+
+        self.items[self.pos] = value;
+        self.pos = (self.pos + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    pub fn clear(&mut self) {
+        self.pos = 0;
+        self.len = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self) -> Option<u32> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let mut sorted = self.items;
+        let sorted = &mut sorted[..self.len];
+        sorted.sort_unstable();
+
+        Some(sorted[self.len / 2])
+    }
+}
@@ -0,0 +1,737 @@
+//
+// IR remote control protocol decoders and encoders
+//
+// Decoders are fed with pulses: durations of marks (carrier on) and spaces
+// (carrier off) as seen by the IR receiver, e.g. from timer edge capture.
+// Supported protocols: NEC (with repeat codes), Philips RC5 and RC6 mode 0,
+// Sony SIRC with 12, 15 and 20 bit frames.
+//
+// Encoders produce NEC and RC5 frames as pulse sequences for transmitters.
+//
+
+/// Default timing tolerance in percent
+pub const TOLERANCE: u32 = 25;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pulse {
+    /// carrier was on
+    pub mark: bool,
+    /// duration in us
+    pub us: u32,
+}
+
+impl Pulse {
+    pub const fn mark(us: u32) -> Self {
+        Pulse { mark: true, us }
+    }
+
+    pub const fn space(us: u32) -> Self {
+        Pulse { mark: false, us }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    Nec,
+    Rc5,
+    Rc6,
+    Sirc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Command {
+    pub protocol: Protocol,
+    pub address: u16,
+    pub command: u16,
+    /// toggles on every new key press: RC5 and RC6 only
+    pub toggle: bool,
+    /// key is held down: NEC repeat code
+    pub repeat: bool,
+}
+
+pub trait Decoder {
+    /// Feed next pulse, returns command when complete frame was received
+    fn feed(&mut self, p: Pulse) -> Option<Command>;
+
+    fn reset(&mut self);
+}
+
+/// Feed pulse to several decoders, returns first decoded command
+pub fn decode(decoders: &mut [&mut dyn Decoder], p: Pulse) -> Option<Command> {
+    let mut result = None;
+
+    for d in decoders.iter_mut() {
+        if let Some(cmd) = d.feed(p) {
+            result = result.or(Some(cmd));
+        }
+    }
+
+    result
+}
+
+/// Duration is within tolerance in percent of nominal value
+pub fn matches(us: u32, nominal: u32, tolerance: u32) -> bool {
+    let us = us as u64 * 100;
+    let nominal = nominal as u64;
+
+    us >= nominal * (100 - tolerance.min(100)) as u64 && us <= nominal * (100 + tolerance) as u64
+}
+
+// number of unit intervals in the duration: 1..=max
+fn units(us: u32, unit: u32, max: u32, tolerance: u32) -> Option<u32> {
+    let n = (us + unit / 2) / unit;
+    let delta = us.max(n * unit) - us.min(n * unit);
+
+    if (1..=max).contains(&n) && delta <= unit * tolerance / 100 {
+        Some(n)
+    } else {
+        None
+    }
+}
+
+// half bit levels of Manchester coded frames: true for mark
+#[derive(Debug, Default, Clone, Copy)]
+struct Halves {
+    bits: u64,
+    len: u32,
+}
+
+impl Halves {
+    fn push(&mut self, mark: bool, n: u32) {
+        for _ in 0..n {
+            if mark && self.len < 64 {
+                self.bits |= 1 << self.len;
+            }
+            self.len += 1;
+        }
+    }
+
+    fn get(&self, i: u32) -> bool {
+        self.bits & (1 << i) != 0
+    }
+
+    // Manchester bit at half position i: level of the first half
+    fn bit(&self, i: u32) -> Option<bool> {
+        let first = self.get(i);
+
+        if first == self.get(i + 1) {
+            None
+        } else {
+            Some(first)
+        }
+    }
+}
+
+/*
+ * NEC: 9ms leader mark, 4.5ms space, 32 bits LSB first with 560us marks,
+ * 560us space for 0 and 1690us space for 1, final 560us mark.
+ * Repeat code: 9ms mark, 2.25ms space, 560us mark.
+ */
+
+const NEC_LEADER_MARK: u32 = 9000;
+const NEC_LEADER_SPACE: u32 = 4500;
+const NEC_REPEAT_SPACE: u32 = 2250;
+const NEC_MARK: u32 = 560;
+const NEC_ZERO: u32 = 560;
+const NEC_ONE: u32 = 1690;
+const NEC_BITS: u32 = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NecState {
+    Idle,
+    Leader,
+    Mark(u32),
+    Space(u32),
+    Repeat,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Nec {
+    pub tolerance: u32,
+    state: NecState,
+    bits: u32,
+    last: Option<Command>,
+}
+
+impl Default for Nec {
+    fn default() -> Self {
+        Nec::new()
+    }
+}
+
+impl Nec {
+    pub const fn new() -> Self {
+        Nec {
+            tolerance: TOLERANCE,
+            state: NecState::Idle,
+            bits: 0,
+            last: None,
+        }
+    }
+
+    fn command(bits: u32) -> Option<Command> {
+        let [addr, naddr, cmd, ncmd] = bits.to_le_bytes();
+
+        if cmd ^ ncmd != 0xff {
+            return None;
+        }
+
+        // extended NEC uses both bytes for 16 bit address
+        let address = if addr ^ naddr == 0xff {
+            addr as u16
+        } else {
+            (bits & 0xffff) as u16
+        };
+
+        Some(Command {
+            protocol: Protocol::Nec,
+            address,
+            command: cmd as u16,
+            toggle: false,
+            repeat: false,
+        })
+    }
+
+    fn step(&mut self, p: Pulse) -> Option<NecState> {
+        let tol = self.tolerance;
+
+        match (self.state, p.mark) {
+            (NecState::Idle, true) if matches(p.us, NEC_LEADER_MARK, tol) => Some(NecState::Leader),
+            (NecState::Leader, false) if matches(p.us, NEC_LEADER_SPACE, tol) => {
+                self.bits = 0;
+                Some(NecState::Mark(0))
+            }
+            (NecState::Leader, false) if matches(p.us, NEC_REPEAT_SPACE, tol) => {
+                Some(NecState::Repeat)
+            }
+            (NecState::Mark(n), true) if matches(p.us, NEC_MARK, tol) => Some(NecState::Space(n)),
+            (NecState::Space(n), false) if n < NEC_BITS && matches(p.us, NEC_ZERO, tol) => {
+                Some(NecState::Mark(n + 1))
+            }
+            (NecState::Space(n), false) if n < NEC_BITS && matches(p.us, NEC_ONE, tol) => {
+                self.bits |= 1 << n;
+                Some(NecState::Mark(n + 1))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Decoder for Nec {
+    fn feed(&mut self, p: Pulse) -> Option<Command> {
+        // frame ends with the mark following the last bit
+        if p.mark && matches(p.us, NEC_MARK, self.tolerance) {
+            match self.state {
+                NecState::Mark(NEC_BITS) => {
+                    self.state = NecState::Idle;
+                    self.last = Nec::command(self.bits);
+                    return self.last;
+                }
+                NecState::Repeat => {
+                    self.state = NecState::Idle;
+                    return self.last.map(|c| Command { repeat: true, ..c });
+                }
+                _ => {}
+            }
+        }
+
+        self.state = match self.step(p) {
+            Some(s) => s,
+            None => {
+                // resync: pulse may start a new frame
+                self.state = NecState::Idle;
+                self.step(p).unwrap_or(NecState::Idle)
+            }
+        };
+
+        None
+    }
+
+    fn reset(&mut self) {
+        self.state = NecState::Idle;
+        self.bits = 0;
+    }
+}
+
+/*
+ * RC5: 14 Manchester bits with 889us half bit, 1 is space then mark.
+ * Frame: S1 S2 T A4..A0 C5..C0, inverted S2 is command bit 6 (RC5X).
+ */
+
+const RC5_UNIT: u32 = 889;
+const RC5_HALVES: u32 = 28;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Rc5 {
+    pub tolerance: u32,
+    halves: Halves,
+    active: bool,
+}
+
+impl Default for Rc5 {
+    fn default() -> Self {
+        Rc5::new()
+    }
+}
+
+impl Rc5 {
+    pub const fn new() -> Self {
+        Rc5 {
+            tolerance: TOLERANCE,
+            halves: Halves { bits: 0, len: 0 },
+            active: false,
+        }
+    }
+
+    fn command(&self) -> Option<Command> {
+        let mut bits = 0u16;
+
+        for i in 0..RC5_HALVES / 2 {
+            // RC5 bit value is the level of the second half
+            let first = self.halves.bit(2 * i)?;
+            bits = (bits << 1) | !first as u16;
+        }
+
+        let s1 = bits & (1 << 13) != 0;
+        let s2 = bits & (1 << 12) != 0;
+
+        if !s1 {
+            return None;
+        }
+
+        Some(Command {
+            protocol: Protocol::Rc5,
+            address: (bits >> 6) & 0x1f,
+            command: (bits & 0x3f) | if s2 { 0 } else { 0x40 },
+            toggle: bits & (1 << 11) != 0,
+            repeat: false,
+        })
+    }
+}
+
+impl Decoder for Rc5 {
+    fn feed(&mut self, p: Pulse) -> Option<Command> {
+        let n = units(p.us, RC5_UNIT, 2, self.tolerance);
+
+        if !self.active {
+            // first half of start bit is space: frame starts with a mark
+            if p.mark && n.is_some() {
+                self.halves = Halves::default();
+                self.halves.push(false, 1);
+                self.active = true;
+            } else {
+                return None;
+            }
+        }
+
+        let result = match n {
+            Some(n) => {
+                self.halves.push(p.mark, n);
+
+                if self.halves.len < RC5_HALVES {
+                    return None;
+                }
+
+                if self.halves.len == RC5_HALVES {
+                    self.command()
+                } else {
+                    None
+                }
+            }
+            None if !p.mark => {
+                // long space after the frame: last half was space
+                if self.halves.len == RC5_HALVES - 1 {
+                    self.halves.push(false, 1);
+                    self.command()
+                } else {
+                    None
+                }
+            }
+            None => None,
+        };
+
+        self.reset();
+        result
+    }
+
+    fn reset(&mut self) {
+        self.active = false;
+        self.halves = Halves::default();
+    }
+}
+
+/*
+ * RC6 mode 0: 2666us leader mark, 889us space, then Manchester bits with
+ * 444us half bit, 1 is mark then space: start bit 1, 3 mode bits, double
+ * width trailer (toggle) bit, 8 address and 8 command bits MSB first.
+ */
+
+const RC6_UNIT: u32 = 444;
+const RC6_LEADER_MARK: u32 = 2666;
+const RC6_LEADER_SPACE: u32 = 889;
+const RC6_HALVES: u32 = 44;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Rc6State {
+    Idle,
+    Leader,
+    Data,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Rc6 {
+    pub tolerance: u32,
+    state: Rc6State,
+    halves: Halves,
+}
+
+impl Default for Rc6 {
+    fn default() -> Self {
+        Rc6::new()
+    }
+}
+
+impl Rc6 {
+    pub const fn new() -> Self {
+        Rc6 {
+            tolerance: TOLERANCE,
+            state: Rc6State::Idle,
+            halves: Halves { bits: 0, len: 0 },
+        }
+    }
+
+    fn command(&self) -> Option<Command> {
+        let h = &self.halves;
+
+        // start bit and mode 0
+        if !h.bit(0)? {
+            return None;
+        }
+
+        for i in 0..3 {
+            if h.bit(2 + 2 * i)? {
+                return None;
+            }
+        }
+
+        // trailer bit halves are two units long
+        let toggle = h.get(8);
+        if h.get(9) != toggle || h.get(10) == toggle || h.get(11) == toggle {
+            return None;
+        }
+
+        let mut bits = 0u16;
+        for i in 0..16 {
+            bits = (bits << 1) | h.bit(12 + 2 * i)? as u16;
+        }
+
+        Some(Command {
+            protocol: Protocol::Rc6,
+            address: bits >> 8,
+            command: bits & 0xff,
+            toggle,
+            repeat: false,
+        })
+    }
+}
+
+impl Decoder for Rc6 {
+    fn feed(&mut self, p: Pulse) -> Option<Command> {
+        let tol = self.tolerance;
+
+        match self.state {
+            Rc6State::Idle => {
+                if p.mark && matches(p.us, RC6_LEADER_MARK, tol) {
+                    self.state = Rc6State::Leader;
+                }
+                None
+            }
+            Rc6State::Leader => {
+                if !p.mark && matches(p.us, RC6_LEADER_SPACE, tol) {
+                    self.halves = Halves::default();
+                    self.state = Rc6State::Data;
+                } else {
+                    self.reset();
+                }
+                None
+            }
+            Rc6State::Data => {
+                let result = match units(p.us, RC6_UNIT, 3, tol) {
+                    Some(n) => {
+                        self.halves.push(p.mark, n);
+
+                        if self.halves.len < RC6_HALVES {
+                            return None;
+                        }
+
+                        if self.halves.len == RC6_HALVES {
+                            self.command()
+                        } else {
+                            None
+                        }
+                    }
+                    None if !p.mark && self.halves.len == RC6_HALVES - 1 => {
+                        self.halves.push(false, 1);
+                        self.command()
+                    }
+                    None => None,
+                };
+
+                self.reset();
+                result
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.state = Rc6State::Idle;
+        self.halves = Halves::default();
+    }
+}
+
+/*
+ * Sony SIRC: 2.4ms leader mark, bits LSB first as 1.2ms (1) or 0.6ms (0)
+ * marks separated by 0.6ms spaces, frame ends with a long space.
+ * 7 command bits followed by 5, 8 or 13 address bits.
+ */
+
+const SIRC_LEADER_MARK: u32 = 2400;
+const SIRC_SPACE: u32 = 600;
+const SIRC_ZERO: u32 = 600;
+const SIRC_ONE: u32 = 1200;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SircState {
+    Idle,
+    Leader,
+    Mark,
+    Space,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sirc {
+    pub tolerance: u32,
+    state: SircState,
+    bits: u32,
+    count: u32,
+}
+
+impl Default for Sirc {
+    fn default() -> Self {
+        Sirc::new()
+    }
+}
+
+impl Sirc {
+    pub const fn new() -> Self {
+        Sirc {
+            tolerance: TOLERANCE,
+            state: SircState::Idle,
+            bits: 0,
+            count: 0,
+        }
+    }
+
+    fn command(&self) -> Option<Command> {
+        let address = match self.count {
+            12 | 15 | 20 => (self.bits >> 7) as u16,
+            _ => return None,
+        };
+
+        Some(Command {
+            protocol: Protocol::Sirc,
+            address,
+            command: (self.bits & 0x7f) as u16,
+            toggle: false,
+            repeat: false,
+        })
+    }
+}
+
+impl Decoder for Sirc {
+    fn feed(&mut self, p: Pulse) -> Option<Command> {
+        let tol = self.tolerance;
+
+        match (self.state, p.mark) {
+            (SircState::Idle, true) if matches(p.us, SIRC_LEADER_MARK, tol) => {
+                self.state = SircState::Leader;
+            }
+            (SircState::Leader, false) | (SircState::Space, false)
+                if matches(p.us, SIRC_SPACE, tol) =>
+            {
+                self.state = SircState::Mark;
+            }
+            (SircState::Mark, true) if self.count < 20 && matches(p.us, SIRC_ZERO, tol) => {
+                self.count += 1;
+                self.state = SircState::Space;
+            }
+            (SircState::Mark, true) if self.count < 20 && matches(p.us, SIRC_ONE, tol) => {
+                self.bits |= 1 << self.count;
+                self.count += 1;
+                self.state = SircState::Space;
+            }
+            (SircState::Space, false) if p.us > SIRC_SPACE * (100 + tol) / 100 => {
+                let result = self.command();
+                self.reset();
+                return result;
+            }
+            (_, true) if matches(p.us, SIRC_LEADER_MARK, tol) => {
+                self.reset();
+                self.state = SircState::Leader;
+            }
+            _ => self.reset(),
+        }
+
+        None
+    }
+
+    fn reset(&mut self) {
+        self.state = SircState::Idle;
+        self.bits = 0;
+        self.count = 0;
+    }
+}
+
+/*
+ * Encoders: frames as pulse sequences for IR transmitters, matching the
+ * decoders above. Leading and trailing spaces are not included: carrier is
+ * off between frames anyway.
+ */
+
+/// Maximum number of pulses in encoded frame: NEC has 67
+pub const FRAME_SIZE: usize = 72;
+
+/// NEC frame repeat period, from start to start
+pub const NEC_REPEAT_PERIOD_US: u32 = 108_000;
+
+/// RC5 frame repeat period, from start to start
+pub const RC5_REPEAT_PERIOD_US: u32 = 113_778;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    pulses: [Pulse; FRAME_SIZE],
+    len: usize,
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Frame::new()
+    }
+}
+
+impl Frame {
+    pub const fn new() -> Self {
+        Frame {
+            pulses: [Pulse::space(0); FRAME_SIZE],
+            len: 0,
+        }
+    }
+
+    /// Append pulse, merged with the last one if both have the same level
+    pub fn push(&mut self, p: Pulse) {
+        if self.len == 0 && !p.mark {
+            return;
+        }
+
+        if self.len > 0 && self.pulses[self.len - 1].mark == p.mark {
+            self.pulses[self.len - 1].us += p.us;
+        } else if self.len < FRAME_SIZE {
+            self.pulses[self.len] = p;
+            self.len += 1;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn pulses(&self) -> &[Pulse] {
+        &self.pulses[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Frame duration in us
+    pub fn duration(&self) -> u32 {
+        self.pulses().iter().map(|p| p.us).sum()
+    }
+}
+
+/// NEC frame: 8 bit address is sent with its inverse, larger addresses use
+/// both address bytes (extended NEC)
+pub fn encode_nec(address: u16, command: u8) -> Frame {
+    let mut frame = Frame::new();
+
+    let address = if address <= 0xff {
+        address | (!address & 0xff) << 8
+    } else {
+        address
+    };
+
+    let bits = address as u32 | (command as u32) << 16 | (!command as u32) << 24;
+
+    frame.push(Pulse::mark(NEC_LEADER_MARK));
+    frame.push(Pulse::space(NEC_LEADER_SPACE));
+
+    for n in 0..NEC_BITS {
+        let space = if bits & (1 << n) != 0 {
+            NEC_ONE
+        } else {
+            NEC_ZERO
+        };
+
+        frame.push(Pulse::mark(NEC_MARK));
+        frame.push(Pulse::space(space));
+    }
+
+    frame.push(Pulse::mark(NEC_MARK));
+    frame
+}
+
+/// NEC repeat code sent while the key is held down
+pub fn encode_nec_repeat() -> Frame {
+    let mut frame = Frame::new();
+
+    frame.push(Pulse::mark(NEC_LEADER_MARK));
+    frame.push(Pulse::space(NEC_REPEAT_SPACE));
+    frame.push(Pulse::mark(NEC_MARK));
+    frame
+}
+
+/// RC5 frame: 5 bit address, 7 bit command (RC5X for commands above 63)
+pub fn encode_rc5(address: u8, command: u8, toggle: bool) -> Frame {
+    let mut frame = Frame::new();
+
+    let s2 = command & 0x40 == 0;
+    let bits = 1 << 13
+        | (s2 as u16) << 12
+        | (toggle as u16) << 11
+        | ((address & 0x1f) as u16) << 6
+        | (command & 0x3f) as u16;
+
+    for i in (0..RC5_HALVES / 2).rev() {
+        let one = bits & (1 << i) != 0;
+
+        frame.push(Pulse {
+            mark: !one,
+            us: RC5_UNIT,
+        });
+        frame.push(Pulse {
+            mark: one,
+            us: RC5_UNIT,
+        });
+    }
+
+    // trailing space is part of the idle time
+    if frame.len > 0 && !frame.pulses[frame.len - 1].mark {
+        frame.len -= 1;
+    }
+
+    frame
+}
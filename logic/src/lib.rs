@@ -0,0 +1,20 @@
+#![no_std]
+
+pub mod access;
+pub mod capture;
+pub mod clocks;
+pub mod color;
+pub mod datetime;
+pub mod effects;
+pub mod fade;
+pub mod filter;
+pub mod font;
+pub mod ir;
+pub mod matrix;
+pub mod mifare;
+pub mod ndef;
+pub mod power;
+pub mod servo;
+pub mod telemetry;
+pub mod ultralight;
+pub mod usonic;
@@ -0,0 +1,251 @@
+//
+// LED matrix layer for WS2812 panels
+//
+// Maps logical (x, y) coordinates to strip indices for common panel wiring,
+// provides embedded-graphics DrawTarget for RGB8 frame buffers and a
+// scrolling text ticker using the built-in 5x7 font.
+//
+
+use crate::font;
+use core::convert::Infallible;
+use core::fmt;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use smart_leds::RGB8;
+
+/// Order of LEDs along the strip
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// every row starts on the left
+    Progressive,
+    /// odd rows run from right to left
+    Serpentine,
+}
+
+/// Clockwise rotation of the logical image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rotation {
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+/// Panel geometry: width and height are physical, in wiring order
+#[derive(Debug, Clone, Copy)]
+pub struct Matrix {
+    pub width: usize,
+    pub height: usize,
+    pub layout: Layout,
+    pub rotation: Rotation,
+}
+
+impl Matrix {
+    pub const fn new(width: usize, height: usize, layout: Layout) -> Self {
+        Matrix {
+            width,
+            height,
+            layout,
+            rotation: Rotation::Deg0,
+        }
+    }
+
+    pub const fn rotate(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn leds(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// Logical size after rotation
+    pub fn size(&self) -> (usize, usize) {
+        match self.rotation {
+            Rotation::Deg0 | Rotation::Deg180 => (self.width, self.height),
+            Rotation::Deg90 | Rotation::Deg270 => (self.height, self.width),
+        }
+    }
+
+    /// Strip index of logical pixel, None if outside of the panel
+    pub fn index(&self, x: usize, y: usize) -> Option<usize> {
+        let (w, h) = self.size();
+
+        if x >= w || y >= h {
+            return None;
+        }
+
+        let (px, py) = match self.rotation {
+            Rotation::Deg0 => (x, y),
+            Rotation::Deg90 => (self.width - 1 - y, x),
+            Rotation::Deg180 => (self.width - 1 - x, self.height - 1 - y),
+            Rotation::Deg270 => (y, self.height - 1 - x),
+        };
+
+        let px = match self.layout {
+            Layout::Serpentine if py % 2 == 1 => self.width - 1 - px,
+            _ => px,
+        };
+
+        Some(py * self.width + px)
+    }
+}
+
+pub fn to_rgb8(c: Rgb888) -> RGB8 {
+    RGB8 {
+        r: c.r(),
+        g: c.g(),
+        b: c.b(),
+    }
+}
+
+pub fn to_rgb888(c: RGB8) -> Rgb888 {
+    Rgb888::new(c.r, c.g, c.b)
+}
+
+/// Frame buffer view in logical matrix coordinates
+pub struct Canvas<'a> {
+    frame: &'a mut [RGB8],
+    matrix: Matrix,
+}
+
+impl<'a> Canvas<'a> {
+    /// Pixels beyond the end of the frame are ignored
+    pub fn new(frame: &'a mut [RGB8], matrix: Matrix) -> Self {
+        Canvas { frame, matrix }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, c: RGB8) {
+        if let Some(led) = self.matrix.index(x, y).and_then(|i| self.frame.get_mut(i)) {
+            *led = c;
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<RGB8> {
+        self.matrix
+            .index(x, y)
+            .and_then(|i| self.frame.get(i))
+            .copied()
+    }
+
+    pub fn fill(&mut self, c: RGB8) {
+        for led in self.frame.iter_mut() {
+            *led = c;
+        }
+    }
+}
+
+impl<'a> OriginDimensions for Canvas<'a> {
+    fn size(&self) -> Size {
+        let (w, h) = self.matrix.size();
+        Size::new(w as u32, h as u32)
+    }
+}
+
+impl<'a> DrawTarget for Canvas<'a> {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(p, c) in pixels {
+            if p.x >= 0 && p.y >= 0 {
+                self.set(p.x as usize, p.y as usize, to_rgb8(c));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub const TICKER_SIZE: usize = 32;
+
+/// Text scrolling from right to left, one pixel per step
+pub struct Ticker {
+    text: [u8; TICKER_SIZE],
+    len: usize,
+    offset: usize,
+    pub color: RGB8,
+    /// top row of the text
+    pub row: i32,
+}
+
+impl Ticker {
+    pub fn new(color: RGB8) -> Self {
+        Ticker {
+            text: [0; TICKER_SIZE],
+            len: 0,
+            offset: 0,
+            color,
+            row: 0,
+        }
+    }
+
+    /// Text is truncated to TICKER_SIZE bytes, scroll position is kept
+    pub fn set_text(&mut self, text: &str) {
+        self.len = 0;
+        fmt::Write::write_str(self, text).ok();
+    }
+
+    pub fn text(&self) -> &str {
+        core::str::from_utf8(&self.text[..self.len]).unwrap_or("")
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Move text one pixel left, wraps around after text has left the screen
+    pub fn step(&mut self, screen_width: usize) {
+        let period = screen_width + font::text_width(self.text());
+        self.offset = (self.offset + 1) % period.max(1);
+    }
+
+    pub fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb888>,
+    {
+        let width = target.bounding_box().size.width as i32;
+        let color = to_rgb888(self.color);
+        let start = width - self.offset as i32;
+        let row = self.row;
+
+        for (n, c) in self.text().chars().enumerate() {
+            let left = start + (n * font::ADVANCE) as i32;
+
+            if left + (font::WIDTH as i32) < 0 || left >= width {
+                continue;
+            }
+
+            let pixels = (0..font::WIDTH).flat_map(move |x| {
+                (0..font::HEIGHT)
+                    .filter(move |&y| font::pixel(c, x, y))
+                    .map(move |y| Pixel(Point::new(left + x as i32, row + y as i32), color))
+            });
+
+            target.draw_iter(pixels)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Write for Ticker {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            let mut buf = [0u8; 4];
+            let bytes = c.encode_utf8(&mut buf).as_bytes();
+
+            if self.len + bytes.len() > TICKER_SIZE {
+                return Err(fmt::Error);
+            }
+
+            self.text[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+            self.len += bytes.len();
+        }
+
+        Ok(())
+    }
+}
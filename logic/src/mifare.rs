@@ -0,0 +1,330 @@
+//
+// MIFARE Classic memory layout, access conditions and value blocks
+//
+
+pub const BLOCK_SIZE: usize = 16;
+
+pub type Key = [u8; 6];
+pub type Block = [u8; BLOCK_SIZE];
+
+/// Factory default transport key
+pub const DEFAULT_KEY: Key = [0xff; 6];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CardType {
+    Mini,
+    Classic1K,
+    Classic4K,
+}
+
+impl CardType {
+    /// Identify MIFARE Classic card by SAK
+    pub fn from_sak(sak: u8) -> Option<Self> {
+        match sak {
+            0x09 => Some(CardType::Mini),
+            0x08 | 0x88 => Some(CardType::Classic1K),
+            0x18 | 0x98 => Some(CardType::Classic4K),
+            _ => None,
+        }
+    }
+
+    pub fn sectors(self) -> u8 {
+        match self {
+            CardType::Mini => 5,
+            CardType::Classic1K => 16,
+            CardType::Classic4K => 40,
+        }
+    }
+
+    pub fn blocks(self) -> u16 {
+        match self {
+            CardType::Mini => 20,
+            CardType::Classic1K => 64,
+            CardType::Classic4K => 256,
+        }
+    }
+}
+
+/// Sectors 0..31 have 4 blocks, sectors 32..39 of 4K cards have 16 blocks
+pub fn sector_of(block: u8) -> u8 {
+    if block < 128 {
+        block / 4
+    } else {
+        32 + (block - 128) / 16
+    }
+}
+
+pub fn first_block(sector: u8) -> u8 {
+    if sector < 32 {
+        sector * 4
+    } else {
+        128 + (sector - 32) * 16
+    }
+}
+
+pub fn blocks_in_sector(sector: u8) -> u8 {
+    if sector < 32 {
+        4
+    } else {
+        16
+    }
+}
+
+pub fn trailer_block(sector: u8) -> u8 {
+    first_block(sector) + (blocks_in_sector(sector) - 1)
+}
+
+pub fn is_trailer(block: u8) -> bool {
+    block == trailer_block(sector_of(block))
+}
+
+/// Access conditions of a data block: (C1, C2, C3) bits packed as 0bC1C2C3
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataAccess {
+    /// read/write/increment/decrement with key A|B (transport configuration)
+    Transport = 0b000,
+    /// read with key A|B, no write
+    ReadOnly = 0b010,
+    /// read with key A|B, write with key B
+    WriteB = 0b100,
+    /// value block: read/decrement with key A|B, write/increment with key B
+    Value = 0b110,
+    /// value block: read/decrement with key A|B only
+    ValueDecOnly = 0b001,
+    /// read/write with key B only
+    KeyBOnly = 0b011,
+    /// read with key B only
+    ReadB = 0b101,
+    /// no access
+    Locked = 0b111,
+}
+
+/// Access conditions of a sector trailer: (C1, C2, C3) bits packed as 0bC1C2C3
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrailerAccess {
+    /// key A writes keys, access bits read-only, key B readable
+    KeyAWriteKeys = 0b000,
+    /// key A reads access bits and key B, nothing writable
+    KeyAFrozen = 0b010,
+    /// key B writes keys, access bits read-only
+    KeyBWriteKeys = 0b100,
+    /// nothing writable, access bits readable with key A|B
+    Frozen = 0b110,
+    /// key A writes everything, key B readable (transport configuration)
+    Transport = 0b001,
+    /// key B writes keys and access bits
+    KeyB = 0b011,
+    /// key B writes access bits only
+    KeyBAccessOnly = 0b101,
+    /// nothing writable
+    Locked = 0b111,
+}
+
+impl DataAccess {
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b111 {
+            0b000 => DataAccess::Transport,
+            0b010 => DataAccess::ReadOnly,
+            0b100 => DataAccess::WriteB,
+            0b110 => DataAccess::Value,
+            0b001 => DataAccess::ValueDecOnly,
+            0b011 => DataAccess::KeyBOnly,
+            0b101 => DataAccess::ReadB,
+            _ => DataAccess::Locked,
+        }
+    }
+
+    /// Conditions that need key B for some operations
+    fn needs_key_b(self) -> bool {
+        match self {
+            DataAccess::Transport | DataAccess::ReadOnly | DataAccess::ValueDecOnly => false,
+            _ => true,
+        }
+    }
+}
+
+impl TrailerAccess {
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b111 {
+            0b000 => TrailerAccess::KeyAWriteKeys,
+            0b010 => TrailerAccess::KeyAFrozen,
+            0b100 => TrailerAccess::KeyBWriteKeys,
+            0b110 => TrailerAccess::Frozen,
+            0b001 => TrailerAccess::Transport,
+            0b011 => TrailerAccess::KeyB,
+            0b101 => TrailerAccess::KeyBAccessOnly,
+            _ => TrailerAccess::Locked,
+        }
+    }
+
+    /// Access bits can be changed later
+    pub fn access_writable(self) -> bool {
+        match self {
+            TrailerAccess::Transport | TrailerAccess::KeyB | TrailerAccess::KeyBAccessOnly => true,
+            _ => false,
+        }
+    }
+
+    /// Key B can be read, so it can not be used for authentication
+    pub fn key_b_readable(self) -> bool {
+        match self {
+            TrailerAccess::KeyAWriteKeys | TrailerAccess::KeyAFrozen | TrailerAccess::Transport => {
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Decoded access conditions of a sector: 3 data block groups and trailer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccessBits {
+    pub data: [DataAccess; 3],
+    pub trailer: TrailerAccess,
+}
+
+impl Default for AccessBits {
+    fn default() -> Self {
+        AccessBits {
+            data: [DataAccess::Transport; 3],
+            trailer: TrailerAccess::Transport,
+        }
+    }
+}
+
+impl AccessBits {
+    /// Decode access bytes 6..8 of a sector trailer
+    pub fn decode(bytes: &[u8; 3]) -> Option<Self> {
+        let c1 = bytes[1] >> 4;
+        let c2 = bytes[2] & 0x0f;
+        let c3 = bytes[2] >> 4;
+
+        let c1_inv = bytes[0] & 0x0f;
+        let c2_inv = bytes[0] >> 4;
+        let c3_inv = bytes[1] & 0x0f;
+
+        if c1 != !c1_inv & 0x0f || c2 != !c2_inv & 0x0f || c3 != !c3_inv & 0x0f {
+            return None;
+        }
+
+        let cond = |n: u8| ((c1 >> n) & 1) << 2 | ((c2 >> n) & 1) << 1 | ((c3 >> n) & 1);
+
+        Some(AccessBits {
+            data: [
+                DataAccess::from_bits(cond(0)),
+                DataAccess::from_bits(cond(1)),
+                DataAccess::from_bits(cond(2)),
+            ],
+            trailer: TrailerAccess::from_bits(cond(3)),
+        })
+    }
+
+    /// Encode access conditions into access bytes 6..8 of a sector trailer
+    pub fn encode(&self) -> [u8; 3] {
+        let conds = [
+            self.data[0] as u8,
+            self.data[1] as u8,
+            self.data[2] as u8,
+            self.trailer as u8,
+        ];
+
+        let (mut c1, mut c2, mut c3) = (0u8, 0u8, 0u8);
+
+        for (n, cond) in conds.iter().enumerate() {
+            c1 |= ((cond >> 2) & 1) << n;
+            c2 |= ((cond >> 1) & 1) << n;
+            c3 |= (cond & 1) << n;
+        }
+
+        [
+            (!c2 & 0x0f) << 4 | (!c1 & 0x0f),
+            c1 << 4 | (!c3 & 0x0f),
+            c3 << 4 | c2,
+        ]
+    }
+
+    /// Reject configurations that leave the sector unmanageable:
+    /// - access bits can not be rewritten anymore
+    /// - data blocks need key B while key B is readable and thus unusable
+    pub fn is_safe(&self) -> bool {
+        if !self.trailer.access_writable() {
+            return false;
+        }
+
+        !(self.trailer.key_b_readable() && self.data.iter().any(|d| d.needs_key_b()))
+    }
+}
+
+/// Sector trailer: key A, access bits, general purpose byte, key B
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Trailer {
+    pub key_a: Key,
+    pub access: AccessBits,
+    pub user: u8,
+    pub key_b: Key,
+}
+
+impl Trailer {
+    /// Key A is never readable and is returned as zeros
+    pub fn decode(block: &Block) -> Option<Self> {
+        let mut key_a = [0u8; 6];
+        let mut key_b = [0u8; 6];
+        let mut access = [0u8; 3];
+
+        key_a.copy_from_slice(&block[0..6]);
+        access.copy_from_slice(&block[6..9]);
+        key_b.copy_from_slice(&block[10..16]);
+
+        Some(Trailer {
+            key_a,
+            access: AccessBits::decode(&access)?,
+            user: block[9],
+            key_b,
+        })
+    }
+
+    pub fn encode(&self) -> Block {
+        let mut block = [0u8; BLOCK_SIZE];
+
+        block[0..6].copy_from_slice(&self.key_a);
+        block[6..9].copy_from_slice(&self.access.encode());
+        block[9] = self.user;
+        block[10..16].copy_from_slice(&self.key_b);
+
+        block
+    }
+}
+
+/// Value block format: value, inverted value, value, then address byte
+/// stored as addr, !addr, addr, !addr
+pub fn encode_value(value: i32, addr: u8) -> Block {
+    let mut block = [0u8; BLOCK_SIZE];
+    let v = value.to_le_bytes();
+    let nv = (!value).to_le_bytes();
+
+    block[0..4].copy_from_slice(&v);
+    block[4..8].copy_from_slice(&nv);
+    block[8..12].copy_from_slice(&v);
+    block[12..16].copy_from_slice(&[addr, !addr, addr, !addr]);
+
+    block
+}
+
+pub fn decode_value(block: &Block) -> Option<(i32, u8)> {
+    let mut buf = [0u8; 4];
+
+    buf.copy_from_slice(&block[0..4]);
+    let v1 = i32::from_le_bytes(buf);
+    buf.copy_from_slice(&block[4..8]);
+    let nv = i32::from_le_bytes(buf);
+    buf.copy_from_slice(&block[8..12]);
+    let v2 = i32::from_le_bytes(buf);
+
+    let addr = block[12];
+
+    if v1 != v2 || v1 != !nv || block[13] != !addr || block[14] != addr || block[15] != !addr {
+        return None;
+    }
+
+    Some((v1, addr))
+}
@@ -0,0 +1,378 @@
+//
+// NDEF message encoding and decoding for NFC Forum Type 2 tags
+//
+
+use core::str;
+
+// TLV blocks in Type 2 tag memory
+const TLV_NULL: u8 = 0x00;
+const TLV_NDEF: u8 = 0x03;
+const TLV_TERMINATOR: u8 = 0xfe;
+
+// record header flags
+const MB: u8 = 0x80;
+const ME: u8 = 0x40;
+const CF: u8 = 0x20;
+const SR: u8 = 0x10;
+const IL: u8 = 0x08;
+
+/// URI identifier codes from NFC Forum URI RTD
+const URI_PREFIXES: [&str; 36] = [
+    "",
+    "http://www.",
+    "https://www.",
+    "http://",
+    "https://",
+    "tel:",
+    "mailto:",
+    "ftp://anonymous:anonymous@",
+    "ftp://ftp.",
+    "ftps://",
+    "sftp://",
+    "smb://",
+    "nfs://",
+    "ftp://",
+    "dav://",
+    "news:",
+    "telnet://",
+    "imap:",
+    "rtsp://",
+    "urn:",
+    "pop:",
+    "sip:",
+    "sips:",
+    "tftp:",
+    "btspp://",
+    "btl2cap://",
+    "btgoep://",
+    "tcpobex://",
+    "irdaobex://",
+    "file://",
+    "urn:epc:id:",
+    "urn:epc:tag:",
+    "urn:epc:pat:",
+    "urn:epc:raw:",
+    "urn:epc:",
+    "urn:nfc:",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    /// data ends in the middle of TLV or record
+    Truncated,
+    /// no NDEF message TLV found
+    NoMessage,
+    /// output buffer is too small
+    NoRoom,
+    /// record payload is not valid for its type
+    InvalidRecord,
+    /// chunked records are not supported
+    Unsupported,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tnf {
+    Empty,
+    WellKnown,
+    Media,
+    AbsoluteUri,
+    External,
+    Unknown,
+    Unchanged,
+    Reserved,
+}
+
+impl Tnf {
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0x07 {
+            0 => Tnf::Empty,
+            1 => Tnf::WellKnown,
+            2 => Tnf::Media,
+            3 => Tnf::AbsoluteUri,
+            4 => Tnf::External,
+            5 => Tnf::Unknown,
+            6 => Tnf::Unchanged,
+            _ => Tnf::Reserved,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Record<'a> {
+    pub tnf: Tnf,
+    pub kind: &'a [u8],
+    pub id: &'a [u8],
+    pub payload: &'a [u8],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Text<'a> {
+    pub lang: &'a str,
+    pub text: &'a str,
+}
+
+impl<'a> Record<'a> {
+    /// Well-known URI record: returns prefix and the rest of URI
+    pub fn uri(&self) -> Option<(&'static str, &'a str)> {
+        if self.tnf != Tnf::WellKnown || self.kind != b"U" || self.payload.is_empty() {
+            return None;
+        }
+
+        let prefix = URI_PREFIXES.get(self.payload[0] as usize)?;
+        let rest = str::from_utf8(&self.payload[1..]).ok()?;
+
+        Some((*prefix, rest))
+    }
+
+    /// Well-known text record: only UTF-8 encoding is supported
+    pub fn text(&self) -> Option<Text<'a>> {
+        if self.tnf != Tnf::WellKnown || self.kind != b"T" || self.payload.is_empty() {
+            return None;
+        }
+
+        let status = self.payload[0];
+        if status & 0x80 != 0 {
+            return None;
+        }
+
+        let lang_len = (status & 0x3f) as usize;
+        if self.payload.len() < 1 + lang_len {
+            return None;
+        }
+
+        Some(Text {
+            lang: str::from_utf8(&self.payload[1..1 + lang_len]).ok()?,
+            text: str::from_utf8(&self.payload[1 + lang_len..]).ok()?,
+        })
+    }
+}
+
+/// Iterator over records of NDEF message
+pub struct Records<'a> {
+    data: &'a [u8],
+    done: bool,
+}
+
+impl<'a> Records<'a> {
+    pub fn new(message: &'a [u8]) -> Self {
+        Records {
+            data: message,
+            done: message.is_empty(),
+        }
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<Record<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match parse_record(self.data) {
+            Ok((record, last, rest)) => {
+                self.data = rest;
+                self.done = last || rest.is_empty();
+                Some(Ok(record))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+fn take(data: &[u8], n: usize) -> Result<(&[u8], &[u8]), Error> {
+    if data.len() < n {
+        return Err(Error::Truncated);
+    }
+
+    Ok(data.split_at(n))
+}
+
+fn parse_record(data: &[u8]) -> Result<(Record<'_>, bool, &[u8]), Error> {
+    let (hdr, data) = take(data, 2)?;
+    let flags = hdr[0];
+    let kind_len = hdr[1] as usize;
+
+    if flags & CF != 0 {
+        return Err(Error::Unsupported);
+    }
+
+    let (payload_len, data) = if flags & SR != 0 {
+        let (len, data) = take(data, 1)?;
+        (len[0] as usize, data)
+    } else {
+        let (len, data) = take(data, 4)?;
+        (
+            u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize,
+            data,
+        )
+    };
+
+    let (id_len, data) = if flags & IL != 0 {
+        let (len, data) = take(data, 1)?;
+        (len[0] as usize, data)
+    } else {
+        (0, data)
+    };
+
+    let (kind, data) = take(data, kind_len)?;
+    let (id, data) = take(data, id_len)?;
+    let (payload, data) = take(data, payload_len)?;
+
+    let record = Record {
+        tnf: Tnf::from_bits(flags),
+        kind,
+        id,
+        payload,
+    };
+
+    Ok((record, flags & ME != 0, data))
+}
+
+/// Find NDEF message TLV in Type 2 tag user memory (starting from page 4)
+pub fn find_message(mem: &[u8]) -> Result<&[u8], Error> {
+    let mut data = mem;
+
+    loop {
+        let (tag, rest) = take(data, 1)?;
+
+        match tag[0] {
+            TLV_NULL => {
+                data = rest;
+                continue;
+            }
+            TLV_TERMINATOR => return Err(Error::NoMessage),
+            _ => {}
+        }
+
+        let (len, rest) = take(rest, 1)?;
+        let (len, rest) = if len[0] == 0xff {
+            let (len, rest) = take(rest, 2)?;
+            (u16::from_be_bytes([len[0], len[1]]) as usize, rest)
+        } else {
+            (len[0] as usize, rest)
+        };
+
+        let (value, rest) = take(rest, len)?;
+
+        if tag[0] == TLV_NDEF {
+            return Ok(value);
+        }
+
+        data = rest;
+    }
+}
+
+/// Wrap NDEF message into TLV with terminator, returns number of bytes used
+pub fn wrap_message(message: &[u8], buf: &mut [u8]) -> Result<usize, Error> {
+    let hdr = if message.len() < 0xff { 2 } else { 4 };
+    let total = hdr + message.len() + 1;
+
+    if message.len() > 0xfffe || buf.len() < total {
+        return Err(Error::NoRoom);
+    }
+
+    buf[0] = TLV_NDEF;
+    if hdr == 2 {
+        buf[1] = message.len() as u8;
+    } else {
+        buf[1] = 0xff;
+        buf[2..4].copy_from_slice(&(message.len() as u16).to_be_bytes());
+    }
+
+    buf[hdr..hdr + message.len()].copy_from_slice(message);
+    buf[total - 1] = TLV_TERMINATOR;
+
+    Ok(total)
+}
+
+/// Builder of NDEF message with short records in a caller provided buffer
+pub struct MessageWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    last: Option<usize>,
+}
+
+impl<'a> MessageWriter<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        MessageWriter {
+            buf,
+            len: 0,
+            last: None,
+        }
+    }
+
+    pub fn push(&mut self, tnf: Tnf, kind: &[u8], parts: &[&[u8]]) -> Result<(), Error> {
+        let payload_len: usize = parts.iter().map(|p| p.len()).sum();
+
+        if payload_len > 0xff || kind.len() > 0xff {
+            return Err(Error::Unsupported);
+        }
+
+        if self.len + 3 + kind.len() + payload_len > self.buf.len() {
+            return Err(Error::NoRoom);
+        }
+
+        let start = self.len;
+        let mut flags = SR | tnf as u8;
+
+        match self.last {
+            Some(prev) => self.buf[prev] &= !ME,
+            None => flags |= MB,
+        }
+
+        self.buf[start] = flags | ME;
+        self.buf[start + 1] = kind.len() as u8;
+        self.buf[start + 2] = payload_len as u8;
+
+        let mut pos = start + 3;
+        self.buf[pos..pos + kind.len()].copy_from_slice(kind);
+        pos += kind.len();
+
+        for part in parts {
+            self.buf[pos..pos + part.len()].copy_from_slice(part);
+            pos += part.len();
+        }
+
+        self.last = Some(start);
+        self.len = pos;
+
+        Ok(())
+    }
+
+    /// Add URI record using the longest matching URI prefix code
+    pub fn push_uri(&mut self, uri: &str) -> Result<(), Error> {
+        let (code, prefix) = URI_PREFIXES
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, p)| uri.starts_with(**p))
+            .max_by_key(|(_, p)| p.len())
+            .map(|(c, p)| (c as u8, p.len()))
+            .unwrap_or((0, 0));
+
+        self.push(Tnf::WellKnown, b"U", &[&[code], uri[prefix..].as_bytes()])
+    }
+
+    /// Add UTF-8 text record
+    pub fn push_text(&mut self, lang: &str, text: &str) -> Result<(), Error> {
+        if lang.len() > 0x3f {
+            return Err(Error::InvalidRecord);
+        }
+
+        self.push(
+            Tnf::WellKnown,
+            b"T",
+            &[&[lang.len() as u8], lang.as_bytes(), text.as_bytes()],
+        )
+    }
+
+    pub fn finish(self) -> &'a [u8] {
+        let buf: &'a [u8] = self.buf;
+        &buf[..self.len]
+    }
+}
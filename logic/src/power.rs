@@ -0,0 +1,30 @@
+//
+// Low-power mode selection and time accounting
+//
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Sleep,
+    Stop,
+    Standby,
+}
+
+/// Time spent in each mode in RTC ticks
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub run: u32,
+    pub sleep: u32,
+    pub stop: u32,
+}
+
+impl Stats {
+    /// Part of time spent in Sleep and Stop in 0.1% units
+    pub fn low_power_permille(&self) -> u32 {
+        let total = self.run as u64 + self.sleep as u64 + self.stop as u64;
+        if total == 0 {
+            return 0;
+        }
+
+        ((self.sleep as u64 + self.stop as u64) * 1000 / total) as u32
+    }
+}
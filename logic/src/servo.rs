@@ -0,0 +1,204 @@
+//
+// RC servo control on 50Hz hardware PWM channels
+//
+// Servo keeps current and target pulse widths and moves towards the target
+// with limited speed when updated from a periodic timer interrupt.
+//
+
+use embedded_hal::PwmPin;
+
+/// Servo PWM frequency and period
+pub const FREQ_HZ: u32 = 50;
+pub const PERIOD_US: u32 = 1_000_000 / FREQ_HZ;
+
+/// Per-servo calibration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    /// pulse width at 0 degrees
+    pub min_us: u16,
+    /// pulse width at full range
+    pub max_us: u16,
+    /// mechanical range in degrees
+    pub range: u16,
+    /// offset added to every pulse
+    pub trim_us: i16,
+    /// swap direction of rotation
+    pub inverted: bool,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration {
+            min_us: 1000,
+            max_us: 2000,
+            range: 180,
+            trim_us: 0,
+            inverted: false,
+        }
+    }
+}
+
+impl Calibration {
+    /// Pulse width for the angle, angle is clamped to range
+    pub fn pulse(&self, angle: u16) -> u16 {
+        let range = self.range.max(1) as u32;
+        let reversed = self.reversed();
+        let angle = angle.min(self.range) as u32;
+        let angle = if reversed { range - angle } else { angle };
+        let (min, max) = self.limits();
+        let span = (max - min) as u32;
+
+        min + (span * angle / range) as u16
+    }
+
+    /// Angle for the pulse width without trim
+    pub fn angle(&self, pulse: u16) -> u16 {
+        let range = self.range.max(1) as u32;
+        let reversed = self.reversed();
+        let (min, max) = self.limits();
+        let span = (max - min).max(1) as u32;
+        let pulse = self.clamp(pulse);
+        let angle = (pulse.saturating_sub(min) as u32 * range / span).min(range);
+        let angle = if reversed { range - angle } else { angle };
+
+        angle as u16
+    }
+
+    /// Pulse width limits
+    pub fn clamp(&self, pulse: u16) -> u16 {
+        let (min, max) = self.limits();
+        pulse.max(min).min(max)
+    }
+
+    /// Speed in us per second for the speed given in degrees per second
+    fn us_per_s(&self, deg_per_s: u16) -> u32 {
+        let (min, max) = self.limits();
+        let span = (max - min) as u32;
+        deg_per_s as u32 * span / self.range.max(1) as u32
+    }
+
+    /// Pulse width decreases with angle: inverted or max_us below min_us
+    fn reversed(&self) -> bool {
+        self.inverted != (self.min_us > self.max_us)
+    }
+
+    /// Pulse width limits in ascending order
+    fn limits(&self) -> (u16, u16) {
+        if self.min_us <= self.max_us {
+            (self.min_us, self.max_us)
+        } else {
+            (self.max_us, self.min_us)
+        }
+    }
+}
+
+/// PWM duty for the pulse width: channel has to run at FREQ_HZ
+pub fn duty(pulse_us: u16, max_duty: u16) -> u16 {
+    (pulse_us as u32 * max_duty as u32 / PERIOD_US) as u16
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Servo {
+    pub cal: Calibration,
+    // positions in ns for smooth slow moves
+    pos: u32,
+    target: u32,
+    // ns per ms, zero for immediate moves
+    speed: u32,
+    enabled: bool,
+}
+
+impl Servo {
+    /// Servo starts disabled in the middle of its range
+    pub fn new(cal: Calibration) -> Self {
+        let mid = cal.pulse(cal.range / 2) as u32 * 1000;
+
+        Servo {
+            cal,
+            pos: mid,
+            target: mid,
+            speed: 0,
+            enabled: false,
+        }
+    }
+
+    /// Jump to the angle
+    pub fn set_angle(&mut self, angle: u16) {
+        let pulse = self.cal.pulse(angle);
+        self.set_pulse(pulse);
+    }
+
+    /// Jump to the pulse width, clamped to calibration limits
+    pub fn set_pulse(&mut self, pulse_us: u16) {
+        let pulse = self.cal.clamp(pulse_us) as u32 * 1000;
+
+        self.pos = pulse;
+        self.target = pulse;
+        self.speed = 0;
+        self.enabled = true;
+    }
+
+    /// Move to the angle with limited speed, zero speed moves immediately
+    pub fn move_to(&mut self, angle: u16, deg_per_s: u16) {
+        if deg_per_s == 0 {
+            self.set_angle(angle);
+            return;
+        }
+
+        self.target = self.cal.pulse(angle) as u32 * 1000;
+        // us per s is the same as ns per ms
+        self.speed = self.cal.us_per_s(deg_per_s).max(1);
+        self.enabled = true;
+    }
+
+    /// Stop pulses: most servos then release the shaft
+    pub fn disable(&mut self) {
+        self.enabled = false;
+        self.target = self.pos;
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn moving(&self) -> bool {
+        self.pos != self.target
+    }
+
+    /// Current pulse width in us without trim
+    pub fn pulse(&self) -> u16 {
+        (self.pos / 1000) as u16
+    }
+
+    pub fn angle(&self) -> u16 {
+        self.cal.angle(self.pulse())
+    }
+
+    /// Advance move by dt ms
+    pub fn update(&mut self, dt: u32) {
+        let step = self.speed.saturating_mul(dt);
+
+        if self.pos < self.target {
+            self.pos = self.pos.saturating_add(step).min(self.target);
+        } else {
+            self.pos = self.pos.saturating_sub(step).max(self.target);
+        }
+    }
+
+    /// Output pulse width including trim, zero when disabled
+    pub fn output_us(&self) -> u16 {
+        if !self.enabled {
+            return 0;
+        }
+
+        (self.pulse() as i32 + self.cal.trim_us as i32).max(0) as u16
+    }
+
+    /// Apply current pulse width to 50Hz PWM channel
+    pub fn apply<P>(&self, pwm: &mut P)
+    where
+        P: PwmPin<Duty = u16> + ?Sized,
+    {
+        pwm.set_duty(duty(self.output_us(), pwm.get_max_duty()));
+    }
+}
@@ -0,0 +1,163 @@
+//
+// Telemetry packets for radio links: ADC samples from sensor nodes
+//
+// Packet layout (little endian):
+//  0      type
+//  1      node id
+//  2..4   sequence number
+//  4      number of channels
+//  5..    channel values, u16 each
+//  last   CRC-8
+//
+
+pub const MAX_CHANNELS: usize = 8;
+pub const MAX_PACKET: usize = 6 + 2 * MAX_CHANNELS;
+
+const TYPE_ADC: u8 = 0x01;
+const HEADER_SIZE: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    /// packet is too short or too long
+    Length,
+    /// unknown packet type
+    Type,
+    Crc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Telemetry {
+    pub node: u8,
+    pub seq: u16,
+    values: [u16; MAX_CHANNELS],
+    count: usize,
+}
+
+impl Telemetry {
+    /// Extra values above MAX_CHANNELS are dropped
+    pub fn new(node: u8, seq: u16, samples: &[u16]) -> Self {
+        let mut values = [0u16; MAX_CHANNELS];
+        let count = samples.len().min(MAX_CHANNELS);
+        values[..count].copy_from_slice(&samples[..count]);
+
+        Telemetry {
+            node,
+            seq,
+            values,
+            count,
+        }
+    }
+
+    pub fn values(&self) -> &[u16] {
+        &self.values[..self.count]
+    }
+
+    /// Encode packet into buffer, returns packet length
+    pub fn encode(&self, buf: &mut [u8; MAX_PACKET]) -> usize {
+        buf[0] = TYPE_ADC;
+        buf[1] = self.node;
+        buf[2..4].copy_from_slice(&self.seq.to_le_bytes());
+        buf[4] = self.count as u8;
+
+        for (n, v) in self.values().iter().enumerate() {
+            let pos = HEADER_SIZE + 2 * n;
+            buf[pos..pos + 2].copy_from_slice(&v.to_le_bytes());
+        }
+
+        let len = HEADER_SIZE + 2 * self.count;
+        buf[len] = crc8(&buf[..len]);
+
+        len + 1
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < HEADER_SIZE + 1 {
+            return Err(Error::Length);
+        }
+
+        let count = data[4] as usize;
+        let len = HEADER_SIZE + 2 * count;
+
+        if count > MAX_CHANNELS || data.len() != len + 1 {
+            return Err(Error::Length);
+        }
+
+        if crc8(&data[..len]) != data[len] {
+            return Err(Error::Crc);
+        }
+
+        if data[0] != TYPE_ADC {
+            return Err(Error::Type);
+        }
+
+        let mut values = [0u16; MAX_CHANNELS];
+        for (n, v) in values[..count].iter_mut().enumerate() {
+            let pos = HEADER_SIZE + 2 * n;
+            *v = u16::from_le_bytes([data[pos], data[pos + 1]]);
+        }
+
+        Ok(Telemetry {
+            node: data[1],
+            seq: u16::from_le_bytes([data[2], data[3]]),
+            values,
+            count,
+        })
+    }
+}
+
+/// Per-node sequence tracking on receiver side
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Link {
+    last: Option<u16>,
+    pub received: u32,
+    pub lost: u32,
+    pub duplicates: u32,
+}
+
+impl Link {
+    pub const fn new() -> Self {
+        Link {
+            last: None,
+            received: 0,
+            lost: 0,
+            duplicates: 0,
+        }
+    }
+
+    /// Account packet: returns false for duplicates
+    pub fn update(&mut self, seq: u16) -> bool {
+        if let Some(last) = self.last {
+            let gap = seq.wrapping_sub(last);
+
+            if gap == 0 {
+                self.duplicates += 1;
+                return false;
+            }
+
+            // large gaps are treated as sender restarts
+            if gap < 0x8000 {
+                self.lost += (gap - 1) as u32;
+            }
+        }
+
+        self.last = Some(seq);
+        self.received += 1;
+
+        true
+    }
+}
+
+/// CRC-8 with polynomial 0x07
+pub fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, b| {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                crc << 1 ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
@@ -0,0 +1,84 @@
+//
+// MIFARE Ultralight and NTAG21x (NFC Forum Type 2) identification
+//
+
+pub const PAGE_SIZE: usize = 4;
+pub const USER_START: u8 = 4;
+
+pub type Page = [u8; PAGE_SIZE];
+
+// capability container magic for NDEF formatted tags
+const CC_MAGIC: u8 = 0xe1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TagType {
+    Ultralight,
+    /// MF0UL11: 48 bytes of user memory
+    UltralightEv1,
+    /// MF0UL21: 128 bytes of user memory
+    UltralightEv1_21,
+    Ntag213,
+    Ntag215,
+    Ntag216,
+    Unknown,
+}
+
+impl TagType {
+    /// Identify tag by GET_VERSION response: product type and storage size
+    pub fn from_version(version: &[u8; 8]) -> Self {
+        match (version[2], version[6]) {
+            (0x03, 0x0b) => TagType::UltralightEv1,
+            (0x03, 0x0e) => TagType::UltralightEv1_21,
+            (0x04, 0x0f) => TagType::Ntag213,
+            (0x04, 0x11) => TagType::Ntag215,
+            (0x04, 0x13) => TagType::Ntag216,
+            _ => TagType::Unknown,
+        }
+    }
+
+    /// Total number of pages
+    pub fn pages(self) -> u8 {
+        match self {
+            TagType::Ultralight => 16,
+            TagType::UltralightEv1 => 20,
+            TagType::UltralightEv1_21 => 41,
+            TagType::Ntag213 => 45,
+            TagType::Ntag215 => 135,
+            TagType::Ntag216 => 231,
+            TagType::Unknown => 16,
+        }
+    }
+
+    /// First page after user memory: NTAG and MF0UL21 tags have 5 configuration
+    /// pages at the end, MF0UL11 has 4, original Ultralight has none
+    pub fn user_end(self) -> u8 {
+        match self {
+            TagType::Ultralight => self.pages(),
+            TagType::UltralightEv1 => self.pages() - 4,
+            _ => self.pages() - 5,
+        }
+    }
+}
+
+/// Capability container from page 3
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capability {
+    pub version: u8,
+    /// size of NDEF data area in bytes
+    pub size: usize,
+    pub read_only: bool,
+}
+
+impl Capability {
+    pub fn decode(page: &Page) -> Option<Self> {
+        if page[0] != CC_MAGIC {
+            return None;
+        }
+
+        Some(Capability {
+            version: page[1],
+            size: page[2] as usize * 8,
+            read_only: page[3] & 0x0f != 0,
+        })
+    }
+}
@@ -0,0 +1,24 @@
+//
+// HC-SR04 echo timing to distance conversion with temperature compensation
+//
+
+use embedded_hal::blocking::i2c;
+use lm75::Lm75;
+
+/// Speed of sound in mm/s for temperature in 0.1 degrees Celsius
+pub fn speed_of_sound(temp_dc: i32) -> u32 {
+    (331_300 + 606 * temp_dc / 10).max(0) as u32
+}
+
+/// LM75 temperature in 0.1 degrees Celsius for speed_of_sound
+pub fn read_temperature_dc<I2C, E>(sensor: &mut Lm75<I2C>) -> Result<i32, lm75::Error<E>>
+where
+    I2C: i2c::Write<Error = E> + i2c::WriteRead<Error = E>,
+{
+    sensor.read_temperature().map(|t| (t * 10.0) as i32)
+}
+
+/// Distance to object in mm for echo pulse width in us
+pub fn distance_mm(echo_us: u32, speed: u32) -> u32 {
+    (echo_us as u64 * speed as u64 / 2_000_000) as u32
+}
@@ -0,0 +1,219 @@
+use blue_pill_logic::access::{
+    self, AccessControl, CardId, Command, Config, Decision, Error, Event, EventLog, Whitelist,
+    LOG_SIZE, STORAGE_SIZE, WHITELIST_SIZE,
+};
+
+const MASTER: [u8; 4] = [0xde, 0xad, 0xbe, 0xef];
+
+fn card(n: u8) -> CardId {
+    CardId::new(&[0x04, 0x10, 0x20, n]).unwrap()
+}
+
+fn controller() -> AccessControl {
+    let mut list = Whitelist::default();
+    list.add(card(1)).unwrap();
+    list.set_master(CardId::new(&MASTER));
+
+    AccessControl::new(list, Config::default())
+}
+
+#[test]
+fn card_id_parse_and_display() {
+    let id = CardId::parse("04:A1:b2:c3").unwrap();
+
+    assert_eq!(id.as_bytes(), [0x04, 0xa1, 0xb2, 0xc3]);
+    assert_eq!(id.to_string(), "04:a1:b2:c3");
+    assert_eq!(CardId::parse("04a1b2c3"), Some(id));
+
+    assert_eq!(CardId::parse(""), None);
+    assert_eq!(CardId::parse("04:a"), None);
+    assert_eq!(CardId::parse("04:zz"), None);
+    assert_eq!(CardId::parse("0102030405060708090a0b"), None);
+    assert!(CardId::parse("0102030405060708090a").is_some());
+}
+
+#[test]
+fn whitelist_add_remove() {
+    let mut list = Whitelist::default();
+
+    assert!(list.is_empty());
+    assert_eq!(list.add(card(1)), Ok(()));
+    assert_eq!(list.add(card(2)), Ok(()));
+    assert_eq!(list.add(card(1)), Err(Error::Exists));
+    assert_eq!(list.len(), 2);
+
+    assert_eq!(list.remove(&card(1)), Ok(()));
+    assert_eq!(list.remove(&card(1)), Err(Error::NotFound));
+    assert!(!list.contains(&card(1)));
+    assert!(list.contains(&card(2)));
+}
+
+#[test]
+fn whitelist_full() {
+    let mut list = Whitelist::default();
+
+    for n in 0..WHITELIST_SIZE {
+        list.add(card(n as u8)).unwrap();
+    }
+
+    assert_eq!(list.add(card(0xff)), Err(Error::Full));
+}
+
+#[test]
+fn storage_round_trip() {
+    let mut list = Whitelist::default();
+    list.add(card(1)).unwrap();
+    list.add(CardId::new(&[1, 2, 3, 4, 5, 6, 7]).unwrap())
+        .unwrap();
+    list.set_master(CardId::new(&MASTER));
+
+    let mut buf = [0u8; STORAGE_SIZE];
+    list.serialize(&mut buf);
+
+    let copy = Whitelist::deserialize(&buf).unwrap();
+    assert_eq!(copy.master(), list.master());
+    assert!(copy.iter().eq(list.iter()));
+
+    // without master
+    list.set_master(None);
+    list.serialize(&mut buf);
+    assert_eq!(Whitelist::deserialize(&buf).unwrap().master(), None);
+}
+
+#[test]
+fn storage_corruption() {
+    let mut buf = [0u8; STORAGE_SIZE];
+    Whitelist::default().serialize(&mut buf);
+
+    // blank eeprom
+    assert!(Whitelist::deserialize(&[0xff; STORAGE_SIZE]).is_none());
+
+    for pos in [0, 2, 3, 10, STORAGE_SIZE - 1].iter() {
+        let mut bad = buf;
+        bad[*pos] ^= 0x01;
+        assert!(Whitelist::deserialize(&bad).is_none(), "byte {}", pos);
+    }
+}
+
+#[test]
+fn grant_and_deny() {
+    let mut ac = controller();
+
+    assert_eq!(ac.on_card(card(1), 0), Some(Decision::Granted));
+    assert!(ac.poll(1000));
+    assert!(!ac.poll(3000));
+
+    assert_eq!(ac.on_card(card(2), 5000), Some(Decision::Denied));
+    assert!(!ac.poll(5000));
+}
+
+#[test]
+fn repeated_reads_are_debounced() {
+    let mut ac = controller();
+
+    assert_eq!(ac.on_card(card(1), 0), Some(Decision::Granted));
+    assert_eq!(ac.on_card(card(1), 1500), None);
+
+    // card kept on the reader keeps extending the window
+    assert_eq!(ac.on_card(card(1), 3000), None);
+    assert_eq!(ac.on_card(card(1), 5000), Some(Decision::Granted));
+
+    // another card is not debounced
+    assert_eq!(ac.on_card(card(2), 5100), Some(Decision::Denied));
+    assert_eq!(ac.log.len(), 3);
+}
+
+#[test]
+fn debounce_with_wrapping_clock() {
+    let mut ac = controller();
+
+    assert!(ac.on_card(card(1), u32::MAX - 500).is_some());
+    assert_eq!(ac.on_card(card(1), 500), None);
+}
+
+#[test]
+fn master_card_enrollment() {
+    let mut ac = controller();
+    let master = CardId::new(&MASTER).unwrap();
+
+    assert_eq!(ac.on_card(master, 0), Some(Decision::EnrollStart));
+    assert!(ac.enrolling());
+    assert_eq!(ac.on_card(card(2), 100), Some(Decision::Enrolled));
+    assert!(!ac.enrolling());
+    assert!(ac.dirty());
+    assert!(ac.take_dirty());
+    assert!(!ac.dirty());
+    assert!(!ac.take_dirty());
+
+    assert_eq!(ac.on_card(card(2), 5000), Some(Decision::Granted));
+
+    // enrolling card again removes it
+    assert_eq!(ac.on_card(master, 6000), Some(Decision::EnrollStart));
+    assert_eq!(ac.on_card(card(2), 6100), Some(Decision::Removed));
+    assert!(ac.take_dirty());
+    assert_eq!(ac.on_card(card(2), 9000), Some(Decision::Denied));
+
+    // second master read cancels enrollment
+    assert_eq!(ac.on_card(master, 12_000), Some(Decision::EnrollStart));
+    assert_eq!(ac.on_card(master, 15_000), None);
+    assert!(!ac.enrolling());
+}
+
+#[test]
+fn enroll_failure_and_timeout() {
+    let mut ac = controller();
+    for n in 2..=WHITELIST_SIZE as u8 {
+        ac.add(card(n)).unwrap();
+    }
+
+    ac.start_enroll(0);
+    assert_eq!(ac.on_card(card(0xff), 100), Some(Decision::EnrollFailed));
+
+    ac.start_enroll(1000);
+    ac.poll(10_999);
+    assert!(ac.enrolling());
+    ac.poll(11_000);
+    assert!(!ac.enrolling());
+}
+
+#[test]
+fn event_log_ring() {
+    let mut log = EventLog::default();
+    assert!(log.is_empty());
+
+    for n in 0..LOG_SIZE as u32 + 5 {
+        log.push(Event {
+            id: card(1),
+            time: n,
+            decision: Decision::Granted,
+        });
+    }
+
+    assert_eq!(log.len(), LOG_SIZE);
+    let times: Vec<_> = log.iter().map(|e| e.time).collect();
+    assert_eq!(times, (5..LOG_SIZE as u32 + 5).collect::<Vec<_>>());
+
+    log.clear();
+    assert_eq!(log.iter().count(), 0);
+}
+
+#[test]
+fn console_commands() {
+    assert_eq!(access::parse_command("help"), Some(Command::Help));
+    assert_eq!(access::parse_command("  list \r\n"), Some(Command::List));
+    assert_eq!(
+        access::parse_command("add 04:10:20:01"),
+        Some(Command::Add(card(1)))
+    );
+    assert_eq!(
+        access::parse_command("del 04102001"),
+        Some(Command::Del(card(1)))
+    );
+    assert_eq!(access::parse_command("clear"), Some(Command::ClearLog));
+
+    assert_eq!(access::parse_command(""), None);
+    assert_eq!(access::parse_command("add"), None);
+    assert_eq!(access::parse_command("add xyz"), None);
+    assert_eq!(access::parse_command("list all"), None);
+    assert_eq!(access::parse_command("add 01 02"), None);
+}
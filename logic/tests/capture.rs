@@ -0,0 +1,119 @@
+use blue_pill_logic::capture::{auto_range, Average, Config, Measurement, AVERAGE_SIZE};
+
+const CLK: u32 = 72_000_000;
+
+fn m(period: u64, high: u64) -> Measurement {
+    Measurement {
+        period,
+        high,
+        clk: CLK,
+    }
+}
+
+#[test]
+fn measurement() {
+    // 1kHz at 25% duty
+    let m = m(72_000, 18_000);
+
+    assert_eq!(m.frequency_mhz(), 1_000_000);
+    assert_eq!(m.frequency_hz(), 1000);
+    assert_eq!(m.period_us(), 1000);
+    assert_eq!(m.high_us(), 250);
+    assert_eq!(m.duty_permille(), 250);
+    assert_eq!(m.rpm(2), 30_000);
+    assert_eq!(m.rpm(0), 60_000);
+}
+
+#[test]
+fn measurement_without_period() {
+    let m = m(0, 100);
+
+    assert_eq!(m.frequency_mhz(), 0);
+    assert_eq!(m.duty_permille(), 0);
+    assert_eq!(m.rpm(1), 0);
+}
+
+#[test]
+fn high_time_longer_than_period() {
+    assert_eq!(m(1000, 1200).duty_permille(), 1000);
+}
+
+#[test]
+fn slow_signal() {
+    // 0.5Hz: period does not fit in u32 ticks after overflows
+    let m = m(144_000_000, 72_000_000);
+
+    assert_eq!(m.frequency_mhz(), 500);
+    assert_eq!(m.frequency_hz(), 0);
+    assert_eq!(m.period_us(), 2_000_000);
+    assert_eq!(m.duty_permille(), 500);
+}
+
+#[test]
+fn moving_average() {
+    let mut avg = Average::new(2);
+    assert_eq!(avg.get(CLK), None);
+
+    avg.push(&m(100, 10));
+    assert_eq!(avg.get(CLK), Some(m(100, 10)));
+
+    avg.push(&m(200, 30));
+    assert_eq!(avg.get(CLK), Some(m(150, 20)));
+
+    // oldest item is replaced
+    avg.push(&m(400, 50));
+    assert_eq!(avg.get(CLK), Some(m(300, 40)));
+
+    avg.clear();
+    assert_eq!(avg.get(CLK), None);
+}
+
+#[test]
+fn average_size_is_limited() {
+    let mut avg = Average::new(0);
+    avg.push(&m(100, 10));
+    avg.push(&m(200, 20));
+    assert_eq!(avg.get(CLK), Some(m(200, 20)));
+
+    let mut avg = Average::new(100);
+    for n in 1..=AVERAGE_SIZE as u64 + 1 {
+        avg.push(&m(n * 100, 0));
+    }
+    // 2..=9 averaged
+    assert_eq!(avg.get(CLK), Some(m(550, 0)));
+}
+
+#[test]
+fn default_config() {
+    let config = Config::default();
+
+    assert_eq!(config.timeout_ms, 2000);
+    assert!((1..=AVERAGE_SIZE).contains(&config.average));
+}
+
+#[test]
+fn prescaler_kept_in_range() {
+    assert_eq!(auto_range(50_000, 0), None);
+    assert_eq!(auto_range(1000, 0), None);
+    assert_eq!(auto_range(180_000, 2), None);
+    assert_eq!(auto_range(8192 * 4, 3), None);
+}
+
+#[test]
+fn prescaler_for_slow_signal() {
+    // counter would overflow: target is 0xc000 counts
+    assert_eq!(auto_range(100_000, 0), Some(2));
+    assert_eq!(auto_range(72_000_000, 0), Some(1464));
+
+    // longest period uses the largest prescaler
+    assert_eq!(auto_range(1 << 40, 0), Some(0xffff));
+    assert_eq!(auto_range(1 << 40, 0xffff), None);
+}
+
+#[test]
+fn prescaler_for_fast_signal() {
+    // too few counts for resolution
+    assert_eq!(auto_range(1000, 10), Some(0));
+    assert_eq!(auto_range(8191 * 4, 3), Some(0));
+    assert_eq!(auto_range(400_000, 99), Some(8));
+}
@@ -0,0 +1,211 @@
+use blue_pill_logic::clocks::{self, Bus, Domain, Error, Report, Source};
+
+const HSE_HZ: u32 = 8_000_000;
+
+// RCC_CFGR with SYSCLK from PLL fed by HSE, AHB not divided
+fn pll_cfgr(pllmul: u32, ppre1: u32, ppre2: u32, adcpre: u32, usbpre: bool) -> u32 {
+    0b10 | 0b10 << 2
+        | ppre1 << 8
+        | ppre2 << 11
+        | adcpre << 14
+        | 1 << 16
+        | (pllmul - 2) << 18
+        | (usbpre as u32) << 22
+}
+
+// PLL x9, APB1 /2, APB2 /1, ADC /6, USB /1.5
+fn tree_72mhz() -> Report {
+    Report::from_registers(pll_cfgr(9, 0b100, 0b000, 0b10, false), true, HSE_HZ)
+}
+
+// PLL x6, APB1 /2, APB2 /1, ADC /4, USB /1
+fn tree_48mhz() -> Report {
+    Report::from_registers(pll_cfgr(6, 0b100, 0b000, 0b01, true), true, HSE_HZ)
+}
+
+#[test]
+fn reset_state() {
+    let report = Report::from_registers(0, false, HSE_HZ);
+
+    assert_eq!(
+        report,
+        Report {
+            source: Source::Hsi,
+            sysclk: 8_000_000,
+            hclk: 8_000_000,
+            pclk1: 8_000_000,
+            pclk2: 8_000_000,
+            pclk1_tim: 8_000_000,
+            pclk2_tim: 8_000_000,
+            adcclk: 4_000_000,
+            usbclk: 0,
+        }
+    );
+    assert_eq!(report.validate(), Ok(()));
+    assert_eq!(report.check_usb(), Err(Error::NoUsbClock));
+}
+
+#[test]
+fn hse_72mhz() {
+    let report = tree_72mhz();
+
+    assert_eq!(
+        report,
+        Report {
+            source: Source::Pll,
+            sysclk: 72_000_000,
+            hclk: 72_000_000,
+            pclk1: 36_000_000,
+            pclk2: 72_000_000,
+            pclk1_tim: 72_000_000,
+            pclk2_tim: 72_000_000,
+            adcclk: 12_000_000,
+            usbclk: 48_000_000,
+        }
+    );
+    assert_eq!(report.validate(), Ok(()));
+    assert_eq!(report.check_usb(), Ok(()));
+}
+
+#[test]
+fn usb_48mhz() {
+    let report = tree_48mhz();
+
+    assert_eq!(report.sysclk, 48_000_000);
+    assert_eq!(report.pclk1, 24_000_000);
+    assert_eq!(report.pclk1_tim, 48_000_000);
+    assert_eq!(report.adcclk, 12_000_000);
+    assert_eq!(report.usbclk, 48_000_000);
+
+    assert_eq!(report.validate(), Ok(()));
+    assert_eq!(report.check_usb(), Ok(()));
+
+    // ws2812 on SPI2 and console on USART1
+    assert_eq!(report.check_spi(Bus::Apb1, 3_000_000, 50), Ok(3_000_000));
+    assert_eq!(report.check_uart(Bus::Apb2, 115_200), Ok(115_384));
+
+    // PLL output can not be divided to 48MHz
+    let report = Report::from_registers(pll_cfgr(7, 0b100, 0b000, 0b01, true), true, HSE_HZ);
+    assert_eq!(report.check_usb(), Err(Error::NoUsbClock));
+}
+
+#[test]
+fn too_fast() {
+    // APB1 not divided: 72MHz
+    let report = Report::from_registers(pll_cfgr(9, 0b000, 0b000, 0b10, false), true, HSE_HZ);
+    assert_eq!(
+        report.validate(),
+        Err(Error::TooFast {
+            domain: Domain::Pclk1,
+            hz: 72_000_000,
+            max: 36_000_000,
+        })
+    );
+
+    // ADC /2: 36MHz
+    let report = Report::from_registers(pll_cfgr(9, 0b100, 0b000, 0b00, false), true, HSE_HZ);
+    assert_eq!(
+        report.validate(),
+        Err(Error::TooFast {
+            domain: Domain::Adc,
+            hz: 36_000_000,
+            max: 14_000_000,
+        })
+    );
+
+    // PLL x16 from 8MHz HSE
+    let report = Report::from_registers(pll_cfgr(16, 0b100, 0b000, 0b11, false), true, HSE_HZ);
+    assert_eq!(
+        report.validate(),
+        Err(Error::TooFast {
+            domain: Domain::Sysclk,
+            hz: 128_000_000,
+            max: 72_000_000,
+        })
+    );
+}
+
+#[test]
+fn hsi_pll_and_hse_prescaler() {
+    // PLL x16 from HSI/2
+    let cfgr = pll_cfgr(16, 0b100, 0b000, 0b10, false) & !(1 << 16);
+    let report = Report::from_registers(cfgr, true, 0);
+    assert_eq!(report.sysclk, 64_000_000);
+    assert_eq!(report.validate(), Ok(()));
+
+    // HSE/2 into PLL
+    let cfgr = pll_cfgr(9, 0b100, 0b000, 0b10, false) | 1 << 17;
+    assert_eq!(
+        Report::from_registers(cfgr, true, HSE_HZ).sysclk,
+        36_000_000
+    );
+
+    // HSE directly, AHB /2
+    let cfgr = 0b01 | 0b01 << 2 | 0b1000 << 4;
+    let report = Report::from_registers(cfgr, false, HSE_HZ);
+    assert_eq!(report.source, Source::Hse);
+    assert_eq!(report.hclk, 4_000_000);
+}
+
+#[test]
+fn spi_baud() {
+    assert_eq!(clocks::spi_baud(36_000_000, 18_000_000), 18_000_000);
+    assert_eq!(clocks::spi_baud(36_000_000, 1_000_000), 1_125_000);
+    assert_eq!(clocks::spi_baud(72_000_000, 1), 281_250);
+
+    // ws2812 at 3MHz is not possible from 36MHz APB1
+    let report = tree_72mhz();
+    assert_eq!(clocks::spi_baud(36_000_000, 3_000_000), 2_250_000);
+    assert_eq!(
+        report.check_spi(Bus::Apb1, 3_000_000, 50),
+        Err(Error::SpiBaud {
+            requested: 3_000_000,
+            actual: 2_250_000,
+        })
+    );
+}
+
+#[test]
+fn uart_baud() {
+    assert_eq!(clocks::uart_baud(72_000_000, 115_200), 115_200);
+    assert_eq!(clocks::uart_baud(8_000_000, 115_200), 115_942);
+    assert_eq!(clocks::uart_baud(8_000_000, 10_000_000), 0);
+
+    assert_eq!(clocks::error_permille(115_200, 115_942), 6);
+    assert_eq!(clocks::error_permille(1000, 950), 50);
+
+    let report = Report::from_registers(0, false, HSE_HZ);
+    assert_eq!(report.check_uart(Bus::Apb2, 115_200), Ok(115_942));
+    assert_eq!(
+        report.check_uart(Bus::Apb2, 3_000_000),
+        Err(Error::UartBaud {
+            requested: 3_000_000,
+            actual: 4_000_000,
+        })
+    );
+}
+
+#[test]
+fn zero_baud() {
+    let report = tree_72mhz();
+
+    assert_eq!(report.check_spi(Bus::Apb1, 0, 50), Err(Error::ZeroBaud));
+    assert_eq!(report.check_uart(Bus::Apb2, 0), Err(Error::ZeroBaud));
+
+    assert_eq!(clocks::spi_baud(36_000_000, 0), 0);
+    assert_eq!(clocks::uart_baud(72_000_000, 0), 0);
+    assert_eq!(clocks::error_permille(0, 0), u32::MAX);
+}
+
+#[test]
+fn display() {
+    assert_eq!(
+        tree_72mhz().to_string(),
+        "SYSCLK 72000000 Hz from Pll\n\
+         HCLK   72000000 Hz\n\
+         PCLK1  36000000 Hz, timers 72000000 Hz\n\
+         PCLK2  72000000 Hz, timers 72000000 Hz\n\
+         ADC    12000000 Hz\n\
+         USB    48000000 Hz"
+    );
+}
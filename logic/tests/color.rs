@@ -0,0 +1,116 @@
+use blue_pill_logic::color::{self, Hsl, Hsv, PowerLimit, Temperature};
+use smart_leds::RGB8;
+
+const RED: RGB8 = RGB8 { r: 255, g: 0, b: 0 };
+const WHITE: RGB8 = RGB8 {
+    r: 255,
+    g: 255,
+    b: 255,
+};
+
+#[test]
+fn scale8_limits() {
+    assert_eq!(color::scale8(200, 255), 200);
+    assert_eq!(color::scale8(200, 0), 0);
+    assert_eq!(color::scale8(255, 127), 127);
+    assert_eq!(color::scale(WHITE, 255), WHITE);
+}
+
+#[test]
+fn gamma_keeps_endpoints() {
+    assert_eq!(color::gamma(RGB8::default()), RGB8::default());
+    assert_eq!(color::gamma(WHITE), WHITE);
+
+    // dark levels are suppressed, curve is monotonic
+    let mut prev = 0;
+    for v in 0..=255u8 {
+        let g = color::gamma(RGB8 { r: v, g: v, b: v }).r;
+        assert!(g <= v && g >= prev);
+        prev = g;
+    }
+}
+
+#[test]
+fn hsv_primaries() {
+    assert_eq!(Hsv::new(0, 255, 255).to_rgb(), RED);
+    assert_eq!(
+        Hsv::new(0, 0, 100).to_rgb(),
+        RGB8 {
+            r: 100,
+            g: 100,
+            b: 100
+        }
+    );
+    assert_eq!(Hsv::from_rgb(RED), Hsv::new(0, 255, 255));
+    assert_eq!(Hsv::from_rgb(RGB8 { r: 0, g: 255, b: 0 }).h, 85);
+    assert_eq!(Hsv::from_rgb(RGB8 { r: 0, g: 0, b: 255 }).h, 171);
+}
+
+#[test]
+fn hsv_round_trip() {
+    // integer conversion loses a few steps on each channel
+    for h in (0..=255u8).step_by(5) {
+        let rgb = Hsv::new(h, 255, 255).to_rgb();
+        let back = Hsv::from_rgb(rgb).to_rgb();
+
+        for (a, b) in [(rgb.r, back.r), (rgb.g, back.g), (rgb.b, back.b)].iter() {
+            assert!(
+                (*a as i32 - *b as i32).abs() <= 8,
+                "hue {}: {:?} {:?}",
+                h,
+                rgb,
+                back
+            );
+        }
+    }
+}
+
+#[test]
+fn hsl_lightness() {
+    assert_eq!(Hsl::new(0, 255, 0).to_rgb(), RGB8::default());
+    assert_eq!(Hsl::new(0, 255, 255).to_rgb(), WHITE);
+    assert_eq!(Hsl::new(0, 0, 128).to_hsv().s, 0);
+}
+
+#[test]
+fn temperature_correction() {
+    let mut frame = [WHITE; 4];
+
+    Temperature::Uncorrected.correct_frame(&mut frame);
+    assert_eq!(frame, [WHITE; 4]);
+
+    Temperature::Candle.correct_frame(&mut frame);
+    assert_eq!(frame[0], Temperature::Candle.factors());
+}
+
+#[test]
+fn power_limit() {
+    let limit = PowerLimit::new(500);
+    let mut frame = [WHITE; 10];
+
+    // 10mA idle and 600mA for 10 white LEDs
+    assert_eq!(limit.estimate(&frame), 610);
+
+    let factor = limit.apply(&mut frame);
+    assert!(factor < 255);
+    assert!(limit.estimate(&frame) <= 500);
+
+    let mut dark = [RGB8 { r: 10, g: 0, b: 0 }; 10];
+    assert_eq!(limit.apply(&mut dark), 255);
+    assert_eq!(dark[0].r, 10);
+}
+
+#[test]
+fn power_limit_below_idle_current() {
+    let limit = PowerLimit::new(250);
+
+    // 300mA idle alone is over the limit
+    let mut frame = [RGB8::default(); 300];
+    assert_eq!(limit.estimate(&frame), 300);
+    assert_eq!(limit.apply(&mut frame), 0);
+    assert_eq!(frame, [RGB8::default(); 300]);
+
+    let mut frame = [WHITE; 300];
+    assert_eq!(limit.apply(&mut frame), 0);
+    assert_eq!(frame, [RGB8::default(); 300]);
+}
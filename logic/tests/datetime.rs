@@ -0,0 +1,85 @@
+use blue_pill_logic::datetime::{self, DateTime, Weekday};
+
+fn dt(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> DateTime {
+    DateTime::new(year, month, day, hour, minute, second).unwrap()
+}
+
+#[test]
+fn unix_epoch_and_limits() {
+    assert_eq!(DateTime::from_unix(0), dt(1970, 1, 1, 0, 0, 0));
+    assert_eq!(DateTime::from_unix(u32::MAX), dt(2106, 2, 7, 6, 28, 15));
+    assert_eq!(dt(2106, 2, 7, 6, 28, 15).to_unix(), u32::MAX);
+}
+
+#[test]
+fn known_timestamps() {
+    let cases = [
+        (951_782_400, dt(2000, 2, 29, 0, 0, 0)),
+        (1_000_000_000, dt(2001, 9, 9, 1, 46, 40)),
+        (1_234_567_890, dt(2009, 2, 13, 23, 31, 30)),
+        (2_147_483_647, dt(2038, 1, 19, 3, 14, 7)),
+        (4_102_444_800, dt(2100, 1, 1, 0, 0, 0)),
+    ];
+
+    for (t, d) in cases.iter() {
+        assert_eq!(DateTime::from_unix(*t), *d);
+        assert_eq!(d.to_unix(), *t);
+    }
+}
+
+#[test]
+fn round_trip() {
+    for t in (0..=u32::MAX).step_by(86_399 * 7 + 13) {
+        assert_eq!(DateTime::from_unix(t).to_unix(), t);
+    }
+}
+
+#[test]
+fn leap_years() {
+    assert!(datetime::is_leap_year(2000));
+    assert!(datetime::is_leap_year(2024));
+    assert!(!datetime::is_leap_year(2100));
+    assert!(!datetime::is_leap_year(2023));
+
+    assert_eq!(datetime::days_in_month(2024, 2), 29);
+    assert_eq!(datetime::days_in_month(2100, 2), 28);
+    assert_eq!(datetime::days_in_month(2023, 13), 0);
+}
+
+#[test]
+fn validation() {
+    assert!(DateTime::new(1969, 12, 31, 23, 59, 59).is_none());
+    assert!(DateTime::new(2023, 2, 29, 0, 0, 0).is_none());
+    assert!(DateTime::new(2024, 4, 31, 0, 0, 0).is_none());
+    assert!(DateTime::new(2024, 1, 1, 24, 0, 0).is_none());
+    assert!(DateTime::new(2106, 2, 7, 6, 28, 16).is_none());
+    assert!(DateTime::new(2024, 2, 29, 23, 59, 59).is_some());
+}
+
+#[test]
+fn weekdays() {
+    assert_eq!(dt(1970, 1, 1, 0, 0, 0).weekday(), Weekday::Thursday);
+    assert_eq!(dt(2000, 1, 1, 12, 0, 0).weekday(), Weekday::Saturday);
+    assert_eq!(dt(2024, 3, 4, 0, 0, 0).weekday(), Weekday::Monday);
+    assert_eq!(dt(2106, 2, 7, 0, 0, 0).weekday(), Weekday::Sunday);
+}
+
+#[test]
+fn parse_and_display() {
+    let d = dt(2024, 3, 4, 5, 6, 7);
+
+    assert_eq!(DateTime::parse("2024-03-04 05:06:07"), Some(d));
+    assert_eq!(DateTime::parse(" 2024-03-04T05:06:07\r\n"), Some(d));
+    assert_eq!(d.to_string(), "2024-03-04 05:06:07");
+
+    assert_eq!(DateTime::parse("2024-03-04"), None);
+    assert_eq!(DateTime::parse("2024/03/04 05:06:07"), None);
+    assert_eq!(DateTime::parse("2024-03-04 05:06:0x"), None);
+    assert_eq!(DateTime::parse("2024-02-30 00:00:00"), None);
+}
+
+#[test]
+fn ordering() {
+    assert!(dt(2024, 1, 1, 0, 0, 0) < dt(2024, 1, 1, 0, 0, 1));
+    assert!(dt(2023, 12, 31, 23, 59, 59) < dt(2024, 1, 1, 0, 0, 0));
+}
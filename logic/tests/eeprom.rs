@@ -0,0 +1,144 @@
+use blue_pill_logic::access::{self, CardId, Whitelist, WhitelistWriter, STORAGE_SIZE};
+use eeprom24x::{Eeprom24x, SlaveAddr};
+use embedded_hal_mock::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+
+// 24C04: 512 bytes, 16 byte pages, address bit 8 is in device address
+const PAGE: usize = 16;
+const DEVICE: u8 = 0x50;
+
+fn whitelist() -> Whitelist {
+    let mut list = Whitelist::default();
+    list.add(CardId::parse("04:a1:b2:c3").unwrap()).unwrap();
+    list.add(CardId::parse("11:22:33:44:55:66:77").unwrap())
+        .unwrap();
+    list.set_master(CardId::parse("de:ad:be:ef"));
+    list
+}
+
+fn stored() -> [u8; STORAGE_SIZE] {
+    let mut buf = [0u8; STORAGE_SIZE];
+    whitelist().serialize(&mut buf);
+    buf
+}
+
+fn page_write(addr: usize, data: &[u8]) -> I2cTransaction {
+    let mut payload = vec![addr as u8];
+    payload.extend_from_slice(data);
+
+    I2cTransaction::write(DEVICE | (addr >> 8) as u8, payload)
+}
+
+#[test]
+fn save_whitelist() {
+    let buf = stored();
+
+    let expectations: Vec<_> = buf
+        .chunks(PAGE)
+        .enumerate()
+        .map(|(n, page)| page_write(n * PAGE, page))
+        .collect();
+
+    // storage crosses 256 byte boundary: tail goes to the second device address
+    assert_eq!(expectations.len(), 23);
+    assert_eq!(expectations[16], page_write(0x100, &buf[0x100..0x110]));
+
+    let mut i2c = I2cMock::new(&expectations);
+    let mut eeprom = Eeprom24x::new_24x04(i2c.clone(), SlaveAddr::default());
+    let mut waits = 0;
+
+    access::save_whitelist(&mut eeprom, &whitelist(), || waits += 1).unwrap();
+
+    assert_eq!(waits, 23);
+    i2c.done();
+}
+
+#[test]
+fn save_page_by_page() {
+    let buf = stored();
+
+    let expectations = [
+        page_write(0, &buf[..PAGE]),
+        page_write(PAGE, &buf[PAGE..2 * PAGE]),
+    ];
+
+    let mut i2c = I2cMock::new(&expectations);
+    let mut eeprom = Eeprom24x::new_24x04(i2c.clone(), SlaveAddr::default());
+    let mut writer = WhitelistWriter::new(&whitelist());
+
+    writer.write_next(&mut eeprom).unwrap();
+    writer.write_next(&mut eeprom).unwrap();
+    assert!(!writer.done());
+    i2c.done();
+
+    let expectations: Vec<_> = buf[2 * PAGE..]
+        .chunks(PAGE)
+        .enumerate()
+        .map(|(n, page)| page_write((n + 2) * PAGE, page))
+        .collect();
+    assert_eq!(expectations.len(), 21);
+
+    let mut i2c = I2cMock::new(&expectations);
+    let mut eeprom = Eeprom24x::new_24x04(i2c.clone(), SlaveAddr::default());
+
+    while !writer.done() {
+        writer.write_next(&mut eeprom).unwrap();
+    }
+
+    // nothing is written after the last page
+    writer.write_next(&mut eeprom).unwrap();
+    i2c.done();
+}
+
+#[test]
+fn save_stops_on_error() {
+    let buf = stored();
+
+    let expectations = [
+        page_write(0, &buf[..PAGE]),
+        page_write(PAGE, &buf[PAGE..2 * PAGE])
+            .with_error(embedded_hal_mock::MockError::Io(std::io::ErrorKind::Other)),
+    ];
+
+    let mut i2c = I2cMock::new(&expectations);
+    let mut eeprom = Eeprom24x::new_24x04(i2c.clone(), SlaveAddr::default());
+    let mut waits = 0;
+
+    assert!(access::save_whitelist(&mut eeprom, &whitelist(), || waits += 1).is_err());
+
+    assert_eq!(waits, 1);
+    i2c.done();
+}
+
+#[test]
+fn load_whitelist() {
+    let expectations = [I2cTransaction::write_read(
+        DEVICE,
+        vec![0x00],
+        stored().to_vec(),
+    )];
+
+    let mut i2c = I2cMock::new(&expectations);
+    let mut eeprom = Eeprom24x::new_24x04(i2c.clone(), SlaveAddr::default());
+
+    let list = access::load_whitelist(&mut eeprom).unwrap().unwrap();
+    assert!(list.iter().eq(whitelist().iter()));
+    assert_eq!(list.master(), whitelist().master());
+
+    i2c.done();
+}
+
+#[test]
+fn load_blank_eeprom() {
+    let expectations = [I2cTransaction::write_read(
+        DEVICE,
+        vec![0x00],
+        vec![0xff; STORAGE_SIZE],
+    )];
+
+    let mut i2c = I2cMock::new(&expectations);
+    let mut eeprom = Eeprom24x::new_24x04(i2c.clone(), SlaveAddr::default());
+
+    assert!(access::load_whitelist(&mut eeprom).unwrap().is_none());
+
+    i2c.done();
+}
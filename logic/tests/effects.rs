@@ -0,0 +1,325 @@
+use blue_pill_logic::color;
+use blue_pill_logic::effects::{
+    self, Animation, Breathing, Chase, ColorWipe, Comet, Effect, Effects, Fire, Layers, Rainbow,
+    Reverse, Rng, Twinkle,
+};
+use smart_leds::RGB8;
+
+const OFF: RGB8 = RGB8 { r: 0, g: 0, b: 0 };
+const RED: RGB8 = RGB8 { r: 255, g: 0, b: 0 };
+const GREEN: RGB8 = RGB8 { r: 0, g: 255, b: 0 };
+const BLUE: RGB8 = RGB8 { r: 0, g: 0, b: 255 };
+const WHITE: RGB8 = RGB8 {
+    r: 255,
+    g: 255,
+    b: 255,
+};
+
+fn gray(v: u8) -> RGB8 {
+    RGB8 { r: v, g: v, b: v }
+}
+
+#[test]
+fn wheel_and_sine() {
+    assert_eq!(effects::wheel(0), RED);
+    assert_eq!(effects::wheel(85), GREEN);
+    assert_eq!(effects::wheel(170), BLUE);
+    assert_eq!(effects::wheel(255), RED);
+
+    assert_eq!(effects::sin8(0), 128);
+    assert!(effects::sin8(64) >= 254);
+    assert!(effects::sin8(192) <= 1);
+}
+
+#[test]
+fn rng_is_deterministic() {
+    let mut a = Rng::new(42);
+    let mut b = Rng::new(42);
+
+    for _ in 0..100 {
+        assert_eq!(a.next_u32(), b.next_u32());
+        assert!(a.below(10) < 10);
+        b.below(10);
+    }
+
+    // zero seed would get xorshift stuck
+    assert_ne!(Rng::new(0).next_u32(), 0);
+    assert_eq!(Rng::new(1).below(0), 0);
+}
+
+#[test]
+fn rainbow() {
+    let mut rainbow = Rainbow {
+        speed: 85,
+        spread: 85,
+    };
+    let mut frame = [OFF; 3];
+
+    rainbow.render(&mut frame, 0);
+    assert_eq!(frame, [RED, GREEN, BLUE]);
+
+    rainbow.render(&mut frame, 1000);
+    assert_eq!(frame, [GREEN, BLUE, RED]);
+}
+
+#[test]
+fn chase() {
+    let mut chase = Chase {
+        color: RED,
+        spacing: 3,
+        speed: 10,
+    };
+    let mut frame = [OFF; 8];
+
+    chase.render(&mut frame, 0);
+    assert_eq!(frame, [RED, OFF, OFF, RED, OFF, OFF, RED, OFF]);
+
+    chase.render(&mut frame, 100);
+    assert_eq!(frame, [OFF, RED, OFF, OFF, RED, OFF, OFF, RED]);
+
+    chase.render(&mut frame, 300);
+    assert_eq!(frame, [RED, OFF, OFF, RED, OFF, OFF, RED, OFF]);
+}
+
+#[test]
+fn color_wipe() {
+    let mut wipe = ColorWipe {
+        color: RED,
+        speed: 1000,
+    };
+    let mut frame = [OFF; 4];
+
+    wipe.render(&mut frame, 0);
+    assert_eq!(frame, [OFF; 4]);
+
+    wipe.render(&mut frame, 2);
+    assert_eq!(frame, [RED, RED, OFF, OFF]);
+
+    wipe.render(&mut frame, 4);
+    assert_eq!(frame, [RED; 4]);
+
+    wipe.render(&mut frame, 6);
+    assert_eq!(frame, [OFF, OFF, RED, RED]);
+
+    wipe.render(&mut frame, 8);
+    assert_eq!(frame, [OFF; 4]);
+}
+
+#[test]
+fn breathing() {
+    let mut breathing = Breathing {
+        color: RED,
+        period_ms: 1000,
+    };
+    let mut frame = [WHITE; 3];
+
+    breathing.render(&mut frame, 0);
+    assert_eq!(frame, [OFF; 3]);
+
+    breathing.render(&mut frame, 250);
+    assert_eq!(frame, [RGB8 { r: 64, g: 0, b: 0 }; 3]);
+
+    breathing.render(&mut frame, 500);
+    assert_eq!(frame, [RED; 3]);
+
+    breathing.render(&mut frame, 1000);
+    assert_eq!(frame, [OFF; 3]);
+}
+
+#[test]
+fn comet() {
+    let mut comet = Comet {
+        color: WHITE,
+        tail: 3,
+        speed: 1000,
+    };
+    let mut frame = [OFF; 6];
+
+    comet.render(&mut frame, 0);
+    assert_eq!(frame, [WHITE, OFF, OFF, OFF, OFF, OFF]);
+
+    comet.render(&mut frame, 4);
+    assert_eq!(frame, [OFF, OFF, gray(85), gray(170), WHITE, OFF]);
+
+    // tail leaves the strip before the head starts again
+    comet.render(&mut frame, 7);
+    assert_eq!(frame, [OFF, OFF, OFF, OFF, OFF, gray(85)]);
+
+    comet.render(&mut frame, 9);
+    assert_eq!(frame, [WHITE, OFF, OFF, OFF, OFF, OFF]);
+}
+
+#[test]
+fn twinkle() {
+    let mut twinkle = Twinkle::new(BLUE, 0, 64, 7);
+    let mut frame = [WHITE; 16];
+
+    // no new twinkles: strip stays dark
+    twinkle.render(&mut frame, 0);
+    assert_eq!(frame, [OFF; 16]);
+
+    // one LED lights up at full brightness per frame
+    twinkle.density = 255;
+    twinkle.fade = 255;
+
+    for t in 1..10 {
+        twinkle.render(&mut frame, t);
+
+        let lit: Vec<_> = (0..16).filter(|i| frame[*i] != OFF).collect();
+        assert_eq!(lit.len(), 1);
+
+        let mut expected = [OFF; 16];
+        expected[lit[0]] = BLUE;
+        assert_eq!(frame, expected);
+    }
+
+    // and then fades out
+    let lit = frame.iter().position(|c| *c != OFF).unwrap();
+    twinkle.density = 0;
+    twinkle.fade = 64;
+
+    let mut expected = [OFF; 16];
+    for level in [191, 127, 63, 0].iter() {
+        expected[lit] = color::scale(BLUE, *level);
+        twinkle.render(&mut frame, 0);
+        assert_eq!(frame, expected);
+    }
+}
+
+#[test]
+fn heat_palette() {
+    assert_eq!(effects::heat_color(0), OFF);
+    assert_eq!(effects::heat_color(85), RGB8 { r: 252, g: 0, b: 0 });
+    assert_eq!(
+        effects::heat_color(170),
+        RGB8 {
+            r: 255,
+            g: 252,
+            b: 0
+        }
+    );
+    assert_eq!(
+        effects::heat_color(255),
+        RGB8 {
+            r: 255,
+            g: 255,
+            b: 252
+        }
+    );
+}
+
+#[test]
+fn fire() {
+    // no sparks: strip stays dark
+    let mut fire = Fire::new(55, 0, 1);
+    let mut frame = [WHITE; 10];
+
+    fire.render(&mut frame, 0);
+    assert_eq!(frame, [OFF; 10]);
+
+    // sparks start near the bottom and rise
+    fire.sparking = 255;
+    for _ in 0..20 {
+        fire.render(&mut frame, 0);
+    }
+
+    assert!(frame[..7].iter().any(|c| c.r == 255));
+    assert!(frame.iter().all(|c| c.b <= c.g && c.g <= c.r));
+}
+
+#[test]
+fn fire_on_short_strips() {
+    // strongest cooling on a single LED
+    let mut fire = Fire::new(255, 255, 3);
+    let mut frame = [OFF; 1];
+    let mut lit = 0;
+
+    for _ in 0..100 {
+        fire.render(&mut frame, 0);
+        if frame[0] != OFF {
+            lit += 1;
+        }
+    }
+
+    assert!(lit > 0);
+    fire.render(&mut [], 0);
+}
+
+#[test]
+fn layers() {
+    let mut layers = Layers::new(
+        Chase {
+            color: RED,
+            spacing: 2,
+            speed: 0,
+        },
+        Reverse(Chase {
+            color: BLUE,
+            spacing: 2,
+            speed: 0,
+        }),
+    );
+    let mut frame = [OFF; 4];
+
+    layers.render(&mut frame, 0);
+    assert_eq!(frame, [RED, BLUE, RED, BLUE]);
+
+    // colors are added with saturation
+    layers.top = Reverse(Chase {
+        color: WHITE,
+        spacing: 1,
+        speed: 0,
+    });
+    layers.render(&mut frame, 0);
+    assert_eq!(frame, [WHITE; 4]);
+}
+
+#[test]
+fn reverse() {
+    let mut wipe = Reverse(ColorWipe {
+        color: GREEN,
+        speed: 1000,
+    });
+    let mut frame = [OFF; 5];
+
+    wipe.render(&mut frame, 2);
+    assert_eq!(frame, [OFF, OFF, OFF, GREEN, GREEN]);
+}
+
+#[test]
+fn effects_dispatch() {
+    let mut effect = Effects::Chase(Chase {
+        color: GREEN,
+        spacing: 2,
+        speed: 0,
+    });
+    let mut frame = [OFF; 4];
+
+    effect.render(&mut frame, 0);
+    assert_eq!(frame, [GREEN, OFF, GREEN, OFF]);
+}
+
+#[test]
+fn animation_brightness() {
+    let mut anim = Animation::new(Rainbow {
+        speed: 0,
+        spread: 0,
+    });
+    let mut frame = [OFF; 2];
+
+    anim.brightness = 127;
+    anim.next_frame(&mut frame, 20);
+
+    assert_eq!(anim.time(), 20);
+    assert_eq!(frame, [RGB8 { r: 127, g: 0, b: 0 }; 2]);
+
+    anim.set_effect(Rainbow {
+        speed: 85,
+        spread: 0,
+    });
+    assert_eq!(anim.time(), 0);
+
+    anim.brightness = 255;
+    anim.next_frame(&mut frame, 1000);
+    assert_eq!(frame, [GREEN; 2]);
+}
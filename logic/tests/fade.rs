@@ -0,0 +1,170 @@
+use blue_pill_logic::fade::{Fader, Ramp, SoftPwm, Step, BLINK_SLOW, HEARTBEAT, MAX_LEVEL};
+use core::convert::Infallible;
+use embedded_hal::digital::v2::OutputPin;
+use embedded_hal::PwmPin;
+
+// levels after each tick of dt ms
+fn run(fader: &mut Fader, dt: u32, n: usize) -> Vec<u8> {
+    (0..n).map(|_| fader.tick(dt)).collect()
+}
+
+#[derive(Default)]
+struct Pin {
+    levels: Vec<bool>,
+}
+
+impl OutputPin for Pin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.levels.push(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.levels.push(true);
+        Ok(())
+    }
+}
+
+#[test]
+fn ramp_duty() {
+    assert_eq!(Ramp::Linear.duty(0, 1000), 0);
+    assert_eq!(Ramp::Linear.duty(128, 1000), 501);
+    assert_eq!(Ramp::Linear.duty(MAX_LEVEL, 1000), 1000);
+
+    assert_eq!(Ramp::Gamma.duty(0, 1000), 0);
+    assert_eq!(Ramp::Gamma.duty(128, 1000), 126);
+    assert_eq!(Ramp::Gamma.duty(MAX_LEVEL, 1000), 1000);
+    assert_eq!(Ramp::Gamma.duty(MAX_LEVEL, u16::MAX), u16::MAX);
+}
+
+#[test]
+fn fade_up_and_down() {
+    let mut f = Fader::new(Ramp::Linear);
+
+    f.set(0);
+    assert!(!f.active());
+
+    f.fade_to(200, 100);
+    assert!(f.active());
+    assert_eq!(run(&mut f, 25, 5), [50, 100, 150, 200, 200]);
+    assert!(!f.active());
+
+    f.fade_to(0, 100);
+    assert_eq!(run(&mut f, 30, 4), [140, 80, 20, 0]);
+    assert_eq!(f.duty(1000), 0);
+}
+
+#[test]
+fn breathe() {
+    let mut f = Fader::new(Ramp::Linear);
+
+    f.breathe(1000);
+    assert_eq!(run(&mut f, 250, 5), [127, 255, 128, 0, 127]);
+    assert!(f.active());
+}
+
+#[test]
+fn pattern_with_fades() {
+    static STEPS: &[Step] = &[Step::fade(200, 100), Step::hold(50, 50)];
+
+    let mut f = Fader::new(Ramp::Linear);
+    f.pattern(STEPS);
+    assert_eq!(run(&mut f, 50, 6), [100, 50, 50, 125, 50, 50]);
+
+    // long ticks skip whole steps
+    f.set(0);
+    f.pattern(STEPS);
+    assert_eq!(f.tick(350), 125);
+}
+
+#[test]
+fn heartbeat() {
+    let mut f = Fader::new(Ramp::Linear);
+
+    f.pattern(HEARTBEAT);
+
+    let mut expected = vec![255, 0, 0, 0, 255, 255];
+    expected.extend_from_slice(&[0; 18]);
+    expected.push(255);
+
+    assert_eq!(run(&mut f, 40, 25), expected);
+}
+
+#[test]
+fn blink_slow() {
+    let mut f = Fader::new(Ramp::Linear);
+
+    f.pattern(BLINK_SLOW);
+    assert_eq!(run(&mut f, 500, 5), [255, 0, 0, 255, 255]);
+}
+
+#[test]
+fn zero_length_pattern() {
+    static STEPS: &[Step] = &[Step::hold(7, 0), Step::hold(9, 0)];
+
+    let mut f = Fader::new(Ramp::Linear);
+    f.pattern(STEPS);
+    assert_eq!(f.tick(10), 7);
+}
+
+#[test]
+fn blink_code() {
+    let mut f = Fader::new(Ramp::Linear);
+
+    f.blink_code(2);
+
+    // two 200ms blinks with 300ms gaps, then 1.5s pause
+    let mut expected = vec![255, 0, 0, 0, 255, 255, 0, 0, 0];
+    expected.extend_from_slice(&[0; 15]);
+    expected.push(255);
+
+    assert_eq!(run(&mut f, 100, 25), expected);
+}
+
+#[test]
+fn soft_pwm() {
+    let mut pwm = SoftPwm::new(Pin::default(), 4, false);
+
+    pwm.set_duty(1);
+    for _ in 0..8 {
+        pwm.tick();
+    }
+
+    let pin = pwm.release();
+    assert_eq!(
+        pin.levels,
+        [false, true, false, false, false, true, false, false, false]
+    );
+}
+
+#[test]
+fn soft_pwm_inverted() {
+    let mut pwm = SoftPwm::new(Pin::default(), 4, true);
+
+    pwm.set_duty(3);
+    for _ in 0..4 {
+        pwm.tick();
+    }
+
+    let pin = pwm.release();
+    assert_eq!(pin.levels, [true, false, false, false, true]);
+}
+
+#[test]
+fn soft_pwm_duty() {
+    let mut pwm = SoftPwm::new(Pin::default(), 4, false);
+    assert_eq!(pwm.get_max_duty(), 4);
+
+    pwm.set_duty(10);
+    assert_eq!(pwm.get_duty(), 4);
+
+    pwm.disable();
+    assert_eq!(pwm.get_duty(), 0);
+
+    let mut f = Fader::new(Ramp::Linear);
+    f.set(128);
+    f.apply(&mut pwm);
+    assert_eq!(pwm.get_duty(), 2);
+}
@@ -0,0 +1,56 @@
+use blue_pill_logic::filter::Median;
+
+#[test]
+fn empty() {
+    let m = Median::<5>::new();
+
+    assert!(m.is_empty());
+    assert_eq!(m.get(), None);
+}
+
+#[test]
+fn rejects_spikes() {
+    let mut m = Median::<5>::new();
+
+    for v in [100, 102, 9999, 101, 0].iter() {
+        m.push(*v);
+    }
+
+    assert_eq!(m.len(), 5);
+    assert_eq!(m.get(), Some(101));
+}
+
+#[test]
+fn partially_filled() {
+    let mut m = Median::<5>::new();
+
+    m.push(10);
+    assert_eq!(m.get(), Some(10));
+
+    m.push(30);
+    m.push(20);
+    assert_eq!(m.get(), Some(20));
+}
+
+#[test]
+fn keeps_last_values() {
+    let mut m = Median::<3>::new();
+
+    for v in [1000, 1000, 1000, 5, 6, 7].iter() {
+        m.push(*v);
+    }
+
+    assert_eq!(m.len(), 3);
+    assert_eq!(m.get(), Some(6));
+
+    m.clear();
+    assert_eq!(m.get(), None);
+}
+
+#[test]
+fn zero_size() {
+    let mut m = Median::<0>::new();
+
+    m.push(1);
+    assert_eq!(m.get(), None);
+}
@@ -0,0 +1,47 @@
+use blue_pill_logic::font::{glyph, pixel, text_width, ADVANCE, HEIGHT, WIDTH};
+
+// glyph as rows of '#' and '.'
+fn render(c: char) -> Vec<String> {
+    (0..HEIGHT)
+        .map(|y| {
+            (0..WIDTH)
+                .map(|x| if pixel(c, x, y) { '#' } else { '.' })
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn digit() {
+    assert_eq!(
+        render('1'),
+        ["..#..", ".##..", "..#..", "..#..", "..#..", "..#..", ".###."]
+    );
+}
+
+#[test]
+fn space_is_blank() {
+    assert!(render(' ').iter().all(|row| row == "....."));
+}
+
+#[test]
+fn unsupported_characters() {
+    assert_eq!(glyph('\u{e9}'), glyph('?'));
+    assert_eq!(glyph('\n'), glyph('?'));
+    assert_eq!(glyph('\u{7f}'), glyph('?'));
+    assert_ne!(glyph('~'), glyph('?'));
+}
+
+#[test]
+fn pixel_outside_of_glyph() {
+    assert!(pixel('1', 2, HEIGHT - 1));
+    assert!(!pixel('1', 2, HEIGHT));
+    assert!(!pixel('1', WIDTH, 0));
+}
+
+#[test]
+fn width() {
+    assert_eq!(text_width(""), 0);
+    assert_eq!(text_width("ab"), 2 * ADVANCE);
+    assert_eq!(text_width("\u{e9}"), ADVANCE);
+}
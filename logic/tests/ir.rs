@@ -0,0 +1,323 @@
+use blue_pill_logic::ir::{self, Command, Decoder, Frame, Nec, Protocol, Pulse, Rc5, Rc6, Sirc};
+
+// idle time between frames
+const GAP: Pulse = Pulse::space(40_000);
+
+fn feed(decoder: &mut dyn Decoder, pulses: &[Pulse]) -> Vec<Command> {
+    pulses
+        .iter()
+        .chain(Some(&GAP))
+        .filter_map(|p| decoder.feed(*p))
+        .collect()
+}
+
+fn stretch(frame: &Frame, percent: u32) -> Vec<Pulse> {
+    frame
+        .pulses()
+        .iter()
+        .map(|p| Pulse {
+            mark: p.mark,
+            us: p.us * percent / 100,
+        })
+        .collect()
+}
+
+// RC6 mode 0 pulses: start bit, mode 000, trailer, 8 bit address and command
+fn rc6_frame(address: u8, command: u8, toggle: bool) -> Frame {
+    const UNIT: u32 = 444;
+
+    let mut frame = Frame::new();
+    frame.push(Pulse::mark(2666));
+    frame.push(Pulse::space(889));
+
+    let mut bit = |one: bool, width: u32| {
+        frame.push(Pulse {
+            mark: one,
+            us: UNIT * width,
+        });
+        frame.push(Pulse {
+            mark: !one,
+            us: UNIT * width,
+        });
+    };
+
+    bit(true, 1);
+    for _ in 0..3 {
+        bit(false, 1);
+    }
+    bit(toggle, 2);
+
+    let bits = (address as u16) << 8 | command as u16;
+    for i in (0..16).rev() {
+        bit(bits & (1 << i) != 0, 1);
+    }
+
+    frame
+}
+
+// SIRC pulses: leader, 7 command bits and address bits LSB first
+fn sirc_frame(address: u16, command: u8, address_bits: u32) -> Frame {
+    let mut frame = Frame::new();
+    frame.push(Pulse::mark(2400));
+
+    let bits = command as u32 & 0x7f | (address as u32) << 7;
+    for i in 0..7 + address_bits {
+        frame.push(Pulse::space(600));
+        frame.push(Pulse::mark(if bits & (1 << i) != 0 { 1200 } else { 600 }));
+    }
+
+    frame
+}
+
+#[test]
+fn matches_tolerance() {
+    assert!(ir::matches(560, 560, 25));
+    assert!(ir::matches(700, 560, 25));
+    assert!(ir::matches(420, 560, 25));
+    assert!(!ir::matches(701, 560, 25));
+    assert!(!ir::matches(419, 560, 25));
+}
+
+#[test]
+fn nec_encode_decode() {
+    let frame = ir::encode_nec(0x04, 0x08);
+
+    // address and command are sent with inverses: always 16 ones
+    assert_eq!(frame.len(), 67);
+    assert_eq!(frame.duration(), 13_500 + 33 * 560 + 16 * 560 + 16 * 1690);
+
+    let mut nec = Nec::new();
+    let cmds = feed(&mut nec, frame.pulses());
+
+    assert_eq!(
+        cmds,
+        [Command {
+            protocol: Protocol::Nec,
+            address: 0x04,
+            command: 0x08,
+            toggle: false,
+            repeat: false,
+        }]
+    );
+}
+
+#[test]
+fn nec_frame_pulses() {
+    // address 0x04, !address, command 0x08, !command: LSB first
+    let bytes = [0x04u8, 0xfb, 0x08, 0xf7];
+
+    let mut expected = vec![Pulse::mark(9000), Pulse::space(4500)];
+    for byte in bytes.iter() {
+        for i in 0..8 {
+            expected.push(Pulse::mark(560));
+            expected.push(Pulse::space(if byte & (1 << i) != 0 { 1690 } else { 560 }));
+        }
+    }
+    expected.push(Pulse::mark(560));
+
+    assert_eq!(ir::encode_nec(0x04, 0x08).pulses(), &expected[..]);
+}
+
+#[test]
+fn nec_repeat_pulses() {
+    assert_eq!(
+        ir::encode_nec_repeat().pulses(),
+        [Pulse::mark(9000), Pulse::space(2250), Pulse::mark(560)]
+    );
+}
+
+#[test]
+fn nec_extended_address() {
+    let mut nec = Nec::new();
+    let cmds = feed(&mut nec, ir::encode_nec(0x1234, 0x56).pulses());
+
+    assert_eq!(cmds.len(), 1);
+    assert_eq!(cmds[0].address, 0x1234);
+    assert_eq!(cmds[0].command, 0x56);
+}
+
+#[test]
+fn nec_repeat_code() {
+    let mut nec = Nec::new();
+
+    // repeat without preceding frame is ignored
+    assert!(feed(&mut nec, ir::encode_nec_repeat().pulses()).is_empty());
+
+    feed(&mut nec, ir::encode_nec(0x10, 0x20).pulses());
+    let cmds = feed(&mut nec, ir::encode_nec_repeat().pulses());
+
+    assert_eq!(cmds.len(), 1);
+    assert!(cmds[0].repeat);
+    assert_eq!(cmds[0].command, 0x20);
+}
+
+#[test]
+fn nec_timing_tolerance() {
+    let frame = ir::encode_nec(0x01, 0x02);
+    let mut nec = Nec::new();
+
+    assert_eq!(feed(&mut nec, &stretch(&frame, 85)).len(), 1);
+    assert_eq!(feed(&mut nec, &stretch(&frame, 115)).len(), 1);
+    assert!(feed(&mut nec, &stretch(&frame, 140)).is_empty());
+}
+
+#[test]
+fn nec_resync_after_noise() {
+    let mut pulses = vec![Pulse::mark(300), Pulse::space(4500), Pulse::mark(9000)];
+    pulses.extend_from_slice(&ir::encode_nec(0x01, 0x02).pulses()[1..]);
+
+    let mut nec = Nec::new();
+    let cmds = feed(&mut nec, &pulses);
+
+    assert_eq!(cmds.len(), 1);
+    assert_eq!(cmds[0].command, 0x02);
+}
+
+#[test]
+fn nec_bad_checksum() {
+    let mut pulses = ir::encode_nec(0x01, 0x02).pulses().to_vec();
+
+    // flip the first bit of inverted command
+    let space = &mut pulses[2 * 25 + 1];
+    space.us = if space.us == 560 { 1690 } else { 560 };
+
+    let mut nec = Nec::new();
+    assert!(feed(&mut nec, &pulses).is_empty());
+}
+
+#[test]
+fn rc5_encode_decode() {
+    for &(address, command, toggle) in
+        &[(0x05, 0x35, false), (0x1f, 0x00, true), (0x00, 0x7f, true)]
+    {
+        let mut rc5 = Rc5::new();
+        let cmds = feed(&mut rc5, ir::encode_rc5(address, command, toggle).pulses());
+
+        assert_eq!(
+            cmds,
+            [Command {
+                protocol: Protocol::Rc5,
+                address: address as u16,
+                command: command as u16,
+                toggle,
+                repeat: false,
+            }]
+        );
+    }
+}
+
+#[test]
+fn rc5_frame_pulses() {
+    // start, field (command < 64), toggle 0, address 00101, command 110101:
+    // Manchester halves with equal neighbours merged, no leading and trailing space
+    const U: u32 = 889;
+    let expected = [
+        Pulse::mark(U),
+        Pulse::space(U),
+        Pulse::mark(2 * U),
+        Pulse::space(U),
+        Pulse::mark(U),
+        Pulse::space(U),
+        Pulse::mark(U),
+        Pulse::space(2 * U),
+        Pulse::mark(2 * U),
+        Pulse::space(2 * U),
+        Pulse::mark(U),
+        Pulse::space(U),
+        Pulse::mark(U),
+        Pulse::space(U),
+        Pulse::mark(2 * U),
+        Pulse::space(2 * U),
+        Pulse::mark(2 * U),
+        Pulse::space(2 * U),
+        Pulse::mark(U),
+    ];
+
+    assert_eq!(ir::encode_rc5(0x05, 0x35, false).pulses(), expected);
+}
+
+#[test]
+fn rc6_decode() {
+    let mut rc6 = Rc6::new();
+    let cmds = feed(&mut rc6, rc6_frame(0x00, 0x0c, true).pulses());
+
+    assert_eq!(
+        cmds,
+        [Command {
+            protocol: Protocol::Rc6,
+            address: 0x00,
+            command: 0x0c,
+            toggle: true,
+            repeat: false,
+        }]
+    );
+}
+
+#[test]
+fn sirc_decode() {
+    for &bits in &[5, 8, 13] {
+        let address = (1 << bits) - 2;
+
+        let mut sirc = Sirc::new();
+        let cmds = feed(&mut sirc, sirc_frame(address, 0x15, bits).pulses());
+
+        assert_eq!(cmds.len(), 1);
+        assert_eq!(cmds[0].protocol, Protocol::Sirc);
+        assert_eq!(cmds[0].address, address);
+        assert_eq!(cmds[0].command, 0x15);
+    }
+}
+
+#[test]
+fn decode_with_several_decoders() {
+    let mut nec = Nec::new();
+    let mut rc5 = Rc5::new();
+    let mut rc6 = Rc6::new();
+    let mut sirc = Sirc::new();
+
+    let frames = [
+        ir::encode_nec(0x01, 0x02),
+        ir::encode_rc5(0x03, 0x04, false),
+        rc6_frame(0x05, 0x06, false),
+        sirc_frame(0x07, 0x08, 5),
+    ];
+
+    let mut cmds = Vec::new();
+    for frame in frames.iter() {
+        for p in frame.pulses().iter().chain(Some(&GAP)) {
+            let mut decoders: [&mut dyn Decoder; 4] = [&mut nec, &mut rc5, &mut rc6, &mut sirc];
+            cmds.extend(ir::decode(&mut decoders, *p));
+        }
+    }
+
+    let decoded: Vec<_> = cmds
+        .iter()
+        .map(|c| (c.protocol, c.address, c.command))
+        .collect();
+    assert_eq!(
+        decoded,
+        [
+            (Protocol::Nec, 0x01, 0x02),
+            (Protocol::Rc5, 0x03, 0x04),
+            (Protocol::Rc6, 0x05, 0x06),
+            (Protocol::Sirc, 0x07, 0x08),
+        ]
+    );
+}
+
+#[test]
+fn frame_merges_pulses() {
+    let mut frame = Frame::new();
+
+    // leading space is dropped, same levels are merged
+    frame.push(Pulse::space(100));
+    frame.push(Pulse::mark(100));
+    frame.push(Pulse::mark(200));
+    frame.push(Pulse::space(300));
+
+    assert_eq!(frame.pulses(), [Pulse::mark(300), Pulse::space(300)]);
+    assert_eq!(frame.duration(), 600);
+
+    frame.clear();
+    assert!(frame.is_empty());
+}
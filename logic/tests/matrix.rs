@@ -0,0 +1,146 @@
+use blue_pill_logic::matrix::{Canvas, Layout, Matrix, Rotation, Ticker};
+use smart_leds::RGB8;
+
+const RED: RGB8 = RGB8 { r: 255, g: 0, b: 0 };
+const OFF: RGB8 = RGB8 { r: 0, g: 0, b: 0 };
+
+// strip indices of logical pixels, row by row
+fn indices(m: Matrix) -> Vec<Vec<usize>> {
+    let (w, h) = m.size();
+
+    (0..h)
+        .map(|y| (0..w).map(|x| m.index(x, y).unwrap()).collect())
+        .collect()
+}
+
+// 4 wide, 3 high in wiring order
+fn panel(layout: Layout, rotation: Rotation) -> Matrix {
+    Matrix::new(4, 3, layout).rotate(rotation)
+}
+
+#[test]
+fn progressive() {
+    let m = panel(Layout::Progressive, Rotation::Deg0);
+
+    assert_eq!(m.leds(), 12);
+    assert_eq!(m.size(), (4, 3));
+    assert_eq!(indices(m), [[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11]]);
+}
+
+#[test]
+fn serpentine() {
+    let m = panel(Layout::Serpentine, Rotation::Deg0);
+
+    assert_eq!(indices(m), [[0, 1, 2, 3], [7, 6, 5, 4], [8, 9, 10, 11]]);
+}
+
+#[test]
+fn progressive_rotated() {
+    let m = panel(Layout::Progressive, Rotation::Deg90);
+    assert_eq!(m.size(), (3, 4));
+    assert_eq!(indices(m), [[3, 7, 11], [2, 6, 10], [1, 5, 9], [0, 4, 8]]);
+
+    let m = panel(Layout::Progressive, Rotation::Deg180);
+    assert_eq!(m.size(), (4, 3));
+    assert_eq!(indices(m), [[11, 10, 9, 8], [7, 6, 5, 4], [3, 2, 1, 0]]);
+
+    let m = panel(Layout::Progressive, Rotation::Deg270);
+    assert_eq!(m.size(), (3, 4));
+    assert_eq!(indices(m), [[8, 4, 0], [9, 5, 1], [10, 6, 2], [11, 7, 3]]);
+}
+
+#[test]
+fn serpentine_rotated() {
+    let m = panel(Layout::Serpentine, Rotation::Deg90);
+    assert_eq!(indices(m), [[3, 4, 11], [2, 5, 10], [1, 6, 9], [0, 7, 8]]);
+
+    let m = panel(Layout::Serpentine, Rotation::Deg180);
+    assert_eq!(indices(m), [[11, 10, 9, 8], [4, 5, 6, 7], [3, 2, 1, 0]]);
+
+    let m = panel(Layout::Serpentine, Rotation::Deg270);
+    assert_eq!(indices(m), [[8, 7, 0], [9, 6, 1], [10, 5, 2], [11, 4, 3]]);
+}
+
+#[test]
+fn outside_of_panel() {
+    let m = panel(Layout::Serpentine, Rotation::Deg0);
+    assert_eq!(m.index(4, 0), None);
+    assert_eq!(m.index(0, 3), None);
+
+    let m = panel(Layout::Serpentine, Rotation::Deg90);
+    assert_eq!(m.index(2, 3), Some(8));
+    assert_eq!(m.index(3, 0), None);
+    assert_eq!(m.index(0, 4), None);
+}
+
+#[test]
+fn canvas() {
+    let mut frame = [OFF; 12];
+    let mut canvas = Canvas::new(&mut frame, panel(Layout::Serpentine, Rotation::Deg0));
+
+    canvas.set(0, 1, RED);
+    canvas.set(4, 1, RED);
+    assert_eq!(canvas.get(0, 1), Some(RED));
+    assert_eq!(canvas.get(1, 1), Some(OFF));
+    assert_eq!(canvas.get(4, 1), None);
+
+    let mut expected = [OFF; 12];
+    expected[7] = RED;
+    assert_eq!(frame, expected);
+}
+
+#[test]
+fn canvas_shorter_than_panel() {
+    let mut frame = [OFF; 6];
+    let mut canvas = Canvas::new(&mut frame, panel(Layout::Progressive, Rotation::Deg0));
+
+    canvas.set(3, 2, RED);
+    assert_eq!(canvas.get(3, 2), None);
+
+    canvas.fill(RED);
+    assert_eq!(frame, [RED; 6]);
+}
+
+#[test]
+fn ticker() {
+    let m = Matrix::new(8, 7, Layout::Progressive);
+    let mut frame = [OFF; 56];
+    let mut ticker = Ticker::new(RED);
+
+    ticker.set_text("1");
+    assert_eq!(ticker.text(), "1");
+
+    // text starts right of the screen
+    ticker.draw(&mut Canvas::new(&mut frame, m)).unwrap();
+    assert_eq!(frame, [OFF; 56]);
+
+    for _ in 0..8 {
+        ticker.step(8);
+    }
+    ticker.draw(&mut Canvas::new(&mut frame, m)).unwrap();
+
+    let mut expected = [OFF; 56];
+    for &(x, y) in &[(1, 1), (1, 6), (3, 6)] {
+        expected[y * 8 + x] = RED;
+    }
+    for y in 0..7 {
+        expected[y * 8 + 2] = RED;
+    }
+    assert_eq!(frame, expected);
+
+    // wraps after the text has left the screen
+    for _ in 8..14 {
+        ticker.step(8);
+    }
+    let mut frame = [OFF; 56];
+    ticker.draw(&mut Canvas::new(&mut frame, m)).unwrap();
+    assert_eq!(frame, [OFF; 56]);
+}
+
+#[test]
+fn ticker_text_is_truncated() {
+    let mut ticker = Ticker::new(RED);
+
+    ticker.set_text("0123456789abcdefghijklmnopqrstuvwxyz");
+    assert_eq!(ticker.text(), "0123456789abcdefghijklmnopqrstuv");
+}
@@ -0,0 +1,201 @@
+use blue_pill_logic::mifare::{self, AccessBits, DataAccess, Trailer, TrailerAccess, DEFAULT_KEY};
+
+const DATA: [DataAccess; 8] = [
+    DataAccess::Transport,
+    DataAccess::ReadOnly,
+    DataAccess::WriteB,
+    DataAccess::Value,
+    DataAccess::ValueDecOnly,
+    DataAccess::KeyBOnly,
+    DataAccess::ReadB,
+    DataAccess::Locked,
+];
+
+const TRAILER: [TrailerAccess; 8] = [
+    TrailerAccess::KeyAWriteKeys,
+    TrailerAccess::KeyAFrozen,
+    TrailerAccess::KeyBWriteKeys,
+    TrailerAccess::Frozen,
+    TrailerAccess::Transport,
+    TrailerAccess::KeyB,
+    TrailerAccess::KeyBAccessOnly,
+    TrailerAccess::Locked,
+];
+
+#[test]
+fn transport_configuration() {
+    assert_eq!(AccessBits::default().encode(), [0xff, 0x07, 0x80]);
+    assert_eq!(
+        AccessBits::decode(&[0xff, 0x07, 0x80]),
+        Some(AccessBits::default())
+    );
+}
+
+#[test]
+fn access_bits_round_trip() {
+    for block in 0..3 {
+        for cond in DATA.iter() {
+            let mut access = AccessBits::default();
+            access.data[block] = *cond;
+
+            assert_eq!(AccessBits::decode(&access.encode()), Some(access));
+        }
+    }
+
+    for cond in TRAILER.iter() {
+        let access = AccessBits {
+            trailer: *cond,
+            ..AccessBits::default()
+        };
+
+        assert_eq!(AccessBits::decode(&access.encode()), Some(access));
+    }
+}
+
+#[test]
+fn access_bits_layout() {
+    // C1 of block 0, C2 of block 1 and C3 of trailer
+    let access = AccessBits {
+        data: [
+            DataAccess::WriteB,
+            DataAccess::ReadOnly,
+            DataAccess::Transport,
+        ],
+        trailer: TrailerAccess::Transport,
+    };
+
+    assert_eq!(access.encode(), [0xde, 0x17, 0x82]);
+}
+
+#[test]
+fn inverted_nibbles_must_match() {
+    let bytes = AccessBits::default().encode();
+
+    for byte in 0..3 {
+        for bit in 0..8 {
+            let mut bad = bytes;
+            bad[byte] ^= 1 << bit;
+            assert_eq!(AccessBits::decode(&bad), None, "{:02x?}", bad);
+        }
+    }
+
+    assert_eq!(AccessBits::decode(&[0x00, 0x00, 0x00]), None);
+    assert_eq!(AccessBits::decode(&[0xff, 0xff, 0xff]), None);
+}
+
+#[test]
+fn unsafe_trailers() {
+    // access bits can not be changed anymore
+    for cond in [
+        TrailerAccess::KeyAWriteKeys,
+        TrailerAccess::KeyAFrozen,
+        TrailerAccess::KeyBWriteKeys,
+        TrailerAccess::Frozen,
+        TrailerAccess::Locked,
+    ]
+    .iter()
+    {
+        let access = AccessBits {
+            trailer: *cond,
+            ..AccessBits::default()
+        };
+        assert!(!access.is_safe(), "{:?}", cond);
+    }
+
+    // key B is readable in transport configuration, so it can not be used
+    let access = AccessBits {
+        data: [
+            DataAccess::Transport,
+            DataAccess::WriteB,
+            DataAccess::Transport,
+        ],
+        trailer: TrailerAccess::Transport,
+    };
+    assert!(!access.is_safe());
+
+    // the same data access is fine when key B is secret
+    let access = AccessBits {
+        trailer: TrailerAccess::KeyB,
+        ..access
+    };
+    assert!(access.is_safe());
+
+    assert!(AccessBits::default().is_safe());
+}
+
+#[test]
+fn trailer_block() {
+    let trailer = Trailer {
+        key_a: [0; 6],
+        access: AccessBits::default(),
+        user: 0x69,
+        key_b: DEFAULT_KEY,
+    };
+
+    let block = trailer.encode();
+    assert_eq!(
+        block,
+        [0, 0, 0, 0, 0, 0, 0xff, 0x07, 0x80, 0x69, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+    );
+    assert_eq!(Trailer::decode(&block), Some(trailer));
+
+    let mut bad = block;
+    bad[7] = 0x00;
+    assert_eq!(Trailer::decode(&bad), None);
+}
+
+#[test]
+fn value_blocks() {
+    let block = mifare::encode_value(0x1234, 5);
+    assert_eq!(
+        block,
+        [
+            0x34, 0x12, 0x00, 0x00, 0xcb, 0xed, 0xff, 0xff, 0x34, 0x12, 0x00, 0x00, 0x05, 0xfa,
+            0x05, 0xfa
+        ]
+    );
+    assert_eq!(mifare::decode_value(&block), Some((0x1234, 5)));
+
+    for value in [0, 1, -1, -100_000, i32::MIN, i32::MAX].iter() {
+        let block = mifare::encode_value(*value, 8);
+        assert_eq!(mifare::decode_value(&block), Some((*value, 8)));
+    }
+}
+
+#[test]
+fn corrupted_value_blocks() {
+    let block = mifare::encode_value(-42, 9);
+
+    // inverted copy
+    let mut bad = block;
+    bad[5] ^= 0x01;
+    assert_eq!(mifare::decode_value(&bad), None);
+
+    // second copy
+    let mut bad = block;
+    bad[8] ^= 0x80;
+    assert_eq!(mifare::decode_value(&bad), None);
+
+    // address bytes
+    for pos in 13..16 {
+        let mut bad = block;
+        bad[pos] ^= 0x01;
+        assert_eq!(mifare::decode_value(&bad), None, "byte {}", pos);
+    }
+
+    assert_eq!(mifare::decode_value(&[0; 16]), None);
+}
+
+#[test]
+fn sector_layout() {
+    assert_eq!(mifare::sector_of(7), 1);
+    assert_eq!(mifare::trailer_block(1), 7);
+    assert!(mifare::is_trailer(3));
+    assert!(!mifare::is_trailer(4));
+
+    // 4K cards: sectors 32..39 have 16 blocks
+    assert_eq!(mifare::first_block(32), 128);
+    assert_eq!(mifare::sector_of(143), 32);
+    assert_eq!(mifare::trailer_block(39), 255);
+    assert_eq!(mifare::blocks_in_sector(39), 16);
+}
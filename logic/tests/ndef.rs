@@ -0,0 +1,151 @@
+use blue_pill_logic::ndef::{self, Error, MessageWriter, Records, Text, Tnf};
+
+// Type 2 tag memory from page 4: lock control TLV, NDEF TLV with URI record
+// "https://www.example.com", terminator
+const TAG: [u8; 24] = [
+    0x01, 0x03, 0xa0, 0x0c, 0x34, 0x03, 0x10, 0xd1, 0x01, 0x0c, 0x55, 0x02, b'e', b'x', b'a', b'm',
+    b'p', b'l', b'e', b'.', b'c', b'o', b'm', 0xfe,
+];
+
+#[test]
+fn find_message_skips_other_tlvs() {
+    let msg = ndef::find_message(&TAG).unwrap();
+    assert_eq!(msg.len(), 16);
+
+    let records: Vec<_> = Records::new(msg).collect();
+    assert_eq!(records.len(), 1);
+
+    let rec = records[0].unwrap();
+    assert_eq!(rec.tnf, Tnf::WellKnown);
+    assert_eq!(rec.uri(), Some(("https://www.", "example.com")));
+    assert_eq!(rec.text(), None);
+}
+
+#[test]
+fn find_message_errors() {
+    assert_eq!(
+        ndef::find_message(&[0x00, 0x00, 0xfe]),
+        Err(Error::NoMessage)
+    );
+    assert_eq!(
+        ndef::find_message(&[0x03, 0x10, 0xd1]),
+        Err(Error::Truncated)
+    );
+    assert_eq!(ndef::find_message(&[]), Err(Error::Truncated));
+}
+
+#[test]
+fn write_and_parse_records() {
+    let mut buf = [0u8; 128];
+    let mut w = MessageWriter::new(&mut buf);
+
+    w.push_uri("https://github.com/geomatsi").unwrap();
+    w.push_text("en", "blue pill").unwrap();
+    w.push(Tnf::Media, b"text/plain", &[b"abc", b"def"])
+        .unwrap();
+    let msg = w.finish();
+
+    // message begin flag on the first record, message end on the last one
+    assert_eq!(msg[0] & 0xc0, 0x80);
+
+    let records: Vec<_> = Records::new(msg).map(|r| r.unwrap()).collect();
+    assert_eq!(records.len(), 3);
+
+    assert_eq!(records[0].uri(), Some(("https://", "github.com/geomatsi")));
+    assert_eq!(
+        records[1].text(),
+        Some(Text {
+            lang: "en",
+            text: "blue pill",
+        })
+    );
+    assert_eq!(records[2].tnf, Tnf::Media);
+    assert_eq!(records[2].kind, b"text/plain");
+    assert_eq!(records[2].payload, b"abcdef");
+}
+
+#[test]
+fn uri_without_known_prefix() {
+    let mut buf = [0u8; 32];
+    let mut w = MessageWriter::new(&mut buf);
+
+    w.push_uri("ftp.example").unwrap();
+    let msg = w.finish();
+
+    let rec = Records::new(msg).next().unwrap().unwrap();
+    assert_eq!(rec.uri(), Some(("", "ftp.example")));
+}
+
+#[test]
+fn writer_out_of_room() {
+    let mut buf = [0u8; 8];
+    let mut w = MessageWriter::new(&mut buf);
+
+    assert_eq!(w.push_text("en", "too long"), Err(Error::NoRoom));
+    assert_eq!(w.push_text(&"x".repeat(64), ""), Err(Error::InvalidRecord));
+}
+
+#[test]
+fn wrap_and_find() {
+    let message = [0xd1, 0x01, 0x01, b'T', 0x00];
+    let mut tag = [0u8; 16];
+
+    let len = ndef::wrap_message(&message, &mut tag).unwrap();
+    assert_eq!(len, 8);
+    assert_eq!(tag[len - 1], 0xfe);
+    assert_eq!(ndef::find_message(&tag[..len]), Ok(&message[..]));
+
+    assert_eq!(
+        ndef::wrap_message(&message, &mut tag[..7]),
+        Err(Error::NoRoom)
+    );
+}
+
+#[test]
+fn wrap_long_message() {
+    let message = [0u8; 300];
+    let mut tag = [0u8; 310];
+
+    let len = ndef::wrap_message(&message, &mut tag).unwrap();
+    assert_eq!(len, 305);
+    assert_eq!(&tag[..4], [0x03, 0xff, 0x01, 0x2c]);
+    assert_eq!(ndef::find_message(&tag).unwrap().len(), 300);
+}
+
+#[test]
+fn truncated_record() {
+    let mut records = Records::new(&[0xd1, 0x01, 0x05, b'T', 0x00]);
+
+    assert_eq!(records.next(), Some(Err(Error::Truncated)));
+    assert_eq!(records.next(), None);
+}
+
+#[test]
+fn chunked_records_are_rejected() {
+    let mut records = Records::new(&[0xb1, 0x01, 0x01, b'T', 0x00]);
+    assert_eq!(records.next(), Some(Err(Error::Unsupported)));
+}
+
+#[test]
+fn uri_record_bytes() {
+    let mut buf = [0u8; 32];
+    let mut w = MessageWriter::new(&mut buf);
+
+    w.push_uri("https://github.com/geomatsi").unwrap();
+
+    let mut expected = vec![0xd1, 0x01, 0x14, b'U', 0x04];
+    expected.extend_from_slice(b"github.com/geomatsi");
+    assert_eq!(w.finish(), &expected[..]);
+}
+
+#[test]
+fn text_record_bytes() {
+    let mut buf = [0u8; 32];
+    let mut w = MessageWriter::new(&mut buf);
+
+    w.push_text("en", "hi").unwrap();
+    assert_eq!(
+        w.finish(),
+        [0xd1, 0x01, 0x05, b'T', 0x02, b'e', b'n', b'h', b'i']
+    );
+}
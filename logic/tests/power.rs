@@ -0,0 +1,40 @@
+use blue_pill_logic::power::Stats;
+
+#[test]
+fn no_time_accounted() {
+    assert_eq!(Stats::default().low_power_permille(), 0);
+}
+
+#[test]
+fn low_power_part() {
+    let stats = Stats {
+        run: 250,
+        sleep: 500,
+        stop: 250,
+    };
+    assert_eq!(stats.low_power_permille(), 750);
+
+    let stats = Stats {
+        run: 0,
+        sleep: 0,
+        stop: 10,
+    };
+    assert_eq!(stats.low_power_permille(), 1000);
+
+    let stats = Stats {
+        run: 10,
+        sleep: 0,
+        stop: 0,
+    };
+    assert_eq!(stats.low_power_permille(), 0);
+}
+
+#[test]
+fn long_runs_do_not_overflow() {
+    let stats = Stats {
+        run: u32::MAX,
+        sleep: u32::MAX,
+        stop: u32::MAX,
+    };
+    assert_eq!(stats.low_power_permille(), 666);
+}
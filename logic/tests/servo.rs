@@ -0,0 +1,184 @@
+use blue_pill_logic::servo::{duty, Calibration, Servo, PERIOD_US};
+
+fn inverted() -> Calibration {
+    Calibration {
+        inverted: true,
+        ..Calibration::default()
+    }
+}
+
+#[test]
+fn pulse_for_angle() {
+    let cal = Calibration::default();
+
+    assert_eq!(cal.pulse(0), 1000);
+    assert_eq!(cal.pulse(45), 1250);
+    assert_eq!(cal.pulse(90), 1500);
+    assert_eq!(cal.pulse(180), 2000);
+    assert_eq!(cal.pulse(270), 2000);
+
+    assert_eq!(inverted().pulse(0), 2000);
+    assert_eq!(inverted().pulse(45), 1750);
+    assert_eq!(inverted().pulse(180), 1000);
+}
+
+#[test]
+fn angle_for_pulse() {
+    let cal = Calibration::default();
+
+    assert_eq!(cal.angle(1000), 0);
+    assert_eq!(cal.angle(1500), 90);
+    assert_eq!(cal.angle(2000), 180);
+    assert_eq!(cal.angle(500), 0);
+    assert_eq!(cal.angle(2500), 180);
+
+    assert_eq!(inverted().angle(1750), 45);
+    assert_eq!(inverted().angle(500), 180);
+
+    // angles giving whole us pulses convert back exactly
+    for a in (0..=180).step_by(9) {
+        assert_eq!(cal.angle(cal.pulse(a)), a);
+    }
+}
+
+#[test]
+fn swapped_limits() {
+    let cal = Calibration {
+        min_us: 2000,
+        max_us: 1000,
+        ..Calibration::default()
+    };
+
+    // reversed direction: pulse at 0 degrees is still min_us
+    assert_eq!(cal.pulse(0), 2000);
+    assert_eq!(cal.pulse(45), 1750);
+    assert_eq!(cal.pulse(180), 1000);
+    assert_eq!(cal.angle(2500), 0);
+    assert_eq!(cal.angle(1750), 45);
+    assert_eq!(cal.angle(500), 180);
+    assert_eq!(cal.clamp(2500), 2000);
+    assert_eq!(cal.clamp(500), 1000);
+
+    let inverted = Calibration {
+        inverted: true,
+        ..cal
+    };
+    assert_eq!(inverted.pulse(0), 1000);
+    assert_eq!(inverted.angle(1750), 135);
+
+    let mut servo = Servo::new(cal);
+    assert_eq!(servo.pulse(), 1500);
+
+    servo.move_to(180, 90);
+    servo.update(1000);
+    assert_eq!(servo.pulse(), 1000);
+    assert_eq!(servo.angle(), 180);
+}
+
+#[test]
+fn equal_limits() {
+    let cal = Calibration {
+        min_us: 1500,
+        max_us: 1500,
+        inverted: true,
+        ..Calibration::default()
+    };
+
+    assert_eq!(cal.pulse(90), 1500);
+    assert_eq!(cal.angle(1000), 180);
+    assert_eq!(cal.angle(2000), 180);
+}
+
+#[test]
+fn pwm_duty() {
+    assert_eq!(duty(1500, PERIOD_US as u16), 1500);
+    assert_eq!(duty(1000, u16::MAX), 3276);
+    assert_eq!(duty(0, u16::MAX), 0);
+}
+
+#[test]
+fn starts_disabled_in_the_middle() {
+    let servo = Servo::new(Calibration::default());
+
+    assert!(!servo.enabled());
+    assert!(!servo.moving());
+    assert_eq!(servo.pulse(), 1500);
+    assert_eq!(servo.angle(), 90);
+    assert_eq!(servo.output_us(), 0);
+}
+
+#[test]
+fn jump() {
+    let mut servo = Servo::new(Calibration::default());
+
+    servo.set_angle(0);
+    assert!(servo.enabled());
+    assert_eq!(servo.output_us(), 1000);
+
+    servo.set_pulse(2500);
+    assert_eq!(servo.pulse(), 2000);
+
+    servo.disable();
+    assert!(!servo.enabled());
+    assert_eq!(servo.output_us(), 0);
+}
+
+#[test]
+fn trim() {
+    let mut servo = Servo::new(Calibration {
+        trim_us: 20,
+        ..Calibration::default()
+    });
+
+    servo.set_angle(0);
+    assert_eq!(servo.pulse(), 1000);
+    assert_eq!(servo.output_us(), 1020);
+
+    servo.cal.trim_us = -2000;
+    assert_eq!(servo.output_us(), 0);
+}
+
+#[test]
+fn slow_move() {
+    let mut servo = Servo::new(Calibration::default());
+
+    servo.set_angle(90);
+
+    // 90 degrees per second is 500us per second
+    servo.move_to(180, 90);
+    assert!(servo.moving());
+
+    let pulses: Vec<u16> = (0..5)
+        .map(|_| {
+            servo.update(250);
+            servo.pulse()
+        })
+        .collect();
+
+    assert_eq!(pulses, [1625, 1750, 1875, 2000, 2000]);
+    assert!(!servo.moving());
+    assert_eq!(servo.angle(), 180);
+
+    servo.move_to(0, 180);
+    servo.update(1000);
+    assert_eq!(servo.pulse(), 1000);
+
+    // zero speed jumps
+    servo.move_to(90, 0);
+    assert!(!servo.moving());
+    assert_eq!(servo.pulse(), 1500);
+}
+
+#[test]
+fn disable_stops_move() {
+    let mut servo = Servo::new(Calibration::default());
+
+    servo.set_angle(0);
+    servo.move_to(180, 90);
+    servo.update(100);
+    servo.disable();
+
+    assert!(!servo.moving());
+    servo.update(1000);
+    assert_eq!(servo.pulse(), 1050);
+}
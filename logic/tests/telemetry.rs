@@ -0,0 +1,84 @@
+use blue_pill_logic::telemetry::{self, Error, Link, Telemetry, MAX_CHANNELS, MAX_PACKET};
+
+#[test]
+fn crc8_check_value() {
+    // CRC-8/SMBUS check value
+    assert_eq!(telemetry::crc8(b"123456789"), 0xf4);
+    assert_eq!(telemetry::crc8(&[]), 0);
+}
+
+#[test]
+fn encode_layout() {
+    let mut buf = [0u8; MAX_PACKET];
+    let len = Telemetry::new(7, 0x1234, &[0x0102, 0x0fff]).encode(&mut buf);
+
+    assert_eq!(len, 10);
+    assert_eq!(&buf[..9], [0x01, 7, 0x34, 0x12, 2, 0x02, 0x01, 0xff, 0x0f]);
+    assert_eq!(buf[9], telemetry::crc8(&buf[..9]));
+}
+
+#[test]
+fn encode_decode() {
+    let samples = [1, 2, 3, 4095, 0, 17, 42, 1000];
+    let mut buf = [0u8; MAX_PACKET];
+
+    for n in 0..=MAX_CHANNELS {
+        let t = Telemetry::new(3, n as u16, &samples[..n]);
+        let len = t.encode(&mut buf);
+
+        assert_eq!(Telemetry::decode(&buf[..len]), Ok(t));
+        assert_eq!(t.values(), &samples[..n]);
+    }
+}
+
+#[test]
+fn extra_channels_are_dropped() {
+    let t = Telemetry::new(1, 1, &[5; MAX_CHANNELS + 2]);
+    assert_eq!(t.values().len(), MAX_CHANNELS);
+}
+
+#[test]
+fn decode_errors() {
+    let mut buf = [0u8; MAX_PACKET];
+    let len = Telemetry::new(1, 1, &[100, 200]).encode(&mut buf);
+
+    assert_eq!(Telemetry::decode(&buf[..3]), Err(Error::Length));
+    assert_eq!(Telemetry::decode(&buf[..len - 1]), Err(Error::Length));
+
+    let mut bad = buf;
+    bad[5] ^= 0x01;
+    assert_eq!(Telemetry::decode(&bad[..len]), Err(Error::Crc));
+
+    let mut bad = buf;
+    bad[0] = 0x55;
+    bad[len - 1] = telemetry::crc8(&bad[..len - 1]);
+    assert_eq!(Telemetry::decode(&bad[..len]), Err(Error::Type));
+}
+
+#[test]
+fn link_accounting() {
+    let mut link = Link::new();
+
+    assert!(link.update(10));
+    assert!(link.update(11));
+    assert!(!link.update(11));
+    assert!(link.update(15));
+
+    assert_eq!(link.received, 3);
+    assert_eq!(link.duplicates, 1);
+    assert_eq!(link.lost, 3);
+}
+
+#[test]
+fn link_wraparound_and_restart() {
+    let mut link = Link::new();
+
+    link.update(0xfffe);
+    link.update(0x0001);
+    assert_eq!(link.lost, 2);
+
+    // sender restart: sequence goes back, nothing is counted as lost
+    link.update(0x0000);
+    assert_eq!(link.lost, 2);
+    assert_eq!(link.received, 3);
+}
@@ -0,0 +1,60 @@
+use blue_pill_logic::ultralight::{Capability, TagType, PAGE_SIZE, USER_START};
+
+// GET_VERSION responses from datasheets
+const MF0UL11: [u8; 8] = [0x00, 0x04, 0x03, 0x01, 0x01, 0x00, 0x0b, 0x03];
+const MF0UL21: [u8; 8] = [0x00, 0x04, 0x03, 0x01, 0x01, 0x00, 0x0e, 0x03];
+const NTAG213: [u8; 8] = [0x00, 0x04, 0x04, 0x02, 0x01, 0x00, 0x0f, 0x03];
+const NTAG215: [u8; 8] = [0x00, 0x04, 0x04, 0x02, 0x01, 0x00, 0x11, 0x03];
+const NTAG216: [u8; 8] = [0x00, 0x04, 0x04, 0x02, 0x01, 0x00, 0x13, 0x03];
+
+fn user_bytes(tag: TagType) -> usize {
+    (tag.user_end() - USER_START) as usize * PAGE_SIZE
+}
+
+#[test]
+fn tag_from_version() {
+    assert_eq!(TagType::from_version(&MF0UL11), TagType::UltralightEv1);
+    assert_eq!(TagType::from_version(&MF0UL21), TagType::UltralightEv1_21);
+    assert_eq!(TagType::from_version(&NTAG213), TagType::Ntag213);
+    assert_eq!(TagType::from_version(&NTAG215), TagType::Ntag215);
+    assert_eq!(TagType::from_version(&NTAG216), TagType::Ntag216);
+    assert_eq!(TagType::from_version(&[0; 8]), TagType::Unknown);
+}
+
+#[test]
+fn memory_size() {
+    assert_eq!(TagType::Ultralight.pages(), 16);
+    assert_eq!(TagType::UltralightEv1.pages(), 20);
+    assert_eq!(TagType::UltralightEv1_21.pages(), 41);
+    assert_eq!(TagType::Ntag216.pages(), 231);
+
+    assert_eq!(user_bytes(TagType::Ultralight), 48);
+    assert_eq!(user_bytes(TagType::UltralightEv1), 48);
+    assert_eq!(user_bytes(TagType::UltralightEv1_21), 128);
+    assert_eq!(user_bytes(TagType::Ntag213), 144);
+    assert_eq!(user_bytes(TagType::Ntag215), 504);
+    assert_eq!(user_bytes(TagType::Ntag216), 888);
+}
+
+#[test]
+fn capability_container() {
+    assert_eq!(
+        Capability::decode(&[0xe1, 0x10, 0x12, 0x00]),
+        Some(Capability {
+            version: 0x10,
+            size: 144,
+            read_only: false,
+        })
+    );
+    assert_eq!(
+        Capability::decode(&[0xe1, 0x10, 0x10, 0x0f]),
+        Some(Capability {
+            version: 0x10,
+            size: 128,
+            read_only: true,
+        })
+    );
+
+    // blank tag
+    assert_eq!(Capability::decode(&[0x00; 4]), None);
+}
@@ -0,0 +1,89 @@
+use blue_pill_logic::usonic::{distance_mm, read_temperature_dc, speed_of_sound};
+use embedded_hal_mock::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use lm75::{Lm75, SlaveAddr};
+
+const LM75: u8 = 0x48;
+const LM75_TEMPERATURE: u8 = 0x00;
+
+fn lm75_read(msb: u8, lsb: u8) -> I2cTransaction {
+    I2cTransaction::write_read(LM75, vec![LM75_TEMPERATURE], vec![msb, lsb])
+}
+
+#[test]
+fn speed_of_sound_compensation() {
+    assert_eq!(speed_of_sound(0), 331_300);
+    assert_eq!(speed_of_sound(200), 343_420);
+    assert_eq!(speed_of_sound(-400), 307_060);
+
+    // LM75 resolution is 0.5C
+    assert_eq!(speed_of_sound(255), 346_753);
+    assert_eq!(speed_of_sound(-5), 330_997);
+
+    // nonsense readings do not wrap around
+    assert_eq!(speed_of_sound(-10_000), 0);
+}
+
+#[test]
+fn echo_to_distance() {
+    let speed = speed_of_sound(200);
+
+    assert_eq!(distance_mm(0, speed), 0);
+    assert_eq!(distance_mm(5830, speed), 1001);
+    assert_eq!(distance_mm(30_000, speed), 5151);
+}
+
+#[test]
+fn temperature_changes_distance() {
+    // the same echo is ~7% further away in summer than in winter
+    let echo = 11_660;
+
+    assert_eq!(distance_mm(echo, speed_of_sound(-100)), 1896);
+    assert_eq!(distance_mm(echo, speed_of_sound(300)), 2037);
+}
+
+#[test]
+fn lm75_temperature() {
+    let expectations = [
+        lm75_read(0x19, 0x00),
+        lm75_read(0x19, 0x80),
+        lm75_read(0xff, 0x80),
+        lm75_read(0xe7, 0x00),
+    ];
+
+    let i2c = I2cMock::new(&expectations);
+    let mut sensor = Lm75::new(i2c, SlaveAddr::default());
+
+    assert_eq!(read_temperature_dc(&mut sensor).unwrap(), 250);
+    assert_eq!(read_temperature_dc(&mut sensor).unwrap(), 255);
+    assert_eq!(read_temperature_dc(&mut sensor).unwrap(), -5);
+    assert_eq!(read_temperature_dc(&mut sensor).unwrap(), -250);
+
+    sensor.destroy().done();
+}
+
+#[test]
+fn lm75_read_error() {
+    let expectations = [lm75_read(0x19, 0x00)
+        .with_error(embedded_hal_mock::MockError::Io(std::io::ErrorKind::Other))];
+
+    let i2c = I2cMock::new(&expectations);
+    let mut sensor = Lm75::new(i2c, SlaveAddr::default());
+
+    assert!(read_temperature_dc(&mut sensor).is_err());
+
+    sensor.destroy().done();
+}
+
+#[test]
+fn lm75_compensated_distance() {
+    let expectations = [lm75_read(0x1e, 0x00)];
+
+    let i2c = I2cMock::new(&expectations);
+    let mut sensor = Lm75::new(i2c, SlaveAddr::default());
+
+    // 30C reading from the sensor
+    let speed = speed_of_sound(read_temperature_dc(&mut sensor).unwrap());
+    assert_eq!(distance_mm(11_660, speed), 2037);
+
+    sensor.destroy().done();
+}
@@ -0,0 +1,348 @@
+//
+// Async drivers for STM32F103 peripherals on top of executor module
+//
+// Each driver enables its peripheral interrupt source only while a future
+// is waiting for it. Application forwards the interrupt to executor:
+//
+//   #[interrupt]
+//   fn TIM2() {
+//       executor::on_interrupt(Interrupt::TIM2);
+//   }
+//
+// Supported:
+// - delays and timeouts on TIM2/TIM3/TIM4 counting 1us ticks in one pulse mode
+// - ADC1 DMA transfer completion: DMA1_CHANNEL1 interrupt
+// - USART read/write: RXNE/TXE/TC interrupts of USART1/USART2/USART3
+// - EXTI pin edges: pin is configured as interrupt source by application
+//
+
+use crate::executor::{self, poll_fn, select, Either};
+use core::convert::Infallible;
+use core::future::Future;
+use core::ops::Deref;
+use core::task::Poll;
+use embedded_hal::digital::v2::InputPin;
+use embedded_hal::serial;
+use hal::dma::{dma1::C1, RxDma, Transfer, TransferPayload, W};
+use hal::gpio::ExtiPin;
+use hal::rcc::{Clocks, Enable, Reset, APB1};
+use hal::serial::{Rx, Tx};
+use hal::stm32::{self, tim2, usart1, Interrupt};
+use stm32f1xx_hal as hal;
+
+/// Future did not complete in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timeout;
+
+/// General purpose timer and its interrupt line
+pub trait TimInstance: Deref<Target = tim2::RegisterBlock> + Enable<Bus = APB1> + Reset {
+    const IRQ: Interrupt;
+}
+
+impl TimInstance for stm32::TIM2 {
+    const IRQ: Interrupt = Interrupt::TIM2;
+}
+
+impl TimInstance for stm32::TIM3 {
+    const IRQ: Interrupt = Interrupt::TIM3;
+}
+
+impl TimInstance for stm32::TIM4 {
+    const IRQ: Interrupt = Interrupt::TIM4;
+}
+
+/// USART registers and interrupt line
+pub trait UsartInstance {
+    const IRQ: Interrupt;
+
+    fn regs() -> &'static usart1::RegisterBlock;
+}
+
+impl UsartInstance for stm32::USART1 {
+    const IRQ: Interrupt = Interrupt::USART1;
+
+    fn regs() -> &'static usart1::RegisterBlock {
+        unsafe { &*stm32::USART1::ptr() }
+    }
+}
+
+impl UsartInstance for stm32::USART2 {
+    const IRQ: Interrupt = Interrupt::USART2;
+
+    fn regs() -> &'static usart1::RegisterBlock {
+        unsafe { &*stm32::USART2::ptr() }
+    }
+}
+
+impl UsartInstance for stm32::USART3 {
+    const IRQ: Interrupt = Interrupt::USART3;
+
+    fn regs() -> &'static usart1::RegisterBlock {
+        unsafe { &*stm32::USART3::ptr() }
+    }
+}
+
+/// Async delays: one timer serves one delay at a time
+pub struct Timer<TIM> {
+    tim: TIM,
+}
+
+impl<TIM: TimInstance> Timer<TIM> {
+    pub fn new(tim: TIM, clocks: &Clocks, apb: &mut APB1) -> Self {
+        TIM::enable(apb);
+        TIM::reset(apb);
+
+        let psc = (clocks.pclk1_tim().0 / 1_000_000).max(1) - 1;
+
+        tim.psc.write(|w| w.psc().bits(psc as u16));
+
+        // one pulse mode, UG only loads prescaler and does not set update flag
+        tim.cr1.write(|w| w.opm().set_bit().urs().set_bit());
+        tim.egr.write(|w| w.ug().set_bit());
+        tim.sr.reset();
+
+        Timer { tim }
+    }
+
+    pub async fn delay_us(&mut self, us: u32) {
+        let mut left = us;
+
+        while left > 0 {
+            let n = left.min(0x1_0000);
+            left -= n;
+
+            self.start(n);
+            self.wait().await;
+        }
+    }
+
+    pub async fn delay_ms(&mut self, ms: u32) {
+        self.delay_us(ms.saturating_mul(1000)).await
+    }
+
+    /// Run future until it completes or timeout expires
+    pub async fn with_timeout_ms<F: Future>(
+        &mut self,
+        ms: u32,
+        fut: F,
+    ) -> Result<F::Output, Timeout> {
+        match select(fut, self.delay_ms(ms)).await {
+            Either::First(v) => Ok(v),
+            Either::Second(_) => Err(Timeout),
+        }
+    }
+
+    pub fn release(self) -> TIM {
+        self.stop();
+        self.tim
+    }
+
+    fn start(&mut self, ticks: u32) {
+        // counter does not run with zero ARR
+        let arr = (ticks - 1).max(1);
+
+        self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+        self.tim.cnt.reset();
+        self.tim.arr.write(|w| w.arr().bits(arr as u16));
+        self.tim.sr.reset();
+        self.tim.dier.write(|w| w.uie().set_bit());
+        self.tim.cr1.modify(|_, w| w.cen().set_bit());
+    }
+
+    fn stop(&self) {
+        self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+        self.tim.dier.reset();
+        self.tim.sr.reset();
+    }
+
+    fn wait(&mut self) -> impl Future<Output = ()> + '_ {
+        poll_fn(move |cx| {
+            if self.tim.sr.read().uif().bit_is_set() {
+                self.stop();
+                Poll::Ready(())
+            } else {
+                executor::register(TIM::IRQ, cx.waker());
+                Poll::Pending
+            }
+        })
+    }
+}
+
+/// Wait for ADC1 DMA transfer started by AdcDma::read: DMA channel
+/// transfer complete interrupt should be enabled with listen
+pub async fn adc_dma<B, P>(xfer: Transfer<W, B, RxDma<P, C1>>) -> (B, RxDma<P, C1>)
+where
+    RxDma<P, C1>: TransferPayload,
+{
+    poll_fn(|cx| {
+        if xfer.is_done() {
+            Poll::Ready(())
+        } else {
+            executor::register(Interrupt::DMA1_CHANNEL1, cx.waker());
+            Poll::Pending
+        }
+    })
+    .await;
+
+    xfer.wait()
+}
+
+/// Async reader over USART receiver half
+pub struct SerialRx<USART> {
+    rx: Rx<USART>,
+}
+
+impl<USART> SerialRx<USART>
+where
+    USART: UsartInstance,
+    Rx<USART>: serial::Read<u8, Error = hal::serial::Error>,
+{
+    pub fn new(rx: Rx<USART>) -> Self {
+        SerialRx { rx }
+    }
+
+    pub async fn read(&mut self) -> Result<u8, hal::serial::Error> {
+        let rx = &mut self.rx;
+
+        poll_fn(|cx| match serial::Read::read(rx) {
+            Ok(b) => {
+                USART::regs().cr1.modify(|_, w| w.rxneie().clear_bit());
+                Poll::Ready(Ok(b))
+            }
+            Err(nb::Error::Other(e)) => {
+                USART::regs().cr1.modify(|_, w| w.rxneie().clear_bit());
+                Poll::Ready(Err(e))
+            }
+            Err(nb::Error::WouldBlock) => {
+                USART::regs().cr1.modify(|_, w| w.rxneie().set_bit());
+                executor::register(USART::IRQ, cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    pub async fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), hal::serial::Error> {
+        for b in buf.iter_mut() {
+            *b = self.read().await?;
+        }
+
+        Ok(())
+    }
+
+    pub fn release(self) -> Rx<USART> {
+        self.rx
+    }
+}
+
+/// Async writer over USART transmitter half
+pub struct SerialTx<USART> {
+    tx: Tx<USART>,
+}
+
+impl<USART> SerialTx<USART>
+where
+    USART: UsartInstance,
+    Tx<USART>: serial::Write<u8, Error = Infallible>,
+{
+    pub fn new(tx: Tx<USART>) -> Self {
+        SerialTx { tx }
+    }
+
+    pub async fn write(&mut self, byte: u8) {
+        let tx = &mut self.tx;
+
+        poll_fn(|cx| match serial::Write::write(tx, byte) {
+            Ok(()) => {
+                USART::regs().cr1.modify(|_, w| w.txeie().clear_bit());
+                Poll::Ready(())
+            }
+            Err(_) => {
+                USART::regs().cr1.modify(|_, w| w.txeie().set_bit());
+                executor::register(USART::IRQ, cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    pub async fn write_all(&mut self, bytes: &[u8]) {
+        for b in bytes.iter() {
+            self.write(*b).await;
+        }
+    }
+
+    /// Wait until the last byte leaves shift register
+    pub async fn flush(&mut self) {
+        let tx = &mut self.tx;
+
+        poll_fn(|cx| match serial::Write::flush(tx) {
+            Ok(()) => {
+                USART::regs().cr1.modify(|_, w| w.tcie().clear_bit());
+                Poll::Ready(())
+            }
+            Err(_) => {
+                USART::regs().cr1.modify(|_, w| w.tcie().set_bit());
+                executor::register(USART::IRQ, cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    pub fn release(self) -> Tx<USART> {
+        self.tx
+    }
+}
+
+/// Async edge wait on EXTI input: pin should be configured by application
+/// as interrupt source with trigger edge and enabled interrupt
+pub struct ExtiInput<PIN> {
+    pin: PIN,
+    irq: Interrupt,
+}
+
+impl<PIN> ExtiInput<PIN>
+where
+    PIN: ExtiPin + InputPin<Error = Infallible>,
+{
+    /// Interrupt line of the pin: EXTI0..EXTI4, EXTI9_5 or EXTI15_10
+    pub fn new(pin: PIN, irq: Interrupt) -> Self {
+        ExtiInput { pin, irq }
+    }
+
+    /// Forget edges seen so far: call before starting the operation
+    /// which is then completed by wait_for_edge
+    pub fn arm(&mut self) {
+        self.pin.clear_interrupt_pending_bit();
+    }
+
+    /// Wait for an edge: completes at once if there was one since arm
+    pub async fn wait_for_edge(&mut self) {
+        let pin = &mut self.pin;
+        let irq = self.irq;
+
+        poll_fn(|cx| {
+            if pin.check_interrupt() {
+                pin.clear_interrupt_pending_bit();
+                Poll::Ready(())
+            } else {
+                executor::register(irq, cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    pub fn is_high(&self) -> bool {
+        self.pin.is_high().unwrap_or(false)
+    }
+
+    pub fn is_low(&self) -> bool {
+        self.pin.is_low().unwrap_or(false)
+    }
+
+    pub fn release(self) -> PIN {
+        self.pin
+    }
+}
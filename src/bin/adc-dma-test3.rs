@@ -1,9 +1,8 @@
 #![no_main]
 #![no_std]
 
-use cm::interrupt::Mutex;
+use blue_pill_tests::irqcell::IrqCell;
 use cm::singleton;
-use core::cell::RefCell;
 use cortex_m as cm;
 use cortex_m_rt::entry;
 use cortex_m_semihosting::hprintln;
@@ -23,9 +22,9 @@ use stm32f1xx_hal::adc::Continuous;
 type RdmaT = adc::AdcDma<gpio::gpioa::PA0<gpio::Analog>, Continuous>;
 type RbufT = &'static mut [u16; 4];
 
-static G_XFR: Mutex<RefCell<Option<Transfer<W, RbufT, RdmaT>>>> = Mutex::new(RefCell::new(None));
-static G_DMA: Mutex<RefCell<Option<RdmaT>>> = Mutex::new(RefCell::new(None));
-static G_BUF: Mutex<RefCell<Option<RbufT>>> = Mutex::new(RefCell::new(None));
+// transfer in progress is owned by handler, completed one is given back to main
+static G_XFR: IrqCell<Transfer<W, RbufT, RdmaT>> = IrqCell::new();
+static G_DONE: IrqCell<(RdmaT, RbufT)> = IrqCell::new();
 
 #[entry]
 fn main() -> ! {
@@ -64,25 +63,20 @@ fn main() -> ! {
 
     cm::peripheral::NVIC::unpend(stm32::Interrupt::DMA1_CHANNEL1);
 
-    let xfer = adc_dma.read(buf);
-
-    cm::interrupt::free(|cs| {
-        G_XFR.borrow(cs).replace(Some(xfer));
-    });
+    // transfer may complete before it is stored: start it in critical section
+    if G_XFR.put_with(|| adc_dma.read(buf)).is_err() {
+        hprintln!("IDLE: ERR: xfer in progress").unwrap();
+    }
 
     loop {
-        cm::interrupt::free(|cs| {
-            if let (Some(adc_dma), Some(buf)) = (
-                G_DMA.borrow(cs).replace(None),
-                G_BUF.borrow(cs).replace(None),
-            ) {
-                hprintln!("IDLE: start next xfer").unwrap();
-                let xfer = adc_dma.read(buf);
-                G_XFR.borrow(cs).replace(Some(xfer));
-            } else {
-                hprintln!("IDLE: ERR: no rdma").unwrap();
+        if let Some((adc_dma, buf)) = G_DONE.take() {
+            hprintln!("IDLE: start next xfer").unwrap();
+            if G_XFR.put_with(|| adc_dma.read(buf)).is_err() {
+                hprintln!("IDLE: ERR: xfer in progress").unwrap();
             }
-        });
+        } else {
+            hprintln!("IDLE: ERR: no rdma").unwrap();
+        }
 
         hprintln!("IDLE: wait 5 sec").unwrap();
         delay.delay_ms(5_000u16);
@@ -91,14 +85,13 @@ fn main() -> ! {
 
 #[interrupt]
 fn DMA1_CHANNEL1() {
-    cm::interrupt::free(|cs| {
-        if let Some(xfer) = G_XFR.borrow(cs).replace(None) {
-            let (buf, adc_dma) = xfer.wait();
-            hprintln!("DMA1_CH1 IRQ: results: {:?}", buf).unwrap();
-            G_DMA.borrow(cs).replace(Some(adc_dma));
-            G_BUF.borrow(cs).replace(Some(buf));
-        } else {
-            hprintln!("DMA1_CH1 IRQ: ERR: no xfer").unwrap();
+    if let Some(xfer) = G_XFR.take() {
+        let (buf, adc_dma) = xfer.wait();
+        hprintln!("DMA1_CH1 IRQ: results: {:?}", buf).unwrap();
+        if G_DONE.put((adc_dma, buf)).is_err() {
+            hprintln!("DMA1_CH1 IRQ: ERR: rdma not taken").unwrap();
         }
-    });
+    } else {
+        hprintln!("DMA1_CH1 IRQ: ERR: no xfer").unwrap();
+    }
 }
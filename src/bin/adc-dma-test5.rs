@@ -17,14 +17,13 @@ use hal::prelude::*;
 use hal::stm32;
 use panic_semihosting as _;
 use rtic::app;
-use rtic::cyccnt::Instant;
-use rtic::cyccnt::U32Ext;
 use stm32f1xx_hal as hal;
+use systick_monotonic::{ExtU64, Systick};
 
 type RdmaT = adc::AdcDma<AdcPins, Scan>;
 type RbufT = &'static mut [u16; 4];
 
-const PERIOD: u32 = 24_000_000;
+const PERIOD_MS: u64 = 3000;
 
 pub struct AdcPins(PA0<Analog>, PA1<Analog>, PA2<Analog>, PA3<Analog>);
 
@@ -39,17 +38,28 @@ impl SetChannels<AdcPins> for Adc<stm32::ADC1> {
         self.set_regular_sequence(&[0, 1, 2, 3]);
     }
 }
-#[app(device = stm32f1xx_hal::stm32, peripherals = true, monotonic = rtic::cyccnt::CYCCNT)]
-const APP: () = {
-    struct Resources {
-        // late resources
+#[app(device = stm32f1xx_hal::stm32, peripherals = true, dispatchers = [EXTI2])]
+mod app {
+    use super::*;
+
+    #[monotonic(binds = SysTick, default = true)]
+    type MonoTimer = Systick<1000>;
+
+    #[shared]
+    struct Shared {
+        #[lock_free]
         xfr: Option<Transfer<W, RbufT, RdmaT>>,
+        #[lock_free]
         dma: Option<RdmaT>,
+        #[lock_free]
         buf: Option<RbufT>,
     }
 
-    #[init(schedule = [start_adc_dma])]
-    fn init(mut cx: init::Context) -> init::LateResources {
+    #[local]
+    struct Local {}
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
         let mut flash = cx.device.FLASH.constrain();
         let mut rcc = cx.device.RCC.constrain();
 
@@ -77,19 +87,20 @@ const APP: () = {
         let buffer = singleton!(: [u16; 4] = [0; 4]).unwrap();
         let adc_dma = adc1.with_scan_dma(adc_pins, dma_ch1);
 
-        /* Enable the monotonic timer based on CYCCNT */
-        cx.core.DCB.enable_trace();
-        cx.core.DWT.enable_cycle_counter();
+        // SysTick based monotonic timer
+        let mono = Systick::new(cx.core.SYST, clocks.sysclk().0);
 
-        cx.schedule
-            .start_adc_dma(Instant::now() + PERIOD.cycles())
-            .unwrap();
+        start_adc_dma::spawn_after(PERIOD_MS.millis()).unwrap();
 
-        init::LateResources {
-            xfr: None,
-            dma: Some(adc_dma),
-            buf: Some(buffer),
-        }
+        (
+            Shared {
+                xfr: None,
+                dma: Some(adc_dma),
+                buf: Some(buffer),
+            },
+            Local {},
+            init::Monotonics(mono),
+        )
     }
 
     #[idle]
@@ -99,35 +110,28 @@ const APP: () = {
         }
     }
 
-    #[task(resources = [xfr, dma, buf])]
+    #[task(shared = [xfr, dma, buf])]
     fn start_adc_dma(cx: start_adc_dma::Context) {
-        if let (Some(adc_dma), Some(buffer)) = (cx.resources.dma.take(), cx.resources.buf.take()) {
+        if let (Some(adc_dma), Some(buffer)) = (cx.shared.dma.take(), cx.shared.buf.take()) {
             hprintln!("IDLE: start next xfer").unwrap();
             let transfer = adc_dma.read(buffer);
-            *cx.resources.xfr = Some(transfer);
+            *cx.shared.xfr = Some(transfer);
         } else {
             hprintln!("IDLE: ERR: no rdma").unwrap();
         }
     }
 
-    #[task(binds = DMA1_CHANNEL1, schedule = [start_adc_dma], resources = [xfr, dma, buf])]
+    #[task(binds = DMA1_CHANNEL1, shared = [xfr, dma, buf])]
     fn dma1_channel1(cx: dma1_channel1::Context) {
-        if let Some(xfr) = cx.resources.xfr.take() {
+        if let Some(xfr) = cx.shared.xfr.take() {
             let (buf, dma) = xfr.wait();
             hprintln!("DMA1_CH1 IRQ: {:?}", buf).unwrap();
-            *cx.resources.dma = Some(dma);
-            *cx.resources.buf = Some(buf);
+            *cx.shared.dma = Some(dma);
+            *cx.shared.buf = Some(buf);
         } else {
             hprintln!("DMA1_CH1 IRQ: ERR: no xfer").unwrap();
         }
 
-        cx.schedule
-            .start_adc_dma(Instant::now() + PERIOD.cycles())
-            .unwrap();
+        start_adc_dma::spawn_after(PERIOD_MS.millis()).unwrap();
     }
-
-    // needed for RTFM timer queue and task management
-    extern "C" {
-        fn EXTI2();
-    }
-};
+}
@@ -17,9 +17,8 @@ use hal::prelude::*;
 use hal::stm32;
 use panic_semihosting as _;
 use rtic::app;
-use rtic::cyccnt::Instant;
-use rtic::cyccnt::U32Ext;
 use stm32f1xx_hal as hal;
+use systick_monotonic::{ExtU64, Systick};
 
 type RdmaType1 = adc::AdcDma<AdcPinsOne, Scan>;
 type RdmaType2 = adc::AdcDma<AdcPinsTwo, Scan>;
@@ -27,7 +26,7 @@ type RdmaType2 = adc::AdcDma<AdcPinsTwo, Scan>;
 type RbufType1 = &'static mut [u16; 2];
 type RbufType2 = &'static mut [u16; 3];
 
-const PERIOD: u32 = 24_000_000;
+const PERIOD_MS: u64 = 3000;
 
 pub struct AdcPinsOne(PA0<Analog>, PA1<Analog>);
 
@@ -61,23 +60,40 @@ pub enum State {
     Two,
 }
 
-#[app(device = stm32f1xx_hal::stm32, peripherals = true, monotonic = rtic::cyccnt::CYCCNT)]
-const APP: () = {
-    struct Resources {
-        // late resources
+#[app(device = stm32f1xx_hal::stm32, peripherals = true, dispatchers = [EXTI2])]
+mod app {
+    use super::*;
+
+    #[monotonic(binds = SysTick, default = true)]
+    type MonoTimer = Systick<1000>;
+
+    #[shared]
+    struct Shared {
+        #[lock_free]
         state: State,
+        #[lock_free]
         transfer1: Option<Transfer<W, RbufType1, RdmaType1>>,
+        #[lock_free]
         transfer2: Option<Transfer<W, RbufType2, RdmaType2>>,
+        #[lock_free]
         adc_pins1: Option<AdcPinsOne>,
+        #[lock_free]
         adc_pins2: Option<AdcPinsTwo>,
+        #[lock_free]
         adc_dma1: Option<RdmaType1>,
+        #[lock_free]
         adc_dma2: Option<RdmaType2>,
+        #[lock_free]
         buffer1: Option<RbufType1>,
+        #[lock_free]
         buffer2: Option<RbufType2>,
     }
 
-    #[init(schedule = [start_adc_dma])]
-    fn init(mut cx: init::Context) -> init::LateResources {
+    #[local]
+    struct Local {}
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
         let mut flash = cx.device.FLASH.constrain();
         let mut rcc = cx.device.RCC.constrain();
 
@@ -110,27 +126,28 @@ const APP: () = {
 
         let adc_dma1 = adc1.with_scan_dma(adc_pins1, dma_ch1);
 
-        /* Enable the monotonic timer based on CYCCNT */
-        cx.core.DCB.enable_trace();
-        cx.core.DWT.enable_cycle_counter();
+        // SysTick based monotonic timer
+        let mono = Systick::new(cx.core.SYST, clocks.sysclk().0);
 
-        cx.schedule
-            .start_adc_dma(Instant::now() + PERIOD.cycles())
-            .unwrap();
+        start_adc_dma::spawn_after(PERIOD_MS.millis()).unwrap();
 
-        init::LateResources {
-            transfer1: None,
-            adc_pins1: None,
-            adc_dma1: Some(adc_dma1),
-            buffer1: Some(buffer1),
+        (
+            Shared {
+                transfer1: None,
+                adc_pins1: None,
+                adc_dma1: Some(adc_dma1),
+                buffer1: Some(buffer1),
 
-            transfer2: None,
-            adc_pins2: Some(adc_pins2),
-            adc_dma2: None,
-            buffer2: Some(buffer2),
+                transfer2: None,
+                adc_pins2: Some(adc_pins2),
+                adc_dma2: None,
+                buffer2: Some(buffer2),
 
-            state: State::One,
-        }
+                state: State::One,
+            },
+            Local {},
+            init::Monotonics(mono),
+        )
     }
 
     #[idle]
@@ -140,27 +157,27 @@ const APP: () = {
         }
     }
 
-    #[task(resources = [state, transfer1, adc_pins1, adc_dma1, buffer1, transfer2, adc_pins2, adc_dma2, buffer2])]
+    #[task(shared = [state, transfer1, adc_pins1, adc_dma1, buffer1, transfer2, adc_pins2, adc_dma2, buffer2])]
     fn start_adc_dma(cx: start_adc_dma::Context) {
-        match *cx.resources.state {
+        match *cx.shared.state {
             State::One => {
                 if let (Some(adc_dma), Some(buffer)) =
-                    (cx.resources.adc_dma1.take(), cx.resources.buffer1.take())
+                    (cx.shared.adc_dma1.take(), cx.shared.buffer1.take())
                 {
                     hprintln!("TASK: start next xfer").unwrap();
                     let transfer = adc_dma.read(buffer);
-                    *cx.resources.transfer1 = Some(transfer);
+                    *cx.shared.transfer1 = Some(transfer);
                 } else {
                     hprintln!("TASK: ERR: no ADC/DMA type One").unwrap();
                 }
             }
             State::Two => {
                 if let (Some(adc_dma), Some(buffer)) =
-                    (cx.resources.adc_dma2.take(), cx.resources.buffer2.take())
+                    (cx.shared.adc_dma2.take(), cx.shared.buffer2.take())
                 {
                     hprintln!("TASK: start next xfer").unwrap();
                     let transfer = adc_dma.read(buffer);
-                    *cx.resources.transfer2 = Some(transfer);
+                    *cx.shared.transfer2 = Some(transfer);
                 } else {
                     hprintln!("TASK: ERR: no ADC/DMA type Two").unwrap();
                 }
@@ -168,52 +185,45 @@ const APP: () = {
         }
     }
 
-    #[task(binds = DMA1_CHANNEL1, schedule = [start_adc_dma], resources = [state, transfer1, adc_pins1, adc_dma1, buffer1, transfer2, adc_pins2, adc_dma2, buffer2])]
+    #[task(binds = DMA1_CHANNEL1, shared = [state, transfer1, adc_pins1, adc_dma1, buffer1, transfer2, adc_pins2, adc_dma2, buffer2])]
     fn dma1_channel1(cx: dma1_channel1::Context) {
-        match *cx.resources.state {
+        match *cx.shared.state {
             State::One => {
                 if let (Some(transfer), Some(pins2)) =
-                    (cx.resources.transfer1.take(), cx.resources.adc_pins2.take())
+                    (cx.shared.transfer1.take(), cx.shared.adc_pins2.take())
                 {
                     let (buf1, adc_dma) = transfer.wait();
                     let (adc, pins1, chan) = adc_dma.split();
 
                     hprintln!("DMA1_CH1 IRQ: ONE: {:?}", buf1).unwrap();
 
-                    *cx.resources.adc_dma2 = Some(adc.with_scan_dma(pins2, chan));
-                    *cx.resources.adc_pins1 = Some(pins1);
-                    *cx.resources.buffer1 = Some(buf1);
-                    *cx.resources.state = State::Two;
+                    *cx.shared.adc_dma2 = Some(adc.with_scan_dma(pins2, chan));
+                    *cx.shared.adc_pins1 = Some(pins1);
+                    *cx.shared.buffer1 = Some(buf1);
+                    *cx.shared.state = State::Two;
                 } else {
                     hprintln!("DMA1_CH1 IRQ: ERR: no transfer of type One").unwrap();
                 }
             }
             State::Two => {
                 if let (Some(transfer), Some(pins1)) =
-                    (cx.resources.transfer2.take(), cx.resources.adc_pins1.take())
+                    (cx.shared.transfer2.take(), cx.shared.adc_pins1.take())
                 {
                     let (buf2, adc_dma) = transfer.wait();
                     let (adc, pins2, chan) = adc_dma.split();
 
                     hprintln!("DMA1_CH1 IRQ: TWO: {:?}", buf2).unwrap();
 
-                    *cx.resources.adc_dma1 = Some(adc.with_scan_dma(pins1, chan));
-                    *cx.resources.adc_pins2 = Some(pins2);
-                    *cx.resources.buffer2 = Some(buf2);
-                    *cx.resources.state = State::One;
+                    *cx.shared.adc_dma1 = Some(adc.with_scan_dma(pins1, chan));
+                    *cx.shared.adc_pins2 = Some(pins2);
+                    *cx.shared.buffer2 = Some(buf2);
+                    *cx.shared.state = State::One;
                 } else {
                     hprintln!("DMA1_CH1 IRQ: ERR: no transfer of type One").unwrap();
                 }
             }
         }
 
-        cx.schedule
-            .start_adc_dma(Instant::now() + PERIOD.cycles())
-            .unwrap();
+        start_adc_dma::spawn_after(PERIOD_MS.millis()).unwrap();
     }
-
-    // needed for RTFM timer queue and task management
-    extern "C" {
-        fn EXTI2();
-    }
-};
+}
@@ -0,0 +1,177 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::aio::{self, ExtiInput, SerialRx, SerialTx};
+use blue_pill_tests::executor::{self, join};
+use cm::singleton;
+use cortex_m as cm;
+use cortex_m_rt::entry;
+use hal::adc;
+use hal::dma;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::serial::{Config, Serial};
+use hal::stm32;
+use hal::stm32::{interrupt, Interrupt};
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+
+type RdmaT = adc::AdcDma<gpioa::PA0<Analog>, adc::Continuous>;
+type RbufT = &'static mut [u16; 4];
+
+const BLINK_MS: u32 = 500;
+const ADC_PERIOD_MS: u32 = 1000;
+const DEBOUNCE_MS: u32 = 20;
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let dp = stm32::Peripherals::take().unwrap();
+    let mut rcc = dp.RCC.constrain();
+    let mut afio = dp.AFIO.constrain(&mut rcc.apb2);
+    let mut flash = dp.FLASH.constrain();
+
+    let clocks = rcc
+        .cfgr
+        .use_hse(8.mhz())
+        .sysclk(32.mhz())
+        .pclk1(16.mhz())
+        .adcclk(2.mhz())
+        .freeze(&mut flash.acr);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.apb2);
+    let mut gpiob = dp.GPIOB.split(&mut rcc.apb2);
+    let mut gpioc = dp.GPIOC.split(&mut rcc.apb2);
+
+    // one async timer per concurrent sequence
+
+    let blink_tmr = aio::Timer::new(dp.TIM2, &clocks, &mut rcc.apb1);
+    let adc_tmr = aio::Timer::new(dp.TIM3, &clocks, &mut rcc.apb1);
+    let button_tmr = aio::Timer::new(dp.TIM4, &clocks, &mut rcc.apb1);
+
+    // LED on PC13
+
+    let led = gpioc.pc13.into_push_pull_output(&mut gpioc.crh);
+
+    // ADC1 on PA0 with DMA1 CH1
+
+    let mut dma_ch1 = dp.DMA1.split(&mut rcc.ahb).1;
+    dma_ch1.listen(dma::Event::TransferComplete);
+
+    let adc1 = adc::Adc::adc1(dp.ADC1, &mut rcc.apb2, clocks);
+    let adc_ch0 = gpioa.pa0.into_analog(&mut gpioa.crl);
+    let adc_dma = adc1.with_dma(adc_ch0, dma_ch1);
+    let buf = singleton!(: [u16; 4] = [0; 4]).unwrap();
+
+    // USART1 echo: PA9 TX, PA10 RX
+
+    let tx = gpioa.pa9.into_alternate_push_pull(&mut gpioa.crh);
+    let rx = gpioa.pa10.into_floating_input(&mut gpioa.crh);
+
+    let serial = Serial::usart1(
+        dp.USART1,
+        (tx, rx),
+        &mut afio.mapr,
+        Config::default().baudrate(115_200.bps()),
+        clocks,
+        &mut rcc.apb2,
+    );
+
+    let (tx, rx) = serial.split();
+    let tx = SerialTx::new(tx);
+    let rx = SerialRx::new(rx);
+
+    // button on PB0 to ground: EXTI0 on falling edge
+
+    let mut btn = gpiob.pb0.into_pull_up_input(&mut gpiob.crl);
+    btn.make_interrupt_source(&mut afio);
+    btn.trigger_on_edge(&dp.EXTI, Edge::FALLING);
+    btn.enable_interrupt(&dp.EXTI);
+    let btn = ExtiInput::new(btn, Interrupt::EXTI0);
+
+    executor::block_on(join(
+        join(blink(blink_tmr, led), sample(adc_tmr, adc_dma, buf)),
+        join(echo(rx, tx), button(button_tmr, btn)),
+    ));
+
+    unreachable!();
+}
+
+async fn blink(mut tmr: aio::Timer<stm32::TIM2>, mut led: gpioc::PC13<Output<PushPull>>) {
+    loop {
+        led.toggle().unwrap();
+        tmr.delay_ms(BLINK_MS).await;
+    }
+}
+
+async fn sample(mut tmr: aio::Timer<stm32::TIM3>, mut adc_dma: RdmaT, mut buf: RbufT) {
+    loop {
+        let (b, d) = aio::adc_dma(adc_dma.read(buf)).await;
+        rprintln!("ADC: {:?}", b);
+
+        buf = b;
+        adc_dma = d;
+
+        tmr.delay_ms(ADC_PERIOD_MS).await;
+    }
+}
+
+async fn echo(mut rx: SerialRx<stm32::USART1>, mut tx: SerialTx<stm32::USART1>) {
+    tx.write_all(b"async echo\r\n").await;
+
+    loop {
+        match rx.read().await {
+            Ok(b'\r') => tx.write_all(b"\r\n").await,
+            Ok(b) => tx.write(b).await,
+            Err(e) => rprintln!("USART: {:?}", e),
+        }
+    }
+}
+
+async fn button(mut tmr: aio::Timer<stm32::TIM4>, mut btn: ExtiInput<gpiob::PB0<Input<PullUp>>>) {
+    let mut presses: u32 = 0;
+
+    loop {
+        // ignore bounces during the previous debounce delay
+        btn.arm();
+        btn.wait_for_edge().await;
+        tmr.delay_ms(DEBOUNCE_MS).await;
+
+        if btn.is_low() {
+            presses += 1;
+            rprintln!("button: {} presses", presses);
+        }
+    }
+}
+
+#[interrupt]
+fn TIM2() {
+    executor::on_interrupt(Interrupt::TIM2);
+}
+
+#[interrupt]
+fn TIM3() {
+    executor::on_interrupt(Interrupt::TIM3);
+}
+
+#[interrupt]
+fn TIM4() {
+    executor::on_interrupt(Interrupt::TIM4);
+}
+
+#[interrupt]
+fn DMA1_CHANNEL1() {
+    executor::on_interrupt(Interrupt::DMA1_CHANNEL1);
+}
+
+#[interrupt]
+fn USART1() {
+    executor::on_interrupt(Interrupt::USART1);
+}
+
+#[interrupt]
+fn EXTI0() {
+    executor::on_interrupt(Interrupt::EXTI0);
+}
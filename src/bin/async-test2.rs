@@ -0,0 +1,178 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::aio::{self, ExtiInput};
+use blue_pill_tests::executor;
+use blue_pill_tests::rc522::{self, Rc522, Uid};
+use cortex_m_rt::entry;
+use embedded_hal::digital::v2::OutputPin;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::spi::Spi;
+use hal::spi::Spi1Remap;
+use hal::stm32;
+use hal::stm32::{interrupt, Interrupt};
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+
+type SpiSckType = gpiob::PB3<Alternate<PushPull>>;
+type SpiMisoType = gpiob::PB4<Input<Floating>>;
+type SpiMosiType = gpiob::PB5<Alternate<PushPull>>;
+type SpiNssType = gpioa::PA15<Output<PushPull>>;
+type SpiType = Spi<stm32::SPI1, Spi1Remap, (SpiSckType, SpiMisoType, SpiMosiType), u8>;
+
+type Nfc = Rc522<SpiType, SpiNssType>;
+type NfcError = rc522::Error<hal::spi::Error>;
+
+// card is considered removed after this number of missed polls
+const MISSED_POLLS: u8 = 2;
+
+const POLL_MS: u32 = 200;
+
+// RC522 timer gives up after 5ms, IRQ should come well before that
+const REQUEST_TIMEOUT_MS: u32 = 10;
+
+// RC522 interrupt sources for transceive completion
+const COM_IRQS: u8 = rc522::RX_IRQ | rc522::ERR_IRQ | rc522::TIMER_IRQ;
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let dp = stm32::Peripherals::take().unwrap();
+    let mut rcc = dp.RCC.constrain();
+    let mut afio = dp.AFIO.constrain(&mut rcc.apb2);
+    let mut flash = dp.FLASH.constrain();
+
+    let clocks = rcc
+        .cfgr
+        .sysclk(8.mhz())
+        .pclk1(8.mhz())
+        .freeze(&mut flash.acr);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.apb2);
+    let mut gpiob = dp.GPIOB.split(&mut rcc.apb2);
+    let mut gpioc = dp.GPIOC.split(&mut rcc.apb2);
+
+    // configure PC13 pin to show card presence
+
+    let mut led = gpioc.pc13.into_push_pull_output(&mut gpioc.crh);
+    led.set_high().unwrap();
+
+    let tmr = aio::Timer::new(dp.TIM3, &clocks, &mut rcc.apb1);
+
+    // configure external irq line from NFC chip: active high push-pull
+
+    let mut irq = gpiob.pb1.into_floating_input(&mut gpiob.crl);
+    irq.make_interrupt_source(&mut afio);
+    irq.trigger_on_edge(&dp.EXTI, Edge::RISING);
+    irq.enable_interrupt(&dp.EXTI);
+    let irq = ExtiInput::new(irq, Interrupt::EXTI1);
+
+    // configure SPI and connected NFC RC522 board
+
+    let (pa15, pb3, pb4) = afio.mapr.disable_jtag(gpioa.pa15, gpiob.pb3, gpiob.pb4);
+
+    let sck = pb3.into_alternate_push_pull(&mut gpiob.crl);
+    let mosi = gpiob.pb5.into_alternate_push_pull(&mut gpiob.crl);
+    let miso = pb4;
+    let nss = pa15.into_push_pull_output(&mut gpioa.crh);
+
+    let spi = Spi::spi1(
+        dp.SPI1,
+        (sck, miso, mosi),
+        &mut afio.mapr,
+        mfrc522::MODE,
+        1.mhz(),
+        clocks,
+        &mut rcc.apb2,
+    );
+
+    let mut nfc = Rc522::new(spi, nss).unwrap();
+
+    // wait for card response no longer than 5ms
+    nfc.set_timeout_ticks(200).unwrap();
+    nfc.enable_interrupts(COM_IRQS, 0).unwrap();
+    nfc.power_down().unwrap();
+
+    executor::block_on(poll_cards(nfc, irq, tmr, led));
+
+    unreachable!();
+}
+
+async fn poll_cards(
+    mut nfc: Nfc,
+    mut irq: ExtiInput<gpiob::PB1<Input<Floating>>>,
+    mut tmr: aio::Timer<stm32::TIM3>,
+    mut led: gpioc::PC13<Output<PushPull>>,
+) {
+    let mut card: Option<Uid> = None;
+    let mut missed: u8 = 0;
+
+    loop {
+        match read_uid(&mut nfc, &mut irq, &mut tmr).await {
+            Ok(uid) => {
+                missed = 0;
+
+                if card != Some(uid) {
+                    rprintln!("NFC: card arrived {:x?}", uid.as_bytes());
+                    led.set_low().unwrap();
+                    card = Some(uid);
+                }
+            }
+            Err(e) => {
+                if let Some(uid) = card {
+                    missed += 1;
+
+                    if missed >= MISSED_POLLS {
+                        rprintln!("NFC: card removed {:x?}: {:?}", uid.as_bytes(), e);
+                        led.set_high().unwrap();
+                        card = None;
+                    }
+                }
+            }
+        }
+
+        nfc.clear_interrupts().ok();
+        nfc.power_down().ok();
+
+        tmr.delay_ms(POLL_MS).await;
+    }
+}
+
+// WUPA and anticollision/select as straight-line code: only the request
+// waits for IRQ, short select exchanges are polled by the driver
+async fn read_uid(
+    nfc: &mut Nfc,
+    irq: &mut ExtiInput<gpiob::PB1<Input<Floating>>>,
+    tmr: &mut aio::Timer<stm32::TIM3>,
+) -> Result<Uid, NfcError> {
+    nfc.wake_up()?;
+    nfc.clear_interrupts()?;
+
+    // halted card still answers WUPA in the next poll: IRQ edge may come
+    // before wait_for_edge is polled
+    irq.arm();
+    nfc.start_wupa()?;
+
+    tmr.with_timeout_ms(REQUEST_TIMEOUT_MS, irq.wait_for_edge())
+        .await
+        .map_err(|_| rc522::Error::Timeout)?;
+
+    let atqa = nfc.finish_request()?;
+    let uid = nfc.select(&atqa)?;
+    nfc.hlta()?;
+
+    Ok(uid)
+}
+
+#[interrupt]
+fn TIM3() {
+    executor::on_interrupt(Interrupt::TIM3);
+}
+
+#[interrupt]
+fn EXTI1() {
+    executor::on_interrupt(Interrupt::EXTI1);
+}
@@ -1,10 +1,8 @@
 #![no_main]
 #![no_std]
 
+use blue_pill_tests::irqcell::IrqCell;
 use cm::asm::wfi;
-use cm::interrupt::Mutex;
-use core::cell::RefCell;
-use core::ops::DerefMut;
 use cortex_m as cm;
 use cortex_m_rt as rt;
 use cortex_m_semihosting::hprintln;
@@ -20,20 +18,19 @@ use panic_semihosting as _;
 use rt::entry;
 use stm32f1xx_hal as hal;
 
-static G_LED: Mutex<RefCell<Option<PC13<Output<PushPull>>>>> = Mutex::new(RefCell::new(None));
-static G_TIM: Mutex<RefCell<Option<CountDownTimer<TIM2>>>> = Mutex::new(RefCell::new(None));
+// timer and LED are put together: TIM2 interrupt is already enabled
+static G_PERIPH: IrqCell<(CountDownTimer<TIM2>, PC13<Output<PushPull>>)> = IrqCell::new();
 
 #[entry]
 fn main() -> ! {
     let mut cp = cm::Peripherals::take().unwrap();
     let dp = stm32::Peripherals::take().unwrap();
 
-    let (tim, led) = init_periph(&mut cp, dp);
+    let periph = init_periph(&mut cp, dp);
 
-    cm::interrupt::free(|cs| {
-        G_TIM.borrow(cs).replace(Some(tim));
-        G_LED.borrow(cs).replace(Some(led));
-    });
+    if G_PERIPH.put(periph).is_err() {
+        hprintln!("ERR: peripherals are already in use").unwrap();
+    }
 
     loop {
         hprintln!("MAIN LOOP").unwrap();
@@ -75,16 +72,14 @@ fn init_periph(
     (tim, led)
 }
 
+// timer and LED are moved into handler locals on the first interrupt
 #[interrupt]
 fn TIM2() {
-    cm::interrupt::free(|cs| {
-        if let (Some(ref mut tim), Some(ref mut led)) = (
-            G_TIM.borrow(cs).borrow_mut().deref_mut(),
-            G_LED.borrow(cs).borrow_mut().deref_mut(),
-        ) {
-            hprintln!("TIM2 IRQ").unwrap();
-            tim.clear_update_interrupt_flag();
-            led.toggle().unwrap();
-        }
-    });
+    static mut PERIPH: Option<(CountDownTimer<TIM2>, PC13<Output<PushPull>>)> = None;
+
+    if let Some((tim, led)) = G_PERIPH.claim(PERIPH) {
+        hprintln!("TIM2 IRQ").unwrap();
+        tim.clear_update_interrupt_flag();
+        led.toggle().unwrap();
+    }
 }
@@ -1,6 +1,7 @@
 #![no_main]
 #![no_std]
 
+use blue_pill_tests::irqcell::IrqCell;
 use cortex_m as cm;
 use cortex_m_rt as rt;
 use cortex_m_semihosting::hprintln;
@@ -16,8 +17,7 @@ use stm32f1xx_hal as hal;
 type LedT = hal::gpio::gpioc::PC13<hal::gpio::Output<hal::gpio::PushPull>>;
 type TimT = hal::timer::CountDownTimer<stm32::TIM3>;
 
-static mut G_LED: Option<LedT> = None;
-static mut G_TMR: Option<TimT> = None;
+static G_PERIPH: IrqCell<(LedT, TimT)> = IrqCell::new();
 
 #[entry]
 fn main() -> ! {
@@ -44,9 +44,9 @@ fn main() -> ! {
     let mut tmr = Timer::tim3(dp.TIM3, &clocks, &mut rcc.apb1).start_count_down(1.hz());
     tmr.listen(Event::Update);
 
-    unsafe {
-        G_LED = Some(led);
-        G_TMR = Some(tmr);
+    // both are put at once: TIM3 interrupt is already enabled
+    if G_PERIPH.put((led, tmr)).is_err() {
+        hprintln!("ERR: peripherals are already in use").unwrap();
     }
 
     loop {
@@ -67,12 +67,14 @@ fn setup_interrupts(cp: &mut cm::peripheral::Peripherals) {
     cm::peripheral::NVIC::unpend(stm32::Interrupt::TIM3);
 }
 
+// handler locals are safe &mut references, globals are claimed on the first interrupt
 #[interrupt]
 fn TIM3() {
+    static mut PERIPH: Option<(LedT, TimT)> = None;
+
     hprintln!("BLINK").unwrap();
 
-    let led = unsafe { G_LED.as_mut().unwrap() };
-    let tim = unsafe { G_TMR.as_mut().unwrap() };
+    let (led, tim) = G_PERIPH.claim(PERIPH).unwrap();
 
     tim.clear_update_interrupt_flag();
     led.toggle().unwrap();
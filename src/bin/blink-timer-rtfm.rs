@@ -14,12 +14,14 @@ use rtt_target::{rtt_init, UpChannel};
 use stm32f1xx_hal as hal;
 
 #[app(device = stm32f1xx_hal::stm32, peripherals = true)]
-const APP: () = {
-    struct Resources {
-        // resources
-        #[init(0)]
-        beat: u8,
-        // late resources
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {}
+
+    #[local]
+    struct Local {
         stream1: UpChannel,
         stream2: UpChannel,
         led1: hal::gpio::gpioc::PC13<hal::gpio::Output<hal::gpio::PushPull>>,
@@ -28,7 +30,7 @@ const APP: () = {
     }
 
     #[init]
-    fn init(cx: init::Context) -> init::LateResources {
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
         let channels = rtt_init! {
             up: {
                 0: {
@@ -67,13 +69,17 @@ const APP: () = {
         let mut t3 = Timer::tim3(cx.device.TIM3, &clocks, &mut rcc.apb1).start_count_down(5.hz());
         t3.listen(Event::Update);
 
-        init::LateResources {
-            stream1: stream1,
-            stream2: stream2,
-            led1: l1,
-            tmr2: t2,
-            tmr3: t3,
-        }
+        (
+            Shared {},
+            Local {
+                stream1: stream1,
+                stream2: stream2,
+                led1: l1,
+                tmr2: t2,
+                tmr3: t3,
+            },
+            init::Monotonics(),
+        )
     }
 
     #[idle]
@@ -83,18 +89,18 @@ const APP: () = {
         }
     }
 
-    #[task(binds = TIM2, resources = [beat, tmr2, stream1])]
+    #[task(binds = TIM2, local = [tmr2, stream1, beat: u8 = 0])]
     fn tim2(cx: tim2::Context) {
-        writeln!(cx.resources.stream1, "TIM2 beat = {}", *cx.resources.beat).ok();
+        writeln!(cx.local.stream1, "TIM2 beat = {}", *cx.local.beat).ok();
 
-        *cx.resources.beat += 1;
-        cx.resources.tmr2.clear_update_interrupt_flag();
+        *cx.local.beat += 1;
+        cx.local.tmr2.clear_update_interrupt_flag();
     }
 
-    #[task(binds = TIM3, resources = [led1, tmr3, stream2])]
+    #[task(binds = TIM3, local = [led1, tmr3, stream2])]
     fn tim3(cx: tim3::Context) {
-        writeln!(cx.resources.stream2, "TIM3 blink").ok();
-        cx.resources.led1.toggle().unwrap();
-        cx.resources.tmr3.clear_update_interrupt_flag();
+        writeln!(cx.local.stream2, "TIM3 blink").ok();
+        cx.local.led1.toggle().unwrap();
+        cx.local.tmr3.clear_update_interrupt_flag();
     }
-};
+}
@@ -0,0 +1,125 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::capture::{Config, PwmInput};
+use cortex_m as cm;
+use embedded_hal::PwmPin;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::stm32;
+use hal::timer::{Tim2NoRemap, Timer};
+use panic_rtt_target as _;
+use rtic::app;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+use systick_monotonic::{fugit::TimerInstantU64, ExtU64, Systick};
+
+// report period
+const PERIOD_MS: u64 = 1000;
+
+// fan tachometer: two pulses per revolution
+const FAN_PULSES: u32 = 2;
+
+#[app(device = stm32f1xx_hal::stm32, peripherals = true, dispatchers = [EXTI2])]
+mod app {
+    use super::*;
+
+    #[monotonic(binds = SysTick, default = true)]
+    type MonoTimer = Systick<1000>;
+
+    #[shared]
+    struct Shared {
+        input: PwmInput<stm32::TIM3, gpioa::PA6<Input<PullUp>>>,
+    }
+
+    #[local]
+    struct Local {}
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        rtt_init_print!();
+
+        let mut rcc = cx.device.RCC.constrain();
+        let mut afio = cx.device.AFIO.constrain(&mut rcc.apb2);
+        let mut flash = cx.device.FLASH.constrain();
+
+        let clocks = rcc
+            .cfgr
+            .use_hse(8.mhz())
+            .sysclk(72.mhz())
+            .pclk1(36.mhz())
+            .freeze(&mut flash.acr);
+
+        let mut gpioa = cx.device.GPIOA.split(&mut rcc.apb2);
+
+        // test signal on PA0: 1kHz, 30% duty, connect to PA6 for loopback
+
+        let p1 = gpioa.pa0.into_alternate_push_pull(&mut gpioa.crl);
+        let mut test = Timer::tim2(cx.device.TIM2, &clocks, &mut rcc.apb1)
+            .pwm::<Tim2NoRemap, _, _, _>(p1, &mut afio.mapr, 1.khz())
+            .split();
+
+        let max = test.get_max_duty();
+        test.set_duty(max * 3 / 10);
+        test.enable();
+
+        // PWM input on TIM3 TI1: PA6, pulled up for open collector tachometers
+
+        let pin = gpioa.pa6.into_pull_up_input(&mut gpioa.crl);
+        let input = PwmInput::new(
+            cx.device.TIM3,
+            pin,
+            &clocks,
+            &mut rcc.apb1,
+            &Config {
+                filter: 3,
+                ..Config::default()
+            },
+        );
+
+        // SysTick based monotonic timer
+        let mono = Systick::new(cx.core.SYST, clocks.sysclk().0);
+
+        let next = monotonics::now() + PERIOD_MS.millis();
+        report::spawn_at(next, next).unwrap();
+
+        (Shared { input }, Local {}, init::Monotonics(mono))
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cm::asm::wfi();
+        }
+    }
+
+    #[task(binds = TIM3, priority = 2, shared = [input])]
+    fn tim3(mut cx: tim3::Context) {
+        cx.shared.input.lock(|input| input.on_interrupt());
+    }
+
+    #[task(priority = 1, shared = [input])]
+    fn report(mut cx: report::Context, instant: TimerInstantU64<1000>) {
+        let (m, psc) = cx
+            .shared
+            .input
+            .lock(|input| (input.measurement(), input.prescaler()));
+
+        match m {
+            Some(m) => rprintln!(
+                "freq {}.{:03} Hz period {} us duty {}.{}% rpm {} (psc {})",
+                m.frequency_mhz() / 1000,
+                m.frequency_mhz() % 1000,
+                m.period_us(),
+                m.duty_permille() / 10,
+                m.duty_permille() % 10,
+                m.rpm(FAN_PULSES),
+                psc
+            ),
+            None => rprintln!("no signal (psc {})", psc),
+        }
+
+        let next = instant + PERIOD_MS.millis();
+        report::spawn_at(next, next).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::clocks::{self, Bus, Report};
+use cortex_m as cm;
+use cortex_m_rt::{entry, exception};
+use hal::prelude::*;
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+
+const HSE_HZ: u32 = 8_000_000;
+
+// clock tree suitable for USB, ws2812 on SPI2 and console on USART1
+const SYSCLK_HZ: u32 = 48_000_000;
+const PCLK1_HZ: u32 = 24_000_000;
+const PCLK2_HZ: u32 = 48_000_000;
+const ADCCLK_HZ: u32 = 12_000_000;
+
+// ws2812 bit timing tolerates ~5% error
+const WS2812_HZ: u32 = 3_000_000;
+const WS2812_TOLERANCE_PERMILLE: u32 = 50;
+
+const CONSOLE_BAUD: u32 = 115_200;
+
+// build time checks of the planned tree
+const _: () = assert!(
+    clocks::error_permille(WS2812_HZ, clocks::spi_baud(PCLK1_HZ, WS2812_HZ))
+        <= WS2812_TOLERANCE_PERMILLE
+);
+const _: () = assert!(
+    clocks::error_permille(CONSOLE_BAUD, clocks::uart_baud(PCLK2_HZ, CONSOLE_BAUD))
+        <= clocks::UART_MAX_ERROR_PERMILLE
+);
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let dp = hal::stm32::Peripherals::take().unwrap();
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+
+    let _clocks = rcc
+        .cfgr
+        .use_hse(HSE_HZ.hz())
+        .sysclk(SYSCLK_HZ.hz())
+        .pclk1(PCLK1_HZ.hz())
+        .pclk2(PCLK2_HZ.hz())
+        .adcclk(ADCCLK_HZ.hz())
+        .freeze(&mut flash.acr);
+
+    // init time checks of the tree actually configured by HAL
+    let mut report = clocks::read(HSE_HZ);
+    rprintln!("{}", report);
+    check(&report);
+
+    // HSE failure switches SYSCLK to HSI and raises NMI
+    clocks::enable_css();
+
+    let mut seconds: u32 = 0;
+    let mut fallback = false;
+
+    loop {
+        cm::asm::delay(report.sysclk);
+        seconds += 1;
+
+        if clocks::hse_failed() && !fallback {
+            rprintln!("HSE failed: running from HSI");
+            rprintln!("{}", clocks::read(HSE_HZ));
+
+            report = clocks::fallback_to_hsi_pll();
+            rprintln!("fallback to HSI PLL:\n{}", report);
+            check(&report);
+            fallback = true;
+        }
+
+        if seconds % 10 == 0 {
+            rprintln!("{} s: SYSCLK {} Hz", seconds, report.sysclk);
+        }
+    }
+}
+
+fn check(report: &Report) {
+    if let Err(e) = report.validate() {
+        rprintln!("invalid clocks: {:?}", e);
+    }
+
+    if let Err(e) = report.check_usb() {
+        rprintln!("USB: {:?}", e);
+    }
+
+    match report.check_spi(Bus::Apb1, WS2812_HZ, WS2812_TOLERANCE_PERMILLE) {
+        Ok(baud) => rprintln!("ws2812 SPI2: {} Hz", baud),
+        Err(e) => rprintln!("ws2812 SPI2: {:?}", e),
+    }
+
+    match report.check_uart(Bus::Apb2, CONSOLE_BAUD) {
+        Ok(baud) => rprintln!("console USART1: {} bps", baud),
+        Err(e) => rprintln!("console USART1: {:?}", e),
+    }
+}
+
+#[exception]
+fn NonMaskableInt() {
+    clocks::on_css_nmi();
+}
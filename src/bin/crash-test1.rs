@@ -0,0 +1,76 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::crash::{self, Kind};
+use blue_pill_tests::watchdog;
+use core::panic::PanicInfo;
+use core::ptr;
+use cortex_m as cm;
+use cortex_m_rt::{entry, exception, ExceptionFrame};
+use hal::delay::Delay;
+use hal::prelude::*;
+use hal::stm32;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+
+// unmapped address: read causes precise bus fault escalated to HardFault
+const BAD_ADDRESS: u32 = 0x3000_0000;
+
+const CRASH_DELAY_MS: u16 = 3000;
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let cause = watchdog::reset_cause();
+    let last = crash::take();
+
+    rprintln!("reset cause: {:?}", cause);
+
+    match last {
+        Some(ref rec) => rprintln!("last crash:\n{}", rec),
+        None => rprintln!("no crash record"),
+    }
+
+    let cp = cm::Peripherals::take().unwrap();
+    let dp = stm32::Peripherals::take().unwrap();
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+
+    let clocks = rcc
+        .cfgr
+        .use_hse(8.mhz())
+        .sysclk(72.mhz())
+        .pclk1(36.mhz())
+        .freeze(&mut flash.acr);
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    rprintln!("crashing in {} ms", CRASH_DELAY_MS);
+    delay.delay_ms(CRASH_DELAY_MS);
+
+    // alternate between HardFault and panic
+    match last.map(|rec| rec.kind()) {
+        Some(Kind::HardFault) => panic!("crash test: panic after HardFault"),
+        _ => {
+            let v = unsafe { ptr::read_volatile(BAD_ADDRESS as *const u32) };
+            rprintln!("unexpected read: {:#x}", v);
+        }
+    }
+
+    loop {
+        cm::asm::wfi();
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    crash::record_fault(ef);
+    cm::peripheral::SCB::sys_reset();
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    crash::record_panic(info);
+    cm::peripheral::SCB::sys_reset();
+}
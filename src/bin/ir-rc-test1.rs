@@ -0,0 +1,87 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::capture::{EdgeCapture, Interval};
+use blue_pill_tests::ir::{self, Decoder, Nec, Pulse, Rc5, Rc6, Sirc};
+use cortex_m as cm;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::stm32;
+use panic_rtt_target as _;
+use rtic::app;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+
+// longer than any pulse inside supported frames: NEC leader is 9ms
+const IDLE_US: u32 = 12_000;
+
+// input filter: 8 samples at fDTS/8 suppress short glitches
+const FILTER: u8 = 0b1010;
+
+#[app(device = stm32f1xx_hal::stm32, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {}
+
+    #[local]
+    struct Local {
+        edges: EdgeCapture<stm32::TIM3, gpioa::PA6<Input<PullUp>>>,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        rtt_init_print!();
+
+        let mut rcc = cx.device.RCC.constrain();
+        let mut flash = cx.device.FLASH.constrain();
+
+        let clocks = rcc
+            .cfgr
+            .use_hse(8.mhz())
+            .sysclk(48.mhz())
+            .pclk1(24.mhz())
+            .freeze(&mut flash.acr);
+
+        rprintln!("SYSCLK: {} Hz ...", clocks.sysclk().0);
+        rprintln!("PCLK: {} Hz ...", clocks.pclk1().0);
+
+        // IR receiver output (active low) connected to TIM3 TI1: PA6
+
+        let mut gpioa = cx.device.GPIOA.split(&mut rcc.apb2);
+        let irr = gpioa.pa6.into_pull_up_input(&mut gpioa.crl);
+
+        let edges = EdgeCapture::new(cx.device.TIM3, irr, &clocks, &mut rcc.apb1, FILTER, IDLE_US);
+
+        (Shared {}, Local { edges }, init::Monotonics())
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cm::asm::wfi();
+        }
+    }
+
+    #[task(binds = TIM3, local = [edges, nec: Nec = Nec::new(), rc5: Rc5 = Rc5::new(), rc6: Rc6 = Rc6::new(), sirc: Sirc = Sirc::new()])]
+    fn tim3(cx: tim3::Context) {
+        let nec = cx.local.nec;
+        let rc5 = cx.local.rc5;
+        let rc6 = cx.local.rc6;
+        let sirc = cx.local.sirc;
+
+        cx.local.edges.on_interrupt(|i: Interval| {
+            let pulse = Pulse {
+                mark: !i.high,
+                us: i.us,
+            };
+
+            let mut decoders: [&mut dyn Decoder; 4] = [&mut *nec, &mut *rc5, &mut *rc6, &mut *sirc];
+
+            if let Some(cmd) = ir::decode(&mut decoders, pulse) {
+                rprintln!("{:?}", cmd);
+            }
+        });
+    }
+}
@@ -0,0 +1,145 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::capture::{EdgeCapture, Interval};
+use blue_pill_tests::ir::{self, Decoder, Frame, Nec, Pulse, Rc5};
+use blue_pill_tests::irtx::{self, Transmitter};
+use cortex_m as cm;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::pwm::{PwmChannel, C1};
+use hal::stm32;
+use hal::timer::{Tim4NoRemap, Timer};
+use panic_rtt_target as _;
+use rtic::app;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+use systick_monotonic::{fugit::TimerInstantU64, ExtU64, Systick};
+
+// send period
+const PERIOD_MS: u64 = 500;
+
+// receiver settings from ir-rc-test1
+const IDLE_US: u32 = 12_000;
+const FILTER: u8 = 0b1010;
+
+// NEC address and RC5 system
+const NEC_ADDRESS: u16 = 0x04;
+const RC5_ADDRESS: u8 = 0x05;
+
+type Carrier = PwmChannel<stm32::TIM4, C1>;
+
+#[app(device = stm32f1xx_hal::stm32, peripherals = true, dispatchers = [EXTI2])]
+mod app {
+    use super::*;
+
+    #[monotonic(binds = SysTick, default = true)]
+    type MonoTimer = Systick<1000>;
+
+    #[shared]
+    struct Shared {
+        tx: Transmitter<stm32::TIM2, Carrier>,
+    }
+
+    #[local]
+    struct Local {
+        edges: EdgeCapture<stm32::TIM3, gpioa::PA6<Input<PullUp>>>,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        rtt_init_print!();
+
+        let mut rcc = cx.device.RCC.constrain();
+        let mut afio = cx.device.AFIO.constrain(&mut rcc.apb2);
+        let mut flash = cx.device.FLASH.constrain();
+
+        let clocks = rcc
+            .cfgr
+            .use_hse(8.mhz())
+            .sysclk(72.mhz())
+            .pclk1(36.mhz())
+            .freeze(&mut flash.acr);
+
+        let mut gpioa = cx.device.GPIOA.split(&mut rcc.apb2);
+        let mut gpiob = cx.device.GPIOB.split(&mut rcc.apb2);
+
+        // IR LED driver on PB6: TIM4 CH1 carrier, TIM2 gates it
+
+        let pin = gpiob.pb6.into_alternate_push_pull(&mut gpiob.crl);
+        let carrier = Timer::tim4(cx.device.TIM4, &clocks, &mut rcc.apb1)
+            .pwm::<Tim4NoRemap, _, _, _>(pin, &mut afio.mapr, irtx::CARRIER_HZ.hz())
+            .split();
+
+        let tx = Transmitter::new(cx.device.TIM2, carrier, &clocks, &mut rcc.apb1);
+
+        // IR receiver output (active low) on TIM3 TI1: PA6, faces the IR LED for loopback
+
+        let irr = gpioa.pa6.into_pull_up_input(&mut gpioa.crl);
+        let edges = EdgeCapture::new(cx.device.TIM3, irr, &clocks, &mut rcc.apb1, FILTER, IDLE_US);
+
+        // SysTick based monotonic timer
+        let mono = Systick::new(cx.core.SYST, clocks.sysclk().0);
+
+        let next = monotonics::now() + PERIOD_MS.millis();
+        send::spawn_at(next, next).unwrap();
+
+        (Shared { tx }, Local { edges }, init::Monotonics(mono))
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cm::asm::wfi();
+        }
+    }
+
+    // carrier gating: highest priority to keep pulse timing
+    #[task(binds = TIM2, priority = 3, shared = [tx])]
+    fn tim2(mut cx: tim2::Context) {
+        cx.shared.tx.lock(|tx| tx.on_interrupt());
+    }
+
+    #[task(binds = TIM3, priority = 2, local = [edges, nec: Nec = Nec::new(), rc5: Rc5 = Rc5::new()])]
+    fn tim3(cx: tim3::Context) {
+        let nec = cx.local.nec;
+        let rc5 = cx.local.rc5;
+
+        cx.local.edges.on_interrupt(|i: Interval| {
+            let pulse = Pulse {
+                mark: !i.high,
+                us: i.us,
+            };
+
+            let mut decoders: [&mut dyn Decoder; 2] = [&mut *nec, &mut *rc5];
+
+            if let Some(cmd) = ir::decode(&mut decoders, pulse) {
+                rprintln!("rx: {:?}", cmd);
+            }
+        });
+    }
+
+    // alternate NEC frame, NEC repeat code and RC5 frame with flipping toggle bit
+    #[task(priority = 1, local = [count: u32 = 0], shared = [tx])]
+    fn send(mut cx: send::Context, instant: TimerInstantU64<1000>) {
+        let n = *cx.local.count;
+        *cx.local.count += 1;
+
+        let cmd = (n / 3) as u8;
+
+        let frame: Frame = match n % 3 {
+            0 => ir::encode_nec(NEC_ADDRESS, cmd),
+            1 => ir::encode_nec_repeat(),
+            _ => ir::encode_rc5(RC5_ADDRESS, cmd & 0x7f, n % 2 == 0),
+        };
+
+        rprintln!("tx: {} pulses {} us", frame.len(), frame.duration());
+
+        if cx.shared.tx.lock(|tx| tx.send(&frame)).is_err() {
+            rprintln!("tx: busy");
+        }
+
+        let next = instant + PERIOD_MS.millis();
+        send::spawn_at(next, next).unwrap();
+    }
+}
@@ -0,0 +1,203 @@
+#![deny(unsafe_code)]
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::nrf24::{self, Nrf24};
+use blue_pill_tests::telemetry::{self, Telemetry};
+use cortex_m as cm;
+use hal::adc;
+use hal::adc::Adc;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::spi::{Spi, Spi1NoRemap};
+use hal::stm32;
+use hal::timer::{CountDownTimer, Event, Timer};
+use panic_rtt_target as _;
+use rtic::app;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+
+// nRF24L01 telemetry node: send ADC samples to hub, see nrf24-test2
+
+type SpiType = Spi<
+    stm32::SPI1,
+    Spi1NoRemap,
+    (
+        gpioa::PA5<Alternate<PushPull>>,
+        gpioa::PA6<Input<Floating>>,
+        gpioa::PA7<Alternate<PushPull>>,
+    ),
+    u8,
+>;
+type NrfType = Nrf24<SpiType, gpioa::PA4<Output<PushPull>>, gpiob::PB0<Output<PushPull>>>;
+
+// node id: hub listens for nodes 1 and 2
+const NODE_ID: u8 = 1;
+const NODE_ADDR: nrf24::Address = [NODE_ID, 0xe4, 0xe3, 0xe2, 0xe1];
+
+#[app(device = stm32f1xx_hal::stm32, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        #[lock_free]
+        nrf: NrfType,
+        #[lock_free]
+        seq: u16,
+        #[lock_free]
+        busy: bool,
+    }
+
+    #[local]
+    struct Local {
+        irq: gpiob::PB1<Input<PullUp>>,
+        adc: Adc<stm32::ADC1>,
+        ch0: gpioa::PA0<Analog>,
+        ch1: gpioa::PA1<Analog>,
+        tmr: CountDownTimer<stm32::TIM3>,
+        led: gpioc::PC13<Output<PushPull>>,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        rtt_init_print!();
+
+        let mut flash = cx.device.FLASH.constrain();
+        let mut rcc = cx.device.RCC.constrain();
+        let clocks = rcc
+            .cfgr
+            .sysclk(8.mhz())
+            .pclk1(8.mhz())
+            .adcclk(2.mhz())
+            .freeze(&mut flash.acr);
+
+        let mut afio = cx.device.AFIO.constrain(&mut rcc.apb2);
+
+        let mut gpioa = cx.device.GPIOA.split(&mut rcc.apb2);
+        let mut gpiob = cx.device.GPIOB.split(&mut rcc.apb2);
+        let mut gpioc = cx.device.GPIOC.split(&mut rcc.apb2);
+
+        // configure PC13 pin as LED
+        let led = gpioc.pc13.into_push_pull_output(&mut gpioc.crh);
+
+        // configure ADC inputs
+        let adc = Adc::adc1(cx.device.ADC1, &mut rcc.apb2, clocks);
+        let ch0 = gpioa.pa0.into_analog(&mut gpioa.crl);
+        let ch1 = gpioa.pa1.into_analog(&mut gpioa.crl);
+
+        // configure PB0 pin as NRF24 CE and PA4 pin as NRF24 CSN
+        let ce = gpiob.pb0.into_push_pull_output(&mut gpiob.crl);
+        let csn = gpioa.pa4.into_push_pull_output(&mut gpioa.crl);
+
+        // configure PB1 pin as NRF24 IRQ: active low open drain
+        let mut irq = gpiob.pb1.into_pull_up_input(&mut gpiob.crl);
+        irq.make_interrupt_source(&mut afio);
+        irq.trigger_on_edge(&cx.device.EXTI, Edge::FALLING);
+        irq.enable_interrupt(&cx.device.EXTI);
+
+        // configure PA5/PA6/PA7 as SPI1 pins
+        let sck = gpioa.pa5.into_alternate_push_pull(&mut gpioa.crl);
+        let miso = gpioa.pa6;
+        let mosi = gpioa.pa7.into_alternate_push_pull(&mut gpioa.crl);
+
+        let spi = Spi::spi1(
+            cx.device.SPI1,
+            (sck, miso, mosi),
+            &mut afio.mapr,
+            nrf24::MODE,
+            2.mhz(),
+            clocks,
+            &mut rcc.apb2,
+        );
+
+        // nRF24L01 Tx setup: auto-ack on pipe 0, up to 15 retransmits
+        let mut nrf = Nrf24::new(spi, csn, ce, &nrf24::Config::default()).unwrap();
+        nrf.set_tx_addr(&NODE_ADDR).unwrap();
+        nrf.open_rx_pipe(0, &NODE_ADDR).unwrap();
+        nrf.set_interrupts(false, true, true).unwrap();
+        nrf.standby_tx().unwrap();
+
+        // configure and start TIM3 periodic timer
+        let mut tmr = Timer::tim3(cx.device.TIM3, &clocks, &mut rcc.apb1).start_count_down(1.hz());
+        tmr.listen(Event::Update);
+
+        (
+            Shared {
+                nrf,
+                seq: 0,
+                busy: false,
+            },
+            Local {
+                irq,
+                adc,
+                ch0,
+                ch1,
+                tmr,
+                led,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cm::asm::wfi();
+        }
+    }
+
+    #[task(binds = TIM3, local = [tmr, adc, ch0, ch1], shared = [nrf, seq, busy])]
+    fn tim3(cx: tim3::Context) {
+        cx.local.tmr.clear_update_interrupt_flag();
+
+        if *cx.shared.busy {
+            rprintln!("TX: previous packet is still in flight");
+            return;
+        }
+
+        let v0: u16 = cx.local.adc.read(cx.local.ch0).unwrap();
+        let v1: u16 = cx.local.adc.read(cx.local.ch1).unwrap();
+        let temp = cx.local.adc.read_temp() as u16;
+
+        let pkt = Telemetry::new(NODE_ID, *cx.shared.seq, &[v0, v1, temp]);
+        let mut buf = [0u8; telemetry::MAX_PACKET];
+        let len = pkt.encode(&mut buf);
+
+        match cx.shared.nrf.start_send(&buf[..len], true) {
+            Ok(_) => *cx.shared.busy = true,
+            Err(e) => rprintln!("TX: failed to queue packet: {:?}", e),
+        }
+
+        *cx.shared.seq = cx.shared.seq.wrapping_add(1);
+    }
+
+    #[task(binds = EXTI1, local = [irq, led], shared = [nrf, busy, seq])]
+    fn exti1(cx: exti1::Context) {
+        cx.local.irq.clear_interrupt_pending_bit();
+
+        let nrf = cx.shared.nrf;
+        let status = match nrf.status() {
+            Ok(status) => status,
+            Err(e) => {
+                rprintln!("TX: failed to read status: {:?}", e);
+                return;
+            }
+        };
+
+        if status.tx_done || status.tx_failed {
+            nrf.finish_send(&status).ok();
+            *cx.shared.busy = false;
+
+            let (lost, retries) = nrf.observe_tx().unwrap_or((0, 0));
+            let seq = cx.shared.seq.wrapping_sub(1);
+
+            if status.tx_done {
+                rprintln!("TX: seq {} done, retries {}", seq, retries);
+                cx.local.led.toggle().unwrap();
+            } else {
+                rprintln!("TX: seq {} failed, lost {}", seq, lost);
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+#![deny(unsafe_code)]
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::nrf24::{self, Nrf24};
+use blue_pill_tests::telemetry::{Link, Telemetry};
+use cortex_m as cm;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::spi::{Spi, Spi1NoRemap};
+use hal::stm32;
+use panic_rtt_target as _;
+use rtic::app;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+
+// nRF24L01 telemetry hub: receive ADC samples from nodes, see nrf24-test1
+
+type SpiType = Spi<
+    stm32::SPI1,
+    Spi1NoRemap,
+    (
+        gpioa::PA5<Alternate<PushPull>>,
+        gpioa::PA6<Input<Floating>>,
+        gpioa::PA7<Alternate<PushPull>>,
+    ),
+    u8,
+>;
+type NrfType = Nrf24<SpiType, gpioa::PA4<Output<PushPull>>, gpiob::PB0<Output<PushPull>>>;
+
+// node N transmits to address [N, 0xe4, 0xe3, 0xe2, 0xe1]: listen
+// for node 1 on pipe 1 and node 2 on pipe 2
+const NODES: usize = 2;
+const NODE1_ADDR: nrf24::Address = [1, 0xe4, 0xe3, 0xe2, 0xe1];
+const NODE2_ADDR: nrf24::Address = [2, 0xe4, 0xe3, 0xe2, 0xe1];
+
+#[app(device = stm32f1xx_hal::stm32, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {}
+
+    #[local]
+    struct Local {
+        nrf: NrfType,
+        irq: gpiob::PB1<Input<PullUp>>,
+        led: gpioc::PC13<Output<PushPull>>,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        rtt_init_print!();
+
+        let mut flash = cx.device.FLASH.constrain();
+        let mut rcc = cx.device.RCC.constrain();
+        let clocks = rcc
+            .cfgr
+            .sysclk(8.mhz())
+            .pclk1(8.mhz())
+            .freeze(&mut flash.acr);
+
+        let mut afio = cx.device.AFIO.constrain(&mut rcc.apb2);
+
+        let mut gpioa = cx.device.GPIOA.split(&mut rcc.apb2);
+        let mut gpiob = cx.device.GPIOB.split(&mut rcc.apb2);
+        let mut gpioc = cx.device.GPIOC.split(&mut rcc.apb2);
+
+        // configure PC13 pin as LED
+        let led = gpioc.pc13.into_push_pull_output(&mut gpioc.crh);
+
+        // configure PB0 pin as NRF24 CE and PA4 pin as NRF24 CSN
+        let ce = gpiob.pb0.into_push_pull_output(&mut gpiob.crl);
+        let csn = gpioa.pa4.into_push_pull_output(&mut gpioa.crl);
+
+        // configure PB1 pin as NRF24 IRQ: active low open drain
+        let mut irq = gpiob.pb1.into_pull_up_input(&mut gpiob.crl);
+        irq.make_interrupt_source(&mut afio);
+        irq.trigger_on_edge(&cx.device.EXTI, Edge::FALLING);
+        irq.enable_interrupt(&cx.device.EXTI);
+
+        // configure PA5/PA6/PA7 as SPI1 pins
+        let sck = gpioa.pa5.into_alternate_push_pull(&mut gpioa.crl);
+        let miso = gpioa.pa6;
+        let mosi = gpioa.pa7.into_alternate_push_pull(&mut gpioa.crl);
+
+        let spi = Spi::spi1(
+            cx.device.SPI1,
+            (sck, miso, mosi),
+            &mut afio.mapr,
+            nrf24::MODE,
+            2.mhz(),
+            clocks,
+            &mut rcc.apb2,
+        );
+
+        // nRF24L01 Rx setup: one pipe per node
+        let mut nrf = Nrf24::new(spi, csn, ce, &nrf24::Config::default()).unwrap();
+        nrf.open_rx_pipe(1, &NODE1_ADDR).unwrap();
+        nrf.open_rx_pipe(2, &NODE2_ADDR).unwrap();
+        nrf.set_interrupts(true, false, false).unwrap();
+        nrf.listen().unwrap();
+
+        rprintln!("NRF Rx is ready to go...");
+
+        (Shared {}, Local { nrf, irq, led }, init::Monotonics())
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cm::asm::wfi();
+        }
+    }
+
+    #[task(binds = EXTI1, local = [irq, nrf, led, links: [Link; NODES] = [Link::new(); NODES]])]
+    fn exti1(cx: exti1::Context) {
+        cx.local.irq.clear_interrupt_pending_bit();
+
+        let nrf = cx.local.nrf;
+
+        if !nrf.status().map(|s| s.rx_ready).unwrap_or(false) {
+            return;
+        }
+
+        let mut buf = [0u8; nrf24::MAX_PAYLOAD];
+
+        // drain RX FIFO: up to 3 payloads may be queued
+        while let Ok(Some((pipe, len))) = nrf.read_payload(&mut buf) {
+            let pkt = match Telemetry::decode(&buf[..len]) {
+                Ok(pkt) => pkt,
+                Err(e) => {
+                    rprintln!("RX: pipe {}: bad packet: {:?}", pipe, e);
+                    continue;
+                }
+            };
+
+            let link = match pkt
+                .node
+                .checked_sub(1)
+                .and_then(|n| cx.local.links.get_mut(n as usize))
+            {
+                Some(link) => link,
+                _ => {
+                    rprintln!("RX: pipe {}: unknown node {}", pipe, pkt.node);
+                    continue;
+                }
+            };
+
+            if link.update(pkt.seq) {
+                rprintln!(
+                    "RX: node {} seq {} values {:?} lost {}",
+                    pkt.node,
+                    pkt.seq,
+                    pkt.values(),
+                    link.lost
+                );
+                cx.local.led.toggle().unwrap();
+            }
+        }
+    }
+}
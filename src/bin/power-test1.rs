@@ -0,0 +1,108 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::power::{self, Power};
+use blue_pill_tests::watchdog;
+use cortex_m as cm;
+use cortex_m_rt::entry;
+use hal::adc::Adc;
+use hal::prelude::*;
+use hal::rtc::Rtc;
+use hal::stm32;
+use hal::timer::Timer;
+use lm75::{Lm75, SlaveAddr};
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+
+// RTC counter resolution: LSE 32768Hz / 32
+const RTC_HZ: u32 = 1024;
+
+// sensor node: sample every 5 seconds, stay in Stop in between
+const PERIOD_S: u32 = 5;
+
+// after this number of samples enter Standby: RTC alarm starts from reset
+const STANDBY_AFTER: u32 = 12;
+
+// keep debugger and RTT alive in low power modes, costs extra current
+const DEBUG: bool = true;
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let cp = cm::Peripherals::take().unwrap();
+    let dp = stm32::Peripherals::take().unwrap();
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+
+    // RTC in backup domain keeps counting in Stop and Standby
+    let mut pwr = dp.PWR;
+    let mut backup_domain = rcc.bkp.constrain(dp.BKP, &mut rcc.apb1, &mut pwr);
+    let mut rtc = Rtc::rtc(dp.RTC, &mut backup_domain);
+    rtc.select_frequency(RTC_HZ.hz());
+
+    let mut power = Power::new(pwr, cp.SCB, &rtc);
+
+    if power.woke_from_standby() {
+        rprintln!("woke up from Standby");
+    } else {
+        rprintln!("reset cause: {:?}", watchdog::reset_cause());
+    }
+
+    let clocks = rcc
+        .cfgr
+        .use_hse(8.mhz())
+        .sysclk(72.mhz())
+        .pclk1(36.mhz())
+        .adcclk(12.mhz())
+        .freeze(&mut flash.acr);
+
+    power::debug_low_power(&dp.DBGMCU, DEBUG);
+    power::listen_rtc_alarm(&dp.EXTI);
+
+    // internal temperature sensor
+    let mut adc = Adc::adc1(dp.ADC1, &mut rcc.apb2, clocks);
+
+    // LM75 on bitbang I2C: PA1 SCL, PA2 SDA
+    let mut gpioa = dp.GPIOA.split(&mut rcc.apb2);
+    let tmr = Timer::tim2(dp.TIM2, &clocks, &mut rcc.apb1).start_count_down(200.khz());
+    let scl = gpioa.pa1.into_open_drain_output(&mut gpioa.crl);
+    let sda = gpioa.pa2.into_open_drain_output(&mut gpioa.crl);
+    let i2c = bitbang_hal::i2c::I2cBB::new(scl, sda, tmr);
+    let mut sensor = Lm75::new(i2c, SlaveAddr::default());
+
+    let mut cycle: u32 = 0;
+
+    loop {
+        let chip = adc.read_temp();
+
+        match sensor.read_temperature() {
+            Ok(t) => rprintln!("{}: LM75 {} C, chip {} C", cycle, t, chip),
+            Err(_) => rprintln!("{}: LM75 error, chip {} C", cycle, chip),
+        }
+
+        let stats = power.stats();
+        rprintln!(
+            "run {} ms, stop {} ms, low power {}.{}%",
+            stats.run * 1000 / RTC_HZ,
+            stats.stop * 1000 / RTC_HZ,
+            stats.low_power_permille() / 10,
+            stats.low_power_permille() % 10
+        );
+
+        cycle += 1;
+
+        // alarm event is generated on rising edge of the flag
+        rtc.clear_alarm_flag();
+        power::clear_rtc_alarm(&dp.EXTI);
+        rtc.set_alarm(rtc.current_time() + PERIOD_S * RTC_HZ);
+
+        if cycle == STANDBY_AFTER {
+            rprintln!("entering Standby");
+            power.standby();
+        }
+
+        power.stop(&rtc);
+    }
+}
@@ -0,0 +1,161 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::fade::{self, Fader, Ramp, SoftPwm};
+use cortex_m as cm;
+use embedded_hal::PwmPin;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::pwm::{PwmChannel, C1, C2, C3, C4};
+use hal::stm32;
+use hal::timer::{CountDownTimer, Event, Tim4NoRemap, Timer};
+use panic_rtt_target as _;
+use rtic::app;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+
+// fader update period
+const TICK_MS: u32 = 10;
+
+// software PWM: 100 steps at 10kHz give 100Hz PWM frequency
+const SOFT_PWM_STEPS: u16 = 100;
+const SOFT_PWM_RATE: u32 = 10_000;
+
+#[app(device = stm32f1xx_hal::stm32, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        led: SoftPwm<gpioc::PC13<Output<PushPull>>>,
+    }
+
+    #[local]
+    struct Local {
+        tick: CountDownTimer<stm32::TIM3>,
+        fast: CountDownTimer<stm32::TIM2>,
+        ch1: PwmChannel<stm32::TIM4, C1>,
+        ch2: PwmChannel<stm32::TIM4, C2>,
+        ch3: PwmChannel<stm32::TIM4, C3>,
+        ch4: PwmChannel<stm32::TIM4, C4>,
+        faders: [Fader; 5],
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        rtt_init_print!();
+
+        let mut rcc = cx.device.RCC.constrain();
+        let mut afio = cx.device.AFIO.constrain(&mut rcc.apb2);
+        let mut flash = cx.device.FLASH.constrain();
+
+        let clocks = rcc
+            .cfgr
+            .use_hse(8.mhz())
+            .sysclk(48.mhz())
+            .pclk1(24.mhz())
+            .freeze(&mut flash.acr);
+
+        let mut gpiob = cx.device.GPIOB.split(&mut rcc.apb2);
+        let mut gpioc = cx.device.GPIOC.split(&mut rcc.apb2);
+
+        // configure TIM4 hardware PWM on PB6..PB9
+
+        let p1 = gpiob.pb6.into_alternate_push_pull(&mut gpiob.crl);
+        let p2 = gpiob.pb7.into_alternate_push_pull(&mut gpiob.crl);
+        let p3 = gpiob.pb8.into_alternate_push_pull(&mut gpiob.crh);
+        let p4 = gpiob.pb9.into_alternate_push_pull(&mut gpiob.crh);
+
+        let pwm = Timer::tim4(cx.device.TIM4, &clocks, &mut rcc.apb1).pwm::<Tim4NoRemap, _, _, _>(
+            (p1, p2, p3, p4),
+            &mut afio.mapr,
+            1.khz(),
+        );
+
+        let (mut ch1, mut ch2, mut ch3, mut ch4) = pwm.split();
+
+        ch1.enable();
+        ch2.enable();
+        ch3.enable();
+        ch4.enable();
+
+        // configure software PWM on active low PC13 LED
+
+        let pc13 = gpioc.pc13.into_push_pull_output(&mut gpioc.crh);
+        let led = SoftPwm::new(pc13, SOFT_PWM_STEPS, true);
+
+        // ch1 breathing, ch2 heartbeat, ch3 linear ramps, ch4 error code 3, LED breathing
+
+        let mut faders = [Fader::new(Ramp::Gamma); 5];
+        faders[0].breathe(3000);
+        faders[1].pattern(fade::HEARTBEAT);
+        faders[2].ramp = Ramp::Linear;
+        faders[2].fade_to(fade::MAX_LEVEL, 2000);
+        faders[3].blink_code(3);
+        faders[4].breathe(2000);
+
+        // configure timers: TIM2 for software PWM, TIM3 for fader updates
+
+        let mut fast = Timer::tim2(cx.device.TIM2, &clocks, &mut rcc.apb1)
+            .start_count_down(SOFT_PWM_RATE.hz());
+        fast.listen(Event::Update);
+
+        let mut tick = Timer::tim3(cx.device.TIM3, &clocks, &mut rcc.apb1)
+            .start_count_down((1000 / TICK_MS).hz());
+        tick.listen(Event::Update);
+
+        rprintln!("PWM max duty {}", ch1.get_max_duty());
+
+        (
+            Shared { led },
+            Local {
+                tick,
+                fast,
+                ch1,
+                ch2,
+                ch3,
+                ch4,
+                faders,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cm::asm::wfi();
+        }
+    }
+
+    #[task(binds = TIM2, priority = 2, local = [fast], shared = [led])]
+    fn tim2(mut cx: tim2::Context) {
+        cx.local.fast.clear_update_interrupt_flag();
+        cx.shared.led.lock(|led| led.tick());
+    }
+
+    #[task(binds = TIM3, priority = 1, local = [tick, ch1, ch2, ch3, ch4, faders], shared = [led])]
+    fn tim3(mut cx: tim3::Context) {
+        cx.local.tick.clear_update_interrupt_flag();
+
+        let faders = cx.local.faders;
+
+        for f in faders.iter_mut() {
+            f.tick(TICK_MS);
+        }
+
+        // linear ramp goes back and forth
+        if !faders[2].active() {
+            let target = fade::MAX_LEVEL - faders[2].level();
+            faders[2].fade_to(target, 2000);
+        }
+
+        faders[0].apply(cx.local.ch1);
+        faders[1].apply(cx.local.ch2);
+        faders[2].apply(cx.local.ch3);
+        faders[3].apply(cx.local.ch4);
+
+        let led = &faders[4];
+        cx.shared.led.lock(|pwm| led.apply(pwm));
+    }
+}
@@ -0,0 +1,101 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::bridge::{Bridge, Config, Leg, LegMode, Pins};
+use cortex_m as cm;
+use cortex_m_rt::entry;
+use hal::delay::Delay;
+use hal::prelude::*;
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let dp = hal::stm32::Peripherals::take().unwrap();
+    let cp = cm::Peripherals::take().unwrap();
+
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+
+    let clocks = rcc
+        .cfgr
+        .use_hse(8.mhz())
+        .sysclk(72.mhz())
+        .pclk1(36.mhz())
+        .freeze(&mut flash.acr);
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.apb2);
+    let mut gpiob = dp.GPIOB.split(&mut rcc.apb2);
+
+    // break input is active low: pulled up, emergency switch to ground
+    let pins = Pins {
+        ch1: gpioa.pa8.into_alternate_push_pull(&mut gpioa.crh),
+        ch2: gpioa.pa9.into_alternate_push_pull(&mut gpioa.crh),
+        ch3: gpioa.pa10.into_alternate_push_pull(&mut gpioa.crh),
+        ch1n: gpiob.pb13.into_alternate_push_pull(&mut gpiob.crh),
+        ch2n: gpiob.pb14.into_alternate_push_pull(&mut gpiob.crh),
+        ch3n: gpiob.pb15.into_alternate_push_pull(&mut gpiob.crh),
+        bkin: gpiob.pb12.into_pull_up_input(&mut gpiob.crh),
+    };
+
+    let config = Config {
+        freq: 20.khz().into(),
+        dead_time_ns: 500,
+        center_aligned: true,
+        break_high: false,
+    };
+
+    let mut bridge = Bridge::tim1(dp.TIM1, pins, &clocks, &mut rcc.apb2, &config).unwrap();
+    let max = bridge.max_duty();
+
+    rprintln!("TIM1 bridge: max duty {}", max);
+
+    // legs 1 and 2 switch with opposite duty, leg 3 holds low side on
+    bridge.set_duty(Leg::Ch1, 0);
+    bridge.set_duty(Leg::Ch2, max);
+    bridge.set_mode(Leg::Ch1, LegMode::Pwm);
+    bridge.set_mode(Leg::Ch2, LegMode::Pwm);
+    bridge.set_mode(Leg::Ch3, LegMode::Low);
+
+    if !bridge.enable_outputs() {
+        rprintln!("break input active: outputs disabled");
+    }
+
+    let mut duty: u16 = 0;
+    let step = max / 50;
+
+    loop {
+        if bridge.break_detected() {
+            rprintln!("break: outputs shut down");
+            bridge.set_mode(Leg::Ch1, LegMode::Off);
+            bridge.set_mode(Leg::Ch2, LegMode::Off);
+            bridge.set_mode(Leg::Ch3, LegMode::Off);
+
+            // resume after break input is released
+            while !bridge.enable_outputs() {
+                delay.delay_ms(100u16);
+            }
+
+            bridge.set_mode(Leg::Ch1, LegMode::Pwm);
+            bridge.set_mode(Leg::Ch2, LegMode::Pwm);
+            bridge.set_mode(Leg::Ch3, LegMode::Low);
+            rprintln!("break released: outputs enabled");
+        }
+
+        duty = if duty >= max {
+            0
+        } else {
+            (duty + step).min(max)
+        };
+
+        bridge.set_duty(Leg::Ch1, duty);
+        bridge.set_duty(Leg::Ch2, max - duty);
+
+        delay.delay_ms(20u16);
+    }
+}
@@ -26,9 +26,14 @@ type SpiNssType = gpioa::PA15<Output<PushPull>>;
 type SpiType = Spi<stm32::SPI1, Spi1Remap, (SpiSckType, SpiMisoType, SpiMosiType), u8>;
 
 #[app(device = stm32f1xx_hal::stm32, peripherals = true)]
-const APP: () = {
-    struct Resources {
-        // late resources
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {}
+
+    #[local]
+    struct Local {
         stream1: UpChannel,
         stream2: UpChannel,
         tmr: hal::timer::CountDownTimer<stm32::TIM3>,
@@ -38,7 +43,7 @@ const APP: () = {
     }
 
     #[init]
-    fn init(cx: init::Context) -> init::LateResources {
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
         let channels = rtt_init! {
             up: {
                 0: {
@@ -111,14 +116,18 @@ const APP: () = {
 
         let nfc = Mfrc522::new(spi, OldOutputPin::from(nss)).unwrap();
 
-        init::LateResources {
-            stream1,
-            stream2,
-            tmr,
-            led,
-            irq,
-            nfc,
-        }
+        (
+            Shared {},
+            Local {
+                stream1,
+                stream2,
+                tmr,
+                led,
+                irq,
+                nfc,
+            },
+            init::Monotonics(),
+        )
     }
 
     #[idle]
@@ -128,28 +137,28 @@ const APP: () = {
         }
     }
 
-    #[task(binds = EXTI1, resources = [irq, nfc, stream1])]
+    #[task(binds = EXTI1, local = [irq, nfc, stream1])]
     fn exti(cx: exti::Context) {
-        if cx.resources.irq.check_interrupt() {
-            if let Ok(atqa) = cx.resources.nfc.reqa() {
-                if let Ok(uid) = cx.resources.nfc.select(&atqa) {
-                    writeln!(cx.resources.stream1, "NFC: * {:?}", uid).ok();
+        if cx.local.irq.check_interrupt() {
+            if let Ok(atqa) = cx.local.nfc.reqa() {
+                if let Ok(uid) = cx.local.nfc.select(&atqa) {
+                    writeln!(cx.local.stream1, "NFC: * {:?}", uid).ok();
                 } else {
-                    writeln!(cx.resources.stream1, "NFC: failed to read UID").ok();
+                    writeln!(cx.local.stream1, "NFC: failed to read UID").ok();
                 }
             } else {
-                writeln!(cx.resources.stream1, "NFC: empty IRQ").ok();
+                writeln!(cx.local.stream1, "NFC: empty IRQ").ok();
             }
-            cx.resources.irq.clear_interrupt_pending_bit();
+            cx.local.irq.clear_interrupt_pending_bit();
         } else {
-            writeln!(cx.resources.stream1, "NFC: unexpected IRQ").ok();
+            writeln!(cx.local.stream1, "NFC: unexpected IRQ").ok();
         }
     }
 
-    #[task(binds = TIM3, resources = [led, tmr, stream2])]
+    #[task(binds = TIM3, local = [led, tmr, stream2])]
     fn tim3(cx: tim3::Context) {
-        writeln!(cx.resources.stream2, "TIM3 blink").ok();
-        cx.resources.led.toggle().unwrap();
-        cx.resources.tmr.clear_update_interrupt_flag();
+        writeln!(cx.local.stream2, "TIM3 blink").ok();
+        cx.local.led.toggle().unwrap();
+        cx.local.tmr.clear_update_interrupt_flag();
     }
-};
+}
@@ -0,0 +1,115 @@
+#![deny(unsafe_code)]
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::mifare;
+use blue_pill_tests::mifare::{Classic, KeyType, DEFAULT_KEY};
+use blue_pill_tests::rc522::Rc522;
+use cortex_m_rt::entry;
+use embedded_hal::digital::v2::OutputPin;
+use hal::prelude::*;
+use hal::spi::Spi;
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+
+// MIFARE Classic test: use sector 1 for value block experiments
+const SECTOR: u8 = 1;
+
+#[entry]
+fn main() -> ! {
+    let dp = hal::stm32::Peripherals::take().unwrap();
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut afio = dp.AFIO.constrain(&mut rcc.apb2);
+
+    rtt_init_print!();
+
+    let clocks = rcc
+        .cfgr
+        .use_hse(8.mhz())
+        .sysclk(16.mhz())
+        .pclk1(4.mhz())
+        .freeze(&mut flash.acr);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.apb2);
+    let mut gpiob = dp.GPIOB.split(&mut rcc.apb2);
+    let mut gpioc = dp.GPIOC.split(&mut rcc.apb2);
+
+    let mut led = gpioc.pc13.into_push_pull_output(&mut gpioc.crh);
+    led.set_high().unwrap();
+
+    let (pa15, pb3, pb4) = afio.mapr.disable_jtag(gpioa.pa15, gpiob.pb3, gpiob.pb4);
+
+    let sck = pb3.into_alternate_push_pull(&mut gpiob.crl);
+    let miso = pb4;
+    let mosi = gpiob.pb5.into_alternate_push_pull(&mut gpiob.crl);
+
+    let spi = Spi::spi1(
+        dp.SPI1,
+        (sck, miso, mosi),
+        &mut afio.mapr,
+        mfrc522::MODE,
+        1.mhz(),
+        clocks,
+        &mut rcc.apb2,
+    );
+
+    let nss = pa15.into_push_pull_output(&mut gpioa.crh);
+    let mut rc = Rc522::new(spi, nss).unwrap();
+
+    rprintln!("RC522 version: {:#x}", rc.version().unwrap());
+
+    loop {
+        let uid = match rc.reqa().and_then(|atqa| rc.select(&atqa)) {
+            Ok(uid) => uid,
+            Err(_) => continue,
+        };
+
+        rprintln!("* {:x?} SAK {:#x}", uid.as_bytes(), uid.sak());
+
+        let mut card = match Classic::new(&mut rc, uid) {
+            Some(card) => card,
+            None => {
+                rprintln!("not a MIFARE Classic card");
+                continue;
+            }
+        };
+
+        let trailer = mifare::trailer_block(SECTOR);
+        let block = mifare::first_block(SECTOR);
+
+        if let Err(e) = card.authenticate(KeyType::A, trailer, &DEFAULT_KEY) {
+            rprintln!("auth failed: {:?}", e);
+            card.halt().ok();
+            continue;
+        }
+
+        match card.read_trailer(SECTOR) {
+            Ok(t) => rprintln!("sector {} access: {:?}", SECTOR, t.access),
+            Err(e) => rprintln!("failed to read trailer: {:?}", e),
+        }
+
+        let value = match card.read_value(block) {
+            Ok(v) => v,
+            Err(_) => {
+                rprintln!("block {} is not a value block: format it", block);
+                card.write_value(block, 0).ok();
+                0
+            }
+        };
+
+        rprintln!("block {} value {}", block, value);
+
+        if card
+            .increment(block, 1)
+            .and_then(|_| card.transfer(block))
+            .is_ok()
+        {
+            rprintln!("block {} value {:?}", block, card.read_value(block));
+        }
+
+        led.toggle().unwrap();
+        card.halt().ok();
+    }
+}
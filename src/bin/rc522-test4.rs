@@ -0,0 +1,144 @@
+#![deny(unsafe_code)]
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::isodep::{self, IsoDep};
+use blue_pill_tests::ndef::{MessageWriter, Records};
+use blue_pill_tests::rc522::Rc522;
+use blue_pill_tests::ultralight::Ultralight;
+use cortex_m_rt::entry;
+use embedded_hal::digital::v2::OutputPin;
+use hal::prelude::*;
+use hal::spi::Spi;
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+
+// SELECT PPSE: lists payment applications on EMV cards
+const SELECT_PPSE: &[u8] = b"\x00\xa4\x04\x00\x0e2PAY.SYS.DDF01\x00";
+
+// write sample NDEF message to blank NTAG tags
+const FORMAT_BLANK: bool = false;
+
+#[entry]
+fn main() -> ! {
+    let dp = hal::stm32::Peripherals::take().unwrap();
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut afio = dp.AFIO.constrain(&mut rcc.apb2);
+
+    rtt_init_print!();
+
+    let clocks = rcc
+        .cfgr
+        .sysclk(8.mhz())
+        .pclk1(8.mhz())
+        .freeze(&mut flash.acr);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.apb2);
+    let mut gpiob = dp.GPIOB.split(&mut rcc.apb2);
+    let mut gpioc = dp.GPIOC.split(&mut rcc.apb2);
+
+    let mut led = gpioc.pc13.into_push_pull_output(&mut gpioc.crh);
+    led.set_high().unwrap();
+
+    // configure SPI and connected NFC RC522 board
+
+    let (pa15, pb3, pb4) = afio.mapr.disable_jtag(gpioa.pa15, gpiob.pb3, gpiob.pb4);
+
+    let sck = pb3.into_alternate_push_pull(&mut gpiob.crl);
+    let mosi = gpiob.pb5.into_alternate_push_pull(&mut gpiob.crl);
+    let miso = pb4;
+    let nss = pa15.into_push_pull_output(&mut gpioa.crh);
+
+    let spi = Spi::spi1(
+        dp.SPI1,
+        (sck, miso, mosi),
+        &mut afio.mapr,
+        mfrc522::MODE,
+        1.mhz(),
+        clocks,
+        &mut rcc.apb2,
+    );
+
+    let mut rc = Rc522::new(spi, nss).unwrap();
+
+    loop {
+        let uid = match rc.reqa().and_then(|atqa| rc.select(&atqa)) {
+            Ok(uid) => uid,
+            Err(_) => continue,
+        };
+
+        rprintln!("* {:x?} SAK {:#x}", uid.as_bytes(), uid.sak());
+        led.toggle().unwrap();
+
+        if IsoDep::supported(uid.sak()) {
+            let mut card = match IsoDep::activate(&mut rc) {
+                Ok(card) => card,
+                Err(e) => {
+                    rprintln!("RATS failed: {:?}", e);
+                    continue;
+                }
+            };
+
+            rprintln!("ATS: {:?}", card.ats());
+
+            let mut resp = [0u8; 256];
+            match card.exchange(SELECT_PPSE, &mut resp) {
+                Ok(n) => rprintln!(
+                    "PPSE: SW {:x?} {:x?}",
+                    isodep::status_word(&resp[..n]),
+                    &resp[..n]
+                ),
+                Err(e) => rprintln!("APDU failed: {:?}", e),
+            }
+
+            card.deselect().ok();
+        } else if uid.sak() == 0x00 {
+            let mut tag = match Ultralight::new(&mut rc) {
+                Ok(tag) => tag,
+                Err(e) => {
+                    rprintln!("GET_VERSION failed: {:?}", e);
+                    continue;
+                }
+            };
+
+            rprintln!("tag: {:?} CC: {:?}", tag.tag_type(), tag.capability());
+
+            let mut mem = [0u8; 888];
+            match tag.read_ndef(&mut mem) {
+                Ok(msg) => {
+                    for record in Records::new(msg) {
+                        match record {
+                            Ok(r) => {
+                                if let Some((prefix, rest)) = r.uri() {
+                                    rprintln!("URI: {}{}", prefix, rest);
+                                } else if let Some(t) = r.text() {
+                                    rprintln!("text [{}]: {}", t.lang, t.text);
+                                } else {
+                                    rprintln!("record: {:?}", r);
+                                }
+                            }
+                            Err(e) => rprintln!("bad record: {:?}", e),
+                        }
+                    }
+                }
+                Err(e) => {
+                    rprintln!("no NDEF message: {:?}", e);
+
+                    if FORMAT_BLANK {
+                        let mut buf = [0u8; 64];
+                        let mut msg = MessageWriter::new(&mut buf);
+                        msg.push_uri("https://github.com/geomatsi").ok();
+                        msg.push_text("en", "blue pill").ok();
+                        rprintln!("write NDEF: {:?}", tag.write_ndef(msg.finish()));
+                    }
+                }
+            }
+
+            tag.halt().ok();
+        } else {
+            rc.hlta().ok();
+        }
+    }
+}
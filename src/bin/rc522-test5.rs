@@ -0,0 +1,211 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::rc522::{self, Rc522, Uid};
+use core::fmt::Write;
+use cortex_m as cm;
+use embedded_hal::digital::v2::OutputPin;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::spi::Spi;
+use hal::spi::Spi1Remap;
+use hal::stm32;
+use hal::timer::Event;
+use hal::timer::Timer;
+use panic_rtt_target as _;
+use rtic::app;
+use rtt_target::{rtt_init, UpChannel};
+use stm32f1xx_hal as hal;
+
+type SpiSckType = gpiob::PB3<Alternate<PushPull>>;
+type SpiMisoType = gpiob::PB4<Input<Floating>>;
+type SpiMosiType = gpiob::PB5<Alternate<PushPull>>;
+type SpiNssType = gpioa::PA15<Output<PushPull>>;
+type SpiType = Spi<stm32::SPI1, Spi1Remap, (SpiSckType, SpiMisoType, SpiMosiType), u8>;
+
+// card is considered removed after this number of missed polls
+const MISSED_POLLS: u8 = 2;
+
+// RC522 interrupt sources for transceive completion
+const COM_IRQS: u8 = rc522::RX_IRQ | rc522::ERR_IRQ | rc522::TIMER_IRQ;
+
+#[app(device = stm32f1xx_hal::stm32, peripherals = true, dispatchers = [EXTI2])]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        #[lock_free]
+        stream1: UpChannel,
+        #[lock_free]
+        led: gpioc::PC13<Output<PushPull>>,
+        #[lock_free]
+        nfc: Rc522<SpiType, SpiNssType>,
+    }
+
+    #[local]
+    struct Local {
+        stream2: UpChannel,
+        tmr: hal::timer::CountDownTimer<stm32::TIM3>,
+        irq: gpiob::PB1<Input<Floating>>,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let channels = rtt_init! {
+            up: {
+                0: {
+                    size: 512
+                    name: "stream1"
+                }
+                1: {
+                    size: 512
+                    name: "stream2"
+                }
+            }
+        };
+
+        let stream1 = channels.up.0;
+        let stream2 = channels.up.1;
+
+        let mut rcc = cx.device.RCC.constrain();
+        let mut afio = cx.device.AFIO.constrain(&mut rcc.apb2);
+        let mut flash = cx.device.FLASH.constrain();
+
+        let clocks = rcc
+            .cfgr
+            .sysclk(8.mhz())
+            .pclk1(8.mhz())
+            .freeze(&mut flash.acr);
+
+        let mut gpioa = cx.device.GPIOA.split(&mut rcc.apb2);
+        let mut gpiob = cx.device.GPIOB.split(&mut rcc.apb2);
+        let mut gpioc = cx.device.GPIOC.split(&mut rcc.apb2);
+
+        // configure PC13 pin to show card presence
+
+        let mut led = gpioc.pc13.into_push_pull_output(&mut gpioc.crh);
+        led.set_high().unwrap();
+
+        // configure TIM3 periodic timer to start polls
+
+        let mut tmr = Timer::tim3(cx.device.TIM3, &clocks, &mut rcc.apb1).start_count_down(5.hz());
+        tmr.listen(Event::Update);
+
+        // configure external irq line from NFC chip: active high push-pull
+
+        let mut irq = gpiob.pb1.into_floating_input(&mut gpiob.crl);
+        irq.make_interrupt_source(&mut afio);
+        irq.trigger_on_edge(&cx.device.EXTI, Edge::RISING);
+        irq.enable_interrupt(&cx.device.EXTI);
+
+        // configure SPI and connected NFC RC522 board
+
+        let (pa15, pb3, pb4) = afio.mapr.disable_jtag(gpioa.pa15, gpiob.pb3, gpiob.pb4);
+
+        let sck = pb3.into_alternate_push_pull(&mut gpiob.crl);
+        let mosi = gpiob.pb5.into_alternate_push_pull(&mut gpiob.crl);
+        let miso = pb4;
+        let nss = pa15.into_push_pull_output(&mut gpioa.crh);
+
+        let spi = Spi::spi1(
+            cx.device.SPI1,
+            (sck, miso, mosi),
+            &mut afio.mapr,
+            mfrc522::MODE,
+            1.mhz(),
+            clocks,
+            &mut rcc.apb2,
+        );
+
+        let mut nfc = Rc522::new(spi, nss).unwrap();
+
+        // wait for card response no longer than 5ms
+        nfc.set_timeout_ticks(200).unwrap();
+        nfc.enable_interrupts(COM_IRQS, 0).unwrap();
+        nfc.power_down().unwrap();
+
+        (
+            Shared { stream1, led, nfc },
+            Local { stream2, tmr, irq },
+            init::Monotonics(),
+        )
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cm::asm::wfi();
+        }
+    }
+
+    // wake up RC522 and start WUPA: result is reported by IRQ
+    #[task(binds = TIM3, local = [tmr, stream2], shared = [nfc])]
+    fn tim3(cx: tim3::Context) {
+        cx.local.tmr.clear_update_interrupt_flag();
+
+        let nfc = cx.shared.nfc;
+
+        if let Err(e) = nfc.wake_up().and_then(|_| nfc.start_wupa()) {
+            writeln!(cx.local.stream2, "NFC: failed to start poll: {:?}", e).ok();
+            nfc.power_down().ok();
+        }
+    }
+
+    #[task(binds = EXTI1, local = [irq, card: Option<Uid> = None, missed: u8 = 0], shared = [nfc, stream1])]
+    fn exti(cx: exti::Context) {
+        if !cx.local.irq.check_interrupt() {
+            writeln!(cx.shared.stream1, "NFC: unexpected IRQ").ok();
+            return;
+        }
+
+        cx.local.irq.clear_interrupt_pending_bit();
+
+        let nfc = cx.shared.nfc;
+
+        let uid = match nfc.finish_request() {
+            Ok(atqa) => nfc.select(&atqa).ok(),
+            Err(rc522::Error::Busy) => return,
+            Err(_) => None,
+        };
+
+        match uid {
+            Some(uid) => {
+                *cx.local.missed = 0;
+
+                // halted card still answers WUPA in the next poll
+                nfc.hlta().ok();
+
+                if *cx.local.card != Some(uid) {
+                    *cx.local.card = Some(uid);
+                    arrived::spawn(uid).ok();
+                }
+            }
+            None => {
+                if let Some(uid) = *cx.local.card {
+                    *cx.local.missed += 1;
+
+                    if *cx.local.missed >= MISSED_POLLS {
+                        *cx.local.card = None;
+                        removed::spawn(uid).ok();
+                    }
+                }
+            }
+        }
+
+        nfc.clear_interrupts().ok();
+        nfc.power_down().ok();
+    }
+
+    #[task(shared = [led, stream1])]
+    fn arrived(cx: arrived::Context, uid: Uid) {
+        writeln!(cx.shared.stream1, "NFC: card arrived {:x?}", uid.as_bytes()).ok();
+        cx.shared.led.set_low().unwrap();
+    }
+
+    #[task(shared = [led, stream1])]
+    fn removed(cx: removed::Context, uid: Uid) {
+        writeln!(cx.shared.stream1, "NFC: card removed {:x?}", uid.as_bytes()).ok();
+        cx.shared.led.set_high().unwrap();
+    }
+}
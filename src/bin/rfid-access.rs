@@ -0,0 +1,345 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::access::{
+    self, AccessControl, CardId, Command, Config, Whitelist, WhitelistWriter,
+};
+use blue_pill_tests::rc522::Rc522;
+use core::fmt::Write;
+use cortex_m as cm;
+use eeprom24x::Eeprom24x;
+use eeprom24x::SlaveAddr;
+use embedded_hal::digital::v2::OutputPin;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::serial::{Config as SerialConfig, Event as SerialEvent, Rx, Serial, Tx};
+use hal::spi::Spi;
+use hal::spi::Spi1Remap;
+use hal::stm32;
+use hal::timer::{CountDownTimer, Event, Timer};
+use panic_rtt_target as _;
+use rtic::app;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+use systick_monotonic::{ExtU64, Systick};
+
+type SpiSckType = gpiob::PB3<Alternate<PushPull>>;
+type SpiMisoType = gpiob::PB4<Input<Floating>>;
+type SpiMosiType = gpiob::PB5<Alternate<PushPull>>;
+type SpiNssType = gpioa::PA15<Output<PushPull>>;
+type SpiType = Spi<stm32::SPI1, Spi1Remap, (SpiSckType, SpiMisoType, SpiMosiType), u8>;
+
+type I2cType = bitbang_hal::i2c::I2cBB<
+    gpioa::PA1<Output<OpenDrain>>,
+    gpioa::PA2<Output<OpenDrain>>,
+    CountDownTimer<stm32::TIM2>,
+>;
+type EepromType = Eeprom24x<I2cType, eeprom24x::page_size::B16, eeprom24x::addr_size::OneByte>;
+
+// card polling and relay timing period
+const TICK_MS: u32 = 100;
+
+// AT24C04 page write cycle time
+const EEPROM_WRITE_MS: u64 = 5;
+
+const LINE_SIZE: usize = 64;
+
+#[app(device = stm32f1xx_hal::stm32, peripherals = true, dispatchers = [EXTI2])]
+mod app {
+    use super::*;
+
+    #[monotonic(binds = SysTick, default = true)]
+    type MonoTimer = Systick<1000>;
+
+    #[shared]
+    struct Shared {
+        #[lock_free]
+        tx: Tx<stm32::USART1>,
+        #[lock_free]
+        ac: AccessControl,
+        #[lock_free]
+        now: u32,
+    }
+
+    #[local]
+    struct Local {
+        tmr: CountDownTimer<stm32::TIM3>,
+        led: gpioc::PC13<Output<PushPull>>,
+        relay: gpioa::PA8<Output<PushPull>>,
+        nfc: Rc522<SpiType, SpiNssType>,
+        eeprom: EepromType,
+        rx: Rx<stm32::USART1>,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        rtt_init_print!();
+
+        let mut rcc = cx.device.RCC.constrain();
+        let mut afio = cx.device.AFIO.constrain(&mut rcc.apb2);
+        let mut flash = cx.device.FLASH.constrain();
+
+        let clocks = rcc
+            .cfgr
+            .sysclk(8.mhz())
+            .pclk1(8.mhz())
+            .freeze(&mut flash.acr);
+
+        let mut gpioa = cx.device.GPIOA.split(&mut rcc.apb2);
+        let mut gpiob = cx.device.GPIOB.split(&mut rcc.apb2);
+        let mut gpioc = cx.device.GPIOC.split(&mut rcc.apb2);
+
+        // configure PC13 LED and PA8 relay outputs
+
+        let mut led = gpioc.pc13.into_push_pull_output(&mut gpioc.crh);
+        led.set_high().unwrap();
+
+        let mut relay = gpioa.pa8.into_push_pull_output(&mut gpioa.crh);
+        relay.set_low().unwrap();
+
+        // configure EEPROM on bitbang I2C
+
+        let i2c_tmr =
+            Timer::tim2(cx.device.TIM2, &clocks, &mut rcc.apb1).start_count_down(200.khz());
+        let scl = gpioa.pa1.into_open_drain_output(&mut gpioa.crl);
+        let sda = gpioa.pa2.into_open_drain_output(&mut gpioa.crl);
+        let i2c = bitbang_hal::i2c::I2cBB::new(scl, sda, i2c_tmr);
+        let mut eeprom = Eeprom24x::new_24x04(i2c, SlaveAddr::default());
+
+        let whitelist = match access::load_whitelist(&mut eeprom) {
+            Ok(list) => list.unwrap_or_default(),
+            Err(_) => {
+                rprintln!("EEPROM: read failed");
+                Whitelist::default()
+            }
+        };
+
+        rprintln!("whitelist: {} cards", whitelist.len());
+
+        let ac = AccessControl::new(whitelist, Config::default());
+
+        // configure USART1 console
+
+        let tx = gpioa.pa9.into_alternate_push_pull(&mut gpioa.crh);
+        let rx = gpioa.pa10;
+
+        let mut serial = Serial::usart1(
+            cx.device.USART1,
+            (tx, rx),
+            &mut afio.mapr,
+            SerialConfig::default().baudrate(115_200.bps()),
+            clocks,
+            &mut rcc.apb2,
+        );
+
+        serial.listen(SerialEvent::Rxne);
+        let (tx, rx) = serial.split();
+
+        // configure SPI and connected NFC RC522 board
+
+        let (pa15, pb3, pb4) = afio.mapr.disable_jtag(gpioa.pa15, gpiob.pb3, gpiob.pb4);
+
+        let sck = pb3.into_alternate_push_pull(&mut gpiob.crl);
+        let mosi = gpiob.pb5.into_alternate_push_pull(&mut gpiob.crl);
+        let miso = pb4;
+        let nss = pa15.into_push_pull_output(&mut gpioa.crh);
+
+        let spi = Spi::spi1(
+            cx.device.SPI1,
+            (sck, miso, mosi),
+            &mut afio.mapr,
+            mfrc522::MODE,
+            1.mhz(),
+            clocks,
+            &mut rcc.apb2,
+        );
+
+        let nfc = Rc522::new(spi, nss).unwrap();
+
+        // configure and start TIM3 periodic timer
+
+        let mut tmr = Timer::tim3(cx.device.TIM3, &clocks, &mut rcc.apb1)
+            .start_count_down((1000 / TICK_MS).hz());
+        tmr.listen(Event::Update);
+
+        // SysTick based monotonic timer
+        let mono = Systick::new(cx.core.SYST, clocks.sysclk().0);
+
+        (
+            Shared { tx, ac, now: 0 },
+            Local {
+                tmr,
+                led,
+                relay,
+                nfc,
+                eeprom,
+                rx,
+            },
+            init::Monotonics(mono),
+        )
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cm::asm::wfi();
+        }
+    }
+
+    #[task(binds = TIM3, local = [tmr, led, relay, nfc], shared = [ac, now, tx])]
+    fn tim3(cx: tim3::Context) {
+        cx.local.tmr.clear_update_interrupt_flag();
+        *cx.shared.now = cx.shared.now.wrapping_add(TICK_MS);
+
+        let now = *cx.shared.now;
+        let nfc = cx.local.nfc;
+        let ac = cx.shared.ac;
+
+        if let Ok(uid) = nfc.reqa().and_then(|atqa| nfc.select(&atqa)) {
+            nfc.hlta().ok();
+
+            if let Some(id) = CardId::new(uid.as_bytes()) {
+                if let Some(decision) = ac.on_card(id, now) {
+                    writeln!(cx.shared.tx, "{} {}\r", id, decision).ok();
+                }
+            }
+        }
+
+        let unlocked = ac.poll(now);
+
+        if unlocked {
+            cx.local.relay.set_high().unwrap();
+        } else {
+            cx.local.relay.set_low().unwrap();
+        }
+
+        // LED is active low: on while relay is on, blink in enrollment mode
+        if ac.enrolling() {
+            cx.local.led.toggle().unwrap();
+        } else if unlocked {
+            cx.local.led.set_low().unwrap();
+        } else {
+            cx.local.led.set_high().unwrap();
+        }
+
+        if ac.dirty() {
+            persist::spawn().ok();
+        }
+    }
+
+    #[task(binds = USART1, local = [rx, line: [u8; LINE_SIZE] = [0; LINE_SIZE], len: usize = 0], shared = [tx, ac, now])]
+    fn usart1(cx: usart1::Context) {
+        let byte = match cx.local.rx.read() {
+            Ok(b) => b,
+            Err(_) => return,
+        };
+
+        let line = cx.local.line;
+        let len = cx.local.len;
+
+        if byte != b'\r' && byte != b'\n' {
+            if *len < LINE_SIZE {
+                line[*len] = byte;
+                *len += 1;
+            }
+            return;
+        }
+
+        let n = core::mem::replace(len, 0);
+        if n == 0 {
+            return;
+        }
+
+        let tx = cx.shared.tx;
+        let ac = cx.shared.ac;
+
+        let cmd = core::str::from_utf8(&line[..n])
+            .ok()
+            .and_then(access::parse_command);
+
+        match cmd {
+            Some(Command::Help) | None => {
+                writeln!(
+                    tx,
+                    "commands: list, add <uid>, del <uid>, master <uid>, enroll, log, clear\r"
+                )
+                .ok();
+            }
+            Some(Command::List) => {
+                if let Some(m) = ac.whitelist.master() {
+                    writeln!(tx, "master {}\r", m).ok();
+                }
+                for id in ac.whitelist.iter() {
+                    writeln!(tx, "card {}\r", id).ok();
+                }
+            }
+            Some(Command::Add(id)) => {
+                writeln!(tx, "add {}: {:?}\r", id, ac.add(id)).ok();
+            }
+            Some(Command::Del(id)) => {
+                writeln!(tx, "del {}: {:?}\r", id, ac.remove(&id)).ok();
+            }
+            Some(Command::Master(id)) => {
+                ac.set_master(Some(id));
+                writeln!(tx, "master {}\r", id).ok();
+            }
+            Some(Command::Enroll) => {
+                ac.start_enroll(*cx.shared.now);
+                writeln!(tx, "enroll: present card\r").ok();
+            }
+            Some(Command::Log) => {
+                for e in ac.log.iter() {
+                    writeln!(
+                        tx,
+                        "{}.{:03} {} {}\r",
+                        e.time / 1000,
+                        e.time % 1000,
+                        e.id,
+                        e.decision
+                    )
+                    .ok();
+                }
+            }
+            Some(Command::ClearLog) => {
+                ac.log.clear();
+            }
+        }
+
+        if ac.dirty() {
+            persist::spawn().ok();
+        }
+    }
+
+    #[task(local = [eeprom, writer: Option<WhitelistWriter> = None], shared = [ac])]
+    fn persist(cx: persist::Context) {
+        let ac = cx.shared.ac;
+        let writer = cx.local.writer.get_or_insert_with(|| {
+            ac.take_dirty();
+            WhitelistWriter::new(&ac.whitelist)
+        });
+
+        // write cycle of the last page is over
+        if writer.done() {
+            rprintln!("EEPROM: whitelist saved");
+            *cx.local.writer = None;
+
+            // whitelist changed while it was being saved
+            if ac.dirty() {
+                persist::spawn().ok();
+            }
+
+            return;
+        }
+
+        // one page per run: next run comes after the page write cycle
+        match writer.write_next(cx.local.eeprom) {
+            Ok(()) => {
+                persist::spawn_after(EEPROM_WRITE_MS.millis()).ok();
+            }
+            Err(_) => {
+                rprintln!("EEPROM: write failed");
+                *cx.local.writer = None;
+            }
+        }
+    }
+}
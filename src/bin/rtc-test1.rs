@@ -0,0 +1,187 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::datetime::DateTime;
+use blue_pill_tests::rtc::{Clock, Event};
+use hal::backup_domain::BackupDomain;
+use hal::prelude::*;
+use hal::stm32;
+use panic_rtt_target as _;
+use rtic::app;
+use rtt_target::{rprintln, rtt_init, set_print_channel, DownChannel};
+use stm32f1xx_hal as hal;
+
+// EXTI line connected to RTC alarm
+const RTC_ALARM_LINE: u32 = 1 << 17;
+
+// periodic alarm
+const ALARM_S: u32 = 10;
+
+// print time every N seconds
+const PRINT_S: u32 = 5;
+
+// tamper pin PC13 is connected to LED on blue pill: enable on custom boards
+const TAMPER: bool = false;
+
+// optional clock calibration: slow down by CAL * 0.954ppm
+const CAL: u8 = 0;
+
+// time is set by sending "YYYY-MM-DD HH:MM:SS" line over RTT
+const LINE_SIZE: usize = 32;
+
+#[app(device = stm32f1xx_hal::stm32, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        clock: Clock,
+    }
+
+    #[local]
+    struct Local {
+        input: DownChannel,
+        bkp: BackupDomain,
+        exti: stm32::EXTI,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let channels = rtt_init! {
+            up: {
+                0: {
+                    size: 1024
+                    name: "Terminal"
+                }
+            }
+            down: {
+                0: {
+                    size: 64
+                    name: "Terminal"
+                }
+            }
+        };
+
+        set_print_channel(channels.up.0);
+        let input = channels.down.0;
+
+        let mut rcc = cx.device.RCC.constrain();
+        let mut flash = cx.device.FLASH.constrain();
+
+        let _clocks = rcc
+            .cfgr
+            .use_hse(8.mhz())
+            .sysclk(72.mhz())
+            .pclk1(36.mhz())
+            .freeze(&mut flash.acr);
+
+        // RTC keeps running from VBAT, time survives reset and power loss
+        let mut pwr = cx.device.PWR;
+        let mut bkp = rcc.bkp.constrain(cx.device.BKP, &mut rcc.apb1, &mut pwr);
+        let mut clock = Clock::new(cx.device.RTC, &mut bkp);
+
+        rprintln!("RTC source: {:?}", clock.source());
+
+        if clock.is_valid() {
+            let now = clock.datetime();
+            rprintln!("time: {} {:?}", now, now.weekday());
+        } else {
+            rprintln!("time is not set: send 'YYYY-MM-DD HH:MM:SS'");
+        }
+
+        clock.set_calibration(CAL);
+
+        if TAMPER {
+            clock.enable_tamper(true, true);
+        }
+
+        // RTC alarm interrupt comes through EXTI line 17
+        let exti = cx.device.EXTI;
+        exti.rtsr
+            .modify(|r, w| unsafe { w.bits(r.bits() | RTC_ALARM_LINE) });
+        exti.imr
+            .modify(|r, w| unsafe { w.bits(r.bits() | RTC_ALARM_LINE) });
+
+        let next = clock.now() + ALARM_S;
+        clock.set_alarm(next);
+        clock.listen(Event::Second);
+
+        (
+            Shared { clock },
+            Local { input, bkp, exti },
+            init::Monotonics(),
+        )
+    }
+
+    #[idle(local = [input, bkp, line: [u8; LINE_SIZE] = [0; LINE_SIZE], len: usize = 0], shared = [clock])]
+    fn idle(mut cx: idle::Context) -> ! {
+        let line = cx.local.line;
+        let len = cx.local.len;
+        let mut buf = [0u8; 16];
+
+        loop {
+            let n = cx.local.input.read(&mut buf);
+
+            for &c in buf.iter().take(n) {
+                if c != b'\n' && c != b'\r' {
+                    if *len < LINE_SIZE {
+                        line[*len] = c;
+                        *len += 1;
+                    }
+                    continue;
+                }
+
+                if *len == 0 {
+                    continue;
+                }
+
+                match core::str::from_utf8(&line[..*len])
+                    .ok()
+                    .and_then(DateTime::parse)
+                {
+                    Some(dt) => {
+                        let bkp = &mut *cx.local.bkp;
+                        cx.shared.clock.lock(|clock| clock.set_datetime(&dt, bkp));
+                        rprintln!("time set: {} {:?}", dt, dt.weekday());
+                    }
+                    None => rprintln!("bad time: expected 'YYYY-MM-DD HH:MM:SS'"),
+                }
+
+                *len = 0;
+            }
+        }
+    }
+
+    #[task(binds = RTC, shared = [clock])]
+    fn second(mut cx: second::Context) {
+        cx.shared.clock.lock(|clock| {
+            clock.clear(Event::Second);
+
+            let now = clock.now();
+            if clock.is_valid() && now % PRINT_S == 0 {
+                rprintln!("{}", DateTime::from_unix(now));
+            }
+        });
+    }
+
+    #[task(binds = RTCALARM, local = [exti], shared = [clock])]
+    fn alarm(mut cx: alarm::Context) {
+        let exti = cx.local.exti;
+        exti.pr.write(|w| unsafe { w.bits(RTC_ALARM_LINE) });
+
+        cx.shared.clock.lock(|clock| {
+            let now = clock.now();
+            rprintln!("alarm: {}", DateTime::from_unix(now));
+            clock.set_alarm(now + ALARM_S);
+        });
+    }
+
+    #[task(binds = TAMPER, shared = [clock])]
+    fn tamper(mut cx: tamper::Context) {
+        cx.shared.clock.lock(|clock| {
+            if clock.tamper_detected() {
+                rprintln!("tamper: backup registers cleared, time is lost");
+            }
+        });
+    }
+}
@@ -0,0 +1,193 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::servo::{self, Calibration, Servo};
+use cortex_m as cm;
+use embedded_hal::PwmPin;
+use hal::prelude::*;
+use hal::pwm::{PwmChannel, C1, C2, C3, C4};
+use hal::stm32;
+use hal::timer::{CountDownTimer, Event, Tim2NoRemap, Tim3NoRemap, Tim4NoRemap, Timer};
+use panic_rtt_target as _;
+use rtic::app;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+
+type Channels<TIM> = (
+    PwmChannel<TIM, C1>,
+    PwmChannel<TIM, C2>,
+    PwmChannel<TIM, C3>,
+    PwmChannel<TIM, C4>,
+);
+
+fn enable<TIM>(ch: &mut Channels<TIM>)
+where
+    PwmChannel<TIM, C1>: PwmPin,
+    PwmChannel<TIM, C2>: PwmPin,
+    PwmChannel<TIM, C3>: PwmPin,
+    PwmChannel<TIM, C4>: PwmPin,
+{
+    ch.0.enable();
+    ch.1.enable();
+    ch.2.enable();
+    ch.3.enable();
+}
+
+const NUM_SERVOS: usize = 12;
+
+// servo control loop period
+const TICK_MS: u32 = 20;
+
+#[app(device = stm32f1xx_hal::stm32, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {}
+
+    #[local]
+    struct Local {
+        tmr: CountDownTimer<stm32::TIM1>,
+        tim2: Channels<stm32::TIM2>,
+        tim3: Channels<stm32::TIM3>,
+        tim4: Channels<stm32::TIM4>,
+        servos: [Servo; NUM_SERVOS],
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        rtt_init_print!();
+
+        let mut rcc = cx.device.RCC.constrain();
+        let mut afio = cx.device.AFIO.constrain(&mut rcc.apb2);
+        let mut flash = cx.device.FLASH.constrain();
+
+        let clocks = rcc
+            .cfgr
+            .use_hse(8.mhz())
+            .sysclk(72.mhz())
+            .pclk1(36.mhz())
+            .freeze(&mut flash.acr);
+
+        let mut gpioa = cx.device.GPIOA.split(&mut rcc.apb2);
+        let mut gpiob = cx.device.GPIOB.split(&mut rcc.apb2);
+
+        // servo channels: TIM2 on PA0..PA3, TIM3 on PA6, PA7, PB0, PB1, TIM4 on PB6..PB9
+
+        let pins = (
+            gpioa.pa0.into_alternate_push_pull(&mut gpioa.crl),
+            gpioa.pa1.into_alternate_push_pull(&mut gpioa.crl),
+            gpioa.pa2.into_alternate_push_pull(&mut gpioa.crl),
+            gpioa.pa3.into_alternate_push_pull(&mut gpioa.crl),
+        );
+
+        let mut tim2 = Timer::tim2(cx.device.TIM2, &clocks, &mut rcc.apb1)
+            .pwm::<Tim2NoRemap, _, _, _>(pins, &mut afio.mapr, servo::FREQ_HZ.hz())
+            .split();
+
+        let pins = (
+            gpioa.pa6.into_alternate_push_pull(&mut gpioa.crl),
+            gpioa.pa7.into_alternate_push_pull(&mut gpioa.crl),
+            gpiob.pb0.into_alternate_push_pull(&mut gpiob.crl),
+            gpiob.pb1.into_alternate_push_pull(&mut gpiob.crl),
+        );
+
+        let mut tim3 = Timer::tim3(cx.device.TIM3, &clocks, &mut rcc.apb1)
+            .pwm::<Tim3NoRemap, _, _, _>(pins, &mut afio.mapr, servo::FREQ_HZ.hz())
+            .split();
+
+        let pins = (
+            gpiob.pb6.into_alternate_push_pull(&mut gpiob.crl),
+            gpiob.pb7.into_alternate_push_pull(&mut gpiob.crl),
+            gpiob.pb8.into_alternate_push_pull(&mut gpiob.crh),
+            gpiob.pb9.into_alternate_push_pull(&mut gpiob.crh),
+        );
+
+        let mut tim4 = Timer::tim4(cx.device.TIM4, &clocks, &mut rcc.apb1)
+            .pwm::<Tim4NoRemap, _, _, _>(pins, &mut afio.mapr, servo::FREQ_HZ.hz())
+            .split();
+
+        enable(&mut tim2);
+        enable(&mut tim3);
+        enable(&mut tim4);
+
+        rprintln!("servo PWM max duty {}", tim2.0.get_max_duty());
+
+        // calibration examples: wide range SG90 and mirrored mounted servo
+
+        let mut servos = [Servo::new(Calibration::default()); NUM_SERVOS];
+
+        servos[0].cal = Calibration {
+            min_us: 500,
+            max_us: 2400,
+            ..Calibration::default()
+        };
+
+        servos[1].cal = Calibration {
+            trim_us: -20,
+            inverted: true,
+            ..Calibration::default()
+        };
+
+        for (n, s) in servos.iter_mut().enumerate() {
+            s.set_angle(0);
+            s.move_to(180, 30 + 10 * n as u16);
+        }
+
+        // configure TIM1 for servo control loop
+
+        let mut tmr = Timer::tim1(cx.device.TIM1, &clocks, &mut rcc.apb2)
+            .start_count_down((1000 / TICK_MS).hz());
+        tmr.listen(Event::Update);
+
+        (
+            Shared {},
+            Local {
+                tmr,
+                tim2,
+                tim3,
+                tim4,
+                servos,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cm::asm::wfi();
+        }
+    }
+
+    // sweep all servos back and forth with different speeds
+    #[task(binds = TIM1_UP, local = [tmr, tim2, tim3, tim4, servos])]
+    fn tim1_up(cx: tim1_up::Context) {
+        cx.local.tmr.clear_update_interrupt_flag();
+
+        let (t2, t3, t4) = (cx.local.tim2, cx.local.tim3, cx.local.tim4);
+
+        let mut outputs: [&mut dyn PwmPin<Duty = u16>; NUM_SERVOS] = [
+            &mut t2.0, &mut t2.1, &mut t2.2, &mut t2.3, &mut t3.0, &mut t3.1, &mut t3.2, &mut t3.3,
+            &mut t4.0, &mut t4.1, &mut t4.2, &mut t4.3,
+        ];
+
+        for (n, (s, out)) in cx
+            .local
+            .servos
+            .iter_mut()
+            .zip(outputs.iter_mut())
+            .enumerate()
+        {
+            s.update(TICK_MS);
+
+            if !s.moving() {
+                let target = if s.angle() < 90 { 180 } else { 0 };
+                s.move_to(target, 30 + 10 * n as u16);
+            }
+
+            // disabled servo gets zero duty: no pulses
+            s.apply(&mut **out);
+        }
+    }
+}
@@ -11,29 +11,36 @@ use hal::prelude::*;
 use hal::stm32::ADC1;
 use panic_rtt_target as _;
 use rtic::app;
-use rtic::cyccnt::Instant;
-use rtic::cyccnt::U32Ext;
 use rtt_target::rprintln;
 use rtt_target::rtt_init_print;
 use shared_bus::AdcProxy;
 use shared_bus::CortexMMutex;
 use stm32f1xx_hal as hal;
+use systick_monotonic::{ExtU64, Systick};
 
-const PERIOD1: u32 = 24_000_000;
-const PERIOD2: u32 = 12_000_000;
+const PERIOD1_MS: u64 = 750;
+const PERIOD2_MS: u64 = 375;
 
-#[app(device = stm32f1xx_hal::stm32, peripherals = true, monotonic = rtic::cyccnt::CYCCNT)]
-const APP: () = {
-    struct Resources {
-        // late resources
+#[app(device = stm32f1xx_hal::stm32, peripherals = true, dispatchers = [EXTI2])]
+mod app {
+    use super::*;
+
+    #[monotonic(binds = SysTick, default = true)]
+    type MonoTimer = Systick<1000>;
+
+    #[shared]
+    struct Shared {}
+
+    #[local]
+    struct Local {
         adc_proxy1: AdcProxy<'static, CortexMMutex<Adc<ADC1>>>,
         adc_proxy2: AdcProxy<'static, CortexMMutex<Adc<ADC1>>>,
         adc_ch1: PA0<Analog>,
         adc_ch2: PA1<Analog>,
     }
 
-    #[init(schedule = [task1, task2])]
-    fn init(mut cx: init::Context) -> init::LateResources {
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
         let mut flash = cx.device.FLASH.constrain();
         let mut rcc = cx.device.RCC.constrain();
         let clocks = rcc
@@ -58,24 +65,22 @@ const APP: () = {
         let adc_ch1 = gpioa.pa0.into_analog(&mut gpioa.crl);
         let adc_ch2 = gpioa.pa1.into_analog(&mut gpioa.crl);
 
-        /* Enable the monotonic timer based on CYCCNT */
-        cx.core.DCB.enable_trace();
-        cx.core.DWT.enable_cycle_counter();
-
-        cx.schedule
-            .task1(Instant::now() + PERIOD1.cycles())
-            .unwrap();
+        // SysTick based monotonic timer
+        let mono = Systick::new(cx.core.SYST, clocks.sysclk().0);
 
-        cx.schedule
-            .task2(Instant::now() + PERIOD2.cycles())
-            .unwrap();
+        task1::spawn_after(PERIOD1_MS.millis()).unwrap();
+        task2::spawn_after(PERIOD2_MS.millis()).unwrap();
 
-        init::LateResources {
-            adc_ch1,
-            adc_ch2,
-            adc_proxy1,
-            adc_proxy2,
-        }
+        (
+            Shared {},
+            Local {
+                adc_ch1,
+                adc_ch2,
+                adc_proxy1,
+                adc_proxy2,
+            },
+            init::Monotonics(mono),
+        )
     }
 
     #[idle]
@@ -85,26 +90,17 @@ const APP: () = {
         }
     }
 
-    #[task(schedule = [task1], resources = [adc_proxy1, adc_ch1])]
+    #[task(local = [adc_proxy1, adc_ch1])]
     fn task1(cx: task1::Context) {
-        let val: u16 = cx.resources.adc_proxy1.read(cx.resources.adc_ch1).unwrap();
+        let val: u16 = cx.local.adc_proxy1.read(cx.local.adc_ch1).unwrap();
         rprintln!("reading1: {}", val);
-        cx.schedule
-            .task1(Instant::now() + PERIOD1.cycles())
-            .unwrap();
+        task1::spawn_after(PERIOD1_MS.millis()).unwrap();
     }
 
-    #[task(schedule = [task2], resources = [adc_proxy2, adc_ch2])]
+    #[task(local = [adc_proxy2, adc_ch2])]
     fn task2(cx: task2::Context) {
-        let val: u16 = cx.resources.adc_proxy2.read(cx.resources.adc_ch2).unwrap();
+        let val: u16 = cx.local.adc_proxy2.read(cx.local.adc_ch2).unwrap();
         rprintln!("reading2: {}", val);
-        cx.schedule
-            .task2(Instant::now() + PERIOD2.cycles())
-            .unwrap();
-    }
-
-    // needed for RTFM timer queue and task management
-    extern "C" {
-        fn EXTI2();
+        task2::spawn_after(PERIOD2_MS.millis()).unwrap();
     }
-};
+}
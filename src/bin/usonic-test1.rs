@@ -0,0 +1,212 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::usonic::{self, Error, Median, Ranger};
+use core::convert::Infallible;
+use cortex_m as cm;
+use embedded_hal::digital::v2::OutputPin;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::stm32;
+use hal::timer::{CountDownTimer, Timer};
+use lm75::{Lm75, SlaveAddr};
+use panic_rtt_target as _;
+use rtic::app;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+use systick_monotonic::{fugit::TimerInstantU64, ExtU64, Systick};
+
+type I2cType = bitbang_hal::i2c::I2cBB<
+    gpioa::PA1<Output<OpenDrain>>,
+    gpioa::PA2<Output<OpenDrain>>,
+    CountDownTimer<stm32::TIM2>,
+>;
+
+type EchoPins = (
+    gpiob::PB6<Input<Floating>>,
+    gpiob::PB7<Input<Floating>>,
+    gpiob::PB8<Input<Floating>>,
+);
+
+const NUM_SENSORS: usize = 3;
+
+// measurement slot
+const SLOT_MS: u64 = usonic::CYCLE_MS as u64;
+
+// report distances and update temperature every 16 slots: ~1 second
+const REPORT_SLOTS: u32 = 16;
+
+// median over last 5 measurements of each sensor
+const MEDIAN_SIZE: usize = 5;
+
+// temperature used until the first LM75 reading: 20C
+const DEFAULT_TEMP_DC: i32 = 200;
+
+#[app(device = stm32f1xx_hal::stm32, peripherals = true, dispatchers = [EXTI2])]
+mod app {
+    use super::*;
+
+    #[monotonic(binds = SysTick, default = true)]
+    type MonoTimer = Systick<1000>;
+
+    #[shared]
+    struct Shared {
+        ranger: Ranger<stm32::TIM4, EchoPins>,
+    }
+
+    #[local]
+    struct Local {
+        trig0: gpiob::PB12<Output<PushPull>>,
+        trig1: gpiob::PB13<Output<PushPull>>,
+        trig2: gpiob::PB14<Output<PushPull>>,
+        sensor: Lm75<I2cType>,
+        filters: [Median<MEDIAN_SIZE>; NUM_SENSORS],
+        errors: [Option<Error>; NUM_SENSORS],
+        temp_dc: i32,
+        slot: u32,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        rtt_init_print!();
+
+        let mut rcc = cx.device.RCC.constrain();
+        let mut flash = cx.device.FLASH.constrain();
+
+        let clocks = rcc
+            .cfgr
+            .use_hse(8.mhz())
+            .sysclk(72.mhz())
+            .pclk1(36.mhz())
+            .freeze(&mut flash.acr);
+
+        let mut gpioa = cx.device.GPIOA.split(&mut rcc.apb2);
+        let mut gpiob = cx.device.GPIOB.split(&mut rcc.apb2);
+
+        // LM75 on bitbang I2C: PA1 SCL, PA2 SDA
+
+        let i2c_tmr =
+            Timer::tim2(cx.device.TIM2, &clocks, &mut rcc.apb1).start_count_down(200.khz());
+        let scl = gpioa.pa1.into_open_drain_output(&mut gpioa.crl);
+        let sda = gpioa.pa2.into_open_drain_output(&mut gpioa.crl);
+        let i2c = bitbang_hal::i2c::I2cBB::new(scl, sda, i2c_tmr);
+        let sensor = Lm75::new(i2c, SlaveAddr::default());
+
+        // HC-SR04 triggers on PB12-PB14, echoes on 5V tolerant TIM4 CH1-CH3: PB6-PB8
+
+        let trig0 = gpiob.pb12.into_push_pull_output(&mut gpiob.crh);
+        let trig1 = gpiob.pb13.into_push_pull_output(&mut gpiob.crh);
+        let trig2 = gpiob.pb14.into_push_pull_output(&mut gpiob.crh);
+
+        let echoes = (
+            gpiob.pb6.into_floating_input(&mut gpiob.crl),
+            gpiob.pb7.into_floating_input(&mut gpiob.crl),
+            gpiob.pb8.into_floating_input(&mut gpiob.crh),
+        );
+
+        let ranger = Ranger::new(cx.device.TIM4, echoes, &clocks, &mut rcc.apb1, 3);
+
+        // SysTick based monotonic timer
+        let mono = Systick::new(cx.core.SYST, clocks.sysclk().0);
+
+        let next = monotonics::now() + SLOT_MS.millis();
+        measure::spawn_at(next, next).unwrap();
+
+        (
+            Shared { ranger },
+            Local {
+                trig0,
+                trig1,
+                trig2,
+                sensor,
+                filters: [Median::new(); NUM_SENSORS],
+                errors: [None; NUM_SENSORS],
+                temp_dc: DEFAULT_TEMP_DC,
+                slot: 0,
+            },
+            init::Monotonics(mono),
+        )
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cm::asm::wfi();
+        }
+    }
+
+    #[task(binds = TIM4, priority = 2, shared = [ranger])]
+    fn tim4(mut cx: tim4::Context) {
+        cx.shared.ranger.lock(|ranger| ranger.on_interrupt());
+    }
+
+    // round robin: collect echo of the previous sensor, then trigger the next one
+    #[task(priority = 1, local = [trig0, trig1, trig2, sensor, filters, errors, temp_dc, slot], shared = [ranger])]
+    fn measure(mut cx: measure::Context, instant: TimerInstantU64<1000>) {
+        let slot = *cx.local.slot;
+        *cx.local.slot += 1;
+
+        let prev = (slot as usize + NUM_SENSORS - 1) % NUM_SENSORS;
+        let next = slot as usize % NUM_SENSORS;
+
+        let speed = usonic::speed_of_sound(*cx.local.temp_dc);
+
+        let trig: &mut dyn OutputPin<Error = Infallible> = match next {
+            0 => cx.local.trig0,
+            1 => cx.local.trig1,
+            _ => cx.local.trig2,
+        };
+
+        let result = cx.shared.ranger.lock(|ranger| {
+            let result = ranger.finish();
+            ranger.start(next, trig).ok();
+            result
+        });
+
+        if slot > 0 {
+            match result {
+                Ok(us) => {
+                    cx.local.filters[prev].push(usonic::distance_mm(us, speed));
+                    cx.local.errors[prev] = None;
+                }
+                Err(e) => {
+                    cx.local.filters[prev].clear();
+                    cx.local.errors[prev] = Some(e);
+                }
+            }
+        }
+
+        if slot % REPORT_SLOTS == 0 {
+            if let Ok(t) = usonic::read_temperature_dc(cx.local.sensor) {
+                *cx.local.temp_dc = t;
+            }
+
+            let t = *cx.local.temp_dc;
+            let sign = if t < 0 { "-" } else { "" };
+            rprintln!(
+                "T: {}{}.{} C, speed {} mm/s",
+                sign,
+                t.abs() / 10,
+                t.abs() % 10,
+                speed
+            );
+
+            for (i, (f, e)) in cx
+                .local
+                .filters
+                .iter()
+                .zip(cx.local.errors.iter())
+                .enumerate()
+            {
+                match (f.get(), e) {
+                    (Some(mm), None) => rprintln!("sensor {}: {} mm", i, mm),
+                    (_, Some(e)) => rprintln!("sensor {}: {:?}", i, e),
+                    (None, None) => rprintln!("sensor {}: no data", i),
+                }
+            }
+        }
+
+        let next = instant + SLOT_MS.millis();
+        measure::spawn_at(next, next).unwrap();
+    }
+}
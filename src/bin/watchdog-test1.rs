@@ -0,0 +1,161 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::watchdog::{self, Supervisor, TaskId, WindowWatchdog};
+use cortex_m as cm;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::watchdog::IndependentWatchdog;
+use panic_rtt_target as _;
+use rtic::app;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32f1xx_hal as hal;
+use systick_monotonic::{fugit::TimerInstantU64, ExtU64, Systick};
+
+// IWDG: fed only if every supervised task checked in during service period
+const IWDG_TIMEOUT_MS: u32 = 1000;
+const SERVICE_MS: u64 = 500;
+
+// WWDG: refreshed every 20ms, neither before 10ms nor after 40ms
+const WWDG_TIMEOUT_US: u32 = 40_000;
+const WWDG_MIN_US: u32 = 10_000;
+const REFRESH_MS: u64 = 20;
+
+const BLINK_MS: u64 = 250;
+const WORK_MS: u64 = 100;
+
+// worker stops checking in after this number of runs: IWDG resets the board
+const HANG_AFTER: u32 = 100;
+
+#[app(device = stm32f1xx_hal::stm32, peripherals = true, dispatchers = [EXTI2, EXTI3])]
+mod app {
+    use super::*;
+
+    #[monotonic(binds = SysTick, default = true)]
+    type MonoTimer = Systick<1000>;
+
+    #[shared]
+    struct Shared {
+        #[lock_free]
+        sv: Supervisor,
+    }
+
+    #[local]
+    struct Local {
+        iwdg: IndependentWatchdog,
+        wwdg: WindowWatchdog,
+        led: gpioc::PC13<Output<PushPull>>,
+        blink_id: TaskId,
+        work_id: TaskId,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        rtt_init_print!();
+
+        // reset flags stay in RCC_CSR until cleared
+        rprintln!("reset cause: {:?}", watchdog::reset_cause());
+
+        let mut rcc = cx.device.RCC.constrain();
+        let mut flash = cx.device.FLASH.constrain();
+
+        let clocks = rcc
+            .cfgr
+            .use_hse(8.mhz())
+            .sysclk(72.mhz())
+            .pclk1(36.mhz())
+            .freeze(&mut flash.acr);
+
+        let mut gpioc = cx.device.GPIOC.split(&mut rcc.apb2);
+        let led = gpioc.pc13.into_push_pull_output(&mut gpioc.crh);
+
+        // keep watchdogs quiet while stepping in debugger
+        watchdog::stop_on_debug(&cx.device.DBGMCU, true);
+
+        let mut sv = Supervisor::new();
+        let blink_id = sv.register().unwrap();
+        let work_id = sv.register().unwrap();
+
+        let mut iwdg = IndependentWatchdog::new(cx.device.IWDG);
+        iwdg.start(IWDG_TIMEOUT_MS.ms());
+
+        let mut wwdg = WindowWatchdog::new(cx.device.WWDG);
+        wwdg.start(&clocks, WWDG_TIMEOUT_US, WWDG_MIN_US).unwrap();
+
+        // SysTick based monotonic timer
+        let mono = Systick::new(cx.core.SYST, clocks.sysclk().0);
+
+        let now = monotonics::now();
+        service::spawn_at(now + SERVICE_MS.millis(), now + SERVICE_MS.millis()).unwrap();
+        refresh::spawn_at(now + REFRESH_MS.millis(), now + REFRESH_MS.millis()).unwrap();
+        blink::spawn_at(now + BLINK_MS.millis(), now + BLINK_MS.millis()).unwrap();
+        work::spawn_at(now + WORK_MS.millis(), now + WORK_MS.millis()).unwrap();
+
+        (
+            Shared { sv },
+            Local {
+                iwdg,
+                wwdg,
+                led,
+                blink_id,
+                work_id,
+            },
+            init::Monotonics(mono),
+        )
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cm::asm::wfi();
+        }
+    }
+
+    // WWDG checks timing of the highest priority periodic task
+    #[task(priority = 2, local = [wwdg])]
+    fn refresh(cx: refresh::Context, instant: TimerInstantU64<1000>) {
+        if !cx.local.wwdg.feed_in_window() {
+            rprintln!("WWDG: refresh too early");
+        }
+
+        let next = instant + REFRESH_MS.millis();
+        refresh::spawn_at(next, next).unwrap();
+    }
+
+    #[task(priority = 1, local = [iwdg], shared = [sv])]
+    fn service(cx: service::Context, instant: TimerInstantU64<1000>) {
+        let sv = cx.shared.sv;
+
+        if !sv.service(cx.local.iwdg) {
+            rprintln!("IWDG: not fed, missing tasks {:#b}", sv.missing());
+        }
+
+        let next = instant + SERVICE_MS.millis();
+        service::spawn_at(next, next).unwrap();
+    }
+
+    #[task(priority = 1, local = [led, blink_id], shared = [sv])]
+    fn blink(cx: blink::Context, instant: TimerInstantU64<1000>) {
+        cx.local.led.toggle().unwrap();
+        cx.shared.sv.check_in(*cx.local.blink_id);
+
+        let next = instant + BLINK_MS.millis();
+        blink::spawn_at(next, next).unwrap();
+    }
+
+    #[task(priority = 1, local = [work_id, runs: u32 = 0], shared = [sv])]
+    fn work(cx: work::Context, instant: TimerInstantU64<1000>) {
+        *cx.local.runs += 1;
+
+        if *cx.local.runs == HANG_AFTER {
+            rprintln!("work: simulating hang");
+        }
+
+        if *cx.local.runs < HANG_AFTER {
+            cx.shared.sv.check_in(*cx.local.work_id);
+        }
+
+        let next = instant + WORK_MS.millis();
+        work::spawn_at(next, next).unwrap();
+    }
+}
@@ -1,6 +1,7 @@
 #![no_main]
 #![no_std]
 
+use blue_pill_tests::color::Hsv;
 use cortex_m as cm;
 use cortex_m_rt::entry;
 use hal::delay::Delay;
@@ -52,21 +53,9 @@ fn main() -> ! {
     let mut ws = ws2812_spi::Ws2812::new(spi);
     let mut pdata = [RGB8::default(); NUM_LEDS];
     let cdata: [RGB8; 3] = [
-        RGB8 {
-            r: 0x10,
-            g: 0x0,
-            b: 0x0,
-        },
-        RGB8 {
-            r: 0x0,
-            g: 0x10,
-            b: 0x0,
-        },
-        RGB8 {
-            r: 0x0,
-            g: 0x0,
-            b: 0x10,
-        },
+        Hsv::new(0, 255, 0x10).to_rgb(),
+        Hsv::new(85, 255, 0x10).to_rgb(),
+        Hsv::new(171, 255, 0x10).to_rgb(),
     ];
     let mut p: usize = 0;
     let mut c: usize = 0;
@@ -0,0 +1,175 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::effects::{
+    Animation, Breathing, Chase, ColorWipe, Comet, Effects, Fire, Rainbow, Twinkle,
+};
+use cortex_m as cm;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::spi::{Spi, Spi2NoRemap};
+use hal::stm32;
+use hal::timer::{CountDownTimer, Event, Timer};
+use panic_rtt_target as _;
+use rtic::app;
+use rtt_target::{rprintln, rtt_init_print};
+use smart_leds::{SmartLedsWrite, RGB8};
+use stm32f1xx_hal as hal;
+use ws2812_spi::Ws2812;
+
+type SpiSckType = gpiob::PB13<Alternate<PushPull>>;
+type SpiMisoType = gpiob::PB14<Input<Floating>>;
+type SpiMosiType = gpiob::PB15<Alternate<PushPull>>;
+type SpiType = Spi<stm32::SPI2, Spi2NoRemap, (SpiSckType, SpiMisoType, SpiMosiType), u8>;
+
+const NUM_LEDS: usize = 8;
+const FRAME_RATE: u32 = 50;
+const NUM_EFFECTS: usize = 7;
+
+const RED: RGB8 = RGB8 { r: 255, g: 0, b: 0 };
+const GREEN: RGB8 = RGB8 { r: 0, g: 255, b: 0 };
+const BLUE: RGB8 = RGB8 { r: 0, g: 0, b: 255 };
+const WHITE: RGB8 = RGB8 {
+    r: 255,
+    g: 255,
+    b: 255,
+};
+
+fn effect(n: usize) -> Effects {
+    match n % NUM_EFFECTS {
+        0 => Effects::Rainbow(Rainbow {
+            speed: 64,
+            spread: 16,
+        }),
+        1 => Effects::Chase(Chase {
+            color: RED,
+            spacing: 3,
+            speed: 10,
+        }),
+        2 => Effects::ColorWipe(ColorWipe {
+            color: GREEN,
+            speed: 8,
+        }),
+        3 => Effects::Breathing(Breathing {
+            color: BLUE,
+            period_ms: 4000,
+        }),
+        4 => Effects::Comet(Comet {
+            color: WHITE,
+            tail: 4,
+            speed: 12,
+        }),
+        5 => Effects::Twinkle(Twinkle::new(WHITE, 64, 8, 0x1234_5678)),
+        _ => Effects::Fire(Fire::new(55, 120, 0x8765_4321)),
+    }
+}
+
+#[app(device = stm32f1xx_hal::stm32, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        anim: Animation<Effects>,
+    }
+
+    #[local]
+    struct Local {
+        tmr: CountDownTimer<stm32::TIM3>,
+        button: gpioa::PA0<Input<PullUp>>,
+        ws: Ws2812<SpiType>,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        rtt_init_print!();
+
+        let mut rcc = cx.device.RCC.constrain();
+        let mut afio = cx.device.AFIO.constrain(&mut rcc.apb2);
+        let mut flash = cx.device.FLASH.constrain();
+
+        let clocks = rcc
+            .cfgr
+            .use_hse(8.mhz())
+            .sysclk(48.mhz())
+            .pclk1(24.mhz())
+            .freeze(&mut flash.acr);
+
+        let mut gpioa = cx.device.GPIOA.split(&mut rcc.apb2);
+        let mut gpiob = cx.device.GPIOB.split(&mut rcc.apb2);
+
+        // configure PA0 button to switch effects
+
+        let mut button = gpioa.pa0.into_pull_up_input(&mut gpioa.crl);
+        button.make_interrupt_source(&mut afio);
+        button.trigger_on_edge(&cx.device.EXTI, Edge::FALLING);
+        button.enable_interrupt(&cx.device.EXTI);
+
+        // configure SPI2 for WS2812 strip: data on MOSI
+
+        let pins = (
+            gpiob.pb13.into_alternate_push_pull(&mut gpiob.crh),
+            gpiob.pb14.into_floating_input(&mut gpiob.crh),
+            gpiob.pb15.into_alternate_push_pull(&mut gpiob.crh),
+        );
+
+        let spi = Spi::spi2(
+            cx.device.SPI2,
+            pins,
+            ws2812_spi::MODE,
+            3.mhz(),
+            clocks,
+            &mut rcc.apb1,
+        );
+
+        let ws = Ws2812::new(spi);
+
+        let mut anim = Animation::new(effect(0));
+        anim.brightness = 32;
+
+        // configure TIM3 frame timer
+
+        let mut tmr =
+            Timer::tim3(cx.device.TIM3, &clocks, &mut rcc.apb1).start_count_down(FRAME_RATE.hz());
+        tmr.listen(Event::Update);
+
+        rprintln!("ready to go...");
+
+        (
+            Shared { anim },
+            Local { tmr, button, ws },
+            init::Monotonics(),
+        )
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cm::asm::wfi();
+        }
+    }
+
+    #[task(binds = TIM3, priority = 2, local = [tmr, ws, frame: [RGB8; NUM_LEDS] = [RGB8 { r: 0, g: 0, b: 0 }; NUM_LEDS]], shared = [anim])]
+    fn tim3(mut cx: tim3::Context) {
+        cx.local.tmr.clear_update_interrupt_flag();
+
+        let frame = cx.local.frame;
+
+        cx.shared
+            .anim
+            .lock(|anim| anim.next_frame(frame, 1000 / FRAME_RATE));
+        cx.local.ws.write(frame.iter().cloned()).ok();
+    }
+
+    #[task(binds = EXTI0, priority = 1, local = [button, current: usize = 0], shared = [anim])]
+    fn exti0(mut cx: exti0::Context) {
+        cx.local.button.clear_interrupt_pending_bit();
+
+        *cx.local.current = (*cx.local.current + 1) % NUM_EFFECTS;
+        let next = effect(*cx.local.current);
+
+        rprintln!("effect: {}", *cx.local.current);
+
+        cx.shared.anim.lock(|anim| anim.set_effect(next));
+    }
+}
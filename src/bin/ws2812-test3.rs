@@ -0,0 +1,157 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::color::{self, PowerLimit, Temperature};
+use blue_pill_tests::effects::{Animation, Effects, Fire, Rainbow};
+use blue_pill_tests::ws2812::{self, Ws2812Dma};
+use cm::singleton;
+use cortex_m as cm;
+use hal::dma;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::spi::{Spi, Spi2NoRemap};
+use hal::stm32;
+use hal::timer::{CountDownTimer, Event, Timer};
+use panic_rtt_target as _;
+use rtic::app;
+use rtt_target::{rprintln, rtt_init_print};
+use smart_leds::RGB8;
+use stm32f1xx_hal as hal;
+
+type SpiSckType = gpiob::PB13<Alternate<PushPull>>;
+type SpiMisoType = gpiob::PB14<Input<Floating>>;
+type SpiMosiType = gpiob::PB15<Alternate<PushPull>>;
+type SpiPins = (SpiSckType, SpiMisoType, SpiMosiType);
+
+const NUM_LEDS: usize = 60;
+const BUF_SIZE: usize = ws2812::buffer_size(NUM_LEDS);
+const FRAME_RATE: u32 = 50;
+
+// strip powered from USB port
+const POWER: PowerLimit = PowerLimit::new(450);
+
+// switch effect every 10 seconds
+const EFFECT_FRAMES: u32 = 10 * FRAME_RATE;
+
+#[app(device = stm32f1xx_hal::stm32, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        #[lock_free]
+        ws: Ws2812Dma<Spi2NoRemap, SpiPins>,
+    }
+
+    #[local]
+    struct Local {
+        tmr: CountDownTimer<stm32::TIM3>,
+        anim: Animation<Effects>,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        rtt_init_print!();
+
+        let mut rcc = cx.device.RCC.constrain();
+        let mut flash = cx.device.FLASH.constrain();
+
+        let clocks = rcc
+            .cfgr
+            .use_hse(8.mhz())
+            .sysclk(48.mhz())
+            .pclk1(24.mhz())
+            .freeze(&mut flash.acr);
+
+        let mut gpiob = cx.device.GPIOB.split(&mut rcc.apb2);
+
+        // configure SPI2 for WS2812 strip: data on MOSI
+
+        let pins = (
+            gpiob.pb13.into_alternate_push_pull(&mut gpiob.crh),
+            gpiob.pb14.into_floating_input(&mut gpiob.crh),
+            gpiob.pb15.into_alternate_push_pull(&mut gpiob.crh),
+        );
+
+        let spi = Spi::spi2(
+            cx.device.SPI2,
+            pins,
+            ws2812::MODE,
+            3.mhz(),
+            clocks,
+            &mut rcc.apb1,
+        );
+
+        // configure DMA1 channel 5 for SPI2 TX
+
+        let mut dma_ch5 = cx.device.DMA1.split(&mut rcc.ahb).5;
+        dma_ch5.listen(dma::Event::TransferComplete);
+
+        let buf = singleton!(: [u8; BUF_SIZE] = [0; BUF_SIZE]).unwrap();
+        let ws = Ws2812Dma::new(spi.with_tx_dma(dma_ch5), buf);
+
+        let mut anim = Animation::new(Effects::Rainbow(Rainbow {
+            speed: 64,
+            spread: 4,
+        }));
+        anim.brightness = 128;
+
+        // configure TIM3 frame timer
+
+        let mut tmr =
+            Timer::tim3(cx.device.TIM3, &clocks, &mut rcc.apb1).start_count_down(FRAME_RATE.hz());
+        tmr.listen(Event::Update);
+
+        rprintln!("ready to go...");
+
+        (Shared { ws }, Local { tmr, anim }, init::Monotonics())
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cm::asm::wfi();
+        }
+    }
+
+    // render next frame and queue it for DMA: frame is dropped if strip is busy
+    #[task(binds = TIM3, local = [tmr, anim, frame: [RGB8; NUM_LEDS] = [RGB8 { r: 0, g: 0, b: 0 }; NUM_LEDS], frames: u32 = 0, dropped: u32 = 0], shared = [ws])]
+    fn tim3(cx: tim3::Context) {
+        cx.local.tmr.clear_update_interrupt_flag();
+
+        let anim = cx.local.anim;
+        let frames = cx.local.frames;
+
+        *frames += 1;
+
+        if *frames % EFFECT_FRAMES == 0 {
+            let next = match anim.effect {
+                Effects::Rainbow(_) => Effects::Fire(Fire::new(55, 120, *frames)),
+                _ => Effects::Rainbow(Rainbow {
+                    speed: 64,
+                    spread: 4,
+                }),
+            };
+
+            anim.set_effect(next);
+
+            rprintln!("frames {} dropped {}", *frames, *cx.local.dropped);
+        }
+
+        let frame = cx.local.frame;
+        anim.next_frame(frame, 1000 / FRAME_RATE);
+
+        color::gamma_frame(frame);
+        Temperature::Tungsten100W.correct_frame(frame);
+        POWER.apply(frame);
+
+        if cx.shared.ws.show(frame.iter().cloned()).is_err() {
+            *cx.local.dropped += 1;
+        }
+    }
+
+    #[task(binds = DMA1_CHANNEL5, shared = [ws])]
+    fn dma1_channel5(cx: dma1_channel5::Context) {
+        cx.shared.ws.complete();
+    }
+}
@@ -0,0 +1,184 @@
+#![no_main]
+#![no_std]
+
+use blue_pill_tests::color::{self, PowerLimit};
+use blue_pill_tests::matrix::{Canvas, Layout, Matrix, Ticker};
+use blue_pill_tests::ws2812::{self, Ws2812Dma};
+use cm::singleton;
+use core::fmt::Write;
+use cortex_m as cm;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+use hal::adc::Adc;
+use hal::dma;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::spi::{Spi, Spi2NoRemap};
+use hal::stm32;
+use hal::timer::{CountDownTimer, Event, Timer};
+use panic_rtt_target as _;
+use rtic::app;
+use rtt_target::{rprintln, rtt_init_print};
+use smart_leds::RGB8;
+use stm32f1xx_hal as hal;
+
+type SpiSckType = gpiob::PB13<Alternate<PushPull>>;
+type SpiMisoType = gpiob::PB14<Input<Floating>>;
+type SpiMosiType = gpiob::PB15<Alternate<PushPull>>;
+type SpiPins = (SpiSckType, SpiMisoType, SpiMosiType);
+
+// 32x8 panel wired in rows, zigzag
+const MATRIX: Matrix = Matrix::new(32, 8, Layout::Serpentine);
+
+const NUM_LEDS: usize = 32 * 8;
+const BUF_SIZE: usize = ws2812::buffer_size(NUM_LEDS);
+const FRAME_RATE: u32 = 20;
+
+// strip powered from USB port
+const POWER: PowerLimit = PowerLimit::new(450);
+
+#[app(device = stm32f1xx_hal::stm32, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        #[lock_free]
+        ws: Ws2812Dma<Spi2NoRemap, SpiPins>,
+    }
+
+    #[local]
+    struct Local {
+        tmr: CountDownTimer<stm32::TIM3>,
+        adc: Adc<stm32::ADC1>,
+        ch0: gpioa::PA0<Analog>,
+        ticker: Ticker,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        rtt_init_print!();
+
+        let mut rcc = cx.device.RCC.constrain();
+        let mut flash = cx.device.FLASH.constrain();
+
+        let clocks = rcc
+            .cfgr
+            .use_hse(8.mhz())
+            .sysclk(48.mhz())
+            .pclk1(24.mhz())
+            .adcclk(2.mhz())
+            .freeze(&mut flash.acr);
+
+        let mut gpioa = cx.device.GPIOA.split(&mut rcc.apb2);
+        let mut gpiob = cx.device.GPIOB.split(&mut rcc.apb2);
+
+        // configure ADC: PA0 and internal temperature sensor
+
+        let adc = Adc::adc1(cx.device.ADC1, &mut rcc.apb2, clocks);
+        let ch0 = gpioa.pa0.into_analog(&mut gpioa.crl);
+
+        // configure SPI2 and DMA1 channel 5 for WS2812 panel
+
+        let pins = (
+            gpiob.pb13.into_alternate_push_pull(&mut gpiob.crh),
+            gpiob.pb14.into_floating_input(&mut gpiob.crh),
+            gpiob.pb15.into_alternate_push_pull(&mut gpiob.crh),
+        );
+
+        let spi = Spi::spi2(
+            cx.device.SPI2,
+            pins,
+            ws2812::MODE,
+            3.mhz(),
+            clocks,
+            &mut rcc.apb1,
+        );
+
+        let mut dma_ch5 = cx.device.DMA1.split(&mut rcc.ahb).5;
+        dma_ch5.listen(dma::Event::TransferComplete);
+
+        let buf = singleton!(: [u8; BUF_SIZE] = [0; BUF_SIZE]).unwrap();
+        let ws = Ws2812Dma::new(spi.with_tx_dma(dma_ch5), buf);
+
+        let mut ticker = Ticker::new(RGB8 {
+            r: 0x40,
+            g: 0x20,
+            b: 0,
+        });
+        ticker.set_text("ready");
+
+        // configure TIM3 frame timer
+
+        let mut tmr =
+            Timer::tim3(cx.device.TIM3, &clocks, &mut rcc.apb1).start_count_down(FRAME_RATE.hz());
+        tmr.listen(Event::Update);
+
+        rprintln!("ready to go...");
+
+        (
+            Shared { ws },
+            Local {
+                tmr,
+                adc,
+                ch0,
+                ticker,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cm::asm::wfi();
+        }
+    }
+
+    #[task(binds = TIM3, local = [tmr, adc, ch0, ticker, frame: [RGB8; NUM_LEDS] = [RGB8 { r: 0, g: 0, b: 0 }; NUM_LEDS], frames: u32 = 0], shared = [ws])]
+    fn tim3(cx: tim3::Context) {
+        cx.local.tmr.clear_update_interrupt_flag();
+
+        let ticker = cx.local.ticker;
+        let frames = cx.local.frames;
+
+        // update ticker text once per second
+        if *frames % FRAME_RATE == 0 {
+            let v: u16 = cx.local.adc.read(cx.local.ch0).unwrap();
+            let temp = cx.local.adc.read_temp();
+
+            ticker.clear();
+            write!(ticker, "T {}C  A0 {}", temp, v).ok();
+        }
+
+        *frames += 1;
+
+        let (width, _) = MATRIX.size();
+        ticker.step(width);
+
+        let frame = cx.local.frame;
+        let mut canvas = Canvas::new(frame, MATRIX);
+
+        canvas.clear(Rgb888::BLACK).ok();
+
+        // dim frame around the text
+        Rectangle::new(Point::zero(), canvas.size())
+            .into_styled(PrimitiveStyle::with_stroke(Rgb888::new(0, 0, 8), 1))
+            .draw(&mut canvas)
+            .ok();
+
+        ticker.draw(&mut canvas).ok();
+
+        POWER.apply(frame);
+
+        if cx.shared.ws.show(frame.iter().cloned()).is_err() {
+            rprintln!("frame dropped");
+        }
+    }
+
+    #[task(binds = DMA1_CHANNEL5, shared = [ws])]
+    fn dma1_channel5(cx: dma1_channel5::Context) {
+        cx.shared.ws.complete();
+    }
+}
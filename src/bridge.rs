@@ -0,0 +1,282 @@
+//
+// TIM1 complementary PWM for half-bridge and motor driver boards
+//
+// Three legs on CH1/CH1N..CH3/CH3N with hardware dead-time insertion and
+// BKIN emergency shutdown. Each leg is switched only as a complementary pair,
+// so high and low side can not be enabled at the same time.
+//
+// Pins (no remap):
+//  CH1  PA8    CH1N  PB13
+//  CH2  PA9    CH2N  PB14
+//  CH3  PA10   CH3N  PB15
+//  BKIN PB12
+//
+
+use hal::gpio::gpioa::{PA10, PA8, PA9};
+use hal::gpio::gpiob::{PB12, PB13, PB14, PB15};
+use hal::gpio::{Alternate, Input, PushPull};
+use hal::rcc::{Clocks, Enable, Reset, APB2};
+use hal::stm32::TIM1;
+use hal::time::Hertz;
+use stm32f1xx_hal as hal;
+
+pub struct Pins<BKIN> {
+    pub ch1: PA8<Alternate<PushPull>>,
+    pub ch2: PA9<Alternate<PushPull>>,
+    pub ch3: PA10<Alternate<PushPull>>,
+    pub ch1n: PB13<Alternate<PushPull>>,
+    pub ch2n: PB14<Alternate<PushPull>>,
+    pub ch3n: PB15<Alternate<PushPull>>,
+    pub bkin: PB12<Input<BKIN>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Leg {
+    Ch1,
+    Ch2,
+    Ch3,
+}
+
+/// Output state of a leg
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LegMode {
+    /// both switches off
+    Off,
+    /// complementary PWM with dead-time
+    Pwm,
+    /// low side switch on
+    Low,
+    /// high side switch on
+    High,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    /// frequency can not be reached with timer clock
+    Frequency,
+    /// dead-time is longer than 1008 timer clock cycles
+    DeadTime,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    pub freq: Hertz,
+    pub dead_time_ns: u32,
+    /// center-aligned counting: symmetric PWM for motor control
+    pub center_aligned: bool,
+    /// break input is active high, otherwise active low
+    pub break_high: bool,
+}
+
+/// DTG field encoding for dead-time in timer clock ticks
+pub fn dead_time_bits(ticks: u32) -> Option<u8> {
+    match ticks {
+        0..=127 => Some(ticks as u8),
+        128..=254 => Some(0x80 | ((ticks / 2 - 64) as u8)),
+        255..=504 => Some(0xc0 | ((ticks / 8).max(32) - 32) as u8),
+        505..=1008 => Some(0xe0 | ((ticks / 16).max(32) - 32) as u8),
+        _ => None,
+    }
+}
+
+/// Prescaler and auto-reload values for PWM period in timer clock ticks
+fn period(ticks: u32, center_aligned: bool) -> Option<(u16, u16)> {
+    // up counting period is ARR + 1 counter ticks, up-down counting 2 * ARR
+    let (counts, offset) = if center_aligned {
+        (ticks / 2, 0)
+    } else {
+        (ticks, 1)
+    };
+
+    if counts < 2 {
+        return None;
+    }
+
+    let psc = (counts - 1) / (0xffff + offset);
+    let arr = counts / (psc + 1) - offset;
+
+    if psc > 0xffff {
+        return None;
+    }
+
+    Some((psc as u16, arr as u16))
+}
+
+pub struct Bridge<BKIN> {
+    tim: TIM1,
+    pins: Pins<BKIN>,
+    max_duty: u16,
+}
+
+impl<BKIN> Bridge<BKIN> {
+    /// Configure TIM1: all legs off, main output disabled
+    pub fn tim1(
+        tim: TIM1,
+        pins: Pins<BKIN>,
+        clocks: &Clocks,
+        apb: &mut APB2,
+        config: &Config,
+    ) -> Result<Self, Error> {
+        let clk = clocks.pclk2_tim().0;
+
+        let (psc, arr) =
+            period(clk / config.freq.0, config.center_aligned).ok_or(Error::Frequency)?;
+
+        // dead-time generator runs at timer clock: CKD = 0
+        let dt_ticks = (clk as u64 * config.dead_time_ns as u64 / 1_000_000_000) as u32;
+        let dtg = dead_time_bits(dt_ticks).ok_or(Error::DeadTime)?;
+
+        TIM1::enable(apb);
+        TIM1::reset(apb);
+
+        tim.psc.write(|w| w.psc().bits(psc));
+        tim.arr.write(|w| w.arr().bits(arr));
+
+        // PWM mode 1 with preload, start with forced inactive references
+        tim.ccmr1_output()
+            .write(|w| w.oc1pe().set_bit().oc2pe().set_bit());
+        tim.ccmr2_output().write(|w| w.oc3pe().set_bit());
+
+        // all outputs active high, both switches of every leg disabled
+        tim.ccer.reset();
+
+        // idle states low: both switches off when MOE is cleared by break
+        tim.cr2.reset();
+
+        tim.bdtr.write(|w| {
+            unsafe { w.dtg().bits(dtg) };
+            w.bke()
+                .set_bit()
+                .bkp()
+                .bit(config.break_high)
+                .ossr()
+                .set_bit()
+                .ossi()
+                .set_bit()
+                .aoe()
+                .clear_bit()
+                .moe()
+                .clear_bit()
+        });
+
+        if config.center_aligned {
+            tim.cr1
+                .write(|w| w.cms().center_aligned1().arpe().set_bit());
+        } else {
+            tim.cr1.write(|w| w.arpe().set_bit());
+        }
+
+        // load prescaler and preload registers, do not report it as break
+        tim.egr.write(|w| w.ug().set_bit());
+        tim.sr.reset();
+
+        tim.cr1.modify(|_, w| w.cen().set_bit());
+
+        let mut bridge = Bridge {
+            tim,
+            pins,
+            max_duty: arr,
+        };
+
+        bridge.set_mode(Leg::Ch1, LegMode::Off);
+        bridge.set_mode(Leg::Ch2, LegMode::Off);
+        bridge.set_mode(Leg::Ch3, LegMode::Off);
+
+        Ok(bridge)
+    }
+
+    pub fn max_duty(&self) -> u16 {
+        self.max_duty
+    }
+
+    /// High side on-time in PWM mode, clamped to max duty
+    pub fn set_duty(&mut self, leg: Leg, duty: u16) {
+        let duty = duty.min(self.max_duty);
+
+        match leg {
+            Leg::Ch1 => self.tim.ccr1.write(|w| w.ccr().bits(duty)),
+            Leg::Ch2 => self.tim.ccr2.write(|w| w.ccr().bits(duty)),
+            Leg::Ch3 => self.tim.ccr3.write(|w| w.ccr().bits(duty)),
+        }
+    }
+
+    /// Switch leg mode: high and low side outputs are always changed together
+    pub fn set_mode(&mut self, leg: Leg, mode: LegMode) {
+        let tim = &self.tim;
+
+        // disable outputs first, then change reference mode
+        if mode == LegMode::Off {
+            match leg {
+                Leg::Ch1 => tim
+                    .ccer
+                    .modify(|_, w| w.cc1e().clear_bit().cc1ne().clear_bit()),
+                Leg::Ch2 => tim
+                    .ccer
+                    .modify(|_, w| w.cc2e().clear_bit().cc2ne().clear_bit()),
+                Leg::Ch3 => tim
+                    .ccer
+                    .modify(|_, w| w.cc3e().clear_bit().cc3ne().clear_bit()),
+            }
+        }
+
+        match (leg, mode) {
+            (Leg::Ch1, LegMode::Pwm) => tim.ccmr1_output().modify(|_, w| w.oc1m().pwm_mode1()),
+            (Leg::Ch1, LegMode::High) => tim.ccmr1_output().modify(|_, w| w.oc1m().force_active()),
+            (Leg::Ch1, _) => tim.ccmr1_output().modify(|_, w| w.oc1m().force_inactive()),
+            (Leg::Ch2, LegMode::Pwm) => tim.ccmr1_output().modify(|_, w| w.oc2m().pwm_mode1()),
+            (Leg::Ch2, LegMode::High) => tim.ccmr1_output().modify(|_, w| w.oc2m().force_active()),
+            (Leg::Ch2, _) => tim.ccmr1_output().modify(|_, w| w.oc2m().force_inactive()),
+            (Leg::Ch3, LegMode::Pwm) => tim.ccmr2_output().modify(|_, w| w.oc3m().pwm_mode1()),
+            (Leg::Ch3, LegMode::High) => tim.ccmr2_output().modify(|_, w| w.oc3m().force_active()),
+            (Leg::Ch3, _) => tim.ccmr2_output().modify(|_, w| w.oc3m().force_inactive()),
+        }
+
+        // complementary pair with dead-time: never both switches on
+        if mode != LegMode::Off {
+            match leg {
+                Leg::Ch1 => tim.ccer.modify(|_, w| w.cc1e().set_bit().cc1ne().set_bit()),
+                Leg::Ch2 => tim.ccer.modify(|_, w| w.cc2e().set_bit().cc2ne().set_bit()),
+                Leg::Ch3 => tim.ccer.modify(|_, w| w.cc3e().set_bit().cc3ne().set_bit()),
+            }
+        }
+    }
+
+    /// Set main output enable: refused while break input is active
+    pub fn enable_outputs(&mut self) -> bool {
+        self.tim.sr.modify(|_, w| w.bif().clear_bit());
+        self.tim.bdtr.modify(|_, w| w.moe().set_bit());
+        self.tim.bdtr.read().moe().bit_is_set()
+    }
+
+    /// Clear main output enable: all switches off
+    pub fn disable_outputs(&mut self) {
+        self.tim.bdtr.modify(|_, w| w.moe().clear_bit());
+    }
+
+    pub fn outputs_enabled(&self) -> bool {
+        self.tim.bdtr.read().moe().bit_is_set()
+    }
+
+    /// Break event was detected: outputs were shut down by hardware
+    pub fn break_detected(&self) -> bool {
+        self.tim.sr.read().bif().bit_is_set()
+    }
+
+    /// Break interrupt: TIM1_BRK
+    pub fn listen_break(&mut self) {
+        self.tim.dier.modify(|_, w| w.bie().set_bit());
+    }
+
+    pub fn clear_break_interrupt(&mut self) {
+        self.tim.sr.modify(|_, w| w.bif().clear_bit());
+    }
+
+    /// Stop timer and give back peripheral and pins
+    pub fn release(self) -> (TIM1, Pins<BKIN>) {
+        self.tim.bdtr.modify(|_, w| w.moe().clear_bit());
+        self.tim.ccer.reset();
+        self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+
+        (self.tim, self.pins)
+    }
+}
@@ -0,0 +1,370 @@
+//
+// PWM input measurement on TIM2/TIM3/TIM4
+//
+// Channel pair in PWM input configuration: TI1 rising edge captures period
+// into CCR1 and resets the counter, falling edge captures high time into CCR2.
+// Counter overflows are counted to measure slow signals, prescaler is adjusted
+// automatically to keep period resolution high.
+//
+// Edge capture mode reports durations between successive edges for pulse
+// train decoders like IR remote protocols.
+//
+// TI1 pins without remap: TIM2 PA0, TIM3 PA6, TIM4 PB6.
+//
+
+use core::ops::Deref;
+use hal::rcc::{Clocks, Enable, Reset, APB1};
+use hal::stm32::tim2::RegisterBlock;
+use stm32f1xx_hal as hal;
+
+pub use blue_pill_logic::capture::{
+    auto_range, Average, Config, Interval, Measurement, AVERAGE_SIZE,
+};
+
+// 16-bit counter range
+const MAX_COUNTS: u64 = 0xffff;
+
+pub struct PwmInput<TIM, PINS> {
+    tim: TIM,
+    pins: PINS,
+    clk: u32,
+    psc: u16,
+    timeout: u64,
+    // overflows since last rising edge and at last falling edge
+    overflows: u32,
+    high_overflows: u32,
+    // measurements to drop after prescaler change
+    skip: u8,
+    average: Average,
+}
+
+impl<TIM, PINS> PwmInput<TIM, PINS>
+where
+    TIM: Deref<Target = RegisterBlock> + Enable<Bus = APB1> + Reset,
+{
+    /// Configure timer for PWM input, TI1 pin has to be configured by caller
+    pub fn new(tim: TIM, pins: PINS, clocks: &Clocks, apb: &mut APB1, config: &Config) -> Self {
+        TIM::enable(apb);
+        TIM::reset(apb);
+
+        let clk = clocks.pclk1_tim().0;
+        let filter = config.filter & 0xf;
+
+        // both channels capture TI1: CC1 on rising edge, CC2 on falling edge
+        tim.ccmr1_input().write(|w| {
+            unsafe { w.ic1f().bits(filter).ic2f().bits(filter) };
+            w.cc1s().ti1().cc2s().ti1()
+        });
+
+        tim.ccer.write(|w| {
+            w.cc1p()
+                .clear_bit()
+                .cc2p()
+                .set_bit()
+                .cc1e()
+                .set_bit()
+                .cc2e()
+                .set_bit()
+        });
+
+        // rising edge resets the counter
+        tim.smcr.write(|w| w.ts().ti1fp1().sms().reset_mode());
+
+        tim.arr.write(|w| w.arr().bits(MAX_COUNTS as u16));
+        tim.psc.write(|w| w.psc().bits(0));
+
+        // only counter overflow sets update flag, not reset by slave mode
+        tim.cr1.write(|w| w.urs().set_bit());
+        tim.egr.write(|w| w.ug().set_bit());
+        tim.sr.reset();
+
+        tim.dier
+            .write(|w| w.uie().set_bit().cc1ie().set_bit().cc2ie().set_bit());
+        tim.cr1.modify(|_, w| w.cen().set_bit());
+
+        PwmInput {
+            tim,
+            pins,
+            clk,
+            psc: 0,
+            timeout: clk as u64 * config.timeout_ms as u64 / 1000,
+            overflows: 0,
+            high_overflows: 0,
+            skip: 1,
+            average: Average::new(config.average),
+        }
+    }
+
+    /// Handle timer interrupt, returns new single period measurement
+    pub fn on_interrupt(&mut self) -> Option<Measurement> {
+        let sr = self.tim.sr.read();
+        let mut result = None;
+
+        // overflow flag pending together with capture means it was before capture:
+        // counter is reset on capture and needs full range to overflow again
+        if sr.uif().bit_is_set() {
+            self.tim.sr.modify(|_, w| w.uif().clear_bit());
+            self.overflows += 1;
+
+            if self.ticks(self.overflows, 0) > self.timeout {
+                self.no_signal();
+            }
+        }
+
+        if sr.cc2if().bit_is_set() {
+            // reading CCR2 clears CC2IF
+            let _ = self.tim.ccr2.read();
+            self.high_overflows = self.overflows;
+        }
+
+        if sr.cc1if().bit_is_set() {
+            let period = self.tim.ccr1.read().ccr().bits() as u32;
+            let high = self.tim.ccr2.read().ccr().bits() as u32;
+
+            let m = Measurement {
+                period: self.ticks(self.overflows, period),
+                high: self.ticks(self.high_overflows, high),
+                clk: self.clk,
+            };
+
+            self.overflows = 0;
+            self.high_overflows = 0;
+
+            if self.skip > 0 {
+                self.skip -= 1;
+            } else {
+                self.average.push(&m);
+                result = Some(m);
+
+                if let Some(psc) = auto_range(m.period, self.psc) {
+                    self.set_prescaler(psc);
+                }
+            }
+        }
+
+        // lost captures mean the signal is too fast for current prescaler
+        if sr.cc1of().bit_is_set() || sr.cc2of().bit_is_set() {
+            self.tim
+                .sr
+                .modify(|_, w| w.cc1of().clear_bit().cc2of().clear_bit());
+        }
+
+        result
+    }
+
+    /// Averaged measurement, None when there is no signal
+    pub fn measurement(&self) -> Option<Measurement> {
+        self.average.get(self.clk)
+    }
+
+    pub fn prescaler(&self) -> u16 {
+        self.psc
+    }
+
+    pub fn release(self) -> (TIM, PINS) {
+        self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+        self.tim.dier.reset();
+
+        (self.tim, self.pins)
+    }
+
+    // period in timer input clock ticks
+    fn ticks(&self, overflows: u32, counts: u32) -> u64 {
+        (overflows as u64 * (MAX_COUNTS + 1) + counts as u64) * (self.psc as u64 + 1)
+    }
+
+    fn set_prescaler(&mut self, psc: u16) {
+        // new value is loaded on the next edge: drop the period in between
+        self.tim.psc.write(|w| w.psc().bits(psc));
+        self.psc = psc;
+        self.skip = 2;
+    }
+
+    fn no_signal(&mut self) {
+        self.overflows = 0;
+        self.high_overflows = 0;
+        self.average.clear();
+
+        // next edges may come very slowly: overflow once per timeout
+        let psc = (self.timeout / (MAX_COUNTS + 1)).min(0xffff) as u16;
+
+        if self.psc != psc {
+            self.set_prescaler(psc);
+        }
+    }
+}
+
+/// Edge timestamps on TI1 with 1us resolution
+///
+/// CC1 captures falling and CC2 rising edges of the free running counter.
+/// Intervals longer than `idle_us` are reported once, on counter overflow,
+/// so that decoders see the end of a frame without waiting for the next edge.
+pub struct EdgeCapture<TIM, PINS> {
+    tim: TIM,
+    pins: PINS,
+    idle_us: u32,
+    last: u16,
+    wraps: u32,
+    // line level after the last edge
+    high: bool,
+    idle_reported: bool,
+}
+
+impl<TIM, PINS> EdgeCapture<TIM, PINS>
+where
+    TIM: Deref<Target = RegisterBlock> + Enable<Bus = APB1> + Reset,
+{
+    /// Configure timer for edge capture, TI1 pin has to be configured by caller
+    pub fn new(
+        tim: TIM,
+        pins: PINS,
+        clocks: &Clocks,
+        apb: &mut APB1,
+        filter: u8,
+        idle_us: u32,
+    ) -> Self {
+        TIM::enable(apb);
+        TIM::reset(apb);
+
+        let psc = (clocks.pclk1_tim().0 / 1_000_000).max(1) - 1;
+        let filter = filter & 0xf;
+
+        tim.ccmr1_input().write(|w| {
+            unsafe { w.ic1f().bits(filter).ic2f().bits(filter) };
+            w.cc1s().ti1().cc2s().ti1()
+        });
+
+        tim.ccer.write(|w| {
+            w.cc1p()
+                .set_bit()
+                .cc2p()
+                .clear_bit()
+                .cc1e()
+                .set_bit()
+                .cc2e()
+                .set_bit()
+        });
+
+        tim.arr.write(|w| w.arr().bits(MAX_COUNTS as u16));
+        tim.psc.write(|w| w.psc().bits(psc as u16));
+        tim.egr.write(|w| w.ug().set_bit());
+        tim.sr.reset();
+
+        tim.dier
+            .write(|w| w.uie().set_bit().cc1ie().set_bit().cc2ie().set_bit());
+        tim.cr1.modify(|_, w| w.cen().set_bit());
+
+        EdgeCapture {
+            tim,
+            pins,
+            idle_us,
+            last: 0,
+            wraps: 0,
+            high: true,
+            // nothing to report before the first edge
+            idle_reported: true,
+        }
+    }
+
+    /// Handle timer interrupt: finished intervals are passed to the callback
+    pub fn on_interrupt<F>(&mut self, mut f: F)
+    where
+        F: FnMut(Interval),
+    {
+        let sr = self.tim.sr.read();
+
+        // captures as (timestamp, level before edge)
+        let fall = if sr.cc1if().bit_is_set() {
+            Some((self.tim.ccr1.read().ccr().bits(), true))
+        } else {
+            None
+        };
+
+        let rise = if sr.cc2if().bit_is_set() {
+            Some((self.tim.ccr2.read().ccr().bits(), false))
+        } else {
+            None
+        };
+
+        // order captures by time since last edge
+        let (first, second) = match (fall, rise) {
+            (Some(a), Some(b)) if b.0.wrapping_sub(self.last) < a.0.wrapping_sub(self.last) => {
+                (Some(b), Some(a))
+            }
+            (a, b) => (a.or(b), a.and(b)),
+        };
+
+        // capture close to the end of counter range happened before overflow
+        let overflow = sr.uif().bit_is_set();
+        let early = first.map_or(false, |(t, _)| t > 0x8000);
+
+        if overflow {
+            self.tim.sr.modify(|_, w| w.uif().clear_bit());
+        }
+
+        if overflow && !early {
+            self.wrap(&mut f);
+        }
+
+        for (t, high) in first.iter().chain(second.iter()) {
+            self.edge(*t, *high, &mut f);
+        }
+
+        if overflow && early {
+            self.wrap(&mut f);
+        }
+
+        if sr.cc1of().bit_is_set() || sr.cc2of().bit_is_set() {
+            self.tim
+                .sr
+                .modify(|_, w| w.cc1of().clear_bit().cc2of().clear_bit());
+        }
+    }
+
+    pub fn release(self) -> (TIM, PINS) {
+        self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+        self.tim.dier.reset();
+
+        (self.tim, self.pins)
+    }
+
+    fn elapsed(&self, t: u16) -> u32 {
+        self.wraps
+            .saturating_mul(MAX_COUNTS as u32 + 1)
+            .saturating_add(t as u32)
+            .wrapping_sub(self.last as u32)
+    }
+
+    fn edge<F>(&mut self, t: u16, high: bool, f: &mut F)
+    where
+        F: FnMut(Interval),
+    {
+        let us = self.elapsed(t);
+
+        if !self.idle_reported {
+            f(Interval { high, us });
+        }
+
+        self.last = t;
+        self.wraps = 0;
+        self.high = !high;
+        self.idle_reported = false;
+    }
+
+    fn wrap<F>(&mut self, f: &mut F)
+    where
+        F: FnMut(Interval),
+    {
+        self.wraps = self.wraps.saturating_add(1);
+
+        let us = self.elapsed(0);
+
+        if !self.idle_reported && us >= self.idle_us {
+            f(Interval {
+                high: self.high,
+                us,
+            });
+            self.idle_reported = true;
+        }
+    }
+}
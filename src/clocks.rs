@@ -0,0 +1,102 @@
+//
+// Runtime clock report and Clock Security System: on HSE failure hardware
+// switches SYSCLK to HSI, SYSCLK can then be raised back to 64MHz using PLL
+//
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use hal::stm32;
+use stm32f1xx_hal as hal;
+
+pub use blue_pill_logic::clocks::{
+    error_permille, spi_baud, uart_baud, Bus, Domain, Error, Report, Source, ADCCLK_MAX_HZ, HSI_HZ,
+    PCLK1_MAX_HZ, PCLK2_MAX_HZ, SYSCLK_MAX_HZ, UART_MAX_ERROR_PERMILLE, USB_HZ,
+};
+
+// RCC_CFGR system clock switch status field
+const CFGR_SWS_SHIFT: u32 = 2;
+
+// PLL x16 from HSI/2: 64MHz is the max SYSCLK without crystal
+const HSI_PLLMUL: u8 = 0b1110;
+
+static HSE_FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Read running clock tree from RCC: HSE frequency is not known to hardware
+pub fn read(hse_hz: u32) -> Report {
+    // RCC is constrained by HAL, registers are only read here
+    let rcc = unsafe { &*stm32::RCC::ptr() };
+
+    Report::from_registers(
+        rcc.cfgr.read().bits(),
+        rcc.cr.read().pllrdy().bit_is_set(),
+        hse_hz,
+    )
+}
+
+/// Enable Clock Security System: call after HSE is running
+pub fn enable_css() {
+    let rcc = unsafe { &*stm32::RCC::ptr() };
+
+    rcc.cr.modify(|_, w| w.csson().set_bit());
+}
+
+/// Call from NMI handler: returns true if NMI was caused by HSE failure
+pub fn on_css_nmi() -> bool {
+    let rcc = unsafe { &*stm32::RCC::ptr() };
+
+    if rcc.cir.read().cssf().bit_is_clear() {
+        return false;
+    }
+
+    // NMI is taken again immediately unless CSSF is cleared
+    rcc.cir.modify(|_, w| w.cssc().set_bit());
+    HSE_FAILED.store(true, Ordering::Relaxed);
+
+    true
+}
+
+/// HSE failed and SYSCLK was switched to HSI by hardware
+pub fn hse_failed() -> bool {
+    HSE_FAILED.load(Ordering::Relaxed)
+}
+
+/// Run SYSCLK from PLL fed by HSI/2 after HSE failure: 64MHz.
+/// AHB and APB2 prescalers are kept, APB1 and ADC prescalers are
+/// raised if needed to stay within limits: check the new report.
+pub fn fallback_to_hsi_pll() -> Report {
+    // FLASH and RCC are constrained by HAL, clocks are already frozen
+    let flash = unsafe { &*stm32::FLASH::ptr() };
+    let rcc = unsafe { &*stm32::RCC::ptr() };
+
+    // PLL is stopped by CSS, configuration can be changed only when it is off
+    rcc.cr.modify(|_, w| w.pllon().clear_bit());
+    while rcc.cr.read().pllrdy().bit_is_set() {}
+
+    rcc.cfgr
+        .modify(|_, w| unsafe { w.pllsrc().clear_bit().pllmul().bits(HSI_PLLMUL) });
+
+    rcc.cr.modify(|_, w| w.pllon().set_bit());
+    while rcc.cr.read().pllrdy().bit_is_clear() {}
+
+    // tree as it is going to be after switching SYSCLK to PLL
+    let cfgr = (rcc.cfgr.read().bits() & !(0b11 << CFGR_SWS_SHIFT)) | (0b10 << CFGR_SWS_SHIFT);
+    let next = Report::from_registers(cfgr, true, 0);
+
+    if next.pclk1 > PCLK1_MAX_HZ {
+        rcc.cfgr.modify(|_, w| unsafe { w.ppre1().bits(0b100) });
+    }
+
+    // slower ADC clock set by application is kept
+    let adcpre = (0..4u8)
+        .find(|n| next.pclk2 / (2 * (*n as u32 + 1)) <= ADCCLK_MAX_HZ)
+        .unwrap_or(3)
+        .max(rcc.cfgr.read().adcpre().bits());
+    rcc.cfgr.modify(|_, w| unsafe { w.adcpre().bits(adcpre) });
+
+    // 2 wait states are needed above 48MHz
+    flash.acr.modify(|_, w| w.latency().ws2());
+
+    rcc.cfgr.modify(|_, w| unsafe { w.sw().bits(0b10) });
+    while rcc.cfgr.read().sws().bits() != 0b10 {}
+
+    read(0)
+}
@@ -0,0 +1,299 @@
+//
+// Crash recorder: HardFault and panic records persistent across reset
+//
+// Record is kept in .uninit RAM section provided by cortex-m-rt: it is not
+// zeroed or initialized at startup, so it survives system reset, but not
+// power loss. Random RAM content after power-on is rejected by magic value
+// and checksum. Backup registers are not used: medium density devices
+// have only 20 bytes there.
+//
+// Application calls record_fault from its HardFault handler and record_panic
+// from its panic handler, then resets the chip. Stored record is taken on the
+// next boot and printed with the configured log backend.
+//
+
+use core::fmt::{self, Write};
+use core::mem::MaybeUninit;
+use core::panic::PanicInfo;
+use core::ptr;
+use cortex_m::peripheral::SCB;
+use cortex_m_rt::ExceptionFrame;
+
+/// Number of stack words saved above the faulting stack pointer
+pub const STACK_WORDS: usize = 16;
+
+/// Max length of panic message, longer messages are truncated
+pub const MSG_SIZE: usize = 128;
+
+const MAGIC: u32 = 0xdead_c0de;
+
+const RAM_START: u32 = 0x2000_0000;
+
+// end of RAM: initial stack pointer defined by cortex-m-rt linker script
+extern "C" {
+    static _stack_start: u32;
+}
+
+#[link_section = ".uninit.crash"]
+static mut RECORD: MaybeUninit<Record> = MaybeUninit::uninit();
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    HardFault,
+    Panic,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Record {
+    magic: u32,
+    kind: u32,
+    /// stacked r0, r1, r2, r3, r12, lr, pc, xpsr: valid for HardFault only
+    pub frame: [u32; 8],
+    pub cfsr: u32,
+    pub hfsr: u32,
+    pub mmfar: u32,
+    pub bfar: u32,
+    /// stack pointer at the time of crash
+    pub sp: u32,
+    pub stack: [u32; STACK_WORDS],
+    /// number of valid words in stack snapshot
+    pub stack_len: u32,
+    msg_len: u32,
+    msg: [u8; MSG_SIZE],
+    checksum: u32,
+}
+
+impl Record {
+    pub fn kind(&self) -> Kind {
+        if self.kind == Kind::HardFault as u32 {
+            Kind::HardFault
+        } else {
+            Kind::Panic
+        }
+    }
+
+    pub fn pc(&self) -> u32 {
+        self.frame[6]
+    }
+
+    pub fn lr(&self) -> u32 {
+        self.frame[5]
+    }
+
+    /// Panic message, empty for HardFault
+    pub fn message(&self) -> &str {
+        let msg = &self.msg[..(self.msg_len as usize).min(MSG_SIZE)];
+
+        // truncation may split a multibyte character
+        match core::str::from_utf8(msg) {
+            Ok(s) => s,
+            Err(e) => core::str::from_utf8(&msg[..e.valid_up_to()]).unwrap_or(""),
+        }
+    }
+
+    fn compute_checksum(&self) -> u32 {
+        let words = [
+            self.magic,
+            self.kind,
+            self.cfsr,
+            self.hfsr,
+            self.mmfar,
+            self.bfar,
+            self.sp,
+            self.stack_len,
+            self.msg_len,
+        ];
+
+        words
+            .iter()
+            .chain(self.frame.iter())
+            .chain(self.stack.iter())
+            .copied()
+            .chain(self.msg.iter().map(|b| *b as u32))
+            .fold(0x811c_9dc5u32, |h, w| (h ^ w).wrapping_mul(0x0100_0193))
+    }
+
+    const fn empty(kind: Kind) -> Self {
+        Record {
+            magic: MAGIC,
+            kind: kind as u32,
+            frame: [0; 8],
+            cfsr: 0,
+            hfsr: 0,
+            mmfar: 0,
+            bfar: 0,
+            sp: 0,
+            stack: [0; STACK_WORDS],
+            stack_len: 0,
+            msg_len: 0,
+            msg: [0; MSG_SIZE],
+            checksum: 0,
+        }
+    }
+
+    fn save_fault_status(&mut self) {
+        let scb = unsafe { &*SCB::ptr() };
+
+        self.cfsr = scb.cfsr.read();
+        self.hfsr = scb.hfsr.read();
+        self.mmfar = scb.mmfar.read();
+        self.bfar = scb.bfar.read();
+    }
+
+    // copy stack words above sp, without reading past the end of RAM
+    fn save_stack(&mut self, sp: u32) {
+        let end = unsafe { &_stack_start as *const u32 as u32 };
+
+        self.sp = sp;
+        self.stack_len = 0;
+
+        if sp & 3 != 0 || sp < RAM_START || sp >= end {
+            return;
+        }
+
+        let n = (((end - sp) / 4) as usize).min(STACK_WORDS);
+        for (i, w) in self.stack.iter_mut().take(n).enumerate() {
+            *w = unsafe { ptr::read_volatile((sp as *const u32).add(i)) };
+        }
+
+        self.stack_len = n as u32;
+    }
+
+    fn store(mut self) {
+        self.checksum = self.compute_checksum();
+
+        unsafe { ptr::write_volatile(ptr::addr_of_mut!(RECORD) as *mut Record, self) };
+    }
+}
+
+/// Save HardFault record: stacked frame, fault status and stack snapshot
+pub fn record_fault(ef: &ExceptionFrame) {
+    let mut rec = Record::empty(Kind::HardFault);
+
+    rec.frame = [ef.r0, ef.r1, ef.r2, ef.r3, ef.r12, ef.lr, ef.pc, ef.xpsr];
+    rec.save_fault_status();
+
+    // stack of the faulting code starts right above exception frame
+    let sp = ef as *const ExceptionFrame as u32 + core::mem::size_of::<ExceptionFrame>() as u32;
+    rec.save_stack(sp);
+    rec.store();
+}
+
+/// Save panic record: message, fault status and stack snapshot
+pub fn record_panic(info: &PanicInfo) {
+    let mut rec = Record::empty(Kind::Panic);
+
+    let mut w = MsgWriter {
+        buf: &mut rec.msg,
+        len: 0,
+    };
+    write!(w, "{}", info).ok();
+    rec.msg_len = w.len as u32;
+
+    rec.save_fault_status();
+    rec.save_stack(cortex_m::register::msp::read());
+    rec.store();
+}
+
+/// Take stored record: call once at boot, record is invalidated
+pub fn take() -> Option<Record> {
+    let rec = unsafe {
+        let p = ptr::addr_of_mut!(RECORD) as *mut Record;
+        let rec = ptr::read_volatile(p);
+        ptr::write_volatile(ptr::addr_of_mut!((*p).magic), 0);
+        rec
+    };
+
+    if rec.magic != MAGIC || rec.checksum != rec.compute_checksum() {
+        return None;
+    }
+
+    Some(rec)
+}
+
+// names of CFSR bits: MMFSR, BFSR and UFSR
+const CFSR_BITS: [(u32, &str); 17] = [
+    (1 << 0, "IACCVIOL"),
+    (1 << 1, "DACCVIOL"),
+    (1 << 3, "MUNSTKERR"),
+    (1 << 4, "MSTKERR"),
+    (1 << 7, "MMARVALID"),
+    (1 << 8, "IBUSERR"),
+    (1 << 9, "PRECISERR"),
+    (1 << 10, "IMPRECISERR"),
+    (1 << 11, "UNSTKERR"),
+    (1 << 12, "STKERR"),
+    (1 << 15, "BFARVALID"),
+    (1 << 16, "UNDEFINSTR"),
+    (1 << 17, "INVSTATE"),
+    (1 << 18, "INVPC"),
+    (1 << 19, "NOCP"),
+    (1 << 24, "UNALIGNED"),
+    (1 << 25, "DIVBYZERO"),
+];
+
+// HFSR: fault escalated from configurable fault or vector table read error
+const HFSR_FORCED: u32 = 1 << 30;
+const HFSR_VECTTBL: u32 = 1 << 1;
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind() {
+            Kind::HardFault => {
+                let r = &self.frame;
+                writeln!(f, "HardFault at pc {:#010x} lr {:#010x}", r[6], r[5])?;
+                writeln!(
+                    f,
+                    "r0 {:#010x} r1 {:#010x} r2 {:#010x} r3 {:#010x} r12 {:#010x} xpsr {:#010x}",
+                    r[0], r[1], r[2], r[3], r[4], r[7]
+                )?;
+            }
+            Kind::Panic => writeln!(f, "{}", self.message())?,
+        }
+
+        write!(f, "cfsr {:#010x} hfsr {:#010x}", self.cfsr, self.hfsr)?;
+        for (bit, name) in CFSR_BITS.iter() {
+            if self.cfsr & bit != 0 {
+                write!(f, " {}", name)?;
+            }
+        }
+        if self.hfsr & HFSR_FORCED != 0 {
+            write!(f, " FORCED")?;
+        }
+        if self.hfsr & HFSR_VECTTBL != 0 {
+            write!(f, " VECTTBL")?;
+        }
+        writeln!(f)?;
+
+        if self.cfsr & (1 << 7) != 0 {
+            writeln!(f, "mmfar {:#010x}", self.mmfar)?;
+        }
+        if self.cfsr & (1 << 15) != 0 {
+            writeln!(f, "bfar {:#010x}", self.bfar)?;
+        }
+
+        write!(f, "stack at {:#010x}:", self.sp)?;
+        for w in self.stack.iter().take(self.stack_len as usize) {
+            write!(f, " {:08x}", w)?;
+        }
+
+        Ok(())
+    }
+}
+
+// truncating writer for panic message
+struct MsgWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Write for MsgWriter<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let n = s.len().min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+
+        Ok(())
+    }
+}
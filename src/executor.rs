@@ -0,0 +1,244 @@
+//
+// Minimal async executor for bare metal applications
+//
+// block_on polls a single future and sleeps with WFE while it is pending.
+// Concurrency inside that future is provided by join and select combinators,
+// so the whole application is one future tree polled from main.
+//
+// Interrupt driven futures register their waker for an interrupt line and
+// unmask it. Interrupt handler only calls on_interrupt: the line is masked
+// again and the waker is woken, status flags are checked and cleared by the
+// future itself when it is polled. This way handlers do not need access to
+// peripherals and level triggered sources do not re-enter the handler.
+//
+// Each interrupt line keeps one waker: with block_on all wakers are the same,
+// so futures waiting on the same line in different branches of join are fine.
+//
+
+use core::cell::RefCell;
+use core::future::Future;
+use core::mem;
+use core::pin::Pin;
+use core::ptr;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use cortex_m::interrupt::{InterruptNumber, Mutex};
+use cortex_m::peripheral::NVIC;
+
+/// Number of external interrupt lines in STM32F1 family
+pub const MAX_IRQS: usize = 68;
+
+type WakerCell = Mutex<RefCell<Option<Waker>>>;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_WAKER: WakerCell = Mutex::new(RefCell::new(None));
+
+static WAKERS: [WakerCell; MAX_IRQS] = [NO_WAKER; MAX_IRQS];
+
+/// Register waker to be woken by interrupt and unmask interrupt line
+pub fn register<I: InterruptNumber>(irq: I, waker: &Waker) {
+    let n = irq.number() as usize;
+
+    cortex_m::interrupt::free(|cs| {
+        let mut cell = WAKERS[n].borrow(cs).borrow_mut();
+        match *cell {
+            Some(ref w) if w.will_wake(waker) => {}
+            _ => *cell = Some(waker.clone()),
+        }
+    });
+
+    unsafe { NVIC::unmask(irq) };
+}
+
+/// Call from interrupt handler: masks interrupt line and wakes its waker
+pub fn on_interrupt<I: InterruptNumber>(irq: I) {
+    NVIC::mask(irq);
+
+    let n = irq.number() as usize;
+    let waker = cortex_m::interrupt::free(|cs| WAKERS[n].borrow(cs).borrow_mut().take());
+
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+// waker does not need any state: SEV makes sure that WFE in block_on returns
+static VTABLE: RawWakerVTable =
+    RawWakerVTable::new(waker_clone, waker_wake, waker_wake, waker_drop);
+
+unsafe fn waker_clone(_: *const ()) -> RawWaker {
+    RawWaker::new(ptr::null(), &VTABLE)
+}
+
+unsafe fn waker_wake(_: *const ()) {
+    cortex_m::asm::sev();
+}
+
+unsafe fn waker_drop(_: *const ()) {}
+
+/// Run future to completion, sleeping between wake ups
+pub fn block_on<F: Future>(mut fut: F) -> F::Output {
+    let waker = unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) };
+    let mut cx = Context::from_waker(&waker);
+
+    // future is shadowed and never moved again
+    let mut fut = unsafe { Pin::new_unchecked(&mut fut) };
+
+    loop {
+        if let Poll::Ready(v) = fut.as_mut().poll(&mut cx) {
+            return v;
+        }
+
+        // wake up event is latched: no wake up is lost between poll and WFE
+        cortex_m::asm::wfe();
+    }
+}
+
+/// Future from closure, polled with task context
+pub struct PollFn<F> {
+    f: F,
+}
+
+impl<F> Unpin for PollFn<F> {}
+
+pub fn poll_fn<T, F>(f: F) -> PollFn<F>
+where
+    F: FnMut(&mut Context<'_>) -> Poll<T>,
+{
+    PollFn { f }
+}
+
+impl<T, F> Future for PollFn<F>
+where
+    F: FnMut(&mut Context<'_>) -> Poll<T>,
+{
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        (self.f)(cx)
+    }
+}
+
+/// Let other branches of join run before continuing
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+pub struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+enum MaybeDone<F: Future> {
+    Future(F),
+    Done(F::Output),
+    Gone,
+}
+
+impl<F: Future> MaybeDone<F> {
+    // returns true when output is available
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> bool {
+        // future is dropped in place when it completes, it is never moved
+        let this = unsafe { self.get_unchecked_mut() };
+
+        let v = match this {
+            MaybeDone::Future(f) => match unsafe { Pin::new_unchecked(f) }.poll(cx) {
+                Poll::Ready(v) => v,
+                Poll::Pending => return false,
+            },
+            MaybeDone::Done(_) => return true,
+            MaybeDone::Gone => panic!("output already taken"),
+        };
+
+        *this = MaybeDone::Done(v);
+        true
+    }
+
+    fn take(self: Pin<&mut Self>) -> F::Output {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        match mem::replace(this, MaybeDone::Gone) {
+            MaybeDone::Done(v) => v,
+            _ => panic!("output is not ready"),
+        }
+    }
+}
+
+/// Run two futures concurrently and wait for both of them
+pub fn join<A: Future, B: Future>(a: A, b: B) -> Join<A, B> {
+    Join {
+        a: MaybeDone::Future(a),
+        b: MaybeDone::Future(b),
+    }
+}
+
+pub struct Join<A: Future, B: Future> {
+    a: MaybeDone<A>,
+    b: MaybeDone<B>,
+}
+
+impl<A: Future, B: Future> Future for Join<A, B> {
+    type Output = (A::Output, B::Output);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        let mut a = unsafe { Pin::new_unchecked(&mut this.a) };
+        let mut b = unsafe { Pin::new_unchecked(&mut this.b) };
+
+        let a_done = a.as_mut().poll(cx);
+        let b_done = b.as_mut().poll(cx);
+
+        if a_done && b_done {
+            Poll::Ready((a.take(), b.take()))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Either<A, B> {
+    First(A),
+    Second(B),
+}
+
+/// Run two futures concurrently until one of them completes: the other
+/// one is dropped, first future wins if both are ready
+pub fn select<A: Future, B: Future>(a: A, b: B) -> Select<A, B> {
+    Select { a, b }
+}
+
+pub struct Select<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A: Future, B: Future> Future for Select<A, B> {
+    type Output = Either<A::Output, B::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if let Poll::Ready(v) = unsafe { Pin::new_unchecked(&mut this.a) }.poll(cx) {
+            return Poll::Ready(Either::First(v));
+        }
+
+        if let Poll::Ready(v) = unsafe { Pin::new_unchecked(&mut this.b) }.poll(cx) {
+            return Poll::Ready(Either::Second(v));
+        }
+
+        Poll::Pending
+    }
+}
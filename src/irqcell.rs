@@ -0,0 +1,90 @@
+//
+// Cell for moving peripherals from main into interrupt handlers
+//
+// Replaces the usual Mutex<RefCell<Option<T>>> globals: value is put into
+// a static cell after initialization and either accessed in a critical
+// section by lock, or claimed once by the handler into its own `static mut`
+// local, which cortex-m-rt turns into a safe &mut reference. Claimed value
+// is owned by the handler: no critical section is needed afterwards.
+//
+// Ownership is checked at compile time: value moved into the cell can not
+// be used by main anymore, cell is Sync only for Send values.
+//
+
+use core::cell::RefCell;
+use cortex_m::interrupt::{self, Mutex};
+
+pub struct IrqCell<T> {
+    inner: Mutex<RefCell<Option<T>>>,
+}
+
+impl<T> Default for IrqCell<T> {
+    fn default() -> Self {
+        IrqCell::new()
+    }
+}
+
+impl<T> IrqCell<T> {
+    pub const fn new() -> Self {
+        IrqCell {
+            inner: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    /// Move value into empty cell, value is given back if cell is occupied
+    pub fn put(&self, value: T) -> Result<(), T> {
+        interrupt::free(|cs| {
+            let mut cell = self.inner.borrow(cs).borrow_mut();
+            if cell.is_some() {
+                return Err(value);
+            }
+
+            *cell = Some(value);
+            Ok(())
+        })
+    }
+
+    /// Create value and move it into empty cell in one critical section,
+    /// so that interrupt handler can not see the cell empty in between:
+    /// f is given back without being called if cell is occupied
+    pub fn put_with<F>(&self, f: F) -> Result<(), F>
+    where
+        F: FnOnce() -> T,
+    {
+        interrupt::free(|cs| {
+            let mut cell = self.inner.borrow(cs).borrow_mut();
+            if cell.is_some() {
+                return Err(f);
+            }
+
+            *cell = Some(f());
+            Ok(())
+        })
+    }
+
+    pub fn take(&self) -> Option<T> {
+        interrupt::free(|cs| self.inner.borrow(cs).borrow_mut().take())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        interrupt::free(|cs| self.inner.borrow(cs).borrow().is_none())
+    }
+
+    /// Access value in critical section, None if cell is empty
+    pub fn lock<R, F>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        interrupt::free(|cs| self.inner.borrow(cs).borrow_mut().as_mut().map(f))
+    }
+
+    /// Move value into handler local storage on the first call: returns
+    /// None until value is put into the cell
+    pub fn claim<'a>(&self, local: &'a mut Option<T>) -> Option<&'a mut T> {
+        if local.is_none() {
+            *local = self.take();
+        }
+
+        local.as_mut()
+    }
+}
@@ -0,0 +1,171 @@
+//
+// IR transmitter: carrier from timer PWM channel gated by second timer
+//
+// Carrier (36-40kHz, usually 38kHz) is generated by any PWM channel. Gate
+// timer TIM2/TIM3/TIM4 counts 1us ticks and its update interrupt steps through
+// the pulse table of the frame: carrier duty is switched between zero and
+// about 1/3 of the period. ARR is preloaded, so next pulse duration is loaded
+// by hardware at update event and interrupt latency does not add up.
+//
+// CCR is preloaded as well: carrier is switched at the end of current carrier
+// period, i.e. edges are delayed by up to 26us at 38kHz.
+//
+
+use crate::ir::{Frame, Pulse};
+use core::ops::Deref;
+use embedded_hal::PwmPin;
+use hal::rcc::{Clocks, Enable, Reset, APB1};
+use hal::stm32::tim2::RegisterBlock;
+use stm32f1xx_hal as hal;
+
+/// Carrier frequency for NEC and most other protocols
+pub const CARRIER_HZ: u32 = 38_000;
+
+/// Carrier frequency for RC5 and RC6
+pub const CARRIER_RC5_HZ: u32 = 36_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    /// previous frame is still being sent
+    Busy,
+}
+
+pub struct Transmitter<TIM, P> {
+    tim: TIM,
+    carrier: P,
+    /// carrier duty for marks
+    duty: u16,
+    frame: Frame,
+    pos: usize,
+    busy: bool,
+}
+
+impl<TIM, P> Transmitter<TIM, P>
+where
+    TIM: Deref<Target = RegisterBlock> + Enable<Bus = APB1> + Reset,
+    P: PwmPin<Duty = u16>,
+{
+    /// Carrier PWM should already run at carrier frequency
+    pub fn new(tim: TIM, mut carrier: P, clocks: &Clocks, apb: &mut APB1) -> Self {
+        TIM::enable(apb);
+        TIM::reset(apb);
+
+        let psc = (clocks.pclk1_tim().0 / 1_000_000).max(1) - 1;
+
+        tim.psc.write(|w| w.psc().bits(psc as u16));
+
+        // ARR preload, UG does not set update flag
+        tim.cr1.write(|w| w.arpe().set_bit().urs().set_bit());
+        tim.sr.reset();
+        tim.dier.write(|w| w.uie().set_bit());
+
+        let duty = carrier.get_max_duty() / 3;
+
+        carrier.set_duty(0);
+        carrier.enable();
+
+        Transmitter {
+            tim,
+            carrier,
+            duty,
+            frame: Frame::new(),
+            pos: 0,
+            busy: false,
+        }
+    }
+
+    /// Start sending frame, completion is reported by on_interrupt
+    pub fn send(&mut self, frame: &Frame) -> Result<(), Error> {
+        if self.busy {
+            return Err(Error::Busy);
+        }
+
+        if frame.is_empty() {
+            return Ok(());
+        }
+
+        self.frame = *frame;
+        self.pos = 0;
+        self.busy = true;
+
+        let pulses = self.frame.pulses();
+
+        // load first duration into shadow register, then preload the next one
+        self.set_period(pulses[0]);
+        self.tim.egr.write(|w| w.ug().set_bit());
+
+        if let Some(p) = pulses.get(1) {
+            self.set_period(*p);
+        }
+
+        self.set_carrier(pulses[0]);
+        self.tim.cr1.modify(|_, w| w.cen().set_bit());
+
+        Ok(())
+    }
+
+    /// Handle gate timer interrupt, returns true when frame is complete
+    pub fn on_interrupt(&mut self) -> bool {
+        self.tim.sr.modify(|_, w| w.uif().clear_bit());
+
+        if !self.busy {
+            return false;
+        }
+
+        self.pos += 1;
+
+        let pulses = self.frame.pulses();
+
+        match pulses.get(self.pos) {
+            Some(p) => {
+                let p = *p;
+                let next = pulses.get(self.pos + 1).copied();
+
+                self.set_carrier(p);
+
+                if let Some(next) = next {
+                    self.set_period(next);
+                }
+
+                false
+            }
+            None => {
+                self.stop();
+                true
+            }
+        }
+    }
+
+    /// Abort current frame, carrier is switched off
+    pub fn stop(&mut self) {
+        self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+        self.carrier.set_duty(0);
+        self.busy = false;
+    }
+
+    pub fn busy(&self) -> bool {
+        self.busy
+    }
+
+    /// Carrier duty for marks, default is 1/3 of the period
+    pub fn set_duty(&mut self, duty: u16) {
+        self.duty = duty.min(self.carrier.get_max_duty());
+    }
+
+    pub fn release(mut self) -> (TIM, P) {
+        self.stop();
+        self.tim.dier.reset();
+        self.carrier.disable();
+
+        (self.tim, self.carrier)
+    }
+
+    fn set_period(&mut self, p: Pulse) {
+        let ticks = p.us.max(2).min(0x1_0000) - 1;
+        self.tim.arr.write(|w| w.arr().bits(ticks as u16));
+    }
+
+    fn set_carrier(&mut self, p: Pulse) {
+        self.carrier.set_duty(if p.mark { self.duty } else { 0 });
+    }
+}
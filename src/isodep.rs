@@ -0,0 +1,265 @@
+//
+// ISO14443-4 (ISO-DEP) half-duplex block transmission protocol on top of RC522 driver
+//
+
+use crate::rc522::{self, Rc522};
+use core::convert::Infallible;
+use embedded_hal::blocking::spi;
+use embedded_hal::digital::v2::OutputPin;
+
+// RC522 FIFO is 64 bytes: use it as frame size for both directions
+const FSD: usize = 64;
+const FSDI: u8 = 5;
+
+const RATS: u8 = 0xe0;
+
+// block types: PCB byte
+const I_BLOCK: u8 = 0x02;
+const R_BLOCK_ACK: u8 = 0xa2;
+const R_BLOCK_NAK: u8 = 0xb2;
+const S_DESELECT: u8 = 0xc2;
+const S_WTX: u8 = 0xf2;
+const CHAINING: u8 = 0x10;
+
+// number of retries of a frame after transmission errors
+const RETRIES: usize = 3;
+
+// frame size for FSCI/FSDI codes
+const FSC_TABLE: [usize; 9] = [16, 24, 32, 40, 48, 64, 96, 128, 256];
+
+#[derive(Debug)]
+pub enum Error<E> {
+    Rc522(rc522::Error<E>),
+    /// card answer does not follow ISO14443-4
+    Protocol,
+    /// response does not fit into buffer
+    NoRoom,
+}
+
+impl<E> From<rc522::Error<E>> for Error<E> {
+    fn from(e: rc522::Error<E>) -> Self {
+        Error::Rc522(e)
+    }
+}
+
+/// Parsed answer to select
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ats {
+    /// maximum frame size accepted by card
+    pub fsc: usize,
+    /// frame waiting time integer
+    pub fwi: u8,
+    /// startup frame guard time integer
+    pub sfgi: u8,
+    pub historical: [u8; 15],
+    pub historical_len: usize,
+}
+
+impl Ats {
+    /// Decode ATS including leading TL byte, defaults are used for missing bytes
+    pub fn decode(ats: &[u8]) -> Option<Self> {
+        let tl = *ats.first()? as usize;
+        if tl > ats.len() || tl == 0 {
+            return None;
+        }
+
+        let mut res = Ats {
+            fsc: 32,
+            fwi: 4,
+            sfgi: 0,
+            historical: [0u8; 15],
+            historical_len: 0,
+        };
+
+        if tl == 1 {
+            return Some(res);
+        }
+
+        let t0 = ats[1];
+        res.fsc = FSC_TABLE[((t0 & 0x0f) as usize).min(FSC_TABLE.len() - 1)];
+
+        let mut pos = 2;
+
+        // TA(1): bit rates, only 106 kbit/s is used
+        if t0 & 0x10 != 0 {
+            pos += 1;
+        }
+
+        // TB(1): FWI and SFGI
+        if t0 & 0x20 != 0 {
+            let tb = *ats.get(pos)?;
+            res.fwi = tb >> 4;
+            res.sfgi = tb & 0x0f;
+            pos += 1;
+        }
+
+        // TC(1): NAD/CID support, both are not used
+        if t0 & 0x40 != 0 {
+            pos += 1;
+        }
+
+        if pos > tl {
+            return None;
+        }
+
+        let hist = &ats[pos..tl];
+        let n = hist.len().min(res.historical.len());
+        res.historical[..n].copy_from_slice(&hist[..n]);
+        res.historical_len = n;
+
+        Some(res)
+    }
+
+    pub fn historical_bytes(&self) -> &[u8] {
+        &self.historical[..self.historical_len]
+    }
+}
+
+/// Status word of APDU response
+pub fn status_word(resp: &[u8]) -> Option<u16> {
+    if resp.len() < 2 {
+        return None;
+    }
+
+    Some(u16::from_be_bytes([
+        resp[resp.len() - 2],
+        resp[resp.len() - 1],
+    ]))
+}
+
+/// ISO-DEP session for a selected card with SAK bit 6 set
+pub struct IsoDep<'a, SPI, NSS> {
+    rc: &'a mut Rc522<SPI, NSS>,
+    ats: Ats,
+    block: u8,
+}
+
+impl<'a, E, SPI, NSS> IsoDep<'a, SPI, NSS>
+where
+    SPI: spi::Transfer<u8, Error = E> + spi::Write<u8, Error = E>,
+    NSS: OutputPin<Error = Infallible>,
+{
+    /// Card supports ISO14443-4 according to its SAK
+    pub fn supported(sak: u8) -> bool {
+        sak & 0x20 != 0
+    }
+
+    /// Send RATS and configure frame waiting time from ATS
+    pub fn activate(rc: &'a mut Rc522<SPI, NSS>) -> Result<Self, Error<E>> {
+        let mut rx = [0u8; 32];
+        let n = rc.transceive_crc(&[RATS, FSDI << 4], &mut rx)?;
+        let ats = Ats::decode(&rx[..n]).ok_or(Error::Protocol)?;
+
+        // FWT = 302us * 2^FWI, RC522 timer tick is 25us
+        let fwt = (302u32 << ats.fwi.min(14)) / 25 + 1;
+        rc.set_timeout_ticks(fwt.min(0xffff) as u16)?;
+
+        Ok(IsoDep { rc, ats, block: 0 })
+    }
+
+    pub fn ats(&self) -> &Ats {
+        &self.ats
+    }
+
+    /// Exchange APDU using I-block chaining in both directions,
+    /// returns response length including status word
+    pub fn exchange(&mut self, apdu: &[u8], resp: &mut [u8]) -> Result<usize, Error<E>> {
+        let max_inf = self.ats.fsc.min(FSD) - 3;
+        let mut frame = [0u8; FSD];
+        let mut rx = [0u8; FSD];
+        let mut chunks = apdu.chunks(max_inf).peekable();
+
+        // send command: all but last chunk are acknowledged by R(ACK)
+        let mut n = loop {
+            let chunk = chunks.next().unwrap_or(&[]);
+            let more = chunks.peek().is_some();
+
+            frame[0] = I_BLOCK | self.block | if more { CHAINING } else { 0 };
+            frame[1..1 + chunk.len()].copy_from_slice(chunk);
+
+            let n = self.transceive(&frame[..1 + chunk.len()], &mut rx)?;
+            self.block ^= 1;
+
+            if !more {
+                break n;
+            }
+
+            if rx[0] & 0xf6 != R_BLOCK_ACK & 0xf6 {
+                return Err(Error::Protocol);
+            }
+        };
+
+        // receive response: acknowledge chained I-blocks
+        let mut len = 0;
+
+        loop {
+            let pcb = rx[0];
+
+            if pcb & 0xe2 != I_BLOCK || n < 1 {
+                return Err(Error::Protocol);
+            }
+
+            let inf = &rx[1..n];
+            if len + inf.len() > resp.len() {
+                return Err(Error::NoRoom);
+            }
+
+            resp[len..len + inf.len()].copy_from_slice(inf);
+            len += inf.len();
+
+            if pcb & CHAINING == 0 {
+                break;
+            }
+
+            n = self.transceive(&[R_BLOCK_ACK | self.block], &mut rx)?;
+            self.block ^= 1;
+        }
+
+        Ok(len)
+    }
+
+    /// Send S(DESELECT) and release the reader
+    pub fn deselect(self) -> Result<(), Error<E>> {
+        let mut rx = [0u8; 4];
+        let n = self.rc.transceive_crc(&[S_DESELECT], &mut rx)?;
+
+        if n != 1 || rx[0] & 0xf7 != S_DESELECT {
+            return Err(Error::Protocol);
+        }
+
+        Ok(())
+    }
+
+    // transceive frame, answer waiting time extension requests and
+    // send R(NAK) after transmission errors to get the last block again
+    fn transceive(&mut self, tx: &[u8], rx: &mut [u8]) -> Result<usize, Error<E>> {
+        let nak = [R_BLOCK_NAK | self.block];
+        let mut frame = tx;
+        let mut retries = 0;
+
+        let mut n = loop {
+            match self.rc.transceive_crc(frame, rx) {
+                Ok(n) => break n,
+                Err(e) => {
+                    retries += 1;
+                    if retries > RETRIES {
+                        return Err(Error::Rc522(e));
+                    }
+
+                    frame = &nak;
+                }
+            }
+        };
+
+        while n == 2 && rx[0] & 0xf7 == S_WTX {
+            let wtxm = rx[1] & 0x3f;
+            n = self.rc.transceive_crc(&[S_WTX, wtxm], rx)?;
+        }
+
+        if n == 0 {
+            return Err(Error::Protocol);
+        }
+
+        Ok(n)
+    }
+}
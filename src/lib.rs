@@ -0,0 +1,24 @@
+#![no_std]
+
+pub use blue_pill_logic::{
+    access, color, datetime, effects, fade, filter, font, ir, matrix, ndef, servo, telemetry,
+};
+
+pub mod aio;
+pub mod bridge;
+pub mod capture;
+pub mod clocks;
+pub mod crash;
+pub mod executor;
+pub mod irqcell;
+pub mod irtx;
+pub mod isodep;
+pub mod mifare;
+pub mod nrf24;
+pub mod power;
+pub mod rc522;
+pub mod rtc;
+pub mod ultralight;
+pub mod usonic;
+pub mod watchdog;
+pub mod ws2812;
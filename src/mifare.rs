@@ -0,0 +1,239 @@
+//
+// MIFARE Classic 1K/4K support on top of RC522 driver
+//
+
+use crate::rc522::{self, Rc522, Uid};
+use core::convert::Infallible;
+use embedded_hal::blocking::spi;
+use embedded_hal::digital::v2::OutputPin;
+
+pub use blue_pill_logic::mifare::{
+    blocks_in_sector, decode_value, encode_value, first_block, is_trailer, sector_of,
+    trailer_block, AccessBits, Block, CardType, DataAccess, Key, Trailer, TrailerAccess,
+    BLOCK_SIZE, DEFAULT_KEY,
+};
+
+const MF_READ: u8 = 0x30;
+const MF_WRITE: u8 = 0xa0;
+const MF_DECREMENT: u8 = 0xc0;
+const MF_INCREMENT: u8 = 0xc1;
+const MF_RESTORE: u8 = 0xc2;
+const MF_TRANSFER: u8 = 0xb0;
+
+#[derive(Debug)]
+pub enum Error<E> {
+    Rc522(rc522::Error<E>),
+    /// block number is outside of card memory
+    InvalidBlock,
+    /// block is not a sector trailer or is a sector trailer
+    WrongBlockType,
+    /// value block redundancy check failed
+    InvalidValue,
+    /// access bytes inverted copies do not match
+    InvalidAccessBits,
+    /// sector trailer would become permanently locked
+    Brick,
+}
+
+impl<E> From<rc522::Error<E>> for Error<E> {
+    fn from(e: rc522::Error<E>) -> Self {
+        Error::Rc522(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyType {
+    A = 0x60,
+    B = 0x61,
+}
+
+/// MIFARE Classic session for a selected card
+pub struct Classic<'a, SPI, NSS> {
+    rc: &'a mut Rc522<SPI, NSS>,
+    uid: Uid,
+    card: CardType,
+}
+
+impl<'a, E, SPI, NSS> Classic<'a, SPI, NSS>
+where
+    SPI: spi::Transfer<u8, Error = E> + spi::Write<u8, Error = E>,
+    NSS: OutputPin<Error = Infallible>,
+{
+    /// Returns None if selected card is not a MIFARE Classic
+    pub fn new(rc: &'a mut Rc522<SPI, NSS>, uid: Uid) -> Option<Self> {
+        let card = CardType::from_sak(uid.sak())?;
+        Some(Classic { rc, uid, card })
+    }
+
+    pub fn card_type(&self) -> CardType {
+        self.card
+    }
+
+    pub fn uid(&self) -> &Uid {
+        &self.uid
+    }
+
+    /// Crypto1 authentication for the sector containing block
+    pub fn authenticate(
+        &mut self,
+        key_type: KeyType,
+        block: u8,
+        key: &Key,
+    ) -> Result<(), Error<E>> {
+        self.check_block(block)?;
+
+        // for 7-byte UIDs the last 4 bytes are used
+        let uid = self.uid.as_bytes();
+        let uid = &uid[uid.len() - 4..];
+
+        let mut frame = [0u8; 12];
+        frame[0] = key_type as u8;
+        frame[1] = block;
+        frame[2..8].copy_from_slice(key);
+        frame[8..12].copy_from_slice(uid);
+
+        self.rc.mf_authent(&frame).map_err(Error::Rc522)
+    }
+
+    pub fn read_block(&mut self, block: u8) -> Result<Block, Error<E>> {
+        self.check_block(block)?;
+
+        let mut rx = [0u8; BLOCK_SIZE + 2];
+        let n = self.rc.transceive_crc(&[MF_READ, block], &mut rx)?;
+        if n != BLOCK_SIZE {
+            return Err(Error::Rc522(rc522::Error::IncompleteFrame));
+        }
+
+        let mut data = [0u8; BLOCK_SIZE];
+        data.copy_from_slice(&rx[..BLOCK_SIZE]);
+
+        Ok(data)
+    }
+
+    /// Write data block: sector trailers and manufacturer block are rejected
+    pub fn write_block(&mut self, block: u8, data: &Block) -> Result<(), Error<E>> {
+        self.check_block(block)?;
+
+        if block == 0 || is_trailer(block) {
+            return Err(Error::WrongBlockType);
+        }
+
+        self.write_raw(block, data)
+    }
+
+    pub fn read_trailer(&mut self, sector: u8) -> Result<Trailer, Error<E>> {
+        let block = self.read_block(trailer_block(sector))?;
+        Trailer::decode(&block).ok_or(Error::InvalidAccessBits)
+    }
+
+    /// Write sector trailer refusing configurations that brick the sector
+    pub fn write_trailer(&mut self, sector: u8, trailer: &Trailer) -> Result<(), Error<E>> {
+        if sector >= self.card.sectors() {
+            return Err(Error::InvalidBlock);
+        }
+
+        if !trailer.access.is_safe() {
+            return Err(Error::Brick);
+        }
+
+        // make sure encoded bytes pass the same format check as on card
+        let block = trailer.encode();
+        let mut access = [0u8; 3];
+        access.copy_from_slice(&block[6..9]);
+        if AccessBits::decode(&access) != Some(trailer.access) {
+            return Err(Error::InvalidAccessBits);
+        }
+
+        self.write_raw(trailer_block(sector), &block)
+    }
+
+    /// Write trailer without brick checks: use with care
+    pub fn write_trailer_unchecked(
+        &mut self,
+        sector: u8,
+        trailer: &Trailer,
+    ) -> Result<(), Error<E>> {
+        if sector >= self.card.sectors() {
+            return Err(Error::InvalidBlock);
+        }
+
+        self.write_raw(trailer_block(sector), &trailer.encode())
+    }
+
+    pub fn read_value(&mut self, block: u8) -> Result<i32, Error<E>> {
+        let data = self.read_block(block)?;
+        decode_value(&data)
+            .map(|(v, _)| v)
+            .ok_or(Error::InvalidValue)
+    }
+
+    pub fn write_value(&mut self, block: u8, value: i32) -> Result<(), Error<E>> {
+        self.write_block(block, &encode_value(value, block))
+    }
+
+    /// Increment value into internal transfer buffer: use transfer to commit
+    pub fn increment(&mut self, block: u8, delta: u32) -> Result<(), Error<E>> {
+        self.value_op(MF_INCREMENT, block, delta)
+    }
+
+    /// Decrement value into internal transfer buffer: use transfer to commit
+    pub fn decrement(&mut self, block: u8, delta: u32) -> Result<(), Error<E>> {
+        self.value_op(MF_DECREMENT, block, delta)
+    }
+
+    /// Load value into internal transfer buffer
+    pub fn restore(&mut self, block: u8) -> Result<(), Error<E>> {
+        self.value_op(MF_RESTORE, block, 0)
+    }
+
+    /// Commit internal transfer buffer into value block
+    pub fn transfer(&mut self, block: u8) -> Result<(), Error<E>> {
+        self.check_data_block(block)?;
+        self.rc.transceive_ack(&[MF_TRANSFER, block])?;
+        Ok(())
+    }
+
+    /// Stop Crypto1 session and halt the card
+    pub fn halt(self) -> Result<(), Error<E>> {
+        self.rc.hlta()?;
+        self.rc.stop_crypto1()?;
+        Ok(())
+    }
+
+    fn value_op(&mut self, cmd: u8, block: u8, operand: u32) -> Result<(), Error<E>> {
+        self.check_data_block(block)?;
+        self.rc.transceive_ack(&[cmd, block])?;
+
+        // card does not acknowledge the second part of value operation
+        let mut rx = [0u8; 1];
+        match self.rc.transceive_crc(&operand.to_le_bytes(), &mut rx) {
+            Err(rc522::Error::Timeout) => Ok(()),
+            Err(e) => Err(Error::Rc522(e)),
+            Ok(_) => Err(Error::Rc522(rc522::Error::Protocol)),
+        }
+    }
+
+    fn write_raw(&mut self, block: u8, data: &Block) -> Result<(), Error<E>> {
+        self.rc.transceive_ack(&[MF_WRITE, block])?;
+        self.rc.transceive_ack(data)?;
+        Ok(())
+    }
+
+    fn check_block(&self, block: u8) -> Result<(), Error<E>> {
+        if block as u16 >= self.card.blocks() {
+            return Err(Error::InvalidBlock);
+        }
+
+        Ok(())
+    }
+
+    fn check_data_block(&self, block: u8) -> Result<(), Error<E>> {
+        self.check_block(block)?;
+
+        if block == 0 || is_trailer(block) {
+            return Err(Error::WrongBlockType);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,401 @@
+//
+// Register level driver for nRF24L01+ radio
+//
+
+use core::convert::Infallible;
+use embedded_hal::blocking::spi;
+use embedded_hal::digital::v2::OutputPin;
+
+pub use embedded_hal::spi::MODE_0 as MODE;
+
+pub const MAX_PAYLOAD: usize = 32;
+pub const ADDR_WIDTH: usize = 5;
+pub const PIPES: u8 = 6;
+
+pub type Address = [u8; ADDR_WIDTH];
+
+// commands
+const R_REGISTER: u8 = 0x00;
+const W_REGISTER: u8 = 0x20;
+const R_RX_PAYLOAD: u8 = 0x61;
+const W_TX_PAYLOAD: u8 = 0xa0;
+const W_TX_PAYLOAD_NOACK: u8 = 0xb0;
+const FLUSH_TX: u8 = 0xe1;
+const FLUSH_RX: u8 = 0xe2;
+const R_RX_PL_WID: u8 = 0x60;
+const NOP: u8 = 0xff;
+
+#[allow(dead_code)]
+#[derive(Clone, Copy)]
+enum Register {
+    Config = 0x00,
+    EnAa = 0x01,
+    EnRxAddr = 0x02,
+    SetupAw = 0x03,
+    SetupRetr = 0x04,
+    RfCh = 0x05,
+    RfSetup = 0x06,
+    Status = 0x07,
+    ObserveTx = 0x08,
+    Rpd = 0x09,
+    RxAddrP0 = 0x0a,
+    TxAddr = 0x10,
+    RxPwP0 = 0x11,
+    FifoStatus = 0x17,
+    Dynpd = 0x1c,
+    Feature = 0x1d,
+}
+
+// CONFIG bits
+const MASK_RX_DR: u8 = 1 << 6;
+const MASK_TX_DS: u8 = 1 << 5;
+const MASK_MAX_RT: u8 = 1 << 4;
+const EN_CRC: u8 = 1 << 3;
+const CRCO: u8 = 1 << 2;
+const PWR_UP: u8 = 1 << 1;
+const PRIM_RX: u8 = 1 << 0;
+
+// STATUS bits
+const RX_DR: u8 = 1 << 6;
+const TX_DS: u8 = 1 << 5;
+const MAX_RT: u8 = 1 << 4;
+
+// FIFO_STATUS bits
+const RX_EMPTY: u8 = 1 << 0;
+
+// FEATURE bits
+const EN_DPL: u8 = 1 << 2;
+const EN_DYN_ACK: u8 = 1 << 0;
+
+#[derive(Debug)]
+pub enum Error<E> {
+    Spi(E),
+    /// payload is empty or longer than 32 bytes
+    InvalidPayload,
+    /// pipe number is out of range
+    InvalidPipe,
+    /// chip does not respond: registers read back wrong values
+    NotFound,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataRate {
+    R250Kbps,
+    R1Mbps,
+    R2Mbps,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Power {
+    Min = 0,  // -18 dBm
+    Low = 1,  // -12 dBm
+    High = 2, // -6 dBm
+    Max = 3,  // 0 dBm
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// RF channel 0..125: frequency is 2400 + channel MHz
+    pub channel: u8,
+    pub rate: DataRate,
+    pub power: Power,
+    /// auto retransmit delay in 250us steps (0 means 250us)
+    pub retransmit_delay: u8,
+    /// auto retransmit count, 0 disables retransmits
+    pub retransmit_count: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            channel: 120,
+            rate: DataRate::R250Kbps,
+            power: Power::Max,
+            retransmit_delay: 4,
+            retransmit_count: 15,
+        }
+    }
+}
+
+/// Decoded and cleared interrupt status
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Status {
+    /// payload received
+    pub rx_ready: bool,
+    /// payload sent and acknowledged
+    pub tx_done: bool,
+    /// max retransmits reached: payload is still in TX FIFO
+    pub tx_failed: bool,
+}
+
+pub struct Nrf24<SPI, CSN, CE> {
+    spi: SPI,
+    csn: CSN,
+    ce: CE,
+    config: u8,
+}
+
+impl<E, SPI, CSN, CE> Nrf24<SPI, CSN, CE>
+where
+    SPI: spi::Transfer<u8, Error = E> + spi::Write<u8, Error = E>,
+    CSN: OutputPin<Error = Infallible>,
+    CE: OutputPin<Error = Infallible>,
+{
+    /// Configure radio with 2-byte CRC, dynamic payloads and auto-ack on all pipes
+    pub fn new(spi: SPI, csn: CSN, ce: CE, cfg: &Config) -> Result<Self, Error<E>> {
+        let mut nrf = Nrf24 {
+            spi,
+            csn,
+            ce,
+            config: EN_CRC | CRCO,
+        };
+
+        nrf.ce.set_low().ok();
+        nrf.csn.set_high().ok();
+
+        nrf.write(Register::Config, nrf.config)?;
+        nrf.write(Register::SetupAw, 0b11)?;
+
+        // make sure chip is connected
+        if nrf.read(Register::SetupAw)? != 0b11 {
+            return Err(Error::NotFound);
+        }
+
+        nrf.configure(cfg)?;
+
+        nrf.write(Register::Feature, EN_DPL | EN_DYN_ACK)?;
+        nrf.write(Register::Dynpd, 0x3f)?;
+        nrf.write(Register::EnAa, 0x3f)?;
+        nrf.write(Register::EnRxAddr, 0x00)?;
+
+        nrf.flush_tx()?;
+        nrf.flush_rx()?;
+        nrf.clear_status()?;
+
+        Ok(nrf)
+    }
+
+    pub fn configure(&mut self, cfg: &Config) -> Result<(), Error<E>> {
+        let rate = match cfg.rate {
+            DataRate::R250Kbps => 1 << 5,
+            DataRate::R1Mbps => 0,
+            DataRate::R2Mbps => 1 << 3,
+        };
+
+        self.write(Register::RfCh, cfg.channel.min(125))?;
+        self.write(Register::RfSetup, rate | (cfg.power as u8) << 1)?;
+        self.write(
+            Register::SetupRetr,
+            (cfg.retransmit_delay & 0x0f) << 4 | (cfg.retransmit_count & 0x0f),
+        )
+    }
+
+    /// Select pipes using auto-acknowledgement
+    pub fn set_auto_ack(&mut self, pipes: u8) -> Result<(), Error<E>> {
+        self.write(Register::EnAa, pipes & 0x3f)
+    }
+
+    /// Select interrupt sources on IRQ pin
+    pub fn set_interrupts(&mut self, rx: bool, tx: bool, max_rt: bool) -> Result<(), Error<E>> {
+        self.config &= !(MASK_RX_DR | MASK_TX_DS | MASK_MAX_RT);
+
+        if !rx {
+            self.config |= MASK_RX_DR;
+        }
+
+        if !tx {
+            self.config |= MASK_TX_DS;
+        }
+
+        if !max_rt {
+            self.config |= MASK_MAX_RT;
+        }
+
+        self.write(Register::Config, self.config)
+    }
+
+    /// Set TX address: pipe 0 receives ACKs, so it uses the same address
+    pub fn set_tx_addr(&mut self, addr: &Address) -> Result<(), Error<E>> {
+        self.write_buf(Register::TxAddr, addr)?;
+        self.write_buf(Register::RxAddrP0, addr)
+    }
+
+    /// Enable RX pipe: pipes 2..5 share bytes 1..4 of pipe 1 address,
+    /// so only the first (least significant) address byte is used for them
+    pub fn open_rx_pipe(&mut self, pipe: u8, addr: &Address) -> Result<(), Error<E>> {
+        if pipe >= PIPES {
+            return Err(Error::InvalidPipe);
+        }
+
+        let reg = Register::RxAddrP0 as u8 + pipe;
+
+        if pipe < 2 {
+            self.write_raw(W_REGISTER | reg, addr)?;
+        } else {
+            self.write_raw(W_REGISTER | reg, &addr[..1])?;
+        }
+
+        let en = self.read(Register::EnRxAddr)?;
+        self.write(Register::EnRxAddr, en | 1 << pipe)
+    }
+
+    pub fn close_rx_pipe(&mut self, pipe: u8) -> Result<(), Error<E>> {
+        if pipe >= PIPES {
+            return Err(Error::InvalidPipe);
+        }
+
+        let en = self.read(Register::EnRxAddr)?;
+        self.write(Register::EnRxAddr, en & !(1 << pipe))
+    }
+
+    /// Power up in RX mode and start listening
+    pub fn listen(&mut self) -> Result<(), Error<E>> {
+        self.config |= PWR_UP | PRIM_RX;
+        self.write(Register::Config, self.config)?;
+        self.ce.set_high().ok();
+        Ok(())
+    }
+
+    /// Power up in TX mode: chip is in standby until send is started
+    pub fn standby_tx(&mut self) -> Result<(), Error<E>> {
+        self.ce.set_low().ok();
+        self.config = (self.config | PWR_UP) & !PRIM_RX;
+        self.write(Register::Config, self.config)
+    }
+
+    pub fn power_down(&mut self) -> Result<(), Error<E>> {
+        self.ce.set_low().ok();
+        self.config &= !PWR_UP;
+        self.write(Register::Config, self.config)
+    }
+
+    /// Queue payload and start transmission: CE stays high until
+    /// finish_send is called from IRQ handler, so TX FIFO is drained
+    pub fn start_send(&mut self, payload: &[u8], ack: bool) -> Result<(), Error<E>> {
+        if payload.is_empty() || payload.len() > MAX_PAYLOAD {
+            return Err(Error::InvalidPayload);
+        }
+
+        let cmd = if ack {
+            W_TX_PAYLOAD
+        } else {
+            W_TX_PAYLOAD_NOACK
+        };
+        self.write_raw(cmd, payload)?;
+        self.ce.set_high().ok();
+
+        Ok(())
+    }
+
+    /// Stop transmission after TX_DS or MAX_RT interrupt, failed payload is dropped
+    pub fn finish_send(&mut self, status: &Status) -> Result<(), Error<E>> {
+        self.ce.set_low().ok();
+
+        if status.tx_failed {
+            self.flush_tx()?;
+        }
+
+        Ok(())
+    }
+
+    /// Read and clear interrupt flags
+    pub fn status(&mut self) -> Result<Status, Error<E>> {
+        let status = self.command(NOP)?;
+        self.write(Register::Status, status & (RX_DR | TX_DS | MAX_RT))?;
+
+        Ok(Status {
+            rx_ready: status & RX_DR != 0,
+            tx_done: status & TX_DS != 0,
+            tx_failed: status & MAX_RT != 0,
+        })
+    }
+
+    /// Read next payload from RX FIFO: returns pipe number and payload length
+    pub fn read_payload(
+        &mut self,
+        buf: &mut [u8; MAX_PAYLOAD],
+    ) -> Result<Option<(u8, usize)>, Error<E>> {
+        if self.read(Register::FifoStatus)? & RX_EMPTY != 0 {
+            return Ok(None);
+        }
+
+        let pipe = (self.command(NOP)? >> 1) & 0x07;
+
+        let mut wid = [R_RX_PL_WID, 0];
+        self.transfer(&mut wid)?;
+        let len = wid[1] as usize;
+
+        // corrupted payload width: the only way to recover is flush
+        if len == 0 || len > MAX_PAYLOAD {
+            self.flush_rx()?;
+            return Ok(None);
+        }
+
+        let mut data = [0u8; MAX_PAYLOAD + 1];
+        data[0] = R_RX_PAYLOAD;
+        self.transfer(&mut data[..len + 1])?;
+        buf[..len].copy_from_slice(&data[1..len + 1]);
+
+        Ok(Some((pipe, len)))
+    }
+
+    /// Lost packets counter and retransmits of the last packet
+    pub fn observe_tx(&mut self) -> Result<(u8, u8), Error<E>> {
+        let v = self.read(Register::ObserveTx)?;
+        Ok((v >> 4, v & 0x0f))
+    }
+
+    /// Received power detector: signal above -64 dBm on the channel
+    pub fn carrier(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.read(Register::Rpd)? & 0x01 != 0)
+    }
+
+    pub fn flush_tx(&mut self) -> Result<(), Error<E>> {
+        self.command(FLUSH_TX).map(|_| ())
+    }
+
+    pub fn flush_rx(&mut self) -> Result<(), Error<E>> {
+        self.command(FLUSH_RX).map(|_| ())
+    }
+
+    fn clear_status(&mut self) -> Result<(), Error<E>> {
+        self.write(Register::Status, RX_DR | TX_DS | MAX_RT)
+    }
+
+    // single byte command, returns STATUS register
+    fn command(&mut self, cmd: u8) -> Result<u8, Error<E>> {
+        let mut buf = [cmd];
+        self.transfer(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read(&mut self, reg: Register) -> Result<u8, Error<E>> {
+        let mut buf = [R_REGISTER | reg as u8, 0];
+        self.transfer(&mut buf)?;
+        Ok(buf[1])
+    }
+
+    fn write(&mut self, reg: Register, val: u8) -> Result<(), Error<E>> {
+        self.write_raw(W_REGISTER | reg as u8, &[val])
+    }
+
+    fn write_buf(&mut self, reg: Register, data: &[u8]) -> Result<(), Error<E>> {
+        self.write_raw(W_REGISTER | reg as u8, data)
+    }
+
+    fn write_raw(&mut self, cmd: u8, data: &[u8]) -> Result<(), Error<E>> {
+        self.csn.set_low().ok();
+        let res = self.spi.write(&[cmd]).and_then(|_| self.spi.write(data));
+        self.csn.set_high().ok();
+
+        res.map_err(Error::Spi)
+    }
+
+    fn transfer(&mut self, buf: &mut [u8]) -> Result<(), Error<E>> {
+        self.csn.set_low().ok();
+        let res = self.spi.transfer(buf).map(|_| ());
+        self.csn.set_high().ok();
+
+        res.map_err(Error::Spi)
+    }
+}
@@ -0,0 +1,217 @@
+//
+// Low-power modes: Sleep, Stop and Standby with RTC alarm wakeup
+//
+// Sleep: core clock is stopped, peripherals keep running, any interrupt
+// wakes up the core.
+//
+// Stop: all clocks in 1.8V domain are stopped, regulator is in low power
+// mode, RAM and registers are preserved. Stop is entered with WFE: wakeup by
+// any EXTI line configured as event, e.g. RTC alarm on line 17, or as enabled
+// interrupt, e.g. pins. No interrupt handler is needed for RTC alarm event.
+// System runs from HSI after wakeup, so the clock tree saved before entering
+// Stop is restored: HSE and PLL are re-locked and selected as SYSCLK again.
+//
+// Standby: 1.8V domain is powered off, only backup domain and RTC keep
+// running. Wakeup by RTC alarm, WKUP pin (PA0) rising edge, NRST or IWDG:
+// chip starts from reset, SBF flag in PWR_CSR tells that it was in Standby.
+//
+// Time spent in each mode is measured by RTC counter, which keeps running
+// in all modes. RTC is clocked from LSE, prescaler sets counter resolution.
+//
+
+use hal::rtc::Rtc;
+use hal::stm32;
+use stm32f1xx_hal as hal;
+
+pub use blue_pill_logic::power::{Mode, Stats};
+
+// EXTI line connected to RTC alarm
+const RTC_ALARM_LINE: u32 = 1 << 17;
+
+// SCB_SCR bit selecting deep sleep: Stop or Standby
+const SCR_SLEEPDEEP: u32 = 1 << 2;
+
+// clock tree state to restore after Stop
+struct SavedClocks {
+    hse: bool,
+    pll: bool,
+    sw: u8,
+}
+
+pub struct Power {
+    pwr: stm32::PWR,
+    scb: cortex_m::peripheral::SCB,
+    stats: Stats,
+    last: u32,
+}
+
+impl Power {
+    /// PWR clock is enabled by HAL when backup domain is constrained
+    pub fn new(pwr: stm32::PWR, scb: cortex_m::peripheral::SCB, rtc: &Rtc) -> Self {
+        Power {
+            pwr,
+            scb,
+            stats: Stats::default(),
+            last: rtc.current_time(),
+        }
+    }
+
+    /// Chip was woken up from Standby: call once at boot, flag is cleared
+    pub fn woke_from_standby(&mut self) -> bool {
+        let sbf = self.pwr.csr.read().sbf().bit_is_set();
+
+        self.pwr
+            .cr
+            .modify(|_, w| w.csbf().set_bit().cwuf().set_bit());
+
+        sbf
+    }
+
+    /// WKUP pin PA0 wakes up from Standby on rising edge
+    pub fn enable_wakeup_pin(&mut self, enable: bool) {
+        self.pwr.csr.modify(|_, w| w.ewup().bit(enable));
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self, rtc: &Rtc) {
+        self.stats = Stats::default();
+        self.last = rtc.current_time();
+    }
+
+    /// Wait for interrupt with running peripherals
+    pub fn sleep(&mut self, rtc: &Rtc) {
+        self.account_run(rtc);
+
+        self.set_sleepdeep(false);
+        cortex_m::asm::wfi();
+
+        self.stats.sleep += self.elapsed(rtc);
+    }
+
+    /// Enter Stop until EXTI event or interrupt, e.g. RTC alarm enabled
+    /// with listen_rtc_alarm, and restore clocks
+    pub fn stop(&mut self, rtc: &Rtc) {
+        self.account_run(rtc);
+
+        let clocks = save_clocks();
+
+        // regulator in low power mode, clear pending wakeup flag
+        self.pwr
+            .cr
+            .modify(|_, w| w.pdds().clear_bit().lpds().set_bit().cwuf().set_bit());
+
+        // clear latched event first: otherwise WFE returns immediately
+        self.set_sleepdeep(true);
+        cortex_m::asm::sev();
+        cortex_m::asm::wfe();
+        cortex_m::asm::wfe();
+        self.set_sleepdeep(false);
+
+        restore_clocks(&clocks);
+
+        self.stats.stop += self.elapsed(rtc);
+    }
+
+    /// Enter Standby: wakeup starts from reset
+    pub fn standby(&mut self) -> ! {
+        self.pwr
+            .cr
+            .modify(|_, w| w.pdds().set_bit().cwuf().set_bit());
+
+        self.set_sleepdeep(true);
+
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+
+    pub fn enter(&mut self, mode: Mode, rtc: &Rtc) {
+        match mode {
+            Mode::Sleep => self.sleep(rtc),
+            Mode::Stop => self.stop(rtc),
+            Mode::Standby => self.standby(),
+        }
+    }
+
+    fn set_sleepdeep(&mut self, deep: bool) {
+        unsafe {
+            self.scb.scr.modify(|r| {
+                if deep {
+                    r | SCR_SLEEPDEEP
+                } else {
+                    r & !SCR_SLEEPDEEP
+                }
+            })
+        };
+    }
+
+    fn account_run(&mut self, rtc: &Rtc) {
+        self.stats.run += self.elapsed(rtc);
+    }
+
+    fn elapsed(&mut self, rtc: &Rtc) -> u32 {
+        let now = rtc.current_time();
+        let dt = now.wrapping_sub(self.last);
+        self.last = now;
+        dt
+    }
+}
+
+/// Route RTC alarm to EXTI line 17 event: needed to wake up from Stop,
+/// event is generated only if RTC alarm flag was cleared after previous alarm
+pub fn listen_rtc_alarm(exti: &stm32::EXTI) {
+    exti.rtsr
+        .modify(|r, w| unsafe { w.bits(r.bits() | RTC_ALARM_LINE) });
+    exti.emr
+        .modify(|r, w| unsafe { w.bits(r.bits() | RTC_ALARM_LINE) });
+}
+
+/// Clear EXTI pending bit of RTC alarm after wakeup, RTC alarm flag
+/// is cleared by Rtc::clear_alarm_flag
+pub fn clear_rtc_alarm(exti: &stm32::EXTI) {
+    exti.pr.write(|w| unsafe { w.bits(RTC_ALARM_LINE) });
+}
+
+/// Keep debugger connection in Sleep, Stop and Standby
+pub fn debug_low_power(dbg: &stm32::DBGMCU, enable: bool) {
+    dbg.cr.modify(|_, w| {
+        w.dbg_sleep()
+            .bit(enable)
+            .dbg_stop()
+            .bit(enable)
+            .dbg_standby()
+            .bit(enable)
+    });
+}
+
+fn save_clocks() -> SavedClocks {
+    // HAL configures clocks once in freeze, RCC is accessed directly
+    let rcc = unsafe { &*stm32::RCC::ptr() };
+    let cr = rcc.cr.read();
+
+    SavedClocks {
+        hse: cr.hseon().bit_is_set(),
+        pll: cr.pllon().bit_is_set(),
+        sw: rcc.cfgr.read().sws().bits(),
+    }
+}
+
+fn restore_clocks(saved: &SavedClocks) {
+    let rcc = unsafe { &*stm32::RCC::ptr() };
+
+    if saved.hse {
+        rcc.cr.modify(|_, w| w.hseon().set_bit());
+        while rcc.cr.read().hserdy().bit_is_clear() {}
+    }
+
+    if saved.pll {
+        rcc.cr.modify(|_, w| w.pllon().set_bit());
+        while rcc.cr.read().pllrdy().bit_is_clear() {}
+    }
+
+    rcc.cfgr.modify(|_, w| unsafe { w.sw().bits(saved.sw) });
+    while rcc.cfgr.read().sws().bits() != saved.sw {}
+}
@@ -0,0 +1,665 @@
+//
+// Register level driver for MFRC522 NFC frontend
+//
+// Note: mfrc522 crate does not expose raw transceive, MFAuthent
+// command or register access, so they are implemented here.
+//
+
+use core::convert::Infallible;
+use embedded_hal::blocking::spi;
+use embedded_hal::digital::v2::OutputPin;
+
+pub use mfrc522::MODE;
+
+// number of status polls before giving up on chip response
+const POLL_LIMIT: u32 = 5_000;
+
+#[allow(dead_code)]
+#[derive(Clone, Copy)]
+pub enum Register {
+    Command = 0x01,
+    ComIEn = 0x02,
+    DivIEn = 0x03,
+    ComIrq = 0x04,
+    DivIrq = 0x05,
+    Error = 0x06,
+    Status1 = 0x07,
+    Status2 = 0x08,
+    FifoData = 0x09,
+    FifoLevel = 0x0a,
+    WaterLevel = 0x0b,
+    Control = 0x0c,
+    BitFraming = 0x0d,
+    Coll = 0x0e,
+    Mode = 0x11,
+    TxMode = 0x12,
+    RxMode = 0x13,
+    TxControl = 0x14,
+    TxAsk = 0x15,
+    CrcResultH = 0x21,
+    CrcResultL = 0x22,
+    ModWidth = 0x24,
+    RfCfg = 0x26,
+    TMode = 0x2a,
+    TPrescaler = 0x2b,
+    TReloadH = 0x2c,
+    TReloadL = 0x2d,
+    Version = 0x37,
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Command {
+    Idle = 0x00,
+    Mem = 0x01,
+    GenerateRandomId = 0x02,
+    CalcCrc = 0x03,
+    Transmit = 0x04,
+    NoCmdChange = 0x07,
+    Receive = 0x08,
+    Transceive = 0x0c,
+    MfAuthent = 0x0e,
+    SoftReset = 0x0f,
+}
+
+// CommandReg bits
+pub const POWER_DOWN: u8 = 1 << 4;
+
+// ComIrqReg bits
+pub const TX_IRQ: u8 = 1 << 6;
+pub const RX_IRQ: u8 = 1 << 5;
+pub const IDLE_IRQ: u8 = 1 << 4;
+pub const ERR_IRQ: u8 = 1 << 1;
+pub const TIMER_IRQ: u8 = 1 << 0;
+
+// DivIrqReg bits
+pub const CRC_IRQ: u8 = 1 << 2;
+
+// DivIEnReg bits
+const IRQ_PUSH_PULL: u8 = 1 << 7;
+
+// ErrorReg bits
+const ERR_BUFFER_OVFL: u8 = 1 << 4;
+const ERR_COLL: u8 = 1 << 3;
+const ERR_CRC: u8 = 1 << 2;
+const ERR_PARITY: u8 = 1 << 1;
+const ERR_PROTOCOL: u8 = 1 << 0;
+
+// CollReg bits
+const COLL_POS_NOT_VALID: u8 = 1 << 5;
+const COLL_POS_MASK: u8 = 0x1f;
+
+// Status2Reg bits
+pub const MF_CRYPTO1_ON: u8 = 1 << 3;
+
+// PICC commands
+const PICC_REQA: u8 = 0x26;
+const PICC_WUPA: u8 = 0x52;
+const PICC_HLTA: u8 = 0x50;
+const PICC_CASCADE_TAG: u8 = 0x88;
+const PICC_SEL: [u8; 3] = [0x93, 0x95, 0x97];
+
+#[derive(Debug)]
+pub enum Error<E> {
+    Spi(E),
+    BufferOverflow,
+    Collision,
+    Crc,
+    Parity,
+    Protocol,
+    Timeout,
+    NoRoom,
+    IncompleteFrame,
+    Bcc,
+    /// 4-bit ACK/NAK answer other than ACK
+    Nak(u8),
+    Auth,
+    /// started operation is not finished yet
+    Busy,
+}
+
+/// ATQA answer to REQA/WUPA
+#[derive(Debug, Clone, Copy)]
+pub struct AtqA(pub [u8; 2]);
+
+/// Card UID (4, 7 or 10 bytes) and SAK of the last cascade level
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Uid {
+    bytes: [u8; 10],
+    len: usize,
+    sak: u8,
+}
+
+impl Uid {
+    pub fn from_bytes(uid: &[u8], sak: u8) -> Self {
+        let mut bytes = [0u8; 10];
+        let len = uid.len().min(bytes.len());
+        bytes[..len].copy_from_slice(&uid[..len]);
+        Uid { bytes, len, sak }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    pub fn sak(&self) -> u8 {
+        self.sak
+    }
+}
+
+pub struct Rc522<SPI, NSS> {
+    spi: SPI,
+    nss: NSS,
+}
+
+impl<E, SPI, NSS> Rc522<SPI, NSS>
+where
+    SPI: spi::Transfer<u8, Error = E> + spi::Write<u8, Error = E>,
+    NSS: OutputPin<Error = Infallible>,
+{
+    pub fn new(spi: SPI, nss: NSS) -> Result<Self, Error<E>> {
+        let mut rc = Rc522 { spi, nss };
+
+        rc.nss.set_high().ok();
+        rc.reset()?;
+
+        // 106 kbit/s, no CRC on Tx/Rx: CRC is handled explicitly
+        rc.write(Register::TxMode, 0x00)?;
+        rc.write(Register::RxMode, 0x00)?;
+        rc.write(Register::ModWidth, 0x26)?;
+
+        // timer: TAuto, f_timer = 13.56 MHz / (2 * 0xa9 + 1) ~ 40 kHz, 25ms timeout
+        rc.write(Register::TMode, 0x80)?;
+        rc.write(Register::TPrescaler, 0xa9)?;
+        rc.set_timeout_ticks(1000)?;
+
+        // 100% ASK modulation, CRC preset 0x6363
+        rc.write(Register::TxAsk, 0x40)?;
+        rc.write(Register::Mode, 0x3d)?;
+
+        rc.antenna_on()?;
+
+        Ok(rc)
+    }
+
+    pub fn release(self) -> (SPI, NSS) {
+        (self.spi, self.nss)
+    }
+
+    pub fn version(&mut self) -> Result<u8, Error<E>> {
+        self.read(Register::Version)
+    }
+
+    pub fn reset(&mut self) -> Result<(), Error<E>> {
+        self.command(Command::SoftReset)?;
+        self.poll(Register::Command, POWER_DOWN, 0)
+    }
+
+    pub fn antenna_on(&mut self) -> Result<(), Error<E>> {
+        self.rmw(Register::TxControl, |v| v | 0x03)
+    }
+
+    pub fn antenna_off(&mut self) -> Result<(), Error<E>> {
+        self.rmw(Register::TxControl, |v| v & !0x03)
+    }
+
+    /// Timeout for transceive operations in 25us ticks of the internal timer
+    pub fn set_timeout_ticks(&mut self, ticks: u16) -> Result<(), Error<E>> {
+        self.write(Register::TReloadH, (ticks >> 8) as u8)?;
+        self.write(Register::TReloadL, ticks as u8)
+    }
+
+    pub fn reqa(&mut self) -> Result<AtqA, Error<E>> {
+        self.request(PICC_REQA)
+    }
+
+    pub fn wupa(&mut self) -> Result<AtqA, Error<E>> {
+        self.request(PICC_WUPA)
+    }
+
+    /// Run anticollision and select loop over all cascade levels
+    ///
+    /// With several cards in the field, 1 is taken at every colliding UID bit.
+    pub fn select(&mut self, _atqa: &AtqA) -> Result<Uid, Error<E>> {
+        let mut uid = [0u8; 10];
+        let mut len = 0;
+
+        // all received bits are cleared after collision
+        self.rmw(Register::Coll, |v| v & !0x80)?;
+
+        for sel in PICC_SEL.iter() {
+            let rx = self.anticollision(*sel)?;
+
+            if rx[0] ^ rx[1] ^ rx[2] ^ rx[3] != rx[4] {
+                return Err(Error::Bcc);
+            }
+
+            let mut tx = [*sel, 0x70, rx[0], rx[1], rx[2], rx[3], rx[4], 0, 0];
+            let crc = self.calc_crc(&tx[..7])?;
+            tx[7..].copy_from_slice(&crc);
+
+            let mut sak = [0u8; 3];
+            let (n, _) = self.transceive(&tx, 0, &mut sak)?;
+            if n != 3 {
+                return Err(Error::IncompleteFrame);
+            }
+
+            if self.calc_crc(&sak[..1])? != sak[1..] {
+                return Err(Error::Crc);
+            }
+
+            if rx[0] == PICC_CASCADE_TAG && sak[0] & 0x04 != 0 {
+                uid[len..len + 3].copy_from_slice(&rx[1..4]);
+                len += 3;
+            } else {
+                uid[len..len + 4].copy_from_slice(&rx[..4]);
+                len += 4;
+                return Ok(Uid::from_bytes(&uid[..len], sak[0]));
+            }
+        }
+
+        Err(Error::Protocol)
+    }
+
+    /// Put selected card into HALT state
+    pub fn hlta(&mut self) -> Result<(), Error<E>> {
+        let mut rx = [0u8; 1];
+        // card does not respond to HLTA if it is accepted
+        match self.transceive_crc(&[PICC_HLTA, 0x00], &mut rx) {
+            Err(Error::Timeout) => Ok(()),
+            Err(e) => Err(e),
+            Ok(_) => Err(Error::Protocol),
+        }
+    }
+
+    /// Append CRC to frame, transceive it and check CRC of the response
+    pub fn transceive_crc(&mut self, data: &[u8], rx: &mut [u8]) -> Result<usize, Error<E>> {
+        let mut tx = [0u8; 64];
+        if data.len() + 2 > tx.len() {
+            return Err(Error::NoRoom);
+        }
+
+        let crc = self.calc_crc(data)?;
+        tx[..data.len()].copy_from_slice(data);
+        tx[data.len()..data.len() + 2].copy_from_slice(&crc);
+
+        let (n, _) = self.transceive(&tx[..data.len() + 2], 0, rx)?;
+        if n < 3 {
+            return Err(Error::IncompleteFrame);
+        }
+
+        if self.calc_crc(&rx[..n - 2])? != rx[n - 2..n] {
+            return Err(Error::Crc);
+        }
+
+        Ok(n - 2)
+    }
+
+    /// Send frame with CRC and wait for 4-bit ACK
+    pub fn transceive_ack(&mut self, data: &[u8]) -> Result<(), Error<E>> {
+        let mut tx = [0u8; 64];
+        if data.len() + 2 > tx.len() {
+            return Err(Error::NoRoom);
+        }
+
+        let crc = self.calc_crc(data)?;
+        tx[..data.len()].copy_from_slice(data);
+        tx[data.len()..data.len() + 2].copy_from_slice(&crc);
+
+        let mut rx = [0u8; 1];
+        let (n, bits) = self.transceive(&tx[..data.len() + 2], 0, &mut rx)?;
+        if n != 1 || bits != 4 {
+            return Err(Error::IncompleteFrame);
+        }
+
+        match rx[0] & 0x0f {
+            0x0a => Ok(()),
+            nak => Err(Error::Nak(nak)),
+        }
+    }
+
+    /// Transmit frame and receive response: returns number of received
+    /// bytes and number of valid bits in the last byte (0 means 8)
+    pub fn transceive(
+        &mut self,
+        tx: &[u8],
+        tx_last_bits: u8,
+        rx: &mut [u8],
+    ) -> Result<(usize, u8), Error<E>> {
+        self.communicate(
+            Command::Transceive,
+            RX_IRQ | IDLE_IRQ,
+            tx,
+            tx_last_bits & 0x07,
+        )?;
+        self.read_fifo(rx)
+    }
+
+    /// Start transceive without waiting for completion: completion is
+    /// signalled by RX, timer or error interrupt, see finish_transceive
+    pub fn start_transceive(&mut self, tx: &[u8], tx_last_bits: u8) -> Result<(), Error<E>> {
+        self.start(Command::Transceive, tx, tx_last_bits & 0x07)
+    }
+
+    /// Collect result of transceive started by start_transceive
+    pub fn finish_transceive(&mut self, rx: &mut [u8]) -> Result<(usize, u8), Error<E>> {
+        let irq = self.read(Register::ComIrq)?;
+
+        if irq & (RX_IRQ | ERR_IRQ) == 0 {
+            if irq & TIMER_IRQ != 0 {
+                self.command(Command::Idle)?;
+                return Err(Error::Timeout);
+            }
+
+            return Err(Error::Busy);
+        }
+
+        self.complete(irq)?;
+        self.read_fifo(rx)
+    }
+
+    /// Start WUPA: halted cards answer it as well, so it can be
+    /// used to check that selected card is still in the field
+    pub fn start_wupa(&mut self) -> Result<(), Error<E>> {
+        self.start_transceive(&[PICC_WUPA], 7)
+    }
+
+    pub fn start_reqa(&mut self) -> Result<(), Error<E>> {
+        self.start_transceive(&[PICC_REQA], 7)
+    }
+
+    pub fn finish_request(&mut self) -> Result<AtqA, Error<E>> {
+        let mut rx = [0u8; 2];
+
+        let (n, bits) = self.finish_transceive(&mut rx)?;
+        if n != 2 || bits != 0 {
+            return Err(Error::IncompleteFrame);
+        }
+
+        Ok(AtqA(rx))
+    }
+
+    /// Enable ComIrqReg and DivIrqReg interrupt sources on IRQ pin:
+    /// IRQ pin is configured as active high push-pull output
+    pub fn enable_interrupts(&mut self, com: u8, div: u8) -> Result<(), Error<E>> {
+        self.clear_interrupts()?;
+        self.write(Register::ComIEn, com & 0x7f)?;
+        self.write(Register::DivIEn, IRQ_PUSH_PULL | (div & 0x14))
+    }
+
+    pub fn disable_interrupts(&mut self) -> Result<(), Error<E>> {
+        self.write(Register::ComIEn, 0x00)?;
+        self.write(Register::DivIEn, IRQ_PUSH_PULL)
+    }
+
+    /// Pending ComIrqReg interrupts
+    pub fn interrupts(&mut self) -> Result<u8, Error<E>> {
+        self.read(Register::ComIrq)
+    }
+
+    pub fn clear_interrupts(&mut self) -> Result<(), Error<E>> {
+        self.write(Register::ComIrq, 0x7f)?;
+        self.write(Register::DivIrq, 0x7f)
+    }
+
+    /// Enter soft power-down: oscillator and RF field are switched off,
+    /// register content is preserved
+    pub fn power_down(&mut self) -> Result<(), Error<E>> {
+        self.command(Command::Idle)?;
+        self.write(Register::Command, POWER_DOWN)
+    }
+
+    /// Leave soft power-down and wait for oscillator start
+    pub fn wake_up(&mut self) -> Result<(), Error<E>> {
+        self.command(Command::Idle)?;
+        self.poll(Register::Command, POWER_DOWN, 0)
+    }
+
+    /// Authenticate sector using Crypto1: auth frame is command, block, key, uid
+    pub fn mf_authent(&mut self, frame: &[u8; 12]) -> Result<(), Error<E>> {
+        self.communicate(Command::MfAuthent, IDLE_IRQ, frame, 0)?;
+
+        if self.read(Register::Status2)? & MF_CRYPTO1_ON == 0 {
+            return Err(Error::Auth);
+        }
+
+        Ok(())
+    }
+
+    pub fn stop_crypto1(&mut self) -> Result<(), Error<E>> {
+        self.rmw(Register::Status2, |v| v & !MF_CRYPTO1_ON)
+    }
+
+    pub fn calc_crc(&mut self, data: &[u8]) -> Result<[u8; 2], Error<E>> {
+        self.command(Command::Idle)?;
+        self.write(Register::DivIrq, CRC_IRQ)?;
+        self.write(Register::FifoLevel, 0x80)?;
+        self.write_fifo(data)?;
+        self.command(Command::CalcCrc)?;
+
+        self.poll(Register::DivIrq, CRC_IRQ, CRC_IRQ)?;
+        self.command(Command::Idle)?;
+
+        Ok([
+            self.read(Register::CrcResultL)?,
+            self.read(Register::CrcResultH)?,
+        ])
+    }
+
+    pub fn command(&mut self, cmd: Command) -> Result<(), Error<E>> {
+        self.write(Register::Command, cmd as u8)
+    }
+
+    pub fn read(&mut self, reg: Register) -> Result<u8, Error<E>> {
+        let mut buf = [((reg as u8) << 1) | 0x80, 0];
+
+        self.nss.set_low().ok();
+        let res = self.spi.transfer(&mut buf).map(|b| b[1]);
+        self.nss.set_high().ok();
+
+        res.map_err(Error::Spi)
+    }
+
+    pub fn write(&mut self, reg: Register, val: u8) -> Result<(), Error<E>> {
+        self.nss.set_low().ok();
+        let res = self.spi.write(&[(reg as u8) << 1, val]);
+        self.nss.set_high().ok();
+
+        res.map_err(Error::Spi)
+    }
+
+    pub fn rmw<F>(&mut self, reg: Register, f: F) -> Result<(), Error<E>>
+    where
+        F: FnOnce(u8) -> u8,
+    {
+        let val = self.read(reg)?;
+        self.write(reg, f(val))
+    }
+
+    fn write_fifo(&mut self, data: &[u8]) -> Result<(), Error<E>> {
+        self.nss.set_low().ok();
+        let res = self
+            .spi
+            .write(&[(Register::FifoData as u8) << 1])
+            .and_then(|_| self.spi.write(data));
+        self.nss.set_high().ok();
+
+        res.map_err(Error::Spi)
+    }
+
+    fn request(&mut self, cmd: u8) -> Result<AtqA, Error<E>> {
+        let mut rx = [0u8; 2];
+
+        // short frame: 7 bits
+        let (n, bits) = self.transceive(&[cmd], 7, &mut rx)?;
+        if n != 2 || bits != 0 {
+            return Err(Error::IncompleteFrame);
+        }
+
+        Ok(AtqA(rx))
+    }
+
+    /// UID and BCC of one cascade level: on collision the UID bit at
+    /// collision position is set to 1 and the card with that bit is chosen
+    fn anticollision(&mut self, sel: u8) -> Result<[u8; 5], Error<E>> {
+        // SEL, NVB, UID and BCC
+        let mut frame = [sel, 0, 0, 0, 0, 0, 0];
+        // number of UID bits known so far
+        let mut known = 0;
+
+        loop {
+            let bytes = known / 8;
+            let bits = (known % 8) as u8;
+            let tx_len = 2 + bytes + (bits != 0) as usize;
+            frame[1] = (((2 + bytes) << 4) as u8) | bits;
+
+            // received bits continue the last transmitted partial byte
+            let res = self.communicate(
+                Command::Transceive,
+                RX_IRQ | IDLE_IRQ,
+                &frame[..tx_len],
+                (bits << 4) | bits,
+            );
+
+            let collision = match res {
+                Ok(()) => false,
+                Err(Error::Collision) => true,
+                Err(e) => return Err(e),
+            };
+
+            let mut rx = [0u8; 5];
+            let (n, _) = self.read_fifo(&mut rx)?;
+            if bytes + n != 5 {
+                return Err(Error::IncompleteFrame);
+            }
+
+            let mask = 0xffu8 << bits;
+            frame[2 + bytes] = (frame[2 + bytes] & !mask) | (rx[0] & mask);
+            frame[3 + bytes..].copy_from_slice(&rx[1..n]);
+
+            if !collision {
+                let mut uid = [0u8; 5];
+                uid.copy_from_slice(&frame[2..]);
+                return Ok(uid);
+            }
+
+            let coll = self.read(Register::Coll)?;
+            if coll & COLL_POS_NOT_VALID != 0 {
+                return Err(Error::Collision);
+            }
+
+            // position of the first colliding bit, 1-based, 0 means 32
+            let pos = match coll & COLL_POS_MASK {
+                0 => 32,
+                p => p as usize,
+            };
+
+            if pos <= known {
+                return Err(Error::Protocol);
+            }
+
+            frame[2 + (pos - 1) / 8] |= 1 << ((pos - 1) % 8);
+            known = pos;
+        }
+    }
+
+    fn communicate(
+        &mut self,
+        cmd: Command,
+        wait: u8,
+        tx: &[u8],
+        framing: u8,
+    ) -> Result<(), Error<E>> {
+        self.start(cmd, tx, framing)?;
+
+        let mut n = 0;
+        let irq = loop {
+            let irq = self.read(Register::ComIrq)?;
+
+            if irq & wait != 0 {
+                break irq;
+            }
+
+            if irq & TIMER_IRQ != 0 || n > POLL_LIMIT {
+                self.command(Command::Idle)?;
+                return Err(Error::Timeout);
+            }
+
+            n += 1;
+        };
+
+        self.complete(irq)
+    }
+
+    /// Framing is BitFramingReg value: RxAlign and TxLastBits
+    fn start(&mut self, cmd: Command, tx: &[u8], framing: u8) -> Result<(), Error<E>> {
+        self.command(Command::Idle)?;
+        self.write(Register::ComIrq, 0x7f)?;
+        self.write(Register::FifoLevel, 0x80)?;
+        self.write_fifo(tx)?;
+        self.write(Register::BitFraming, framing & 0x77)?;
+        self.command(cmd)?;
+
+        if cmd == Command::Transceive {
+            // StartSend
+            self.rmw(Register::BitFraming, |v| v | 0x80)?;
+        }
+
+        Ok(())
+    }
+
+    fn complete(&mut self, irq: u8) -> Result<(), Error<E>> {
+        self.rmw(Register::BitFraming, |v| v & !0x80)?;
+
+        if irq & ERR_IRQ != 0 {
+            let err = self.read(Register::Error)?;
+
+            if err & ERR_BUFFER_OVFL != 0 {
+                return Err(Error::BufferOverflow);
+            }
+
+            if err & ERR_COLL != 0 {
+                return Err(Error::Collision);
+            }
+
+            if err & ERR_PARITY != 0 {
+                return Err(Error::Parity);
+            }
+
+            if err & ERR_PROTOCOL != 0 {
+                return Err(Error::Protocol);
+            }
+
+            if err & ERR_CRC != 0 {
+                return Err(Error::Crc);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_fifo(&mut self, rx: &mut [u8]) -> Result<(usize, u8), Error<E>> {
+        let n = self.read(Register::FifoLevel)? as usize;
+        if n > rx.len() {
+            return Err(Error::NoRoom);
+        }
+
+        for b in rx[..n].iter_mut() {
+            *b = self.read(Register::FifoData)?;
+        }
+
+        let bits = self.read(Register::Control)? & 0x07;
+
+        Ok((n, bits))
+    }
+
+    fn poll(&mut self, reg: Register, mask: u8, val: u8) -> Result<(), Error<E>> {
+        for _ in 0..POLL_LIMIT {
+            if self.read(reg)? & mask == val {
+                return Ok(());
+            }
+        }
+
+        Err(Error::Timeout)
+    }
+}
@@ -0,0 +1,317 @@
+//
+// Real-time clock service on STM32F1 RTC: Unix time counter in backup domain
+//
+// RTC counter runs at 1Hz and holds Unix time directly. Backup domain is
+// powered from VBAT, so the counter keeps running across resets and power
+// loss of the main supply. Backup register DR1 holds a marker that the time
+// was set: it is cleared by backup domain reset and by tamper event, so the
+// time is reported as invalid after both of them.
+//
+// Clock source is LSE 32.768kHz crystal. If LSE does not start, RTC falls
+// back to LSI ~40kHz: LSI is not in backup domain, it is stopped on reset
+// and is far less accurate, so it is re-enabled on every boot.
+//
+// Calibration slows the clock down by CAL/2^20, i.e. in ~0.954ppm steps:
+// measure RTC clock divided by 64 on TAMPER pin PC13 with calibration output.
+//
+// Interrupts: seconds and overflow on RTC line, alarm on RTCALARM via EXTI
+// line 17 (see power::listen_rtc_alarm), tamper on TAMPER line.
+//
+
+use crate::datetime::DateTime;
+use hal::backup_domain::BackupDomain;
+use hal::stm32;
+use stm32f1xx_hal as hal;
+
+// marker in backup data register DR1: time was set
+const TIME_SET: u16 = 0x7153;
+const TIME_SET_REG: usize = 0;
+
+// LSE startup takes up to a few hundred ms
+const LSE_POLLS: u32 = 2_000_000;
+
+const LSE_HZ: u32 = 32_768;
+const LSI_HZ: u32 = 40_000;
+
+// RCC_BDCR RTCSEL values
+const RTCSEL_LSE: u8 = 0b01;
+const RTCSEL_LSI: u8 = 0b10;
+
+// RTC_CRL/RTC_CRH bits
+const CRL_SECF: u32 = 1 << 0;
+const CRL_ALRF: u32 = 1 << 1;
+const CRL_OWF: u32 = 1 << 2;
+const CRL_RSF: u32 = 1 << 3;
+const CRL_CNF: u32 = 1 << 4;
+const CRL_RTOFF: u32 = 1 << 5;
+
+// BKP_CR, BKP_CSR and BKP_RTCCR bits
+const CR_TPE: u32 = 1 << 0;
+const CR_TPAL: u32 = 1 << 1;
+const CSR_CTE: u32 = 1 << 0;
+const CSR_CTI: u32 = 1 << 1;
+const CSR_TPIE: u32 = 1 << 2;
+const CSR_TEF: u32 = 1 << 8;
+const RTCCR_CCO: u32 = 1 << 7;
+const RTCCR_CAL_MASK: u32 = 0x7f;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Lse,
+    Lsi,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Second,
+    Alarm,
+    Overflow,
+}
+
+impl Event {
+    // same bit positions in RTC_CRH enable and RTC_CRL flag registers
+    fn mask(self) -> u32 {
+        match self {
+            Event::Second => CRL_SECF,
+            Event::Alarm => CRL_ALRF,
+            Event::Overflow => CRL_OWF,
+        }
+    }
+}
+
+pub struct Clock {
+    rtc: stm32::RTC,
+    source: Source,
+    valid: bool,
+}
+
+impl Clock {
+    /// Start RTC, or keep it running if it was started before reset:
+    /// BackupDomain from HAL enables write access to backup domain
+    pub fn new(rtc: stm32::RTC, bkp: &mut BackupDomain) -> Self {
+        // backup domain control register is not covered by HAL
+        let rcc = unsafe { &*stm32::RCC::ptr() };
+
+        if rcc.bdcr.read().rtcen().bit_is_clear() {
+            return Clock::start(rtc);
+        }
+
+        let source = if rcc.bdcr.read().rtcsel().bits() == RTCSEL_LSI {
+            start_lsi();
+            Source::Lsi
+        } else {
+            Source::Lse
+        };
+
+        // wait until APB registers are synchronized with RTC counter
+        rtc.crl
+            .modify(|r, w| unsafe { w.bits(r.bits() & !CRL_RSF) });
+        while rtc.crl.read().bits() & CRL_RSF == 0 {}
+
+        let valid = bkp.read_data_register_low(TIME_SET_REG) == TIME_SET;
+
+        Clock { rtc, source, valid }
+    }
+
+    pub fn source(&self) -> Source {
+        self.source
+    }
+
+    /// Time was set and not lost by backup domain reset or tamper event
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+
+    /// Unix time
+    pub fn now(&self) -> u32 {
+        // counter halves are read twice to catch carry between them
+        loop {
+            let h = self.rtc.cnth.read().bits();
+            let l = self.rtc.cntl.read().bits();
+            if h == self.rtc.cnth.read().bits() {
+                return (h << 16) | l;
+            }
+        }
+    }
+
+    pub fn datetime(&self) -> DateTime {
+        DateTime::from_unix(self.now())
+    }
+
+    pub fn set(&mut self, t: u32, bkp: &mut BackupDomain) {
+        self.configure(|rtc| {
+            rtc.cnth.write(|w| unsafe { w.bits(t >> 16) });
+            rtc.cntl.write(|w| unsafe { w.bits(t & 0xffff) });
+        });
+
+        bkp.write_data_register_low(TIME_SET_REG, TIME_SET);
+        self.valid = true;
+    }
+
+    pub fn set_datetime(&mut self, dt: &DateTime, bkp: &mut BackupDomain) {
+        self.set(dt.to_unix(), bkp);
+    }
+
+    /// Alarm flag is set when counter reaches alarm time
+    pub fn set_alarm(&mut self, t: u32) {
+        self.clear(Event::Alarm);
+        self.configure(|rtc| {
+            rtc.alrh.write(|w| unsafe { w.bits(t >> 16) });
+            rtc.alrl.write(|w| unsafe { w.bits(t & 0xffff) });
+        });
+    }
+
+    pub fn listen(&mut self, event: Event) {
+        self.wait_write();
+        self.rtc
+            .crh
+            .modify(|r, w| unsafe { w.bits(r.bits() | event.mask()) });
+    }
+
+    pub fn unlisten(&mut self, event: Event) {
+        self.wait_write();
+        self.rtc
+            .crh
+            .modify(|r, w| unsafe { w.bits(r.bits() & !event.mask()) });
+    }
+
+    pub fn is_pending(&self, event: Event) -> bool {
+        self.rtc.crl.read().bits() & event.mask() != 0
+    }
+
+    pub fn clear(&mut self, event: Event) {
+        self.wait_write();
+        self.rtc
+            .crl
+            .modify(|r, w| unsafe { w.bits(r.bits() & !event.mask()) });
+    }
+
+    /// Slow clock down by cal * 0.954ppm, cal is 0..=127
+    pub fn set_calibration(&mut self, cal: u8) {
+        let bkp = unsafe { &*stm32::BKP::ptr() };
+
+        bkp.rtccr.modify(|r, w| unsafe {
+            w.bits((r.bits() & !RTCCR_CAL_MASK) | (cal as u32 & RTCCR_CAL_MASK))
+        });
+    }
+
+    /// RTC clock divided by 64 on TAMPER pin PC13 for calibration
+    pub fn calibration_output(&mut self, enable: bool) {
+        let bkp = unsafe { &*stm32::BKP::ptr() };
+
+        bkp.rtccr.modify(|r, w| unsafe {
+            w.bits(if enable {
+                r.bits() | RTCCR_CCO
+            } else {
+                r.bits() & !RTCCR_CCO
+            })
+        });
+    }
+
+    /// Tamper detection on PC13: backup registers are cleared by hardware,
+    /// so the time is reported as invalid on the next boot
+    pub fn enable_tamper(&mut self, active_low: bool, interrupt: bool) {
+        let bkp = unsafe { &*stm32::BKP::ptr() };
+
+        // TPAL must be written before TPE is set
+        bkp.cr
+            .write(|w| unsafe { w.bits(if active_low { CR_TPAL } else { 0 }) });
+        bkp.csr
+            .write(|w| unsafe { w.bits(CSR_CTE | CSR_CTI | if interrupt { CSR_TPIE } else { 0 }) });
+        bkp.cr.modify(|r, w| unsafe { w.bits(r.bits() | CR_TPE) });
+    }
+
+    /// Tamper event happened: clears event flag and marks time invalid
+    pub fn tamper_detected(&mut self) -> bool {
+        let bkp = unsafe { &*stm32::BKP::ptr() };
+
+        if bkp.csr.read().bits() & CSR_TEF == 0 {
+            return false;
+        }
+
+        // TPE is disabled to clear TEF, pin is checked again when enabled
+        let cr = bkp.cr.read().bits();
+        bkp.cr.write(|w| unsafe { w.bits(cr & !CR_TPE) });
+        bkp.csr
+            .modify(|r, w| unsafe { w.bits(r.bits() | CSR_CTE | CSR_CTI) });
+        bkp.cr.write(|w| unsafe { w.bits(cr) });
+
+        self.valid = false;
+        true
+    }
+
+    pub fn release(self) -> stm32::RTC {
+        self.rtc
+    }
+
+    // first start: select clock source and set 1Hz prescaler
+    fn start(rtc: stm32::RTC) -> Self {
+        let rcc = unsafe { &*stm32::RCC::ptr() };
+
+        // RTCSEL can be changed only after backup domain reset
+        rcc.bdcr.modify(|_, w| w.bdrst().set_bit());
+        rcc.bdcr.modify(|_, w| w.bdrst().clear_bit());
+
+        let (source, sel, hz) = if start_lse() {
+            (Source::Lse, RTCSEL_LSE, LSE_HZ)
+        } else {
+            rcc.bdcr.modify(|_, w| w.lseon().clear_bit());
+            start_lsi();
+            (Source::Lsi, RTCSEL_LSI, LSI_HZ)
+        };
+
+        rcc.bdcr
+            .modify(|_, w| unsafe { w.rtcsel().bits(sel) }.rtcen().set_bit());
+
+        let mut clock = Clock {
+            rtc,
+            source,
+            valid: false,
+        };
+
+        let prl = hz - 1;
+        clock.configure(|rtc| {
+            rtc.prlh.write(|w| unsafe { w.bits(prl >> 16) });
+            rtc.prll.write(|w| unsafe { w.bits(prl & 0xffff) });
+            rtc.cnth.write(|w| unsafe { w.bits(0) });
+            rtc.cntl.write(|w| unsafe { w.bits(0) });
+        });
+
+        clock
+    }
+
+    // wait until previous write to RTC registers is completed
+    fn wait_write(&self) {
+        while self.rtc.crl.read().bits() & CRL_RTOFF == 0 {}
+    }
+
+    // prescaler, counter and alarm are written in configuration mode
+    fn configure<F: FnOnce(&stm32::RTC)>(&mut self, f: F) {
+        self.wait_write();
+        self.rtc
+            .crl
+            .modify(|r, w| unsafe { w.bits(r.bits() | CRL_CNF) });
+
+        f(&self.rtc);
+
+        self.rtc
+            .crl
+            .modify(|r, w| unsafe { w.bits(r.bits() & !CRL_CNF) });
+        self.wait_write();
+    }
+}
+
+fn start_lse() -> bool {
+    let rcc = unsafe { &*stm32::RCC::ptr() };
+
+    rcc.bdcr.modify(|_, w| w.lseon().set_bit());
+
+    (0..LSE_POLLS).any(|_| rcc.bdcr.read().lserdy().bit_is_set())
+}
+
+fn start_lsi() {
+    let rcc = unsafe { &*stm32::RCC::ptr() };
+
+    rcc.csr.modify(|_, w| w.lsion().set_bit());
+    while rcc.csr.read().lsirdy().bit_is_clear() {}
+}
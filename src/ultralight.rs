@@ -0,0 +1,163 @@
+//
+// MIFARE Ultralight and NTAG21x (NFC Forum Type 2) support on top of RC522 driver
+//
+
+use crate::ndef;
+use crate::rc522::{self, Rc522};
+use core::convert::Infallible;
+use embedded_hal::blocking::spi;
+use embedded_hal::digital::v2::OutputPin;
+
+pub use blue_pill_logic::ultralight::{Capability, Page, TagType, PAGE_SIZE, USER_START};
+
+const UL_GET_VERSION: u8 = 0x60;
+const UL_READ: u8 = 0x30;
+const UL_WRITE: u8 = 0xa2;
+
+#[derive(Debug)]
+pub enum Error<E> {
+    Rc522(rc522::Error<E>),
+    Ndef(ndef::Error),
+    /// page number is outside of user memory
+    InvalidPage,
+    /// tag is not NDEF formatted
+    NotFormatted,
+    /// tag is write protected
+    ReadOnly,
+}
+
+impl<E> From<rc522::Error<E>> for Error<E> {
+    fn from(e: rc522::Error<E>) -> Self {
+        Error::Rc522(e)
+    }
+}
+
+impl<E> From<ndef::Error> for Error<E> {
+    fn from(e: ndef::Error) -> Self {
+        Error::Ndef(e)
+    }
+}
+
+/// Type 2 tag session for a selected card
+pub struct Ultralight<'a, SPI, NSS> {
+    rc: &'a mut Rc522<SPI, NSS>,
+    tag: TagType,
+}
+
+impl<'a, E, SPI, NSS> Ultralight<'a, SPI, NSS>
+where
+    SPI: spi::Transfer<u8, Error = E> + spi::Write<u8, Error = E>,
+    NSS: OutputPin<Error = Infallible>,
+{
+    /// Identify tag using GET_VERSION: original Ultralight does not support
+    /// it and has to be woken up again after the failed command
+    pub fn new(rc: &'a mut Rc522<SPI, NSS>) -> Result<Self, Error<E>> {
+        let mut version = [0u8; 10];
+
+        let tag = match rc.transceive_crc(&[UL_GET_VERSION], &mut version) {
+            Ok(8) => {
+                let mut v = [0u8; 8];
+                v.copy_from_slice(&version[..8]);
+                TagType::from_version(&v)
+            }
+            _ => {
+                let atqa = rc.wupa()?;
+                rc.select(&atqa)?;
+                TagType::Ultralight
+            }
+        };
+
+        Ok(Ultralight { rc, tag })
+    }
+
+    pub fn tag_type(&self) -> TagType {
+        self.tag
+    }
+
+    /// Read 4 pages starting from the given one, wraps around at the end of memory
+    pub fn read_pages(&mut self, page: u8) -> Result<[u8; 16], Error<E>> {
+        if page >= self.tag.pages() {
+            return Err(Error::InvalidPage);
+        }
+
+        let mut rx = [0u8; 18];
+        let n = self.rc.transceive_crc(&[UL_READ, page], &mut rx)?;
+        if n != 16 {
+            return Err(Error::Rc522(rc522::Error::IncompleteFrame));
+        }
+
+        let mut data = [0u8; 16];
+        data.copy_from_slice(&rx[..16]);
+
+        Ok(data)
+    }
+
+    /// Write single page: only user memory pages are allowed
+    pub fn write_page(&mut self, page: u8, data: &Page) -> Result<(), Error<E>> {
+        if page < USER_START || page >= self.tag.user_end() {
+            return Err(Error::InvalidPage);
+        }
+
+        let frame = [UL_WRITE, page, data[0], data[1], data[2], data[3]];
+        self.rc.transceive_ack(&frame)?;
+
+        Ok(())
+    }
+
+    pub fn capability(&mut self) -> Result<Capability, Error<E>> {
+        let data = self.read_pages(3)?;
+        let mut cc = [0u8; PAGE_SIZE];
+        cc.copy_from_slice(&data[..PAGE_SIZE]);
+
+        Capability::decode(&cc).ok_or(Error::NotFormatted)
+    }
+
+    /// Read user memory into buffer, returns number of bytes read
+    pub fn read_user(&mut self, buf: &mut [u8]) -> Result<usize, Error<E>> {
+        let cc = self.capability()?;
+        let len = buf.len().min(cc.size);
+        let mut pos = 0;
+        let mut page = USER_START;
+
+        while pos < len {
+            let data = self.read_pages(page)?;
+            let n = (len - pos).min(data.len());
+            buf[pos..pos + n].copy_from_slice(&data[..n]);
+            pos += n;
+            page += 4;
+        }
+
+        Ok(len)
+    }
+
+    /// Read NDEF message from user memory: returns message slice of buffer
+    pub fn read_ndef<'b>(&mut self, buf: &'b mut [u8]) -> Result<&'b [u8], Error<E>> {
+        let len = self.read_user(buf)?;
+        let buf: &'b [u8] = buf;
+        Ok(ndef::find_message(&buf[..len])?)
+    }
+
+    /// Write NDEF message wrapped in TLV into user memory
+    pub fn write_ndef(&mut self, message: &[u8]) -> Result<(), Error<E>> {
+        let cc = self.capability()?;
+        if cc.read_only {
+            return Err(Error::ReadOnly);
+        }
+
+        let mut buf = [0u8; 888];
+        let len = ndef::wrap_message(message, &mut buf[..cc.size.min(888)])?;
+
+        for (n, chunk) in buf[..len].chunks(PAGE_SIZE).enumerate() {
+            let mut page = [0u8; PAGE_SIZE];
+            page[..chunk.len()].copy_from_slice(chunk);
+            self.write_page(USER_START + n as u8, &page)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn halt(self) -> Result<(), Error<E>> {
+        self.rc.hlta()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+//
+// HC-SR04 ultrasonic ranging with timer input capture
+//
+// Echo pulses of up to 4 sensors are captured by channels of TIM2/TIM3/TIM4
+// counting 1us ticks: rising edge is captured first, then channel polarity
+// is flipped to capture falling edge. Sensors are triggered one at a time, so
+// the echo of one sensor is not picked up by another one.
+//
+// HC-SR04 echo output is 5V: use 5V tolerant pins, e.g. TIM4 PB6-PB9.
+//
+
+use core::convert::Infallible;
+use core::ops::Deref;
+use embedded_hal::digital::v2::OutputPin;
+use hal::rcc::{Clocks, Enable, Reset, APB1};
+use hal::stm32::tim2::RegisterBlock;
+use stm32f1xx_hal as hal;
+
+pub use crate::filter::Median;
+pub use blue_pill_logic::usonic::{distance_mm, read_temperature_dc, speed_of_sound};
+
+/// Number of capture channels in a timer
+pub const MAX_SENSORS: usize = 4;
+
+/// Trigger pulse width
+pub const TRIGGER_US: u32 = 10;
+
+/// Minimum time between triggers, so that echoes of previous ping die out
+pub const CYCLE_MS: u32 = 60;
+
+/// Longer echo pulses mean no object in range: sensor gives up after ~38ms
+pub const NO_OBJECT_US: u32 = 30_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    /// previous measurement is still in progress
+    Busy,
+    /// no complete echo pulse: sensor missing or triggered too early
+    Timeout,
+    /// no object in range
+    OutOfRange,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    Wait(usize),
+    Echo(usize, u16),
+    Done(u32),
+}
+
+pub struct Ranger<TIM, PINS> {
+    tim: TIM,
+    pins: PINS,
+    state: State,
+    trigger_cycles: u32,
+}
+
+impl<TIM, PINS> Ranger<TIM, PINS>
+where
+    TIM: Deref<Target = RegisterBlock> + Enable<Bus = APB1> + Reset,
+{
+    /// Echo pins are inputs of timer channels, filter is ICxF value 0..15
+    pub fn new(tim: TIM, pins: PINS, clocks: &Clocks, apb: &mut APB1, filter: u8) -> Self {
+        TIM::enable(apb);
+        TIM::reset(apb);
+
+        let psc = (clocks.pclk1_tim().0 / 1_000_000).max(1) - 1;
+        let filter = filter & 0xf;
+
+        tim.ccmr1_input().write(|w| {
+            unsafe { w.ic1f().bits(filter).ic2f().bits(filter) };
+            w.cc1s().ti1().cc2s().ti2()
+        });
+
+        tim.ccmr2_input().write(|w| {
+            unsafe { w.ic3f().bits(filter).ic4f().bits(filter) };
+            w.cc3s().ti3().cc4s().ti4()
+        });
+
+        // channels are enabled one at a time when sensor is triggered
+        tim.ccer.reset();
+
+        tim.arr.write(|w| w.arr().bits(0xffff));
+        tim.psc.write(|w| w.psc().bits(psc as u16));
+        tim.egr.write(|w| w.ug().set_bit());
+        tim.sr.reset();
+        tim.cr1.write(|w| w.cen().set_bit());
+
+        Ranger {
+            tim,
+            pins,
+            state: State::Idle,
+            trigger_cycles: clocks.sysclk().0 / 1_000_000 * TRIGGER_US,
+        }
+    }
+
+    /// Trigger sensor with echo on channel ch: 0..MAX_SENSORS
+    pub fn start<T>(&mut self, ch: usize, trig: &mut T) -> Result<(), Error>
+    where
+        T: OutputPin<Error = Infallible> + ?Sized,
+    {
+        if self.busy() {
+            return Err(Error::Busy);
+        }
+
+        let ch = ch % MAX_SENSORS;
+
+        self.enable_channel(ch, false);
+        self.state = State::Wait(ch);
+
+        trig.set_high().ok();
+        cortex_m::asm::delay(self.trigger_cycles);
+        trig.set_low().ok();
+
+        Ok(())
+    }
+
+    /// Handle timer interrupt, returns true when echo pulse is complete
+    pub fn on_interrupt(&mut self) -> bool {
+        let ch = match self.state {
+            State::Wait(ch) | State::Echo(ch, _) => ch,
+            _ => {
+                self.disable_channels();
+                return false;
+            }
+        };
+
+        let sr = self.tim.sr.read().bits();
+
+        if sr & (1 << (1 + ch)) == 0 {
+            return false;
+        }
+
+        // reading CCR clears capture flag
+        let t = self.capture(ch);
+
+        match self.state {
+            State::Wait(_) => {
+                self.enable_channel(ch, true);
+                self.state = State::Echo(ch, t);
+                false
+            }
+            State::Echo(_, start) => {
+                self.disable_channels();
+                self.state = State::Done(t.wrapping_sub(start) as u32);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Echo pulse width in us of the last measurement, call before next trigger
+    pub fn finish(&mut self) -> Result<u32, Error> {
+        let state = self.state;
+
+        self.disable_channels();
+        self.state = State::Idle;
+
+        match state {
+            State::Done(us) if us >= NO_OBJECT_US => Err(Error::OutOfRange),
+            State::Done(us) => Ok(us),
+            _ => Err(Error::Timeout),
+        }
+    }
+
+    pub fn busy(&self) -> bool {
+        matches!(self.state, State::Wait(_) | State::Echo(..))
+    }
+
+    pub fn release(mut self) -> (TIM, PINS) {
+        self.disable_channels();
+        self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+
+        (self.tim, self.pins)
+    }
+
+    fn capture(&self, ch: usize) -> u16 {
+        match ch {
+            0 => self.tim.ccr1.read().ccr().bits(),
+            1 => self.tim.ccr2.read().ccr().bits(),
+            2 => self.tim.ccr3.read().ccr().bits(),
+            _ => self.tim.ccr4.read().ccr().bits(),
+        }
+    }
+
+    // capture on rising edge, or on falling edge if inverted
+    fn enable_channel(&mut self, ch: usize, falling: bool) {
+        let ccer = if falling { 0b11 } else { 0b01 } << (4 * ch);
+
+        self.tim.ccer.write(|w| unsafe { w.bits(ccer) });
+        self.tim
+            .sr
+            .write(|w| unsafe { w.bits(!(0x101 << (1 + ch))) });
+        self.tim.dier.write(|w| unsafe { w.bits(1 << (1 + ch)) });
+    }
+
+    fn disable_channels(&mut self) {
+        self.tim.dier.reset();
+        self.tim.ccer.reset();
+        self.tim.sr.reset();
+    }
+}
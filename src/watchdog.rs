@@ -0,0 +1,223 @@
+//
+// Watchdog supervision: task check-in, window watchdog and reset cause
+//
+// Supervisor feeds the watchdog only when every registered task checked in
+// since the previous feed, so a single stuck task leads to reset even if the
+// task feeding the watchdog still runs. Check-in is a single atomic OR and
+// can be done from any interrupt priority.
+//
+// IWDG is driven by HAL IndependentWatchdog: 40kHz LSI, timeout up to ~26s,
+// can not be stopped once started. WWDG is clocked from PCLK1/4096 and must
+// be refreshed within the window: neither too late nor too early. Timeout is
+// 64 ticks at most, i.e. ~58ms with 36MHz PCLK1.
+//
+// Both watchdogs keep counting when the core is halted by debugger, unless
+// frozen with stop_on_debug.
+//
+
+use core::sync::atomic::{AtomicU32, Ordering};
+use embedded_hal::watchdog::Watchdog;
+use hal::rcc::Clocks;
+use hal::stm32;
+use stm32f1xx_hal as hal;
+
+/// Max number of supervised tasks
+pub const MAX_TASKS: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    /// all task slots are used
+    NoRoom,
+    /// timeout or window can not be set with current PCLK1
+    InvalidTimeout,
+}
+
+/// Cause of the last reset from RCC_CSR flags
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResetCause {
+    PowerOn,
+    Pin,
+    Software,
+    IndependentWatchdog,
+    WindowWatchdog,
+    LowPower,
+    Unknown,
+}
+
+/// Read and clear reset flags: call once at boot. Pin flag is set
+/// on any reset, so it is reported only when no other flag is set.
+pub fn reset_cause() -> ResetCause {
+    // RCC is constrained by HAL, CSR is not used by it
+    let rcc = unsafe { &*stm32::RCC::ptr() };
+    let csr = rcc.csr.read();
+
+    let cause = if csr.lpwrrstf().bit_is_set() {
+        ResetCause::LowPower
+    } else if csr.wwdgrstf().bit_is_set() {
+        ResetCause::WindowWatchdog
+    } else if csr.iwdgrstf().bit_is_set() {
+        ResetCause::IndependentWatchdog
+    } else if csr.sftrstf().bit_is_set() {
+        ResetCause::Software
+    } else if csr.porrstf().bit_is_set() {
+        ResetCause::PowerOn
+    } else if csr.pinrstf().bit_is_set() {
+        ResetCause::Pin
+    } else {
+        ResetCause::Unknown
+    };
+
+    rcc.csr.modify(|_, w| w.rmvf().set_bit());
+
+    cause
+}
+
+/// Freeze IWDG and WWDG counters while the core is halted by debugger
+pub fn stop_on_debug(dbg: &stm32::DBGMCU, stop: bool) {
+    dbg.cr
+        .modify(|_, w| w.dbg_iwdg_stop().bit(stop).dbg_wwdg_stop().bit(stop));
+}
+
+/// Handle of supervised task
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TaskId(u8);
+
+impl TaskId {
+    fn mask(self) -> u32 {
+        1 << self.0
+    }
+}
+
+pub struct Supervisor {
+    registered: u32,
+    alive: AtomicU32,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Supervisor::new()
+    }
+}
+
+impl Supervisor {
+    pub const fn new() -> Self {
+        Supervisor {
+            registered: 0,
+            alive: AtomicU32::new(0),
+        }
+    }
+
+    /// Register task during initialization
+    pub fn register(&mut self) -> Result<TaskId, Error> {
+        let n = self.registered.count_ones() as usize;
+        if n >= MAX_TASKS {
+            return Err(Error::NoRoom);
+        }
+
+        self.registered |= 1 << n;
+
+        Ok(TaskId(n as u8))
+    }
+
+    /// Report liveness of the task
+    pub fn check_in(&self, id: TaskId) {
+        self.alive.fetch_or(id.mask(), Ordering::Relaxed);
+    }
+
+    /// Mask of registered tasks not checked in since the last feed
+    pub fn missing(&self) -> u32 {
+        self.registered & !self.alive.load(Ordering::Relaxed)
+    }
+
+    /// Feed watchdog if all tasks checked in, returns true if it was fed
+    pub fn service<W: Watchdog>(&self, wdg: &mut W) -> bool {
+        if self.missing() != 0 {
+            return false;
+        }
+
+        self.alive.fetch_and(!self.registered, Ordering::Relaxed);
+        wdg.feed();
+
+        true
+    }
+}
+
+/// WWDG counter: reset when T6 bit is cleared
+const WWDG_T6: u8 = 0x40;
+const WWDG_MAX_TICKS: u32 = 64;
+
+pub struct WindowWatchdog {
+    wwdg: stm32::WWDG,
+    reload: u8,
+}
+
+impl WindowWatchdog {
+    pub fn new(wwdg: stm32::WWDG) -> Self {
+        // HAL does not provide clock control for WWDG
+        let rcc = unsafe { &*stm32::RCC::ptr() };
+        rcc.apb1enr.modify(|_, w| w.wwdgen().set_bit());
+
+        WindowWatchdog {
+            wwdg,
+            reload: WWDG_T6 | 0x3f,
+        }
+    }
+
+    /// Start watchdog: reset happens if it is not refreshed within timeout_us
+    /// or if it is refreshed earlier than min_us after the previous refresh
+    pub fn start(&mut self, clocks: &Clocks, timeout_us: u32, min_us: u32) -> Result<(), Error> {
+        let pclk = clocks.pclk1().0 as u64;
+
+        // smallest prescaler with enough range gives the best resolution
+        let (tb, tick_ns) = (0..4u8)
+            .map(|tb| (tb, (4096u64 << tb) * 1_000_000_000 / pclk))
+            .find(|(_, tick_ns)| timeout_us as u64 * 1000 <= tick_ns * WWDG_MAX_TICKS as u64)
+            .ok_or(Error::InvalidTimeout)?;
+
+        let ticks = (timeout_us as u64 * 1000 / tick_ns).max(1);
+        let early = (min_us as u64 * 1000 + tick_ns - 1) / tick_ns;
+
+        if early >= ticks {
+            return Err(Error::InvalidTimeout);
+        }
+
+        let reload = WWDG_T6 + (ticks - 1) as u8;
+        let window = reload - early as u8;
+
+        self.reload = reload;
+
+        self.wwdg
+            .cfr
+            .write(|w| unsafe { w.wdgtb().bits(tb).w().bits(window) });
+        self.wwdg
+            .cr
+            .write(|w| unsafe { w.t().bits(reload).wdga().set_bit() });
+
+        Ok(())
+    }
+
+    /// Refresh is allowed: counter is below the window value
+    pub fn in_window(&self) -> bool {
+        let t = self.wwdg.cr.read().t().bits() & 0x7f;
+        let w = self.wwdg.cfr.read().w().bits() & 0x7f;
+
+        t <= w
+    }
+
+    /// Refresh only if it is allowed now, returns true if refreshed
+    pub fn feed_in_window(&mut self) -> bool {
+        if !self.in_window() {
+            return false;
+        }
+
+        self.feed();
+        true
+    }
+}
+
+impl Watchdog for WindowWatchdog {
+    /// Refresh counter: too early refresh resets the chip
+    fn feed(&mut self) {
+        self.wwdg.cr.write(|w| unsafe { w.t().bits(self.reload) });
+    }
+}
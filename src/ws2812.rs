@@ -0,0 +1,151 @@
+//
+// DMA-driven WS2812 output on SPI2 TX (DMA1 channel 5)
+//
+// Colors are pre-encoded into an SPI bit stream: at 3MHz SPI clock every
+// WS2812 bit takes four SPI bits, so each LED needs 12 bytes. Frame is padded
+// with zero bytes to keep the line low before the data and to latch it after.
+//
+
+use hal::dma::{dma1, Transfer, WriteDma, R};
+use hal::spi::SpiTxDma;
+use hal::stm32::SPI2;
+use smart_leds::RGB8;
+use stm32f1xx_hal as hal;
+
+pub use ws2812_spi::MODE;
+
+// pair of WS2812 bits, MSB first, encoded into one SPI byte
+const PATTERNS: [u8; 4] = [0b1000_1000, 0b1000_1110, 0b1110_1000, 0b1110_1110];
+
+pub const BYTES_PER_LED: usize = 12;
+
+// more than 280us of low line at 3MHz
+pub const RESET_BYTES: usize = 112;
+
+/// Size of DMA buffer for the given number of LEDs
+pub const fn buffer_size(leds: usize) -> usize {
+    1 + leds * BYTES_PER_LED + RESET_BYTES
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    /// previous frame is still being sent
+    Busy,
+}
+
+/// Encode colors in GRB order, returns number of encoded LEDs
+///
+/// LEDs that do not fit into buffer are dropped, rest of the buffer is zeroed.
+pub fn encode<I>(colors: I, buf: &mut [u8]) -> usize
+where
+    I: IntoIterator<Item = RGB8>,
+{
+    let leds = buf.len().saturating_sub(1 + RESET_BYTES) / BYTES_PER_LED;
+    let mut count = 0;
+
+    buf[0] = 0;
+
+    for (c, chunk) in colors
+        .into_iter()
+        .zip(buf[1..].chunks_exact_mut(BYTES_PER_LED).take(leds))
+    {
+        for (byte, out) in [c.g, c.r, c.b].iter().zip(chunk.chunks_exact_mut(4)) {
+            for (i, o) in out.iter_mut().enumerate() {
+                *o = PATTERNS[((byte >> (6 - 2 * i)) & 0x3) as usize];
+            }
+        }
+        count += 1;
+    }
+
+    for b in buf[1 + count * BYTES_PER_LED..].iter_mut() {
+        *b = 0;
+    }
+
+    count
+}
+
+pub type TxDma<REMAP, PINS> = SpiTxDma<SPI2, REMAP, PINS, dma1::C5>;
+
+/// WS2812 strip driven by SPI2 TX DMA
+///
+/// SPI2 has to run at 3MHz using ws2812::MODE. DMA channel should have
+/// TransferComplete interrupt enabled to call `complete` from its handler.
+///
+/// Buffer is kept as a slice: embedded-dma only implements DMA buffer traits
+/// for arrays of a few fixed sizes, while slices of any length are supported.
+pub struct Ws2812Dma<REMAP, PINS> {
+    xfr: Option<Transfer<R, &'static mut [u8], TxDma<REMAP, PINS>>>,
+    dma: Option<TxDma<REMAP, PINS>>,
+    buf: Option<&'static mut [u8]>,
+    len: usize,
+}
+
+impl<REMAP, PINS> Ws2812Dma<REMAP, PINS> {
+    /// Buffer size should be `buffer_size(leds)`
+    pub fn new(dma: TxDma<REMAP, PINS>, buf: &'static mut [u8]) -> Self {
+        Ws2812Dma {
+            xfr: None,
+            dma: Some(dma),
+            len: buf.len(),
+            buf: Some(buf),
+        }
+    }
+
+    /// Number of LEDs that fit into DMA buffer
+    pub fn capacity(&self) -> usize {
+        self.len.saturating_sub(1 + RESET_BYTES) / BYTES_PER_LED
+    }
+
+    /// Encode frame and queue it for transfer without waiting for completion
+    pub fn show<I>(&mut self, colors: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = RGB8>,
+    {
+        if self.busy() {
+            return Err(Error::Busy);
+        }
+
+        if let (Some(dma), Some(buf)) = (self.dma.take(), self.buf.take()) {
+            encode(colors, buf);
+            self.xfr = Some(dma.write(buf));
+        }
+
+        Ok(())
+    }
+
+    /// Check for finished transfer, returns true once per completed frame
+    ///
+    /// Intended to be called from DMA1_CHANNEL5 interrupt handler, it also
+    /// clears channel interrupt flags.
+    pub fn complete(&mut self) -> bool {
+        match self.xfr.take() {
+            Some(xfr) if xfr.is_done() => {
+                let (buf, dma) = xfr.wait();
+                self.dma = Some(dma);
+                self.buf = Some(buf);
+                true
+            }
+            xfr => {
+                self.xfr = xfr;
+                false
+            }
+        }
+    }
+
+    /// Transfer is still in progress
+    pub fn busy(&mut self) -> bool {
+        // pick up finished transfer even if interrupt was not handled yet
+        self.complete();
+        self.xfr.is_some()
+    }
+
+    /// Wait for transfer completion and give back DMA and buffer
+    pub fn release(mut self) -> (TxDma<REMAP, PINS>, &'static mut [u8]) {
+        if let Some(xfr) = self.xfr.take() {
+            let (buf, dma) = xfr.wait();
+            return (dma, buf);
+        }
+
+        (self.dma.take().unwrap(), self.buf.take().unwrap())
+    }
+}